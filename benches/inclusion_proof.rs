@@ -0,0 +1,231 @@
+//! Latency of serving an inclusion proof through `App::inclusion_proof`, for
+//! identities in the mined tree of different depths. The end to end latency
+//! covers the database lookup and the Merkle path traversal, the latter is
+//! measured on its own as well.
+//!
+//! Needs Docker for the Postgres container, run with
+//! `cargo bench --bench inclusion_proof`.
+
+use std::cell::RefCell;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use postgres_docker_utils::DockerContainer;
+use prometheus::Registry;
+use signup_sequencer::app::App;
+use signup_sequencer::config::{Config, DatabaseConfig};
+use signup_sequencer::database::methods::DbMethods as _;
+use signup_sequencer::database::{Database, IsolationLevel};
+use signup_sequencer::identity_tree::{
+    CanonicalTreeBuilder, Hash, ProcessedStatus, TreeItem, TreeState, TreeVersionReadOps,
+};
+use signup_sequencer::metrics::Metrics;
+use signup_sequencer::utils::secret::SecretUrl;
+use tempfile::TempDir;
+use testcontainers::clients::Cli;
+use tokio::runtime::Runtime;
+
+const IDENTITY_COUNT: usize = 10_000;
+const TREE_DEPTHS: [usize; 3] = [16, 20, 30];
+const MAX_DENSE_PREFIX_DEPTH: usize = 16;
+const SAMPLER_SEED: u64 = 0x9e37_79b9_7f4a_7c15;
+
+fn commitment(index: usize) -> Hash {
+    Hash::from(index as u64 + 1)
+}
+
+/// A cheap deterministic sampler, so that every run hits the same leaves
+struct Sampler(u64);
+
+impl Sampler {
+    fn next(&mut self) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+
+        (self.0 % IDENTITY_COUNT as u64) as usize
+    }
+}
+
+/// The roots after each of the identities is appended to an empty tree,
+/// starting with the root of the empty tree.
+fn roots(depth: usize, path: &str) -> Vec<Hash> {
+    let (mined, processed_builder) = CanonicalTreeBuilder::new(
+        depth,
+        depth.min(MAX_DENSE_PREFIX_DEPTH),
+        0,
+        Hash::ZERO,
+        &[],
+        path,
+    )
+    .seal();
+    let (processed, batching_builder) = processed_builder.seal_and_continue();
+    let (batching, latest_builder) = batching_builder.seal_and_continue();
+    let latest = latest_builder.seal();
+    let initial_root = latest.get_root();
+
+    let tree_state = TreeState::new(mined, processed, batching, latest);
+    let leaves: Vec<Hash> = (0..IDENTITY_COUNT).map(commitment).collect();
+
+    std::iter::once(initial_root)
+        .chain(
+            tree_state
+                .latest_tree()
+                .simulate_append_many(&leaves)
+                .into_iter()
+                .map(|(root, ..)| root),
+        )
+        .collect()
+}
+
+/// Starts an offchain app whose mined tree holds `IDENTITY_COUNT` identities.
+/// The directory holds the tree cache of the app.
+async fn setup_app(
+    docker: &Cli,
+    depth: usize,
+) -> anyhow::Result<(Arc<App>, DockerContainer, TempDir)> {
+    let db_container = postgres_docker_utils::setup(docker).await?;
+    let url = format!(
+        "postgres://postgres:postgres@{}/database",
+        db_container.address()
+    );
+    let temp_dir = TempDir::new()?;
+
+    let db = Database::new(
+        &DatabaseConfig {
+            database: SecretUrl::from_str(&url)?,
+            migrate: true,
+            max_connections: 1,
+            slow_transaction_threshold: Duration::from_secs(5),
+        },
+        &Metrics::default(),
+    )
+    .await?;
+
+    let roots = roots(depth, temp_dir.path().join("roots").to_str().unwrap());
+
+    let mut tx = db.begin_tx("bench", IsolationLevel::ReadCommitted).await?;
+    for (leaf_index, pre_root_and_root) in roots.windows(2).enumerate() {
+        let [pre_root, root] = pre_root_and_root else {
+            unreachable!()
+        };
+        tx.insert_pending_identity(leaf_index, &commitment(leaf_index), root, pre_root)
+            .await?;
+    }
+    tx.mark_root_as_mined(roots.last().unwrap()).await?;
+    tx.commit().await?;
+
+    let config: Config = toml::from_str(&indoc::formatdoc! {r#"
+            [app]
+            provers_urls = "[]"
+
+            [tree]
+            tree_depth = {depth}
+            dense_tree_prefix_depth = {dense_tree_prefix_depth}
+            cache_file = "{cache_file}"
+
+            [database]
+            database = "{url}"
+
+            [server]
+            address = "127.0.0.1:0"
+
+            [offchain_mode]
+            enabled = true
+        "#,
+        dense_tree_prefix_depth = depth.min(MAX_DENSE_PREFIX_DEPTH),
+        cache_file = temp_dir.path().join("tree").display(),
+    })?;
+
+    // Every depth runs an app of its own
+    let app = App::new_with_registry(config, Registry::new()).await?;
+    app.clone().init_tree().await?;
+
+    Ok((app, db_container, temp_dir))
+}
+
+fn print_percentiles(name: &str, mut latencies: Vec<Duration>) {
+    if latencies.is_empty() {
+        return;
+    }
+
+    latencies.sort();
+    let percentile = |p: usize| latencies[(latencies.len() - 1) * p / 100];
+
+    println!(
+        "{name}: p50 {:?}, p95 {:?}, p99 {:?} over {} requests",
+        percentile(50),
+        percentile(95),
+        percentile(99),
+        latencies.len()
+    );
+}
+
+fn inclusion_proof(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let docker = Cli::default();
+
+    let mut group = c.benchmark_group("inclusion_proof");
+
+    for depth in TREE_DEPTHS {
+        let (app, _db_container, _temp_dir) = runtime.block_on(setup_app(&docker, depth)).unwrap();
+        let app = &app;
+        let tree_state = app.tree_state().unwrap();
+
+        group.bench_with_input(BenchmarkId::new("merkle_path", depth), &depth, |b, _| {
+            let mut sampler = Sampler(SAMPLER_SEED);
+
+            b.iter(|| {
+                let leaf_index = sampler.next();
+                let item = TreeItem {
+                    status: ProcessedStatus::Mined,
+                    leaf_index,
+                };
+
+                tree_state
+                    .get_proof_for(&item, &commitment(leaf_index))
+                    .expect("Proof should exist")
+            });
+        });
+
+        // Criterion only reports averages, the latency of every request is
+        // kept for the percentiles
+        let latencies = RefCell::new(vec![]);
+        group.bench_with_input(BenchmarkId::new("end_to_end", depth), &depth, |b, _| {
+            let mut sampler = Sampler(SAMPLER_SEED);
+            let latencies = &latencies;
+
+            b.to_async(&runtime).iter_custom(move |iterations| {
+                let commitments: Vec<_> = (0..iterations)
+                    .map(|_| commitment(sampler.next()))
+                    .collect();
+
+                async move {
+                    let mut total = Duration::ZERO;
+                    for commitment in &commitments {
+                        let start = Instant::now();
+                        app.inclusion_proof(commitment)
+                            .await
+                            .expect("Proof should exist");
+                        let elapsed = start.elapsed();
+
+                        latencies.borrow_mut().push(elapsed);
+                        total += elapsed;
+                    }
+                    total
+                }
+            });
+        });
+        print_percentiles(
+            &format!("inclusion_proof/end_to_end/{depth}"),
+            latencies.into_inner(),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, inclusion_proof);
+criterion_main!(benches);
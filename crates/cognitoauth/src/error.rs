@@ -1,6 +1,6 @@
 use std::io;
 
-use aws_sdk_cognitoidentityprovider::error::SdkError;
+use aws_sdk_cognitoidentityprovider::error::{ProvideErrorMetadata, SdkError};
 use aws_sdk_cognitoidentityprovider::operation::initiate_auth::InitiateAuthError;
 use aws_sdk_cognitoidentityprovider::operation::respond_to_auth_challenge::RespondToAuthChallengeError;
 use cognito_srp::CognitoSrpError;
@@ -23,3 +23,14 @@ pub enum CognitoSrpAuthError {
     #[error("cognito idp response to auth challenge error: {0}")]
     CognitoResponseToAuthChallengeError(#[from] SdkError<RespondToAuthChallengeError>),
 }
+
+impl CognitoSrpAuthError {
+    /// The error code Cognito responded with, e.g. `ThrottlingException`
+    pub fn cognito_error_code(&self) -> Option<&str> {
+        match self {
+            Self::CognitoInitiateError(err) => err.code(),
+            Self::CognitoResponseToAuthChallengeError(err) => err.code(),
+            _ => None,
+        }
+    }
+}
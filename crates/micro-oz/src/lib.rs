@@ -1,23 +1,37 @@
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, AtomicU64};
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
 
 use anyhow::Context;
 use chrono::Utc;
+#[cfg(feature = "simulate")]
+use ethers::core::rand::{thread_rng, Rng};
 use ethers::prelude::k256::ecdsa::SigningKey;
 use ethers::prelude::SignerMiddleware;
-use ethers::providers::{Http, Middleware, Provider};
+use ethers::providers::{Http, Middleware, Provider, ProviderError};
 use ethers::signers::{LocalWallet, Signer};
 use ethers::types::transaction::eip2718::TypedTransaction;
-use ethers::types::{Eip1559TransactionRequest, U64};
+#[cfg(feature = "simulate")]
+use ethers::types::TxHash;
+use ethers::types::{Address, Eip1559TransactionRequest, U256, U64};
 use oz_api::data::transactions::{RelayerTransactionBase, SendBaseTransactionRequestOwned, Status};
+use thiserror::Error;
 use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
 
+mod metrics;
 pub mod server;
 
 const DEFAULT_GAS_LIMIT: u32 = 1_000_000;
 
-pub use self::server::{spawn, ServerHandle};
+// How often the balance of the signer is refreshed
+const BALANCE_MONITORING_PERIOD: Duration = Duration::from_secs(30);
+
+pub use self::metrics::Metrics;
+#[cfg(feature = "simulate")]
+pub use self::server::spawn_simulated_with_options;
+pub use self::server::{spawn, spawn_with_options, ServerHandle, ServerOptions};
 
 type PinheadSigner = SignerMiddleware<Provider<Http>, LocalWallet>;
 
@@ -27,11 +41,40 @@ pub struct Pinhead {
 }
 
 struct PinheadInner {
-    signer: Arc<PinheadSigner>,
+    backend: Backend,
+    address: Address,
     is_running: AtomicBool,
     tx_id_counter: AtomicU64,
     txs_to_execute: mpsc::Sender<String>,
     txs: Mutex<HashMap<String, Arc<Mutex<RelayerTransactionBase>>>>,
+    metrics: Metrics,
+}
+
+/// Where transactions are executed.
+enum Backend {
+    /// Sent to the chain by a local signer
+    Chain(Arc<PinheadSigner>),
+    /// Marked as mined with a random hash, without any chain
+    #[cfg(feature = "simulate")]
+    Simulated,
+}
+
+#[derive(Debug, Error)]
+pub enum PinheadError {
+    #[error(
+        "signer {address:?} has a balance of {balance} wei, at least {required} wei is required"
+    )]
+    InsufficientBalance {
+        address: Address,
+        balance: U256,
+        required: U256,
+    },
+    #[error("invalid rpc url: {0}")]
+    InvalidUrl(String),
+    #[error(transparent)]
+    Provider(#[from] ProviderError),
+    #[error(transparent)]
+    Metrics(#[from] prometheus::Error),
 }
 
 impl Drop for PinheadInner {
@@ -41,15 +84,24 @@ impl Drop for PinheadInner {
     }
 }
 
+/// Executes the queued transactions until the Pinhead is dropped. The runner
+/// only holds a weak reference, so that dropping the Pinhead drops the sender
+/// and closes the channel.
 async fn runner(
-    inner: Arc<PinheadInner>,
+    inner: Weak<PinheadInner>,
     mut txs_to_execute: mpsc::Receiver<String>,
 ) -> anyhow::Result<()> {
-    loop {
-        let Some(tx_id) = txs_to_execute.recv().await else {
-            break;
+    // Queued transactions are received before the channel reports it's closed
+    while let Some(tx_id) = txs_to_execute.recv().await {
+        let Some(inner) = inner.upgrade() else {
+            return Err(anyhow::anyhow!(
+                "Unexpected runner shutdown: Pinhead was dropped while transaction {tx_id} was \
+                 queued"
+            ));
         };
 
+        inner.metrics.queue_depth.dec();
+
         match runner_inner(&inner, tx_id).await {
             Ok(_) => {}
             Err(err) => {
@@ -58,9 +110,30 @@ async fn runner(
         }
     }
 
+    tracing::info!("Transaction runner shutting down: channel closed");
+
     Ok(())
 }
 
+async fn monitor_balance(inner: Weak<PinheadInner>, signer: Arc<PinheadSigner>) {
+    loop {
+        tokio::time::sleep(BALANCE_MONITORING_PERIOD).await;
+
+        let Some(inner) = inner.upgrade() else {
+            break;
+        };
+
+        if !inner.is_running.load(std::sync::atomic::Ordering::SeqCst) {
+            break;
+        }
+
+        match signer.get_balance(inner.address, None).await {
+            Ok(balance) => inner.metrics.set_wallet_balance(balance),
+            Err(err) => tracing::warn!("Failed to fetch wallet balance: {:?}", err),
+        }
+    }
+}
+
 async fn runner_inner(inner: &Arc<PinheadInner>, tx_id: String) -> Result<(), anyhow::Error> {
     tracing::info!("Executing tx: {tx_id}");
 
@@ -72,6 +145,21 @@ async fn runner_inner(inner: &Arc<PinheadInner>, tx_id: String) -> Result<(), an
         .expect("Missing tx")
         .clone();
 
+    let signer = match &inner.backend {
+        Backend::Chain(signer) => signer,
+        #[cfg(feature = "simulate")]
+        Backend::Simulated => {
+            let mut tx_guard = tx.lock().await;
+
+            tx_guard.status = Status::Mined;
+            tx_guard.hash = Some(TxHash::from(thread_rng().gen::<[u8; 32]>()));
+
+            tracing::info!(hash = ?tx_guard.hash, "Simulated tx");
+
+            return Ok(());
+        }
+    };
+
     let mut typed_tx = {
         let tx_guard = tx.lock().await;
 
@@ -84,9 +172,13 @@ async fn runner_inner(inner: &Arc<PinheadInner>, tx_id: String) -> Result<(), an
         })
     };
 
-    inner.signer.fill_transaction(&mut typed_tx, None).await?;
+    let send_timer = inner.metrics.send_latency.start_timer();
+
+    signer.fill_transaction(&mut typed_tx, None).await?;
 
-    let pending_tx = inner.signer.send_transaction(typed_tx, None).await?;
+    let pending_tx = signer.send_transaction(typed_tx, None).await?;
+
+    send_timer.observe_duration();
 
     {
         let mut tx_guard = tx.lock().await;
@@ -97,8 +189,12 @@ async fn runner_inner(inner: &Arc<PinheadInner>, tx_id: String) -> Result<(), an
 
     tracing::info!("Awaiting for receipt");
 
+    let receipt_timer = inner.metrics.receipt_latency.start_timer();
+
     let receipt = pending_tx.await?;
 
+    receipt_timer.observe_duration();
+
     let mut tx_guard = tx.lock().await;
 
     if let Some(receipt) = receipt {
@@ -118,30 +214,90 @@ async fn runner_inner(inner: &Arc<PinheadInner>, tx_id: String) -> Result<(), an
 
 impl Pinhead {
     pub async fn new(rpc_url: String, secret_key: SigningKey) -> anyhow::Result<Self> {
-        let provider = Provider::<Http>::try_from(rpc_url)?;
+        Ok(Self::new_with_balance_check(rpc_url, secret_key, U256::zero()).await?)
+    }
+
+    /// Creates a new instance, failing if the signer holds less than
+    /// `min_balance_wei`
+    pub async fn new_with_balance_check(
+        rpc_url: String,
+        secret_key: SigningKey,
+        min_balance_wei: U256,
+    ) -> Result<Self, PinheadError> {
+        let provider = Provider::<Http>::try_from(rpc_url)
+            .map_err(|err| PinheadError::InvalidUrl(err.to_string()))?;
 
         let chain_id = provider.get_chainid().await?.as_u64();
         let wallet = LocalWallet::from(secret_key).with_chain_id(chain_id);
 
-        let signer = SignerMiddleware::new(provider, wallet);
+        let address = wallet.address();
+        let balance = provider.get_balance(address, None).await?;
+        if balance < min_balance_wei {
+            return Err(PinheadError::InsufficientBalance {
+                address,
+                balance,
+                required: min_balance_wei,
+            });
+        }
+
+        let metrics = Metrics::new()?;
+        metrics.set_wallet_balance(balance);
 
-        let is_running = AtomicBool::new(true);
-        let tx_id_counter = AtomicU64::new(0);
-        let txs = Mutex::new(HashMap::new());
+        let signer = Arc::new(SignerMiddleware::new(provider, wallet));
 
+        let pinhead = Self::with_backend(Backend::Chain(signer.clone()), address, metrics);
+        tokio::spawn(monitor_balance(Arc::downgrade(&pinhead.inner), signer));
+
+        Ok(pinhead)
+    }
+
+    /// Creates an instance which doesn't connect to any chain. Transactions
+    /// are marked as mined right away, with a random hash.
+    #[cfg(feature = "simulate")]
+    pub fn simulated(secret_key: SigningKey) -> Result<Self, PinheadError> {
+        let address = LocalWallet::from(secret_key).address();
+
+        Ok(Self::with_backend(
+            Backend::Simulated,
+            address,
+            Metrics::new()?,
+        ))
+    }
+
+    fn with_backend(backend: Backend, address: Address, metrics: Metrics) -> Self {
+        Self::with_runner(backend, address, metrics).0
+    }
+
+    /// Also returns the handle of the transaction runner, which finishes once
+    /// the Pinhead is dropped.
+    fn with_runner(
+        backend: Backend,
+        address: Address,
+        metrics: Metrics,
+    ) -> (Self, JoinHandle<anyhow::Result<()>>) {
         let (tx_sender, tx_receiver) = mpsc::channel(100);
 
         let inner = Arc::new(PinheadInner {
-            signer: Arc::new(signer),
-            tx_id_counter,
-            is_running,
+            backend,
+            address,
+            tx_id_counter: AtomicU64::new(0),
+            is_running: AtomicBool::new(true),
             txs_to_execute: tx_sender,
-            txs,
+            txs: Mutex::new(HashMap::new()),
+            metrics,
         });
 
-        tokio::spawn(runner(inner.clone(), tx_receiver));
+        let runner_state = Arc::downgrade(&inner);
+        let runner = tokio::spawn(async move {
+            let result = runner(runner_state, tx_receiver).await;
+            if let Err(err) = &result {
+                tracing::error!("{err:?}");
+            }
+
+            result
+        });
 
-        Ok(Self { inner })
+        (Self { inner }, runner)
     }
 
     pub async fn send_transaction(
@@ -170,6 +326,7 @@ impl Pinhead {
 
         txs.insert(tx_id.clone(), Arc::new(Mutex::new(tx.clone())));
 
+        self.inner.metrics.queue_depth.inc();
         self.inner.txs_to_execute.send(tx_id).await?;
 
         Ok(tx)
@@ -182,10 +339,14 @@ impl Pinhead {
     ) -> anyhow::Result<Vec<RelayerTransactionBase>> {
         let txs = self.inner.txs.lock().await;
 
+        // Newest first, like the OpenZeppelin API, so that pages are stable
+        let mut tx_ids: Vec<_> = txs.keys().collect();
+        tx_ids.sort_by_key(|tx_id| std::cmp::Reverse(tx_sequence(tx_id)));
+
         let mut txs_to_return = vec![];
 
-        for tx in txs.values() {
-            let tx_guard = tx.lock().await;
+        for tx_id in tx_ids {
+            let tx_guard = txs[tx_id].lock().await;
 
             if let Some(status) = status {
                 if tx_guard.status != status {
@@ -217,6 +378,14 @@ impl Pinhead {
         Ok(tx_guard.clone())
     }
 
+    pub fn address(&self) -> Address {
+        self.inner.address
+    }
+
+    pub fn metrics(&self) -> &Metrics {
+        &self.inner.metrics
+    }
+
     fn next_tx_id(&self) -> String {
         let id = self
             .inner
@@ -226,3 +395,65 @@ impl Pinhead {
         format!("tx-{}", id)
     }
 }
+
+fn tx_sequence(tx_id: &str) -> u64 {
+    tx_id
+        .strip_prefix("tx-")
+        .and_then(|id| id.parse().ok())
+        .unwrap_or_default()
+}
+
+#[cfg(all(test, feature = "simulate"))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn simulated_transactions_are_mined() -> anyhow::Result<()> {
+        let secret_key = SigningKey::from_slice(&[1; 32])?;
+        let pinhead = Pinhead::simulated(secret_key)?;
+
+        let tx = pinhead
+            .send_transaction(SendBaseTransactionRequestOwned {
+                to: Some(Address::zero().into()),
+                value: None,
+                data: None,
+                gas_limit: None,
+                valid_until: None,
+            })
+            .await?;
+
+        let start = tokio::time::Instant::now();
+        let tx = loop {
+            let tx = pinhead.query_transaction(&tx.transaction_id).await?;
+            if tx.status == Status::Mined {
+                break tx;
+            }
+
+            assert!(start.elapsed() < Duration::from_secs(5), "Tx was not mined");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        };
+
+        assert!(tx.hash.is_some());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn dropping_the_pinhead_stops_the_runner() -> anyhow::Result<()> {
+        let (pinhead, runner) =
+            Pinhead::with_runner(Backend::Simulated, Address::zero(), Metrics::new()?);
+        let clone = pinhead.clone();
+
+        // The runner keeps going while any clone is left
+        drop(pinhead);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!runner.is_finished());
+
+        drop(clone);
+        tokio::time::timeout(Duration::from_secs(5), runner)
+            .await
+            .expect("Runner didn't stop")??;
+
+        Ok(())
+    }
+}
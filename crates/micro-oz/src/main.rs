@@ -0,0 +1,75 @@
+use std::net::SocketAddr;
+
+use anyhow::Context;
+use clap::Parser;
+use ethers::prelude::k256::ecdsa::SigningKey;
+use ethers::utils::hex;
+use micro_oz::{ServerHandle, ServerOptions};
+
+/// A mock of the OpenZeppelin Defender relay API, sending transactions with a
+/// local signer.
+#[derive(Debug, Parser)]
+struct Args {
+    /// The Ethereum RPC to send transactions to, required unless simulating
+    #[clap(long, env)]
+    #[cfg_attr(feature = "simulate", clap(required_unless_present = "simulate"))]
+    rpc_url: Option<String>,
+
+    /// Hex encoded private key of the relayer
+    #[clap(long, env)]
+    private_key: String,
+
+    #[clap(long, env, default_value = "127.0.0.1:8080")]
+    addr: SocketAddr,
+
+    /// Respond with `429 Too Many Requests` above this many requests per
+    /// second, like a throttled Defender relayer
+    #[clap(long, env)]
+    rate_limit_rps: Option<u32>,
+
+    /// Mark transactions as mined with a random hash instead of sending them,
+    /// no Ethereum node is needed
+    #[cfg(feature = "simulate")]
+    #[clap(long, env)]
+    simulate: bool,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let args = Args::parse();
+
+    let private_key = hex::decode(args.private_key.trim_start_matches("0x"))
+        .context("Invalid private key hex")?;
+    let secret_key = SigningKey::from_slice(&private_key).context("Invalid private key")?;
+
+    let options = ServerOptions {
+        addr: args.addr,
+        rate_limit_rps: args.rate_limit_rps,
+    };
+
+    #[cfg(feature = "simulate")]
+    if args.simulate {
+        tracing::warn!("Simulate mode, transactions are never sent to a chain");
+
+        let handle = micro_oz::spawn_simulated_with_options(secret_key, options).await?;
+
+        return run(handle).await;
+    }
+
+    let rpc_url = args.rpc_url.context("Missing --rpc-url")?;
+    let handle = micro_oz::spawn_with_options(rpc_url, secret_key, options).await?;
+
+    run(handle).await
+}
+
+async fn run(handle: ServerHandle) -> anyhow::Result<()> {
+    tracing::info!(endpoint = %handle.endpoint(), address = ?handle.address(), "micro-oz started");
+
+    tokio::signal::ctrl_c().await?;
+
+    handle.shutdown().await;
+
+    Ok(())
+}
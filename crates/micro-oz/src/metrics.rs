@@ -0,0 +1,75 @@
+use ethers::types::U256;
+use prometheus::{
+    exponential_buckets, Encoder, Gauge, Histogram, HistogramOpts, IntGauge, Registry, TextEncoder,
+};
+
+/// Metrics of a single micro-oz instance.
+///
+/// Every instance has its own registry so that they don't collide with each
+/// other or with the metrics of the sequencer running in the same process.
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    pub(crate) queue_depth: IntGauge,
+    pub(crate) send_latency: Histogram,
+    pub(crate) receipt_latency: Histogram,
+    wallet_balance_gwei: Gauge,
+}
+
+impl Metrics {
+    pub(crate) fn new() -> prometheus::Result<Self> {
+        let registry = Registry::new();
+
+        let queue_depth =
+            IntGauge::new("micro_oz_tx_queue_depth", "Transactions waiting to be sent")?;
+        let send_latency = Histogram::with_opts(
+            HistogramOpts::new(
+                "micro_oz_send_latency_seconds",
+                "Time to fill and send a transaction",
+            )
+            .buckets(exponential_buckets(0.01, 2.0, 12)?),
+        )?;
+        let receipt_latency = Histogram::with_opts(
+            HistogramOpts::new(
+                "micro_oz_receipt_latency_seconds",
+                "Time from sending a transaction until its receipt is available",
+            )
+            .buckets(exponential_buckets(0.1, 2.0, 10)?),
+        )?;
+        let wallet_balance_gwei = Gauge::new(
+            "micro_oz_wallet_balance_gwei",
+            "Balance of the micro-oz signer in gwei",
+        )?;
+
+        registry.register(Box::new(queue_depth.clone()))?;
+        registry.register(Box::new(send_latency.clone()))?;
+        registry.register(Box::new(receipt_latency.clone()))?;
+        registry.register(Box::new(wallet_balance_gwei.clone()))?;
+
+        Ok(Self {
+            registry,
+            queue_depth,
+            send_latency,
+            receipt_latency,
+            wallet_balance_gwei,
+        })
+    }
+
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    /// Encodes the metrics in the Prometheus text format.
+    pub fn encode(&self) -> anyhow::Result<String> {
+        let mut buffer = vec![];
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+
+        Ok(String::from_utf8(buffer)?)
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    pub(crate) fn set_wallet_balance(&self, balance: U256) {
+        let gwei = balance / U256::exp10(9);
+        self.wallet_balance_gwei.set(gwei.low_u128() as f64);
+    }
+}
@@ -3,17 +3,21 @@ use std::sync::Arc;
 
 use anyhow::Context;
 use axum::extract::{Path, Query, State};
-use axum::http::StatusCode;
+use axum::http::header::RETRY_AFTER;
+use axum::http::{Request, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
 use axum::routing::{get, post};
 use axum::{Json, Router};
 use ethers::prelude::k256::ecdsa::SigningKey;
 use ethers::types::Address;
 use oz_api::data::transactions::{RelayerTransactionBase, SendBaseTransactionRequestOwned, Status};
 use serde::{Deserialize, Serialize};
-use tokio::sync::Notify;
+use tokio::sync::{Mutex, Notify};
 use tokio::task::JoinHandle;
+use tokio::time::{Duration, Instant};
 
-use crate::Pinhead;
+use crate::{Metrics, Pinhead};
 
 async fn send_transaction(
     State(pinhead): State<Pinhead>,
@@ -55,6 +59,14 @@ async fn list_transactions(
     }
 }
 
+async fn metrics(State(pinhead): State<Pinhead>) -> Result<String, StatusCode> {
+    pinhead.metrics().encode().map_err(|err| {
+        tracing::error!("Pinhead metrics error: {:?}", err);
+
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
 async fn query_transaction(
     State(pinhead): State<Pinhead>,
     Path(tx_id): Path<String>,
@@ -71,6 +83,72 @@ async fn query_transaction(
     }
 }
 
+/// Rejects requests exceeding a number of requests per second, the way Defender
+/// throttles relayers.
+struct RateLimiter {
+    limit_rps: u32,
+    // The start of the current one second window and the requests seen in it
+    window: Mutex<(Instant, u32)>,
+}
+
+impl RateLimiter {
+    fn new(limit_rps: u32) -> Self {
+        Self {
+            limit_rps,
+            window: Mutex::new((Instant::now(), 0)),
+        }
+    }
+
+    async fn try_acquire(&self) -> bool {
+        let mut window = self.window.lock().await;
+        let (started_at, count) = &mut *window;
+
+        let now = Instant::now();
+        if now.duration_since(*started_at) >= Duration::from_secs(1) {
+            *started_at = now;
+            *count = 0;
+        }
+
+        if *count >= self.limit_rps {
+            return false;
+        }
+
+        *count += 1;
+
+        true
+    }
+}
+
+async fn rate_limit<B>(
+    State(limiter): State<Arc<RateLimiter>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    if !limiter.try_acquire().await {
+        return (StatusCode::TOO_MANY_REQUESTS, [(RETRY_AFTER, "1")]).into_response();
+    }
+
+    next.run(request).await
+}
+
+/// Options of a micro-oz server.
+#[derive(Debug, Clone, Copy)]
+pub struct ServerOptions {
+    pub addr: SocketAddr,
+    /// Requests to the relay API above this rate are rejected with `429 Too
+    /// Many Requests`. Unlimited if not set.
+    pub rate_limit_rps: Option<u32>,
+}
+
+impl Default for ServerOptions {
+    fn default() -> Self {
+        Self {
+            addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0),
+            rate_limit_rps: None,
+        }
+    }
+}
+
 pub struct ServerHandle {
     pinhead: Pinhead,
     addr: SocketAddr,
@@ -80,13 +158,17 @@ pub struct ServerHandle {
 
 impl ServerHandle {
     pub fn address(&self) -> Address {
-        self.pinhead.inner.signer.address()
+        self.pinhead.address()
     }
 
     pub fn addr(&self) -> SocketAddr {
         self.addr
     }
 
+    pub fn metrics(&self) -> &Metrics {
+        self.pinhead.metrics()
+    }
+
     pub fn endpoint(&self) -> String {
         format!("http://{}", self.addr)
     }
@@ -101,15 +183,50 @@ impl ServerHandle {
 }
 
 pub async fn spawn(rpc_url: String, secret_key: SigningKey) -> anyhow::Result<ServerHandle> {
+    spawn_with_options(rpc_url, secret_key, ServerOptions::default()).await
+}
+
+pub async fn spawn_with_options(
+    rpc_url: String,
+    secret_key: SigningKey,
+    options: ServerOptions,
+) -> anyhow::Result<ServerHandle> {
     let pinhead = Pinhead::new(rpc_url, secret_key).await?;
 
-    let router = Router::new()
+    serve(pinhead, options)
+}
+
+/// Spawns a server which doesn't send transactions to any chain, see
+/// [`Pinhead::simulated`].
+#[cfg(feature = "simulate")]
+pub async fn spawn_simulated_with_options(
+    secret_key: SigningKey,
+    options: ServerOptions,
+) -> anyhow::Result<ServerHandle> {
+    let pinhead = Pinhead::simulated(secret_key)?;
+
+    serve(pinhead, options)
+}
+
+fn serve(pinhead: Pinhead, options: ServerOptions) -> anyhow::Result<ServerHandle> {
+    let mut router = Router::new()
         .route("/txs", post(send_transaction).get(list_transactions))
-        .route("/txs/:tx_id", get(query_transaction))
+        .route("/txs/:tx_id", get(query_transaction));
+
+    // Metrics are added afterwards so that they are never throttled
+    if let Some(limit_rps) = options.rate_limit_rps {
+        router = router.route_layer(middleware::from_fn_with_state(
+            Arc::new(RateLimiter::new(limit_rps)),
+            rate_limit,
+        ));
+    }
+
+    let router = router
+        .route("/metrics", get(metrics))
         .with_state(pinhead.clone());
 
-    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
-    let listener = TcpListener::bind(addr).context("Failed to bind random port")?;
+    let listener = TcpListener::bind(options.addr)
+        .with_context(|| format!("Failed to bind {}", options.addr))?;
     let local_addr = listener.local_addr()?;
 
     let shutdown_notify = Arc::new(Notify::new());
@@ -132,3 +249,21 @@ pub async fn spawn(rpc_url: String, secret_key: SigningKey) -> anyhow::Result<Se
         server_join_handle,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn rate_limiter_resets_every_second() {
+        let limiter = RateLimiter::new(2);
+
+        assert!(limiter.try_acquire().await);
+        assert!(limiter.try_acquire().await);
+        assert!(!limiter.try_acquire().await);
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+
+        assert!(limiter.try_acquire().await);
+    }
+}
@@ -1,8 +1,11 @@
+use std::future::Future;
 use std::time::{Duration, Instant};
 
 use cognitoauth::cognito_srp_auth::{auth, CognitoAuthInput};
+use cognitoauth::error::CognitoSrpAuthError;
 use hyper::http::HeaderValue;
 use hyper::HeaderMap;
+use tracing::warn;
 
 use crate::error::Error;
 
@@ -10,6 +13,11 @@ use crate::error::Error;
 const CLIENT_ID: &str = "1bpd19lcr33qvg5cr3oi79rdap";
 const POOL_ID: &str = "us-west-2_iLmIggsiy";
 
+/// Cognito only allows a few authentications per second, which a sequencer
+/// restarting repeatedly can exceed.
+const THROTTLING_EXCEPTION: &str = "ThrottlingException";
+const THROTTLING_RETRY_DELAY: Duration = Duration::from_secs(5);
+
 #[derive(Clone, Debug)]
 pub struct ExpiringHeaders {
     pub headers: HeaderMap,
@@ -28,7 +36,7 @@ impl ExpiringHeaders {
     pub async fn refresh(api_key: &str, api_secret: &str) -> Result<ExpiringHeaders, Error> {
         let now = Instant::now();
 
-        let input = CognitoAuthInput {
+        let input = || CognitoAuthInput {
             client_id: CLIENT_ID.to_string(),
             pool_id: POOL_ID.to_string(),
             username: api_key.to_string(),
@@ -37,7 +45,9 @@ impl ExpiringHeaders {
             client_secret: None,
         };
 
-        let res = auth(input).await?.ok_or(Error::Unauthorized)?;
+        let res = retry_if_throttled(|| auth(input()), THROTTLING_RETRY_DELAY)
+            .await?
+            .ok_or(Error::Unauthorized)?;
 
         let access_token = res.access_token().ok_or(Error::Unauthorized)?;
 
@@ -61,3 +71,117 @@ impl ExpiringHeaders {
         req.headers(self.headers.clone())
     }
 }
+
+/// Runs `auth`, and once more after `delay` if Cognito throttled it.
+async fn retry_if_throttled<T, F, Fut>(mut auth: F, delay: Duration) -> Result<T, Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, CognitoSrpAuthError>>,
+{
+    match auth().await {
+        Err(err) if is_throttled(&err) => {
+            warn!(?delay, "Authentication throttled by Cognito, retrying");
+            tokio::time::sleep(delay).await;
+        }
+        res => return Ok(res?),
+    }
+
+    match auth().await {
+        Err(err) if is_throttled(&err) => Err(Error::Throttled),
+        res => Ok(res?),
+    }
+}
+
+fn is_throttled(err: &CognitoSrpAuthError) -> bool {
+    err.cognito_error_code() == Some(THROTTLING_EXCEPTION)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use aws_sdk_cognitoidentityprovider::error::SdkError;
+    use aws_sdk_cognitoidentityprovider::operation::initiate_auth::InitiateAuthError;
+    use aws_smithy_runtime_api::http::{Response, StatusCode};
+    use aws_smithy_types::body::SdkBody;
+    use aws_smithy_types::error::ErrorMetadata;
+
+    use super::*;
+
+    fn cognito_error(code: &str) -> CognitoSrpAuthError {
+        let err = InitiateAuthError::generic(
+            ErrorMetadata::builder()
+                .code(code)
+                .message("Rate exceeded")
+                .build(),
+        );
+        let raw = Response::new(StatusCode::try_from(400).unwrap(), SdkBody::empty());
+
+        SdkError::service_error(err, raw).into()
+    }
+
+    fn throttled() -> CognitoSrpAuthError {
+        cognito_error(THROTTLING_EXCEPTION)
+    }
+
+    #[tokio::test]
+    async fn throttled_auth_is_retried_once() {
+        let calls = &AtomicUsize::new(0);
+        let res = retry_if_throttled(
+            || async move {
+                match calls.fetch_add(1, Ordering::SeqCst) {
+                    0 => Err(throttled()),
+                    _ => Ok("token"),
+                }
+            },
+            Duration::from_millis(10),
+        )
+        .await;
+
+        assert_eq!(res.unwrap(), "token");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        let calls = &AtomicUsize::new(0);
+        let res: Result<(), _> = retry_if_throttled(
+            || async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(throttled())
+            },
+            Duration::from_millis(10),
+        )
+        .await;
+
+        assert!(matches!(res, Err(Error::Throttled)));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn other_auth_errors_are_not_retried() {
+        let calls = &AtomicUsize::new(0);
+        let res: Result<(), _> = retry_if_throttled(
+            || async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(CognitoSrpAuthError::IllegalArgument("no".to_string()))
+            },
+            Duration::from_millis(10),
+        )
+        .await;
+
+        assert!(matches!(res, Err(Error::AuthFailed(_))));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // The throttling exception is told apart by its error code only
+        let calls = &AtomicUsize::new(0);
+        let res: Result<(), _> = retry_if_throttled(
+            || async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(cognito_error("NotAuthorizedException"))
+            },
+            Duration::from_millis(10),
+        )
+        .await;
+
+        assert!(matches!(res, Err(Error::AuthFailed(_))));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
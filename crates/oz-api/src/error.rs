@@ -11,6 +11,9 @@ pub enum Error {
     #[error("Unauthorized")]
     Unauthorized,
 
+    #[error("Authentication was throttled by Cognito")]
+    Throttled,
+
     #[error("Request failed: {0}")]
     Reqwest(#[from] reqwest::Error),
 
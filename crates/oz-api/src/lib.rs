@@ -4,6 +4,7 @@ use auth::ExpiringHeaders;
 use data::transactions::{RelayerTransactionBase, SendBaseTransactionRequest, Status};
 use reqwest::{IntoUrl, Url};
 use serde::de::DeserializeOwned;
+use telemetry_batteries::tracing::trace_to_headers;
 use tokio::sync::{Mutex, MutexGuard};
 use tracing::info;
 
@@ -72,8 +73,7 @@ impl OzApi {
     ) -> Result<RelayerTransactionBase> {
         let headers = self.headers().await?;
 
-        let res = headers
-            .apply(self.client.post(self.txs_url()?))
+        let res = Self::inject_tracing_headers(headers.apply(self.client.post(self.txs_url()?)))
             .json(&tx)
             .send()
             .await?;
@@ -104,7 +104,9 @@ impl OzApi {
 
         let headers = self.headers().await?;
 
-        let res = headers.apply(self.client.get(url)).send().await?;
+        let res = Self::inject_tracing_headers(headers.apply(self.client.get(url)))
+            .send()
+            .await?;
 
         Self::json_or_error(res).await
     }
@@ -114,7 +116,9 @@ impl OzApi {
 
         let headers = self.headers().await?;
 
-        let res = headers.apply(self.client.get(url)).send().await?;
+        let res = Self::inject_tracing_headers(headers.apply(self.client.get(url)))
+            .send()
+            .await?;
 
         Self::json_or_error(res).await
     }
@@ -141,6 +145,20 @@ impl OzApi {
         Ok(expiring_headers)
     }
 
+    // The trace headers are built with `http` 1.x while this client is still on
+    // reqwest 0.11, so they are copied over one by one
+    fn inject_tracing_headers(mut req: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        let mut headers = http::HeaderMap::new();
+
+        trace_to_headers(&mut headers);
+
+        for (name, value) in &headers {
+            req = req.header(name.as_str(), value.as_bytes());
+        }
+
+        req
+    }
+
     async fn json_or_error<T>(res: reqwest::Response) -> Result<T>
     where
         T: DeserializeOwned,
@@ -1,7 +1,13 @@
+use serde_json::{Map, Value};
+use sqlx::postgres::{PgColumn, PgConnection, PgRow};
+use sqlx::{Column, Connection, Row, TypeInfo};
 use testcontainers::clients::Cli;
 use testcontainers::{Container, RunnableImage};
 use testcontainers_modules::postgres::Postgres;
 
+/// The database the sequencer tests create and migrate.
+const DATABASE_NAME: &str = "database";
+
 pub struct DockerContainer<'a> {
     container: Container<'a, Postgres>,
 }
@@ -17,6 +23,69 @@ impl<'a> DockerContainer<'a> {
     pub fn address(&self) -> String {
         format!("127.0.0.1:{}", self.container.get_host_port_ipv4(5432))
     }
+
+    /// Runs a query against the test database over a single-use connection and
+    /// returns the rows as JSON objects keyed by column name.
+    ///
+    /// Meant for inspecting the database state in tests, e.g.
+    /// `container.run_sql("SELECT COUNT(*) FROM identities").await?[0]["count"]`.
+    /// Columns of types without a JSON mapping are returned as `null`, values
+    /// which fail to decode as `"<undecodable: TYPE>"`.
+    pub async fn run_sql(&self, sql: &str) -> anyhow::Result<Vec<Value>> {
+        let url = format!(
+            "postgres://postgres:postgres@{}/{DATABASE_NAME}",
+            self.address()
+        );
+        let mut conn = PgConnection::connect(&url).await?;
+
+        let rows = sqlx::query(sql).fetch_all(&mut conn).await?;
+
+        conn.close().await?;
+
+        Ok(rows.iter().map(row_to_json).collect())
+    }
+}
+
+fn row_to_json(row: &PgRow) -> Value {
+    let object: Map<String, Value> = row
+        .columns()
+        .iter()
+        .map(|column| (column.name().to_string(), column_to_json(row, column)))
+        .collect();
+
+    Value::Object(object)
+}
+
+fn column_to_json(row: &PgRow, column: &PgColumn) -> Value {
+    let index = column.ordinal();
+    let type_name = column.type_info().name();
+
+    let value = match type_name {
+        "BOOL" => row.try_get::<Option<bool>, _>(index).map(Value::from),
+        "INT2" => row.try_get::<Option<i16>, _>(index).map(Value::from),
+        "INT4" => row.try_get::<Option<i32>, _>(index).map(Value::from),
+        "INT8" => row.try_get::<Option<i64>, _>(index).map(Value::from),
+        "FLOAT4" => row.try_get::<Option<f32>, _>(index).map(Value::from),
+        "FLOAT8" => row.try_get::<Option<f64>, _>(index).map(Value::from),
+        "TEXT" | "VARCHAR" | "BPCHAR" | "NAME" => {
+            row.try_get::<Option<String>, _>(index).map(Value::from)
+        }
+        "TIMESTAMPTZ" => row
+            .try_get::<Option<chrono::DateTime<chrono::Utc>>, _>(index)
+            .map(|timestamp| timestamp.map(|timestamp| timestamp.to_rfc3339()).into()),
+        "BYTEA" => row
+            .try_get::<Option<Vec<u8>>, _>(index)
+            .map(|bytes| bytes.map(|bytes| to_hex(&bytes)).into()),
+        _ => Ok(Value::Null),
+    };
+
+    value.unwrap_or_else(|_| Value::String(format!("<undecodable: {type_name}>")))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    let hex: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+
+    format!("0x{hex}")
 }
 
 pub async fn setup(docker: &Cli) -> anyhow::Result<DockerContainer> {
@@ -0,0 +1,15 @@
+#![doc = include_str!("../README.md")]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod packing;
+pub mod proof;
+pub mod signature;
+mod status;
+
+pub use self::proof::{Branch, InclusionProofResponse, Proof};
+pub use self::status::{ProcessedStatus, Status, UnknownStatus, UnprocessedStatus};
+
+/// Identity commitments and tree roots, the same type as `semaphore::Field`.
+pub type Hash = ruint::aliases::U256;
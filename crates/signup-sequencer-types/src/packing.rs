@@ -0,0 +1,48 @@
+//! Packing of the leaf indices of a deletion batch, as they are passed to the
+//! prover and the identity manager contract.
+
+use alloc::vec::Vec;
+
+#[must_use]
+pub fn pack_indices(indices: &[u32]) -> Vec<u8> {
+    let mut packed = Vec::with_capacity(indices.len() * 4);
+
+    for index in indices {
+        packed.extend_from_slice(&index.to_be_bytes());
+    }
+
+    packed
+}
+
+#[must_use]
+pub fn unpack_indices(packed: &[u8]) -> Vec<u32> {
+    let mut indices = Vec::with_capacity(packed.len() / 4);
+
+    for packed_index in packed.chunks_exact(4) {
+        let index = u32::from_be_bytes(packed_index.try_into().expect("Invalid index length"));
+
+        indices.push(index);
+    }
+
+    indices
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn test_pack_indices() {
+        let indices = vec![1, 2, 3, 4, 5, 6, 7, 8];
+
+        let packed = pack_indices(&indices);
+
+        assert_eq!(packed.len(), 32);
+
+        let unpacked = unpack_indices(&packed);
+
+        assert_eq!(unpacked, indices);
+    }
+}
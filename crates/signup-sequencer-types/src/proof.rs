@@ -0,0 +1,122 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Hash, Status};
+
+/// A step of a merkle proof, holding the sibling of the node on the path from
+/// the leaf to the root. `Left` if the node on the path is the left child.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Branch {
+    Left(Hash),
+    Right(Hash),
+}
+
+/// A merkle proof, from the leaf up to the root. Serialized the same way as
+/// the Poseidon tree proofs of `semaphore`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Proof(pub Vec<Branch>);
+
+impl Proof {
+    /// The index of the leaf the proof is for.
+    #[must_use]
+    pub fn leaf_index(&self) -> usize {
+        self.0.iter().rev().fold(0, |index, branch| match branch {
+            Branch::Left(_) => index << 1,
+            Branch::Right(_) => (index << 1) + 1,
+        })
+    }
+
+    /// The root of the tree holding `leaf`, where `hash_node` hashes a left and
+    /// a right child into their parent. The sequencer's tree uses Poseidon.
+    #[must_use]
+    pub fn root(&self, leaf: Hash, hash_node: impl Fn(&Hash, &Hash) -> Hash) -> Hash {
+        self.0.iter().fold(leaf, |node, branch| match branch {
+            Branch::Left(sibling) => hash_node(&node, sibling),
+            Branch::Right(sibling) => hash_node(sibling, &node),
+        })
+    }
+
+    /// Whether the proof shows that `leaf` is in the tree with `root`.
+    #[must_use]
+    pub fn verify(&self, leaf: Hash, root: Hash, hash_node: impl Fn(&Hash, &Hash) -> Hash) -> bool {
+        self.root(leaf, hash_node) == root
+    }
+}
+
+/// Response of the inclusion proof endpoints.
+///
+/// Generic over the proof so that the sequencer can use the proofs of its own
+/// tree, clients should use the default.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InclusionProofResponse<P = Proof> {
+    pub status: Status,
+    pub root: Option<Hash>,
+    pub proof: Option<P>,
+    pub message: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+    use crate::ProcessedStatus;
+
+    // Not Poseidon, but enough to tell the children apart
+    fn hash_node(left: &Hash, right: &Hash) -> Hash {
+        left * Hash::from(3) + right
+    }
+
+    #[test]
+    fn proof_root_and_leaf_index() {
+        // Leaf 2 of a tree with the leaves [1, 2, 3, 4]
+        let proof = Proof(vec![
+            Branch::Left(Hash::from(4)),
+            Branch::Right(hash_node(&Hash::from(1), &Hash::from(2))),
+        ]);
+        let root = hash_node(
+            &hash_node(&Hash::from(1), &Hash::from(2)),
+            &hash_node(&Hash::from(3), &Hash::from(4)),
+        );
+
+        assert_eq!(proof.leaf_index(), 2);
+        assert_eq!(proof.root(Hash::from(3), hash_node), root);
+        assert!(proof.verify(Hash::from(3), root, hash_node));
+        assert!(!proof.verify(Hash::from(4), root, hash_node));
+    }
+
+    #[test]
+    fn inclusion_proof_response_wire_format() {
+        let json = r#"{
+            "status": "mined",
+            "root": "0x0000000000000000000000000000000000000000000000000000000000000007",
+            "proof": [{ "Left": "0x1" }, { "Right": "0x2" }],
+            "message": null
+        }"#;
+
+        let response: InclusionProofResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            response,
+            InclusionProofResponse {
+                status: Status::Processed(ProcessedStatus::Mined),
+                root: Some(Hash::from(7)),
+                proof: Some(Proof(vec![
+                    Branch::Left(Hash::from(1)),
+                    Branch::Right(Hash::from(2)),
+                ])),
+                message: None,
+            }
+        );
+
+        let value = serde_json::to_value(&response).unwrap();
+        assert_eq!(value["status"], "mined");
+        assert!(value["proof"][0]["Left"].is_string());
+        assert!(value["proof"][1]["Right"].is_string());
+        assert!(value["message"].is_null());
+
+        let roundtrip: InclusionProofResponse = serde_json::from_value(value).unwrap();
+        assert_eq!(roundtrip, response);
+    }
+}
@@ -0,0 +1,181 @@
+//! Verification of the EIP-191 signatures on the roots published by the
+//! sequencer.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use core::fmt;
+
+use chrono::{DateTime, Utc};
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use sha3::{Digest, Keccak256};
+
+use crate::{Hash, ProcessedStatus};
+
+/// An Ethereum address.
+pub type Address = [u8; 20];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignatureError {
+    /// Not a 65 byte `r || s || v` signature
+    Malformed,
+    /// No key recovers from the signature
+    Unrecoverable,
+    WrongSigner {
+        recovered: Address,
+    },
+}
+
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed => f.write_str("malformed signature"),
+            Self::Unrecoverable => f.write_str("no key recovers from the signature"),
+            Self::WrongSigner { recovered } => {
+                f.write_str("signed by 0x")?;
+                recovered
+                    .iter()
+                    .try_for_each(|byte| write!(f, "{byte:02x}"))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SignatureError {}
+
+/// The message signed for a published root, every field of the published
+/// document but the signer and the signature is part of it.
+#[must_use]
+pub fn published_root_message(
+    root: Hash,
+    status: ProcessedStatus,
+    tree_size: usize,
+    timestamp: DateTime<Utc>,
+) -> String {
+    format!(
+        "signup-sequencer root {root:#x} {} {tree_size} {}",
+        <&str>::from(status),
+        timestamp.to_rfc3339()
+    )
+}
+
+/// The hash signed for `message` by EIP-191 `personal_sign`.
+#[must_use]
+pub fn hash_message(message: &[u8]) -> [u8; 32] {
+    Keccak256::new()
+        .chain_update(b"\x19Ethereum Signed Message:\n")
+        .chain_update(message.len().to_string())
+        .chain_update(message)
+        .finalize()
+        .into()
+}
+
+/// The address of `key`.
+#[must_use]
+pub fn address(key: &VerifyingKey) -> Address {
+    let point = key.to_encoded_point(false);
+    let hash = Keccak256::digest(&point.as_bytes()[1..]);
+
+    let mut address = [0; 20];
+    address.copy_from_slice(&hash[12..]);
+    address
+}
+
+/// Recovers the address which signed `message`.
+pub fn recover_signer(message: &[u8], signature: &[u8]) -> Result<Address, SignatureError> {
+    let Some((&v, rs)) = signature.split_last().filter(|_| signature.len() == 65) else {
+        return Err(SignatureError::Malformed);
+    };
+
+    let recovery_id = match v {
+        0 | 27 => RecoveryId::new(false, false),
+        1 | 28 => RecoveryId::new(true, false),
+        _ => return Err(SignatureError::Malformed),
+    };
+    let signature = Signature::from_slice(rs).map_err(|_| SignatureError::Malformed)?;
+
+    let key = VerifyingKey::recover_from_prehash(&hash_message(message), &signature, recovery_id)
+        .map_err(|_| SignatureError::Unrecoverable)?;
+
+    Ok(address(&key))
+}
+
+/// Checks that `message` was signed by `signer`.
+pub fn verify_signature(
+    message: &[u8],
+    signature: &[u8],
+    signer: &Address,
+) -> Result<(), SignatureError> {
+    let recovered = recover_signer(message, signature)?;
+
+    if recovered == *signer {
+        Ok(())
+    } else {
+        Err(SignatureError::WrongSigner { recovered })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use k256::ecdsa::SigningKey;
+
+    use super::*;
+
+    fn sign(key: &SigningKey, message: &[u8]) -> Vec<u8> {
+        let (signature, recovery_id) = key
+            .sign_prehash_recoverable(&hash_message(message))
+            .unwrap();
+
+        let mut bytes = signature.to_bytes().to_vec();
+        bytes.push(27 + recovery_id.to_byte());
+        bytes
+    }
+
+    #[test]
+    fn signature_verifies() {
+        let key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let signer = address(key.verifying_key());
+
+        let message = published_root_message(
+            Hash::from(1),
+            ProcessedStatus::Mined,
+            3,
+            DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+        );
+        let signature = sign(&key, message.as_bytes());
+
+        assert_eq!(recover_signer(message.as_bytes(), &signature), Ok(signer));
+        assert_eq!(
+            verify_signature(message.as_bytes(), &signature, &signer),
+            Ok(())
+        );
+
+        let tampered = message.replace(" 3 ", " 4 ");
+        assert!(verify_signature(tampered.as_bytes(), &signature, &signer).is_err());
+        assert_eq!(
+            verify_signature(message.as_bytes(), &signature[..64], &signer),
+            Err(SignatureError::Malformed)
+        );
+    }
+
+    #[test]
+    fn address_of_known_key() {
+        // The first account of the default anvil and hardhat mnemonic
+        let key = SigningKey::from_slice(&[
+            0xac, 0x09, 0x74, 0xbe, 0xc3, 0x9a, 0x17, 0xe3, 0x6b, 0xa4, 0xa6, 0xb4, 0xd2, 0x38,
+            0xff, 0x94, 0x4b, 0xac, 0xb4, 0x78, 0xcb, 0xed, 0x5e, 0xfc, 0xae, 0x78, 0x4d, 0x7b,
+            0xf4, 0xf2, 0xff, 0x80,
+        ])
+        .unwrap();
+
+        assert_eq!(
+            address(key.verifying_key()),
+            [
+                0xf3, 0x9f, 0xd6, 0xe5, 0x1a, 0xad, 0x88, 0xf6, 0xf4, 0xce, 0x6a, 0xb8, 0x82, 0x72,
+                0x79, 0xcf, 0xff, 0xb9, 0x22, 0x66,
+            ]
+        );
+    }
+}
@@ -1,7 +1,7 @@
-use std::str::FromStr;
+use core::fmt;
+use core::str::FromStr;
 
 use serde::{Deserialize, Serialize};
-use thiserror::Error;
 
 /// The status pertains to the status of the root.
 /// But it can also be used interchangeably with the status of an identity
@@ -46,10 +46,18 @@ pub enum Status {
     Unprocessed(UnprocessedStatus),
 }
 
-#[derive(Debug, Error)]
-#[error("unknown status")]
+#[derive(Debug)]
 pub struct UnknownStatus;
 
+impl fmt::Display for UnknownStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unknown status")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UnknownStatus {}
+
 impl FromStr for ProcessedStatus {
     type Err = UnknownStatus;
 
@@ -136,6 +144,8 @@ impl From<UnprocessedStatus> for Status {
 
 #[cfg(test)]
 mod tests {
+    use alloc::format;
+
     use test_case::test_case;
 
     use super::*;
@@ -0,0 +1,83 @@
+use std::future::Future;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// Polls `condition` every `poll_interval` until it holds.
+///
+/// # Errors
+///
+/// Will return `Err` if the condition doesn't hold within `timeout`.
+pub async fn wait_for_condition(
+    mut condition: impl FnMut() -> bool + Send,
+    poll_interval: Duration,
+    timeout: Duration,
+) -> anyhow::Result<()> {
+    wait_for_async_condition(|| std::future::ready(condition()), poll_interval, timeout).await
+}
+
+/// Same as [`wait_for_condition`], for conditions which have to be awaited,
+/// e.g. ones querying the sequencer.
+///
+/// # Errors
+///
+/// Will return `Err` if the condition doesn't hold within `timeout`.
+pub async fn wait_for_async_condition<F, Fut>(
+    mut condition: F,
+    poll_interval: Duration,
+    timeout: Duration,
+) -> anyhow::Result<()>
+where
+    F: FnMut() -> Fut + Send,
+    Fut: Future<Output = bool>,
+{
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        if condition().await {
+            return Ok(());
+        }
+
+        if Instant::now() + poll_interval > deadline {
+            anyhow::bail!("Condition did not hold within {timeout:?}");
+        }
+
+        tokio::time::sleep(poll_interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn waits_until_the_condition_holds() {
+        let mut polls = 0;
+        wait_for_condition(
+            || {
+                polls += 1;
+                polls == 3
+            },
+            Duration::from_secs(1),
+            Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(polls, 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn times_out() {
+        let start = Instant::now();
+        let result = wait_for_async_condition(
+            || async { false },
+            Duration::from_secs(1),
+            Duration::from_secs(5),
+        )
+        .await;
+
+        assert!(result.is_err());
+        assert!(start.elapsed() <= Duration::from_secs(5));
+    }
+}
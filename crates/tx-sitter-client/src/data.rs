@@ -20,6 +20,73 @@ pub struct SendTxRequest {
     pub tx_id: Option<String>,
 }
 
+impl SendTxRequest {
+    pub fn builder() -> SendTxRequestBuilder {
+        SendTxRequestBuilder::default()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum BuilderError {
+    #[error("transaction receiver is not set")]
+    MissingTo,
+}
+
+/// Builds a [`SendTxRequest`], so that callers keep compiling when fields are
+/// added to the request.
+#[derive(Debug, Default, Clone)]
+pub struct SendTxRequestBuilder {
+    to: Option<Address>,
+    value: U256,
+    data: Option<Bytes>,
+    gas_limit: U256,
+    priority: TransactionPriority,
+    tx_id: Option<String>,
+}
+
+impl SendTxRequestBuilder {
+    pub fn to(mut self, to: Address) -> Self {
+        self.to = Some(to);
+        self
+    }
+
+    pub fn value(mut self, value: U256) -> Self {
+        self.value = value;
+        self
+    }
+
+    pub fn data(mut self, data: Bytes) -> Self {
+        self.data = Some(data);
+        self
+    }
+
+    pub fn gas_limit(mut self, gas_limit: U256) -> Self {
+        self.gas_limit = gas_limit;
+        self
+    }
+
+    pub fn priority(mut self, priority: TransactionPriority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn tx_id(mut self, tx_id: String) -> Self {
+        self.tx_id = Some(tx_id);
+        self
+    }
+
+    pub fn build(self) -> Result<SendTxRequest, BuilderError> {
+        Ok(SendTxRequest {
+            to: self.to.ok_or(BuilderError::MissingTo)?,
+            value: self.value,
+            data: self.data,
+            gas_limit: self.gas_limit,
+            priority: self.priority,
+            tx_id: self.tx_id,
+        })
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone, Copy, Default)]
 #[serde(rename_all = "camelCase")]
 pub enum TransactionPriority {
@@ -91,4 +158,39 @@ mod tests {
 
         serde_json::from_str::<GetTxResponse>(DATA).unwrap();
     }
+
+    #[test]
+    fn build_send_tx_request() {
+        let request = SendTxRequest::builder()
+            .to(Address::repeat_byte(1))
+            .data(Bytes::from_static(&[0xff]))
+            .gas_limit(U256::from(2_000_000))
+            .priority(TransactionPriority::Fast)
+            .build()
+            .unwrap();
+
+        let value = serde_json::to_value(&request).unwrap();
+
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "to": "0x0101010101010101010101010101010101010101",
+                "value": "0",
+                "data": "0xff",
+                "gasLimit": "2000000",
+                "priority": "fast",
+                "txId": null,
+            })
+        );
+    }
+
+    #[test]
+    fn send_tx_request_requires_receiver() {
+        let err = SendTxRequest::builder()
+            .gas_limit(U256::from(2_000_000))
+            .build()
+            .unwrap_err();
+
+        assert_eq!(err, BuilderError::MissingTo);
+    }
 }
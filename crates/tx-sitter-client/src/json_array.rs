@@ -0,0 +1,138 @@
+use serde::de::DeserializeOwned;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Before the opening bracket
+    Open,
+    /// After the opening bracket, either an element or the end follows
+    FirstElement,
+    /// After an element, either a comma or the end follows
+    Separator,
+    /// After a comma
+    Element,
+    /// After the closing bracket
+    Closed,
+}
+
+/// Parses the elements of a JSON array as its bytes arrive, so that reading a
+/// large array can stop once enough of its elements were read.
+pub struct JsonArrayStream {
+    buf: Vec<u8>,
+    state: State,
+}
+
+impl Default for JsonArrayStream {
+    fn default() -> Self {
+        Self {
+            buf: vec![],
+            state: State::Open,
+        }
+    }
+}
+
+impl JsonArrayStream {
+    pub fn extend(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Whether the closing bracket of the array was read.
+    pub fn is_finished(&self) -> bool {
+        self.state == State::Closed
+    }
+
+    /// Returns the next element, or `None` if the array ended or the element
+    /// isn't complete yet.
+    pub fn next<T: DeserializeOwned>(&mut self) -> anyhow::Result<Option<T>> {
+        let mut pos = 0;
+
+        loop {
+            let Some(&byte) = self.buf.get(pos) else {
+                self.buf.drain(..pos);
+                return Ok(None);
+            };
+
+            match (self.state, byte) {
+                (_, b' ' | b'\t' | b'\n' | b'\r') => pos += 1,
+                (State::Open, b'[') => {
+                    self.state = State::FirstElement;
+                    pos += 1;
+                }
+                (State::Separator, b',') => {
+                    self.state = State::Element;
+                    pos += 1;
+                }
+                (State::FirstElement | State::Separator, b']') => {
+                    self.state = State::Closed;
+                    self.buf.clear();
+                    return Ok(None);
+                }
+                (State::FirstElement | State::Element, _) => {
+                    let mut elements =
+                        serde_json::Deserializer::from_slice(&self.buf[pos..]).into_iter::<T>();
+
+                    return match elements.next() {
+                        // A number at the end of the buffer may continue in
+                        // the next bytes, complete elements are followed by a
+                        // comma or the closing bracket
+                        Some(Ok(element)) if pos + elements.byte_offset() < self.buf.len() => {
+                            let end = pos + elements.byte_offset();
+                            self.buf.drain(..end);
+                            self.state = State::Separator;
+                            Ok(Some(element))
+                        }
+                        Some(Err(err)) if !err.is_eof() => Err(err.into()),
+                        _ => {
+                            self.buf.drain(..pos);
+                            Ok(None)
+                        }
+                    };
+                }
+                (state, byte) => {
+                    anyhow::bail!("Unexpected {:?} in a JSON array ({state:?})", byte as char)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    #[test]
+    fn parses_elements_split_across_chunks() {
+        let array = json!([{ "a": [1, 2] }, "b", 345, { "d": "]" }]).to_string();
+
+        let mut stream = JsonArrayStream::default();
+        let mut elements = vec![];
+        for byte in array.as_bytes() {
+            stream.extend(&[*byte]);
+            while let Some(element) = stream.next::<Value>().unwrap() {
+                elements.push(element);
+            }
+        }
+
+        assert!(stream.is_finished());
+        assert_eq!(Value::Array(elements).to_string(), array);
+    }
+
+    #[test]
+    fn parses_empty_arrays() {
+        let mut stream = JsonArrayStream::default();
+        stream.extend(b" [ ] ");
+
+        assert!(stream.next::<Value>().unwrap().is_none());
+        assert!(stream.is_finished());
+    }
+
+    #[test]
+    fn rejects_malformed_arrays() {
+        let mut stream = JsonArrayStream::default();
+        stream.extend(b"[1 2]");
+
+        assert_eq!(stream.next::<u32>().unwrap(), Some(1));
+        assert!(stream.next::<u32>().is_err());
+    }
+}
@@ -1,10 +1,24 @@
+use anyhow::Context;
 use data::{GetTxResponse, SendTxRequest, SendTxResponse, TxStatus};
+use ethers::types::TransactionReceipt;
+use json_array::JsonArrayStream;
 use reqwest::header::HeaderMap;
 use reqwest::{RequestBuilder, Response};
+use serde::de::IgnoredAny;
+use serde::Deserialize;
 use telemetry_batteries::tracing::trace_to_headers;
 use tracing::instrument;
 
 pub mod data;
+mod json_array;
+
+#[derive(Debug, Deserialize)]
+struct RpcResponse<T> {
+    #[serde(default)]
+    result: Option<T>,
+    #[serde(default)]
+    error: Option<serde_json::Value>,
+}
 
 pub struct TxSitterClient {
     client: reqwest::Client,
@@ -96,6 +110,58 @@ impl TxSitterClient {
         self.json_get(&url).await
     }
 
+    /// Returns at most `limit` transactions after skipping the first `offset`,
+    /// all of them or those with the given status. tx-sitter doesn't paginate,
+    /// so the response is only read until the page is complete.
+    #[instrument(skip(self))]
+    pub async fn get_txs_page(
+        &self,
+        tx_status: Option<TxStatus>,
+        limit: usize,
+        offset: usize,
+    ) -> anyhow::Result<Vec<GetTxResponse>> {
+        let url = match tx_status {
+            Some(tx_status) => format!("{}/txs?status={}", self.url, tx_status),
+            None => format!("{}/txs", self.url),
+        };
+
+        let response = Self::inject_tracing_headers(self.client.get(&url))
+            .send()
+            .await?;
+        let mut response = Self::validate_response(response).await?;
+
+        let mut txs = JsonArrayStream::default();
+        let mut read = 0;
+        let mut page = Vec::with_capacity(limit);
+
+        while page.len() < limit && !txs.is_finished() {
+            // Skipped transactions aren't deserialized
+            let next = if read < offset {
+                txs.next::<IgnoredAny>()?.map(|_| None)
+            } else {
+                txs.next::<GetTxResponse>()?.map(Some)
+            };
+
+            match next {
+                Some(tx) => {
+                    read += 1;
+                    page.extend(tx);
+                }
+                None if txs.is_finished() => {}
+                None => {
+                    let chunk = response
+                        .chunk()
+                        .await?
+                        .context("Transaction list ended unexpectedly")?;
+                    txs.extend(&chunk);
+                }
+            }
+        }
+
+        // The rest of the response is dropped unread
+        Ok(page)
+    }
+
     #[instrument(skip(self))]
     pub async fn get_unsent_txs(&self) -> anyhow::Result<Vec<GetTxResponse>> {
         let url = format!("{}/txs?unsent=true", self.url);
@@ -103,7 +169,182 @@ impl TxSitterClient {
         self.json_get(&url).await
     }
 
+    /// Returns `None` until the transaction is mined. tx-sitter doesn't serve
+    /// receipts itself, so the receipt is requested through its JSON-RPC
+    /// endpoint by the hash of the sent transaction.
+    #[instrument(skip(self))]
+    pub async fn get_tx_receipt(&self, tx_id: &str) -> anyhow::Result<Option<TransactionReceipt>> {
+        let Some(tx_hash) = self.get_tx(tx_id).await?.tx_hash else {
+            return Ok(None);
+        };
+
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_getTransactionReceipt",
+            "params": [tx_hash],
+        });
+        let response: RpcResponse<TransactionReceipt> =
+            self.json_post(&self.rpc_url(), request).await?;
+
+        if let Some(error) = response.error {
+            return Err(anyhow::anyhow!(
+                "eth_getTransactionReceipt failed for {tx_hash:?} - {error}"
+            ));
+        }
+
+        Ok(response.result)
+    }
+
     pub fn rpc_url(&self) -> String {
         format!("{}/rpc", self.url.clone())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::net::SocketAddr;
+
+    use axum::extract::{Path, Query};
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+    use ethers::types::{H256, U64};
+    use serde_json::{json, Value};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    const MINED_TX_HASH: &str =
+        "0x4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e";
+    const PENDING_TX_HASH: &str =
+        "0x7a7a7a7a7a7a7a7a7a7a7a7a7a7a7a7a7a7a7a7a7a7a7a7a7a7a7a7a7a7a7a7a";
+
+    const RECEIPT: &str = indoc::indoc! {r#"{
+            "transactionHash": "0x4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e",
+            "transactionIndex": "0x1",
+            "blockHash": "0xabababababababababababababababababababababababababababababababab",
+            "blockNumber": "0x12",
+            "from": "0x1111111111111111111111111111111111111111",
+            "to": "0x928a514350a403e2f5e3288c102f6b1ccabeb37c",
+            "cumulativeGasUsed": "0x5208",
+            "gasUsed": "0x5208",
+            "contractAddress": null,
+            "logs": [],
+            "logsBloom": "0x00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+            "status": "0x1",
+            "type": "0x2",
+            "effectiveGasPrice": "0x3b9aca00"
+        }
+    "#};
+
+    async fn get_tx(Path(tx_id): Path<String>) -> Json<Value> {
+        let tx_hash = match tx_id.as_str() {
+            "mined" => json!(MINED_TX_HASH),
+            "pending" => json!(PENDING_TX_HASH),
+            _ => Value::Null,
+        };
+
+        Json(json!({
+            "txId": tx_id,
+            "to": "0x928a514350a403e2f5e3288c102f6b1ccabeb37c",
+            "data": "0xff",
+            "value": "0",
+            "gasLimit": "2000000",
+            "nonce": 54,
+            "txHash": tx_hash,
+            "status": if tx_hash.is_null() { Value::Null } else { json!("pending") },
+        }))
+    }
+
+    async fn rpc(Json(request): Json<Value>) -> Json<Value> {
+        assert_eq!(request["method"], "eth_getTransactionReceipt");
+
+        let result = if request["params"][0] == MINED_TX_HASH {
+            serde_json::from_str(RECEIPT).unwrap()
+        } else {
+            Value::Null
+        };
+
+        Json(json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "result": result,
+        }))
+    }
+
+    async fn get_txs(Query(query): Query<HashMap<String, String>>) -> Json<Value> {
+        let status = query.get("status").map(String::as_str);
+        let txs = (0..5)
+            .map(|i| {
+                json!({
+                    "txId": format!("tx-{i}"),
+                    "to": "0x928a514350a403e2f5e3288c102f6b1ccabeb37c",
+                    "value": "0",
+                    "gasLimit": "2000000",
+                    "nonce": i,
+                    "txHash": MINED_TX_HASH,
+                    "status": status.unwrap_or("mined"),
+                })
+            })
+            .collect();
+
+        Json(Value::Array(txs))
+    }
+
+    async fn spawn_tx_sitter() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new()
+            .route("/tx/:tx_id", get(get_tx))
+            .route("/txs", get(get_txs))
+            .route("/rpc", post(rpc));
+
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        addr
+    }
+
+    #[tokio::test]
+    async fn get_tx_receipt() {
+        let addr = spawn_tx_sitter().await;
+        let client = TxSitterClient::new(format!("http://{addr}"));
+
+        let receipt = client.get_tx_receipt("mined").await.unwrap().unwrap();
+        assert_eq!(
+            receipt.transaction_hash,
+            MINED_TX_HASH.parse::<H256>().unwrap()
+        );
+        assert_eq!(receipt.block_number, Some(U64::from(0x12)));
+        assert_eq!(receipt.status, Some(U64::from(1)));
+
+        // Sent, but not mined yet
+        assert!(client.get_tx_receipt("pending").await.unwrap().is_none());
+        // Not sent yet
+        assert!(client.get_tx_receipt("unsent").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn get_txs_page() {
+        let addr = spawn_tx_sitter().await;
+        let client = TxSitterClient::new(format!("http://{addr}"));
+
+        let ids = |txs: Vec<GetTxResponse>| -> Vec<String> {
+            txs.into_iter().map(|tx| tx.tx_id).collect()
+        };
+
+        let page = client.get_txs_page(None, 2, 1).await.unwrap();
+        assert_eq!(ids(page), ["tx-1", "tx-2"]);
+
+        // The last page is cut short
+        let page = client.get_txs_page(None, 3, 4).await.unwrap();
+        assert_eq!(ids(page), ["tx-4"]);
+        assert!(client.get_txs_page(None, 3, 5).await.unwrap().is_empty());
+
+        let page = client
+            .get_txs_page(Some(TxStatus::Pending), 1, 0)
+            .await
+            .unwrap();
+        assert_eq!(page[0].status, Some(TxStatus::Pending));
+    }
+}
@@ -14,6 +14,8 @@ use crate::common::prelude::{Request, StatusCode};
 
 const LOCAL_ADDR: &str = "localhost";
 
+const DOCKER_COMMAND_FAILED: &str = "Docker command failed; ensure Docker is installed and running";
+
 #[derive(Debug)]
 pub struct DockerComposeGuard<'a> {
     // Current working dir containing compose.yml
@@ -118,7 +120,8 @@ impl<'a> DockerComposeGuard<'a> {
             stdout, stderr
         );
 
-        Ok(parse_exposed_port(stdout))
+        parse_exposed_port(&stdout)
+            .with_context(|| format!("Unexpected port of {service_name}: {stdout:?}"))
     }
 }
 
@@ -252,11 +255,19 @@ fn run_cmd_to_output(
 
     let output = command
         .output()
-        .with_context(|| format!("Failed to run command: {}", cmd_str))?;
+        .with_context(|| format!("Failed to run command: {cmd_str}. {DOCKER_COMMAND_FAILED}"))?;
 
     let stdout_utf = String::from_utf8(output.stdout)?;
     let stderr_utf = String::from_utf8(output.stderr)?;
 
+    if !output.status.success() {
+        anyhow::bail!(
+            "Command {cmd_str} exited with {}. {DOCKER_COMMAND_FAILED}\nstderr:\n{}",
+            output.status,
+            stderr_utf.trim()
+        );
+    }
+
     Ok((stdout_utf.trim().to_string(), stderr_utf.trim().to_string()))
 }
 
@@ -266,19 +277,13 @@ fn run_cmd(cwd: &str, envs: HashMap<String, String>, cmd_str: String) -> anyhow:
     Ok(())
 }
 
-fn parse_exposed_port(s: String) -> u32 {
-    let parts: Vec<_> = s
+fn parse_exposed_port(s: &str) -> anyhow::Result<u32> {
+    let address = s
         .split_whitespace()
-        .map(|s| s.trim())
-        .filter(|s| !s.is_empty())
-        .collect();
-
-    parts
-        .last()
-        .unwrap()
-        .split(':')
         .last()
-        .unwrap()
-        .parse::<u32>()
-        .unwrap()
+        .context("No port in the output")?;
+
+    let port = address.rsplit_once(':').map_or(address, |(_, port)| port);
+
+    Ok(port.parse::<u32>()?)
 }
@@ -0,0 +1,7 @@
+ALTER TABLE deletions
+    DROP COLUMN source_ip,
+    DROP COLUMN user_agent;
+
+ALTER TABLE unprocessed_identities
+    DROP COLUMN source_ip,
+    DROP COLUMN user_agent;
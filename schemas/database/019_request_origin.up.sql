@@ -0,0 +1,7 @@
+ALTER TABLE unprocessed_identities
+    ADD COLUMN source_ip  TEXT,
+    ADD COLUMN user_agent TEXT;
+
+ALTER TABLE deletions
+    ADD COLUMN source_ip  TEXT,
+    ADD COLUMN user_agent TEXT;
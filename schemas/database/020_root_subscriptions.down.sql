@@ -0,0 +1,2 @@
+DROP TABLE root_notifications;
+DROP TABLE root_subscriptions;
@@ -0,0 +1,13 @@
+CREATE TABLE root_subscriptions (
+    id         BIGSERIAL   PRIMARY KEY,
+    url        TEXT        NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE root_notifications (
+    subscription_id BIGINT      NOT NULL REFERENCES root_subscriptions (id) ON DELETE CASCADE,
+    root            BYTEA       NOT NULL,
+    kind            VARCHAR(50) NOT NULL,
+    sent_at         TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (subscription_id, root, kind)
+);
@@ -0,0 +1 @@
+DROP TABLE bulk_imports;
@@ -0,0 +1,8 @@
+CREATE TABLE bulk_imports (
+    import_id       TEXT        PRIMARY KEY,
+    lines_processed BIGINT      NOT NULL,
+    accepted        BIGINT      NOT NULL,
+    duplicates      BIGINT      NOT NULL,
+    invalid         BIGINT      NOT NULL,
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,11 @@
+ALTER TABLE identities
+    DROP COLUMN trace_id,
+    DROP COLUMN span_id;
+
+ALTER TABLE deletions
+    DROP COLUMN trace_id,
+    DROP COLUMN span_id;
+
+ALTER TABLE unprocessed_identities
+    DROP COLUMN trace_id,
+    DROP COLUMN span_id;
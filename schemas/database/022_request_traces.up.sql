@@ -0,0 +1,11 @@
+ALTER TABLE unprocessed_identities
+    ADD COLUMN trace_id TEXT,
+    ADD COLUMN span_id  TEXT;
+
+ALTER TABLE deletions
+    ADD COLUMN trace_id TEXT,
+    ADD COLUMN span_id  TEXT;
+
+ALTER TABLE identities
+    ADD COLUMN trace_id TEXT,
+    ADD COLUMN span_id  TEXT;
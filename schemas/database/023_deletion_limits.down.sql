@@ -0,0 +1,3 @@
+DROP TABLE deletion_limit_overrides;
+
+DROP TABLE deletion_rate_windows;
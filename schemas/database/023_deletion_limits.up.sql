@@ -0,0 +1,11 @@
+CREATE TABLE deletion_rate_windows (
+    window_start TIMESTAMPTZ PRIMARY KEY,
+    deletions    BIGINT      NOT NULL
+);
+
+CREATE TABLE deletion_limit_overrides (
+    id           BIGSERIAL   PRIMARY KEY,
+    reason       TEXT        NOT NULL,
+    lifted_until TIMESTAMPTZ NOT NULL,
+    created_at   TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
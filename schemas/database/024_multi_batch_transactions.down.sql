@@ -0,0 +1,3 @@
+DROP INDEX idx_transactions_transaction_id;
+
+ALTER TABLE transactions ADD PRIMARY KEY (transaction_id);
@@ -0,0 +1,5 @@
+-- A single transaction can submit several batches
+ALTER TABLE transactions DROP CONSTRAINT IF EXISTS transactions_pkey;
+ALTER TABLE transactions DROP CONSTRAINT IF EXISTS transactions_transaction_id_key;
+
+CREATE INDEX idx_transactions_transaction_id ON transactions (transaction_id);
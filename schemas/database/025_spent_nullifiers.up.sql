@@ -0,0 +1,4 @@
+CREATE TABLE spent_nullifiers (
+    nullifier_hash BYTEA       PRIMARY KEY,
+    spent_at       TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
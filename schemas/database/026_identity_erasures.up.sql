@@ -0,0 +1,4 @@
+CREATE TABLE identity_erasures (
+    commitment BYTEA       PRIMARY KEY,
+    erased_at  TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
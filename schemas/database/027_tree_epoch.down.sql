@@ -0,0 +1,2 @@
+DROP INDEX identities_status_id;
+DROP TABLE tree_epoch;
@@ -0,0 +1,12 @@
+-- Bumped whenever identities lose their processed or mined status, so that
+-- consumers of the tree updates feed know to resync
+CREATE TABLE tree_epoch (
+    Lock      char(1)     NOT NULL DEFAULT 'X',
+    epoch     BIGINT      NOT NULL,
+    bumped_at TIMESTAMPTZ NOT NULL,
+    constraint PK_tree_epoch        PRIMARY KEY (Lock),
+    constraint CK_tree_epoch_Locked CHECK (Lock='X')
+);
+
+-- Pages through the identities of a single status in sequence order
+CREATE INDEX identities_status_id ON identities (status, id);
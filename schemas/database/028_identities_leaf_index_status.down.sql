@@ -0,0 +1 @@
+DROP INDEX identities_leaf_index_status;
@@ -0,0 +1,3 @@
+ALTER TABLE deletions DROP CONSTRAINT deletions_leaf_index_key;
+ALTER TABLE deletions DROP COLUMN id;
+ALTER TABLE deletions ADD PRIMARY KEY (leaf_index);
@@ -0,0 +1,4 @@
+-- Deletions are read in pages by insertion order, so they need a stable key
+ALTER TABLE deletions DROP CONSTRAINT deletions_pkey;
+ALTER TABLE deletions ADD COLUMN id BIGSERIAL PRIMARY KEY;
+ALTER TABLE deletions ADD CONSTRAINT deletions_leaf_index_key UNIQUE (leaf_index);
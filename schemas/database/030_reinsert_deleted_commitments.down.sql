@@ -0,0 +1,5 @@
+DROP TRIGGER validate_commitment_not_in_tree_trigger ON identities;
+DROP FUNCTION validate_commitment_not_in_tree;
+
+DROP INDEX identities_commitment;
+CREATE UNIQUE INDEX identities_unique_commitment on identities(commitment) WHERE commitment != E'\\x0000000000000000000000000000000000000000000000000000000000000000';
@@ -0,0 +1,34 @@
+-- Deleted commitments may be inserted again at a new leaf, see
+-- `app.allow_reinsert_after_deletion_days`. The commitment keeps its rows at
+-- the old leaves, so it can't be unique anymore.
+DROP INDEX identities_unique_commitment;
+CREATE INDEX identities_commitment ON identities (commitment);
+
+-- A commitment must still not be in the tree at two leaves at once
+CREATE OR REPLACE FUNCTION validate_commitment_not_in_tree() RETURNS trigger AS $$
+    BEGIN
+        IF NEW.commitment = E'\\x0000000000000000000000000000000000000000000000000000000000000000' THEN
+            RETURN NEW;
+        END IF;
+
+        IF EXISTS (
+            SELECT 1
+            FROM identities inserted
+            WHERE inserted.commitment = NEW.commitment
+            AND NOT EXISTS (
+                SELECT 1
+                FROM identities deleted
+                WHERE deleted.leaf_index = inserted.leaf_index
+                AND deleted.commitment = E'\\x0000000000000000000000000000000000000000000000000000000000000000'
+                AND deleted.id > inserted.id
+            )
+        ) THEN
+            RAISE EXCEPTION 'Commitment (%) is already in the tree.', NEW.commitment
+                USING ERRCODE = 'unique_violation';
+        END IF;
+
+        RETURN NEW;
+    END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER validate_commitment_not_in_tree_trigger BEFORE INSERT ON identities FOR EACH ROW EXECUTE PROCEDURE validate_commitment_not_in_tree();
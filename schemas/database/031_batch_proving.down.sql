@@ -0,0 +1,2 @@
+ALTER TABLE batches DROP COLUMN proving_duration_ms;
+ALTER TABLE batches DROP COLUMN prover_url;
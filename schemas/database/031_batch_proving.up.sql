@@ -0,0 +1,3 @@
+-- The prover which proved a batch and how long it took, to compare provers
+ALTER TABLE batches ADD COLUMN prover_url TEXT;
+ALTER TABLE batches ADD COLUMN proving_duration_ms BIGINT;
@@ -0,0 +1 @@
+DROP TABLE deletion_intents;
@@ -0,0 +1,11 @@
+-- The deletions taken from the queue to be applied to the tree, recorded
+-- before the tree is touched so that an interrupted deletion can be recovered
+CREATE TABLE deletion_intents (
+    id BIGSERIAL PRIMARY KEY,
+    leaf_indexes BIGINT[] NOT NULL,
+    commitments BYTEA[] NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ
+);
+
+CREATE INDEX deletion_intents_incomplete_idx ON deletion_intents (id) WHERE completed_at IS NULL;
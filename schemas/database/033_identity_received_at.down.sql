@@ -0,0 +1,2 @@
+ALTER TABLE identities
+    DROP COLUMN received_at;
@@ -0,0 +1,4 @@
+-- When the identity was received, copied from unprocessed_identities. Only
+-- set for identities inserted through the API.
+ALTER TABLE identities
+    ADD COLUMN received_at TIMESTAMPTZ;
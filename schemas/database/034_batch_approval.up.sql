@@ -0,0 +1,4 @@
+-- Review state of batches when batch approval is required. NULL until the
+-- batch reaches the submission stage.
+ALTER TABLE batches
+    ADD COLUMN approval VARCHAR(50);
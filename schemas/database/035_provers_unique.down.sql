@@ -0,0 +1,2 @@
+ALTER TABLE provers
+    DROP CONSTRAINT provers_batch_size_prover_type_key;
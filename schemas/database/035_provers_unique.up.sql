@@ -0,0 +1,11 @@
+-- A prover is identified by its batch size and type, the URL and timeout are
+-- only how it's reached. Nothing enforced that since 008 dropped the primary
+-- key, so keep the earliest of any duplicates.
+DELETE FROM provers AS later
+USING provers AS earlier
+WHERE later.batch_size = earlier.batch_size
+    AND later.prover_type = earlier.prover_type
+    AND later.ctid > earlier.ctid;
+
+ALTER TABLE provers
+    ADD CONSTRAINT provers_batch_size_prover_type_key UNIQUE (batch_size, prover_type);
@@ -0,0 +1,2 @@
+ALTER TABLE provers
+    DROP COLUMN max_batch_size;
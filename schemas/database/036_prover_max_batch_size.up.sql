@@ -0,0 +1,4 @@
+-- Ranged insertion provers take any batch from batch_size up to this size,
+-- NULL for provers of a single batch size.
+ALTER TABLE provers
+    ADD COLUMN max_batch_size BIGINT;
@@ -0,0 +1,11 @@
+-- Runtime settings changed through the admin API, written in the same
+-- transaction as the change itself
+CREATE TABLE config_changes (
+    id BIGSERIAL PRIMARY KEY,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    principal TEXT NOT NULL,
+    setting TEXT NOT NULL,
+    old_value JSONB,
+    new_value JSONB,
+    request_id TEXT
+);
@@ -0,0 +1,13 @@
+-- Exports of all identities, checkpointed after every written chunk so that
+-- an interrupted export resumes where it stopped
+CREATE TABLE exports (
+    id BIGSERIAL PRIMARY KEY,
+    -- The last identity at the time the export was requested, later ones are
+    -- left to the next export
+    up_to_sequence_id BIGINT NOT NULL,
+    last_sequence_id BIGINT NOT NULL DEFAULT 0,
+    chunks BIGINT NOT NULL DEFAULT 0,
+    identities BIGINT NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    completed_at TIMESTAMPTZ
+);
@@ -0,0 +1,4 @@
+DROP TABLE batch_notes;
+
+ALTER TABLE batches
+    DROP COLUMN note;
@@ -0,0 +1,15 @@
+-- The latest operator note on a batch
+ALTER TABLE batches
+    ADD COLUMN note VARCHAR(1000);
+
+-- Every note ever added to a batch. Not tied to the batches table, so that
+-- notes on rejected batches outlive the unwound batch.
+CREATE TABLE batch_notes (
+    id BIGSERIAL PRIMARY KEY,
+    batch_next_root BYTEA NOT NULL,
+    note VARCHAR(1000) NOT NULL,
+    principal TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_batch_notes_batch_next_root ON batch_notes (batch_next_root);
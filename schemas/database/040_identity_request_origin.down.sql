@@ -0,0 +1,3 @@
+ALTER TABLE identities
+    DROP COLUMN source_ip,
+    DROP COLUMN user_agent;
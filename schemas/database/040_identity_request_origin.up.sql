@@ -0,0 +1,6 @@
+-- Where the request which inserted the identity, or deleted it for zeroed
+-- leaves, came from. Copied from unprocessed_identities and deletions like the
+-- request traces.
+ALTER TABLE identities
+    ADD COLUMN source_ip  TEXT,
+    ADD COLUMN user_agent TEXT;
@@ -0,0 +1,5 @@
+-- The sequencer instance proving and submitting a batch, and until when its
+-- claim holds. Expired claims are taken over by other instances.
+ALTER TABLE batches
+    ADD COLUMN claimed_by TEXT,
+    ADD COLUMN claimed_until TIMESTAMPTZ;
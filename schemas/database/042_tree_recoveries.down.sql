@@ -0,0 +1 @@
+DROP TABLE tree_recoveries;
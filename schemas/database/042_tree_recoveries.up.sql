@@ -0,0 +1,9 @@
+-- Recoveries applied with `tree.recover_to_root`. A recovery is applied once,
+-- restarts with the option still set leave the tree alone.
+CREATE TABLE tree_recoveries (
+    root BYTEA PRIMARY KEY,
+    sequence_id BIGINT NOT NULL,
+    invalidated BIGINT NOT NULL,
+    forced BOOLEAN NOT NULL,
+    recovered_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
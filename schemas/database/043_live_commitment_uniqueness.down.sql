@@ -0,0 +1,35 @@
+DROP TRIGGER unmark_deleted_insertion_trigger ON identities;
+DROP TRIGGER mark_deleted_insertion_trigger ON identities;
+DROP FUNCTION unmark_deleted_insertion;
+DROP FUNCTION mark_deleted_insertion;
+
+DROP INDEX identities_unique_live_commitment;
+ALTER TABLE identities DROP COLUMN deleted;
+
+CREATE OR REPLACE FUNCTION validate_commitment_not_in_tree() RETURNS trigger AS $$
+    BEGIN
+        IF NEW.commitment = E'\\x0000000000000000000000000000000000000000000000000000000000000000' THEN
+            RETURN NEW;
+        END IF;
+
+        IF EXISTS (
+            SELECT 1
+            FROM identities inserted
+            WHERE inserted.commitment = NEW.commitment
+            AND NOT EXISTS (
+                SELECT 1
+                FROM identities deleted
+                WHERE deleted.leaf_index = inserted.leaf_index
+                AND deleted.commitment = E'\\x0000000000000000000000000000000000000000000000000000000000000000'
+                AND deleted.id > inserted.id
+            )
+        ) THEN
+            RAISE EXCEPTION 'Commitment (%) is already in the tree.', NEW.commitment
+                USING ERRCODE = 'unique_violation';
+        END IF;
+
+        RETURN NEW;
+    END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER validate_commitment_not_in_tree_trigger BEFORE INSERT ON identities FOR EACH ROW EXECUTE PROCEDURE validate_commitment_not_in_tree();
@@ -0,0 +1,58 @@
+-- Replaces the trigger of migration 030, which checked for a live row before
+-- inserting without any lock, so concurrent re-insertions could both pass.
+-- Insertions are now flagged once their leaf is deleted, and the commitments
+-- of the rows which aren't are unique.
+DROP TRIGGER validate_commitment_not_in_tree_trigger ON identities;
+DROP FUNCTION validate_commitment_not_in_tree;
+
+ALTER TABLE identities ADD COLUMN deleted BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- A leaf is inserted and deleted at most once, see migration 015
+UPDATE identities inserted
+SET deleted = TRUE
+WHERE inserted.commitment != E'\\x0000000000000000000000000000000000000000000000000000000000000000'
+AND EXISTS (
+    SELECT 1
+    FROM identities removed
+    WHERE removed.leaf_index = inserted.leaf_index
+    AND removed.commitment = E'\\x0000000000000000000000000000000000000000000000000000000000000000'
+);
+
+CREATE UNIQUE INDEX identities_unique_live_commitment ON identities (commitment)
+WHERE NOT deleted
+AND commitment != E'\\x0000000000000000000000000000000000000000000000000000000000000000';
+
+CREATE FUNCTION mark_deleted_insertion() RETURNS trigger AS $$
+    BEGIN
+        UPDATE identities
+        SET deleted = TRUE
+        WHERE leaf_index = NEW.leaf_index
+        AND commitment != E'\\x0000000000000000000000000000000000000000000000000000000000000000';
+
+        RETURN NULL;
+    END;
+$$ LANGUAGE plpgsql;
+
+-- Deletions are removed when batches are unwound or the tree is recovered
+CREATE FUNCTION unmark_deleted_insertion() RETURNS trigger AS $$
+    BEGIN
+        UPDATE identities
+        SET deleted = FALSE
+        WHERE leaf_index = OLD.leaf_index
+        AND commitment != E'\\x0000000000000000000000000000000000000000000000000000000000000000';
+
+        RETURN NULL;
+    END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER mark_deleted_insertion_trigger
+AFTER INSERT ON identities
+FOR EACH ROW
+WHEN (NEW.commitment = E'\\x0000000000000000000000000000000000000000000000000000000000000000')
+EXECUTE PROCEDURE mark_deleted_insertion();
+
+CREATE TRIGGER unmark_deleted_insertion_trigger
+AFTER DELETE ON identities
+FOR EACH ROW
+WHEN (OLD.commitment = E'\\x0000000000000000000000000000000000000000000000000000000000000000')
+EXECUTE PROCEDURE unmark_deleted_insertion();
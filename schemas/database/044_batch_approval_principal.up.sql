@@ -0,0 +1,4 @@
+-- The admin who approved or rejected the batch, see migration 034. The change
+-- is also kept in the config change log, which outlives unwound batches.
+ALTER TABLE batches
+    ADD COLUMN approval_principal TEXT;
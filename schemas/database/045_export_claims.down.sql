@@ -0,0 +1,3 @@
+ALTER TABLE exports
+    DROP COLUMN claimed_by,
+    DROP COLUMN claimed_until;
@@ -0,0 +1 @@
+ALTER TABLE tree_recoveries ADD COLUMN forced BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,2 @@
+-- Roots which were mined on chain are never rolled back anymore
+ALTER TABLE tree_recoveries DROP COLUMN forced;
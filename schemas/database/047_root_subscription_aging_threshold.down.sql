@@ -0,0 +1 @@
+ALTER TABLE root_subscriptions DROP COLUMN aging_threshold_percent;
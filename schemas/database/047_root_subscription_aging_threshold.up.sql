@@ -0,0 +1,2 @@
+-- Overrides root_notifications.aging_threshold_percent for a single subscriber
+ALTER TABLE root_subscriptions ADD COLUMN aging_threshold_percent INTEGER;
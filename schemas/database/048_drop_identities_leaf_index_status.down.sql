@@ -0,0 +1 @@
+CREATE INDEX identities_leaf_index_status ON identities (leaf_index, status);
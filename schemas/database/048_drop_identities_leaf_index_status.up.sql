@@ -0,0 +1,2 @@
+-- Only served the leaf range lookup of pending identities, which was removed
+DROP INDEX identities_leaf_index_status;
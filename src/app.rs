@@ -1,41 +1,105 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, OnceLock};
+use std::time::Instant;
 
-use chrono::{Duration, Utc};
+use anyhow::Context;
+use bytes::Bytes;
+use chrono::{DateTime, Duration, Utc};
+use futures::{Stream, StreamExt};
+use prometheus::Registry;
 use ruint::Uint;
-use semaphore::protocol::verify_proof;
+use tokio::runtime::Handle;
+use tokio::sync::{Notify, Semaphore};
 use tracing::{info, instrument, warn};
+use url::Url;
 
-use crate::config::Config;
+use crate::config::{Config, ServerConfig, TreeConfig};
 use crate::contracts::IdentityManager;
 use crate::database::methods::DbMethods as _;
-use crate::database::{Database, IsolationLevel};
-use crate::ethereum::Ethereum;
+use crate::database::types::{
+    BatchApproval, BulkImportProgress, ChangeAuthor, ConfigChange, RequestOrigin,
+};
+use crate::database::{Database, IsolationLevel, Tx};
+use crate::ethereum::{Ethereum, TxError};
+use crate::identity::bulk_import::{parse_commitment, LineSplitter};
+use crate::identity::export::chunk_path;
+use crate::identity::flush::FlushSignal;
 use crate::identity::processor::{
     IdentityProcessor, OffChainIdentityProcessor, OnChainIdentityProcessor,
 };
+use crate::identity::proof_verifier::{ProofVerifier, VerifyError};
+use crate::identity::provable_sla::ProvableSla;
+use crate::identity::root_oracle::RootOracle;
 use crate::identity::validator::IdentityValidator;
-use crate::identity_tree::initializer::TreeInitializer;
+use crate::identity_tree::compaction::TreeCacheLock;
+use crate::identity_tree::initializer::{LeafIndexGap, TreeInitializer};
+use crate::identity_tree::publication::RootPublisher;
 use crate::identity_tree::{
-    Hash, ProcessedStatus, RootItem, TreeState, TreeVersionReadOps, UnprocessedStatus,
+    Hash, ProcessedStatus, RootItem, TreeItem, TreeState, TreeVersionReadOps, UnprocessedStatus,
 };
+use crate::metrics::Metrics;
 use crate::prover::map::initialize_prover_maps;
 use crate::prover::repository::ProverRepository;
 use crate::prover::{ProverConfig, ProverType};
 use crate::server::data::{
-    InclusionProofResponse, ListBatchSizesResponse, VerifySemaphoreProofQuery,
-    VerifySemaphoreProofRequest, VerifySemaphoreProofResponse,
+    AddBatchSizeResponse, BatchNoteInfo, BatchNoteRequest, BatchResponse, BulkImportResponse,
+    ConfigChangeInfo, ConfigChangesQuery, ConfigChangesResponse, EraseIdentityResponse, ExportInfo,
+    IdentityCountResponse, IdentityOriginResponse, InclusionProofResponse, InsertRejection,
+    LiftDeletionLimitResponse, ListBatchSizesResponse, NullifierStatusResponse,
+    SimulateInsertResponse, TransactionBatch, TransactionInfo, TransactionSource,
+    TransactionsQuery, TransactionsResponse, TreeInfoResponse, TreeUpdatesQuery,
+    TreeUpdatesResponse, ValidateInsertResponse, VerifySemaphoreProofQuery,
+    VerifySemaphoreProofRequest, VerifySemaphoreProofResponse, MAX_REPORTED_INVALID_LINES,
 };
 use crate::server::error::Error as ServerError;
 
+/// Number of commitments a bulk import inserts per transaction
+const BULK_IMPORT_CHUNK_SIZE: usize = 10_000;
+
+/// Skew between the application and database clocks above which a warning is
+/// logged. Root ages are computed with the database clock either way.
+const CLOCK_SKEW_WARNING_THRESHOLD: Duration = Duration::seconds(5);
+
+/// Proofs computed on the blocking pool at once. A proof takes hundreds of
+/// microseconds at depth 30, more than that only queues up behind the tree
+/// locks.
+const MAX_CONCURRENT_TREE_READS: usize = 16;
+
 pub struct App {
     pub database: Arc<Database>,
     pub identity_processor: Arc<dyn IdentityProcessor>,
     pub prover_repository: Arc<ProverRepository>,
     tree_state: OnceLock<TreeState>,
-    pub config: Config,
+    // Keeps the cache file from being compacted while it's mapped
+    tree_cache_lock: OnceLock<TreeCacheLock>,
+    tree_read_permits: Semaphore,
+    proof_verifier: ProofVerifier,
+    config: Config,
 
     pub identity_validator: IdentityValidator,
+    root_oracle: Option<RootOracle>,
+    provable_sla: Option<Arc<ProvableSla>>,
+    runtime: Handle,
+    flush_signal: Arc<FlushSignal>,
+    metrics: Metrics,
+    instance_id: String,
+    clock_skew: ClockSkew,
+
+    // Set by the canary task, see `task_monitor::tasks::canary`
+    canary_leaf: OnceLock<usize>,
+    canary_failed: AtomicBool,
+
+    // Set by the relayer balance task, see
+    // `task_monitor::tasks::monitor_relayer_balance`
+    batching_paused: AtomicBool,
+
+    // Set when the tree can't be initialized because of a gap in the leaf
+    // indexes, see `TreeConfig::allow_leaf_gaps`
+    leaf_index_gap: AtomicBool,
+
+    // Wakes the export task, see `task_monitor::tasks::export_identities`
+    export_requested: Notify,
 }
 
 impl App {
@@ -49,26 +113,120 @@ impl App {
     /// on the tree state will also error.
     #[instrument(name = "App::new", level = "debug", skip_all)]
     pub async fn new(config: Config) -> anyhow::Result<Arc<Self>> {
-        let db = Database::new(&config.database).await?;
+        Self::build(
+            config,
+            Handle::current(),
+            prometheus::default_registry().clone(),
+        )
+        .await
+    }
+
+    /// Creates the app with its metrics registered to the given registry
+    /// instead of the global one, which allows several apps to run in the
+    /// same process.
+    ///
+    /// # Errors
+    ///
+    /// Same as `App::new`.
+    pub async fn new_with_registry(
+        config: Config,
+        registry: Registry,
+    ) -> anyhow::Result<Arc<Self>> {
+        Self::build(config, Handle::current(), registry).await
+    }
+
+    /// Creates the app from outside of a Tokio runtime, e.g. from synchronous
+    /// test harnesses. The app is built by blocking on the given runtime and
+    /// its background tasks are spawned onto it.
+    ///
+    /// # Errors
+    ///
+    /// Same as `App::new`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from within an asynchronous execution context, use
+    /// `App::new` there instead.
+    pub fn new_with_handle(config: Config, handle: Handle) -> anyhow::Result<Arc<Self>> {
+        handle.block_on(Self::build(
+            config,
+            handle.clone(),
+            prometheus::default_registry().clone(),
+        ))
+    }
+
+    async fn build(
+        config: Config,
+        runtime: Handle,
+        registry: Registry,
+    ) -> anyhow::Result<Arc<Self>> {
+        let metrics = Metrics::new(registry, config.service.metrics_namespace.as_deref())
+            .context("Failed to register metrics")?;
+
+        let db = Database::new(&config.database, &metrics).await?;
         let database = Arc::new(db);
         let mut provers: HashSet<ProverConfig> = database.get_provers().await?;
 
+        for (i, prover) in config.app.provers_urls.0.iter().enumerate() {
+            prover
+                .validate()
+                .with_context(|| format!("Failed to parse prover at index {i}"))?;
+        }
+
         let non_inserted_provers =
             Self::merge_env_provers(&config.app.provers_urls.0, &mut provers);
 
         database.insert_provers(non_inserted_provers).await?;
+        let provers = Self::apply_prover_secrets(&config.app.provers_urls.0, provers);
 
-        let (insertion_prover_map, deletion_prover_map) = initialize_prover_maps(provers)?;
+        let (insertion_prover_map, deletion_prover_map) =
+            initialize_prover_maps(provers, &metrics)?;
 
         let prover_repository = Arc::new(ProverRepository::new(
             insertion_prover_map,
             deletion_prover_map,
+            config.app.max_prover_in_flight,
+            &metrics,
         ));
 
+        let flush_signal = Arc::new(FlushSignal::default());
+
+        let mut provable_sla = None;
         let identity_processor: Arc<dyn IdentityProcessor> = if config.offchain_mode.enabled {
-            Arc::new(OffChainIdentityProcessor::new(database.clone()).await?)
+            // Only allowed with `offchain_mode.ignore_chain_config`, see
+            // `Config::validate`
+            let ignored = config.chain_config_sections();
+            if !ignored.is_empty() {
+                warn!(
+                    ?ignored,
+                    "Offchain mode is enabled, the chain config is ignored and no batches are \
+                     submitted on-chain"
+                );
+            }
+
+            provable_sla = Some(Arc::new(ProvableSla::new(
+                config.offchain_mode.provable_sla.clone(),
+                &metrics,
+            )));
+
+            let root_publisher = config
+                .offchain_mode
+                .root_publication
+                .as_ref()
+                .map(RootPublisher::new)
+                .transpose()?;
+
+            Arc::new(
+                OffChainIdentityProcessor::new(
+                    database.clone(),
+                    prover_repository.clone(),
+                    flush_signal.clone(),
+                    root_publisher,
+                )
+                .await?,
+            )
         } else {
-            let ethereum = Ethereum::new(&config).await?;
+            let ethereum = Ethereum::new(&config, &metrics).await?;
 
             let identity_manager = Arc::new(IdentityManager::new(&config, ethereum.clone()).await?);
 
@@ -79,6 +237,8 @@ impl App {
                     database.clone(),
                     identity_manager.clone(),
                     prover_repository.clone(),
+                    flush_signal.clone(),
+                    &metrics,
                 )
                 .await?,
             )
@@ -86,28 +246,175 @@ impl App {
 
         let identity_validator = IdentityValidator::new(&config);
 
+        let root_oracle = config
+            .app
+            .external_root_oracle
+            .as_ref()
+            .map(|oracle_config| RootOracle::new(oracle_config, &metrics))
+            .transpose()?;
+
+        let proof_verifier = ProofVerifier::new(
+            config.server.verify_worker_threads,
+            config.server.verify_queue_size,
+            &metrics,
+        )?;
+
         let app = Arc::new(Self {
             database,
             identity_processor,
             prover_repository,
             tree_state: OnceLock::new(),
+            tree_cache_lock: OnceLock::new(),
+            tree_read_permits: Semaphore::new(MAX_CONCURRENT_TREE_READS),
+            proof_verifier,
             config,
             identity_validator,
+            root_oracle,
+            provable_sla,
+            runtime,
+            flush_signal,
+            metrics,
+            instance_id: new_instance_id(),
+            clock_skew: ClockSkew::default(),
+            canary_leaf: OnceLock::new(),
+            canary_failed: AtomicBool::new(false),
+            batching_paused: AtomicBool::new(false),
+            leaf_index_gap: AtomicBool::new(false),
+            export_requested: Notify::new(),
         });
 
         Ok(app)
     }
 
+    /// The runtime background tasks of the app are spawned onto.
+    pub fn runtime(&self) -> &Handle {
+        &self.runtime
+    }
+
+    /// The configuration the app was built with. It can't be changed once the
+    /// app is running.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn tree_config(&self) -> &TreeConfig {
+        &self.config.tree
+    }
+
+    pub fn server_config(&self) -> &ServerConfig {
+        &self.config.server
+    }
+
+    /// The metrics of the app, registered to the registry it was built with.
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Identifies this app among the sequencer instances sharing its database,
+    /// e.g. in the claims on the batches it's processing.
+    pub fn instance_id(&self) -> &str {
+        &self.instance_id
+    }
+
+    /// Lets the tasks know whether they should skip waiting for full batches.
+    pub(crate) fn flush_signal(&self) -> &FlushSignal {
+        &self.flush_signal
+    }
+
+    /// Processes every queued insertion and deletion right away, see
+    /// [`IdentityProcessor::flush`].
+    pub async fn flush(&self) -> anyhow::Result<()> {
+        self.identity_processor.flush().await
+    }
+
+    /// Returns `false` once the startup canary failed to make it through the
+    /// pipeline in time, while batch submission is paused if configured to, or
+    /// if the tree can't be built because of a gap in the mined identities.
+    pub fn is_ready(&self) -> bool {
+        let paused = self.config.app.paused_batching_fails_readiness && self.is_batching_paused();
+
+        !self.canary_failed.load(Ordering::Relaxed)
+            && !paused
+            && !self.leaf_index_gap.load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` while batch submission is paused because the relayer
+    /// can't pay for the transactions.
+    pub fn is_batching_paused(&self) -> bool {
+        self.batching_paused.load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` while identities take longer than the offchain mode SLA
+    /// to become provable, see `OffchainModeConfig::provable_sla`.
+    pub fn is_sla_breached(&self) -> bool {
+        self.provable_sla
+            .as_ref()
+            .is_some_and(|provable_sla| provable_sla.is_breached())
+    }
+
+    /// Tracks how long identities take to become provable, only in offchain
+    /// mode.
+    pub(crate) fn provable_sla(&self) -> Option<&ProvableSla> {
+        self.provable_sla.as_deref()
+    }
+
+    /// Updates the offchain mode SLA gauge, see [`ProvableSla::refresh`].
+    pub(crate) fn refresh_provable_sla(&self) {
+        if let Some(provable_sla) = &self.provable_sla {
+            provable_sla.refresh();
+        }
+    }
+
+    pub(crate) fn set_batching_paused(&self, paused: bool) {
+        self.batching_paused.store(paused, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_canary_failed(&self, failed: bool) {
+        self.canary_failed.store(failed, Ordering::Relaxed);
+    }
+
+    /// The leaf index of the canary identity, if it's in the tree.
+    pub(crate) fn canary_leaf(&self) -> Option<usize> {
+        self.canary_leaf.get().copied()
+    }
+
+    pub(crate) fn set_canary_leaf(&self, leaf_index: usize) {
+        let _ = self.canary_leaf.set(leaf_index);
+    }
+
     /// Initializes the tree state. This should only ever be called once.
     /// Attempts to call this method more than once will result in a panic.
     pub async fn init_tree(self: Arc<Self>) -> anyhow::Result<()> {
+        // The insertion task promotes and trims identities in a single
+        // transaction, but rows left behind by older versions (or manual
+        // intervention) would make the commitment both pending and
+        // unprocessed.
+        for commitment in self.database.trim_unprocessed().await? {
+            warn!(
+                ?commitment,
+                "Removed unprocessed identity which was already in the tree"
+            );
+        }
+
+        if self.tree_cache_lock.get().is_none() {
+            let lock = TreeCacheLock::shared(&self.config.tree.cache_file)?;
+            let _ = self.tree_cache_lock.set(lock);
+        }
+
         let tree_state = TreeInitializer::new(
             self.database.clone(),
             self.identity_processor.clone(),
             self.config.tree.clone(),
+            self.config.offchain_mode.enabled,
+            &self.metrics,
         )
         .run()
-        .await?;
+        .await
+        .inspect_err(|err| {
+            if err.is::<LeafIndexGap>() {
+                self.leaf_index_gap.store(true, Ordering::Relaxed);
+            }
+        })?;
 
         self.tree_state.set(tree_state).map_err(|_| {
             anyhow::anyhow!(
@@ -132,257 +439,1554 @@ impl App {
     /// Will return `Err` if identity is already queued, or in the tree, or the
     /// queue malfunctions.
     #[instrument(level = "debug", skip(self))]
-    pub async fn insert_identity(&self, commitment: Hash) -> Result<(), ServerError> {
-        if self.identity_validator.is_initial_leaf(&commitment) {
-            warn!(?commitment, "Attempt to insert initial leaf.");
-            return Err(ServerError::InvalidCommitment);
-        }
-
-        if !self.prover_repository.has_insertion_provers().await {
-            warn!(
-                ?commitment,
-                "Identity Manager has no insertion provers. Add provers with /addBatchSize \
-                 request."
-            );
-            return Err(ServerError::NoProversOnIdInsert);
-        }
-
-        if !self.identity_validator.is_reduced(commitment) {
-            warn!(
-                ?commitment,
-                "The provided commitment is not an element of the field."
-            );
-            return Err(ServerError::UnreducedCommitment);
-        }
+    pub async fn insert_identity(
+        &self,
+        commitment: Hash,
+        origin: RequestOrigin,
+    ) -> Result<(), ServerError> {
+        let started_at = Instant::now();
+
+        let result = self.queue_identity(commitment, origin).await;
+
+        let status = match &result {
+            Ok(false) => "success",
+            Ok(true) => "reinsertion",
+            Err(ServerError::DuplicateCommitment) => "duplicate",
+            Err(ServerError::InvalidCommitment) => "invalid",
+            Err(ServerError::UnreducedCommitment) => "unreduced",
+            Err(ServerError::NoProversOnIdInsert) => "no_provers",
+            Err(_) => "error",
+        };
+        self.metrics
+            .insertion_duration
+            .with_label_values(&[status])
+            .observe(started_at.elapsed().as_secs_f64());
 
-        // TODO: ensure that the id is not in the tree or in unprocessed identities
+        result.map(|_| ())
+    }
 
+    /// Validates the commitment and queues it for insertion, returns whether
+    /// it was deleted before.
+    async fn queue_identity(
+        &self,
+        commitment: Hash,
+        origin: RequestOrigin,
+    ) -> Result<bool, ServerError> {
+        // Read committed is enough here. Concurrent inserts of the same
+        // commitment both pass the existence check under any isolation level,
+        // as neither sees the other's uncommitted row. The unique commitment
+        // of unprocessed identities collapses them into a single row, and the
+        // unique index on the commitments at leaves which weren't deleted keeps
+        // a re-inserted commitment from ending up in the tree twice.
+        // Repeatable read would only turn the later inserts into serialization
+        // failures.
         let mut tx = self
             .database
-            .begin_tx(IsolationLevel::ReadCommitted)
+            .begin_tx("insert_identity", IsolationLevel::ReadCommitted)
             .await?;
 
-        if tx.identity_exists(commitment).await? {
-            return Err(ServerError::DuplicateCommitment);
-        }
+        let result = async {
+            let reinsertion = self.validate_insert(&mut tx, commitment).await?;
 
-        tx.insert_unprocessed_identity(commitment).await?;
+            tx.insert_unprocessed_identity_with_origin(commitment, &origin)
+                .await?;
 
-        tx.commit().await?;
+            if reinsertion {
+                warn!(?commitment, ?origin, "Re-inserting a deleted commitment");
+                self.metrics.commitments_reinserted.inc();
+            }
 
-        Ok(())
+            Ok::<_, ServerError>(reinsertion)
+        }
+        .await;
+
+        tx.finish(result).await
     }
 
-    /// Queues a deletion from the merkle tree.
+    /// Runs the checks of `insert_identity` without queueing the commitment, in
+    /// a read only transaction.
     ///
     /// # Errors
     ///
-    /// Will return `Err` if identity is already queued, not in the tree, or the
-    /// queue malfunctions.
+    /// Will return `Err` if the checks themselves fail. A rejected commitment
+    /// is reported in the response.
     #[instrument(level = "debug", skip(self))]
-    pub async fn delete_identity(&self, commitment: &Hash) -> Result<(), ServerError> {
+    pub async fn validate_insert_identity(
+        &self,
+        commitment: Hash,
+    ) -> Result<ValidateInsertResponse, ServerError> {
         let mut tx = self
             .database
-            .begin_tx(IsolationLevel::RepeatableRead)
+            .begin_read_only_tx("validate_insert_identity", IsolationLevel::ReadCommitted)
             .await?;
 
-        // Ensure that deletion provers exist
-        if !self.prover_repository.has_deletion_provers().await {
-            warn!(
-                ?commitment,
-                "Identity Manager has no deletion provers. Add provers with /addBatchSize request."
-            );
-            return Err(ServerError::NoProversOnIdDeletion);
-        }
-
-        if !tx.identity_exists(*commitment).await? {
-            return Err(ServerError::IdentityCommitmentNotFound);
-        }
-
-        // Get the leaf index for the id commitment
-        let leaf_index = tx
-            .get_identity_leaf_index(commitment)
-            .await?
-            .ok_or(ServerError::IdentityCommitmentNotFound)?
-            .leaf_index;
-
-        // Check if the id has already been deleted
-        if self.tree_state()?.get_latest_tree().get_leaf(leaf_index) == Uint::ZERO {
-            return Err(ServerError::IdentityAlreadyDeleted);
-        }
-
-        // Check if there are any deletions, if not, set the latest deletion timestamp
-        // to now to ensure that the new deletion is processed by the next deletion
-        // interval
-        if tx.get_deletions().await?.is_empty() {
-            tx.update_latest_deletion(Utc::now()).await?;
-        }
-
-        tx.insert_new_deletion(leaf_index, commitment).await?;
-
-        tx.commit().await?;
-
-        Ok(())
-    }
-
-    fn merge_env_provers(
-        prover_urls: &[ProverConfig],
-        existing_provers: &mut HashSet<ProverConfig>,
-    ) -> HashSet<ProverConfig> {
-        let options_set: HashSet<ProverConfig> = prover_urls
-            .iter()
-            .cloned()
-            .map(|opt| ProverConfig {
-                url: opt.url,
-                batch_size: opt.batch_size,
-                timeout_s: opt.timeout_s,
-                prover_type: opt.prover_type,
-            })
-            .collect();
+        let result = self.validate_insert(&mut tx, commitment).await;
 
-        let env_provers: HashSet<_> = options_set.difference(existing_provers).cloned().collect();
+        tx.rollback().await?;
 
-        for unique in &env_provers {
-            existing_provers.insert(unique.clone());
-        }
+        let reason = match result {
+            Ok(_) => None,
+            Err(ServerError::InvalidCommitment) => Some(InsertRejection::InvalidCommitment),
+            Err(ServerError::UnreducedCommitment) => Some(InsertRejection::UnreducedCommitment),
+            Err(ServerError::DuplicateCommitment) => Some(InsertRejection::DuplicateCommitment),
+            Err(ServerError::NoProversOnIdInsert) => Some(InsertRejection::NoInsertionProvers),
+            Err(err) => return Err(err),
+        };
 
-        env_provers
+        Ok(ValidateInsertResponse {
+            insertable: reason.is_none(),
+            reason,
+        })
     }
 
+    /// Computes the root, leaf index and inclusion proof the commitment would
+    /// get if it was inserted into the latest tree right now. Nothing is
+    /// written to the database or the tree.
+    ///
+    /// Identities which are queued but not yet in the latest tree are appended
+    /// first, in the order the insertion task will pick them up.
+    ///
     /// # Errors
     ///
-    /// Will return `Err` if the provided batch size already exists.
-    /// Will return `Err` if the batch size fails to write to database.
+    /// Will return `Err` if the commitment would be rejected by
+    /// `insert_identity`.
     #[instrument(level = "debug", skip(self))]
-    pub async fn add_batch_size(
+    pub async fn simulate_insert_identity(
         &self,
-        url: String,
-        batch_size: usize,
-        timeout_seconds: u64,
-        prover_type: ProverType,
-    ) -> Result<(), ServerError> {
-        self.prover_repository
-            .add_batch_size(&url, batch_size, timeout_seconds, prover_type)
+        commitment: Hash,
+    ) -> Result<SimulateInsertResponse, ServerError> {
+        let mut tx = self
+            .database
+            .begin_read_only_tx("simulate_insert_identity", IsolationLevel::ReadCommitted)
             .await?;
 
-        self.database
-            .insert_prover_configuration(batch_size, url, timeout_seconds, prover_type)
-            .await?;
+        let result = async {
+            self.validate_insert(&mut tx, commitment).await?;
 
-        Ok(())
-    }
+            let queued = tx.get_unprocessed_commitments().await?;
 
-    /// # Errors
-    ///
-    /// Will return `Err` if the requested batch size does not exist.
-    /// Will return `Err` if batch size fails to be removed from database.
-    #[instrument(level = "debug", skip(self))]
-    pub async fn remove_batch_size(
-        &self,
-        batch_size: usize,
-        prover_type: ProverType,
-    ) -> Result<(), ServerError> {
-        self.prover_repository
-            .remove_batch_size(batch_size, prover_type)
-            .await?;
+            Ok::<_, ServerError>(queued)
+        }
+        .await;
 
-        self.database.remove_prover(batch_size, prover_type).await?;
+        tx.rollback().await?;
+        let mut identities = result?;
 
-        Ok(())
-    }
+        let position = identities.len();
+        identities.push(commitment);
 
-    /// # Errors
-    ///
-    /// Will return `Err` if something unknown went wrong.
-    #[instrument(level = "debug", skip(self))]
-    pub async fn list_batch_sizes(&self) -> Result<ListBatchSizesResponse, ServerError> {
-        let batches = self.prover_repository.list_batch_sizes().await?;
+        let (root, proof, leaf_index) = self
+            .read_tree(move |tree_state| {
+                tree_state
+                    .latest_tree()
+                    .simulate_append_with_proofs_for(&identities, &[position])
+                    .pop()
+                    .expect("one simulated update per position")
+            })
+            .await?;
 
-        Ok(ListBatchSizesResponse::from(batches))
+        Ok(SimulateInsertResponse {
+            root,
+            leaf_index,
+            proof,
+        })
     }
 
+    /// Queues the commitments of a newline-delimited body of hex encoded
+    /// commitments, meant for trusted migrations of large identity sets.
+    ///
+    /// The body is processed in chunks, each committed together with the
+    /// import's checkpoint. Repeating a request with the same import id skips
+    /// the lines which were already processed, so an interrupted import can be
+    /// resumed by sending the same body again.
+    ///
     /// # Errors
     ///
-    /// Will return `Err` if the provided index is out of bounds.
-    #[instrument(level = "debug", skip(self))]
-    pub async fn inclusion_proof(
+    /// Will return `Err` if the body can't be read, a line is too long or the
+    /// database malfunctions. Chunks committed until then are kept.
+    #[instrument(level = "debug", skip(self, body))]
+    pub async fn bulk_import<S, E>(
         &self,
-        commitment: &Hash,
-    ) -> Result<InclusionProofResponse, ServerError> {
-        if self.identity_validator.is_initial_leaf(commitment) {
-            return Err(ServerError::InvalidCommitment);
+        import_id: String,
+        mut body: S,
+    ) -> Result<BulkImportResponse, ServerError>
+    where
+        S: Stream<Item = Result<Bytes, E>> + Unpin,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        if !self.prover_repository.has_insertion_provers().await {
+            return Err(ServerError::NoProversOnIdInsert);
         }
 
-        if self
+        let mut progress = self
             .database
-            .get_unprocessed_commitment(commitment)
+            .get_bulk_import(&import_id)
             .await?
-            .is_some()
-        {
-            return Ok(InclusionProofResponse {
-                status: UnprocessedStatus::New.into(),
-                root: None,
-                proof: None,
-                message: None,
+            .unwrap_or_else(|| BulkImportProgress {
+                import_id: import_id.clone(),
+                ..Default::default()
             });
+        let resumed_at = progress.lines_processed;
+
+        let mut splitter = LineSplitter::default();
+        let mut chunk = Vec::with_capacity(BULK_IMPORT_CHUNK_SIZE);
+        let mut invalid_lines = vec![];
+
+        loop {
+            let (lines, done) = match body.next().await {
+                Some(bytes) => (splitter.push(&bytes.map_err(anyhow::Error::from)?)?, false),
+                None => (
+                    std::mem::take(&mut splitter).finish().into_iter().collect(),
+                    true,
+                ),
+            };
+
+            for (line_number, line) in lines {
+                if line_number <= resumed_at {
+                    continue;
+                }
+
+                progress.lines_processed = line_number;
+
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                match parse_commitment(&line) {
+                    Some(commitment)
+                        if !self.identity_validator.is_initial_leaf(&commitment)
+                            && self.identity_validator.is_reduced(commitment) =>
+                    {
+                        chunk.push(commitment);
+                    }
+                    _ => {
+                        progress.invalid += 1;
+                        if invalid_lines.len() < MAX_REPORTED_INVALID_LINES {
+                            invalid_lines.push(line_number);
+                        }
+                    }
+                }
+
+                if chunk.len() >= BULK_IMPORT_CHUNK_SIZE {
+                    self.flush_bulk_import(&mut chunk, &mut progress).await?;
+                }
+            }
+
+            if done {
+                break;
+            }
         }
 
-        let item = self
+        self.flush_bulk_import(&mut chunk, &mut progress).await?;
+
+        info!(
+            %import_id,
+            lines_processed = progress.lines_processed,
+            accepted = progress.accepted,
+            duplicates = progress.duplicates,
+            invalid = progress.invalid,
+            "Bulk import finished"
+        );
+
+        Ok(BulkImportResponse {
+            import_id,
+            lines_processed: progress.lines_processed,
+            accepted: progress.accepted,
+            duplicates: progress.duplicates,
+            invalid: progress.invalid,
+            invalid_lines,
+        })
+    }
+
+    /// Inserts a chunk of a bulk import and advances its checkpoint in the
+    /// same transaction.
+    async fn flush_bulk_import(
+        &self,
+        chunk: &mut Vec<Hash>,
+        progress: &mut BulkImportProgress,
+    ) -> Result<(), ServerError> {
+        let mut tx = self
             .database
-            .get_identity_leaf_index(commitment)
-            .await?
-            .ok_or(ServerError::IdentityCommitmentNotFound)?;
+            .begin_tx("bulk_import", IsolationLevel::ReadCommitted)
+            .await?;
 
-        let (leaf, proof) = self.tree_state()?.get_proof_for(&item);
+        let result = async {
+            // Goes through the same checks as single inserts, so that deleted
+            // commitments are accepted again under the same conditions
+            let mut accepted = Vec::with_capacity(chunk.len());
+            for commitment in chunk.iter() {
+                match self.validate_insert(&mut tx, *commitment).await {
+                    Ok(reinsertion) => {
+                        if reinsertion {
+                            warn!(?commitment, "Re-inserting a deleted commitment");
+                            self.metrics.commitments_reinserted.inc();
+                        }
+                        accepted.push(*commitment);
+                    }
+                    Err(ServerError::DuplicateCommitment) => {}
+                    Err(err) => return Err(err),
+                }
+            }
 
-        if leaf != *commitment {
-            return Err(ServerError::InvalidCommitment);
+            let inserted = tx.insert_unprocessed_identities(&accepted).await?;
+
+            let mut next = progress.clone();
+            next.accepted += inserted;
+            next.duplicates += chunk.len() as u64 - inserted;
+            tx.update_bulk_import(&next).await?;
+
+            Ok::<_, ServerError>(next)
         }
+        .await;
 
-        Ok(proof.into())
+        let next = tx.finish(result).await?;
+
+        *progress = next;
+        chunk.clear();
+
+        info!(
+            import_id = %progress.import_id,
+            lines_processed = progress.lines_processed,
+            accepted = progress.accepted,
+            "Bulk import progress"
+        );
+
+        Ok(())
     }
 
-    /// # Errors
+    /// All checks a commitment has to pass before it's queued. Shared by the
+    /// insert, its validation and its simulation, so that they can't disagree.
     ///
-    /// Will return `Err` if the provided proof is invalid.
-    #[instrument(level = "debug", skip(self))]
-    pub async fn verify_semaphore_proof(
-        &self,
-        request: &VerifySemaphoreProofRequest,
-        query: &VerifySemaphoreProofQuery,
-    ) -> Result<VerifySemaphoreProofResponse, ServerError> {
-        let Some(root_state) = self.database.get_root_state(&request.root).await? else {
-            return Err(ServerError::InvalidRoot);
+    /// Returns whether the commitment is a deleted one inserted again, see
+    /// `AppConfig::allow_reinsert_after_deletion_days`.
+    async fn validate_insert(&self, tx: &mut Tx, commitment: Hash) -> Result<bool, ServerError> {
+        self.validate_new_commitment(commitment).await?;
+
+        // Deleted commitments keep their row and are duplicates as well, unless
+        // they were deleted long enough ago
+        if !tx.identity_exists(commitment).await? {
+            return Ok(false);
+        }
+
+        let Some(days) = self.config.app.allow_reinsert_after_deletion_days else {
+            return Err(ServerError::DuplicateCommitment);
         };
 
-        if let Some(max_root_age_seconds) = query.max_root_age_seconds {
-            let max_root_age = Duration::seconds(max_root_age_seconds);
-            self.validate_root_age(max_root_age, &root_state)?;
+        if !tx.deleted_for_days(commitment, days).await? {
+            return Err(ServerError::DuplicateCommitment);
         }
 
-        let checked = verify_proof(
-            request.root,
-            request.nullifier_hash,
-            request.signal_hash,
-            request.external_nullifier_hash,
-            &request.proof,
-            self.config.tree.tree_depth,
-        );
+        Ok(true)
+    }
 
-        match checked {
-            Ok(true) => Ok(root_state.into()),
-            Ok(false) => Err(ServerError::InvalidProof),
-            Err(err) => {
-                info!(?err, "verify_proof failed with error");
-                Err(ServerError::ProverError)
-            }
+    /// Checks which don't depend on the database state, shared by the real and
+    /// the simulated insert.
+    async fn validate_new_commitment(&self, commitment: Hash) -> Result<(), ServerError> {
+        if self.identity_validator.is_initial_leaf(&commitment) {
+            warn!(?commitment, "Attempt to insert initial leaf.");
+            return Err(ServerError::InvalidCommitment);
+        }
+
+        if !self.prover_repository.has_insertion_provers().await {
+            warn!(
+                ?commitment,
+                "Identity Manager has no insertion provers. Add provers with /addBatchSize \
+                 request."
+            );
+            return Err(ServerError::NoProversOnIdInsert);
+        }
+
+        if !self.identity_validator.is_reduced(commitment) {
+            warn!(
+                ?commitment,
+                "The provided commitment is not an element of the field."
+            );
+            return Err(ServerError::UnreducedCommitment);
+        }
+
+        Ok(())
+    }
+
+    /// Queues a deletion from the merkle tree.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if identity is already queued, not in the tree, or the
+    /// queue malfunctions.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn delete_identity(
+        &self,
+        commitment: &Hash,
+        origin: RequestOrigin,
+    ) -> Result<(), ServerError> {
+        // All checks below must see the same state. Under read committed each
+        // query sees the latest committed data, so e.g. a deletion committed
+        // between `get_identity_leaf_index` and `get_deletions` would be
+        // missing from the leaf check but counted against the limits. With
+        // repeatable read the checks share one snapshot, and concurrent updates
+        // of the same rows, like the latest deletion timestamp, fail instead of
+        // silently overwriting each other.
+        let mut tx = self
+            .database
+            .begin_tx("delete_identity", IsolationLevel::RepeatableRead)
+            .await?;
+
+        let result = async {
+            // Ensure that deletion provers exist
+            if !self.prover_repository.has_deletion_provers().await {
+                warn!(
+                    ?commitment,
+                    "Identity Manager has no deletion provers. Add provers with /addBatchSize request."
+                );
+                return Err(ServerError::NoProversOnIdDeletion);
+            }
+
+            if !tx.identity_exists(*commitment).await? {
+                return Err(ServerError::IdentityCommitmentNotFound);
+            }
+
+            // Get the leaf index for the id commitment
+            let leaf_index = tx
+                .get_identity_leaf_index(commitment)
+                .await?
+                .ok_or(ServerError::IdentityCommitmentNotFound)?
+                .leaf_index;
+
+            // Check if the id has already been deleted
+            if self.tree_state()?.get_latest_tree().get_leaf(leaf_index) == Uint::ZERO {
+                return Err(ServerError::IdentityAlreadyDeleted);
+            }
+
+            let pending_deletions = tx.count_deletions().await?;
+
+            if !tx.is_deletion_limit_lifted().await? {
+                if let Some(max_pending) = self.config.app.max_total_pending_deletions {
+                    if pending_deletions as u64 >= max_pending {
+                        warn!(pending_deletions, "Too many pending deletions");
+                        return Err(ServerError::DeletionRateExceeded);
+                    }
+                }
+
+                if let Some(max_per_hour) = self.config.app.max_deletions_per_hour {
+                    if !tx.record_deletion_in_window(max_per_hour).await? {
+                        warn!(max_per_hour, "Hourly deletion limit reached");
+                        return Err(ServerError::DeletionRateExceeded);
+                    }
+                }
+            }
+
+            // Check if there are any deletions, if not, set the latest deletion timestamp
+            // to now to ensure that the new deletion is processed by the next deletion
+            // interval
+            if pending_deletions == 0 {
+                tx.update_latest_deletion(Utc::now()).await?;
+            }
+
+            tx.insert_new_deletion_with_origin(leaf_index, commitment, &origin)
+                .await?;
+
+            Ok::<_, ServerError>(())
+        }
+        .await;
+
+        tx.finish(result).await
+    }
+
+    /// Approves or rejects a batch held back by
+    /// `AppConfig::require_batch_approval`. Approved batches are submitted,
+    /// rejected ones are unwound by the batch creator.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the batch isn't awaiting approval or the database
+    /// malfunctions.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn decide_batch_approval(
+        &self,
+        next_root: &Hash,
+        approval: BatchApproval,
+        author: &ChangeAuthor,
+    ) -> Result<(), ServerError> {
+        let mut tx = self
+            .database
+            .begin_tx("decide_batch_approval", IsolationLevel::ReadCommitted)
+            .await?;
+
+        let result = async {
+            if !tx
+                .decide_batch_approval(next_root, approval, &author.principal)
+                .await?
+            {
+                return Err(ServerError::NoSuchBatchAwaitingApproval);
+            }
+            // Rejected batches are unwound, the change log keeps the decision
+            tx.record_config_change(
+                author,
+                &ConfigChange {
+                    setting: "batch.approval".to_string(),
+                    old_value: Some(serde_json::json!({
+                        "root": next_root,
+                        "approval": BatchApproval::AwaitingApproval,
+                    })),
+                    new_value: Some(serde_json::json!({
+                        "root": next_root,
+                        "approval": approval,
+                    })),
+                },
+            )
+            .await?;
+
+            Ok::<_, ServerError>(())
+        }
+        .await;
+
+        tx.finish(result).await?;
+
+        warn!(
+            ?next_root,
+            ?approval,
+            principal = %author.principal,
+            "Batch was reviewed"
+        );
+
+        Ok(())
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no batch with the given root.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn batch(&self, next_root: &Hash) -> Result<BatchResponse, ServerError> {
+        let batch = self
+            .database
+            .get_batch(next_root)
+            .await?
+            .ok_or(ServerError::NoSuchBatch)?;
+        let notes = self.database.get_batch_notes(next_root).await?;
+
+        Ok(BatchResponse {
+            root: batch.next_root,
+            prev_root: batch.prev_root,
+            batch_type: batch.batch_type,
+            created_at: batch.created_at,
+            approval: batch.approval,
+            approval_principal: batch.approval_principal,
+            note: batch.note,
+            notes: notes.into_iter().map(BatchNoteInfo::from).collect(),
+        })
+    }
+
+    /// Adds an operator note to a batch, e.g. why it was approved or
+    /// resubmitted. Earlier notes are kept.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the note is empty or too long, or if there is no
+    /// batch with the given root.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn annotate_batch(
+        &self,
+        next_root: &Hash,
+        note: &str,
+        author: &ChangeAuthor,
+    ) -> Result<BatchResponse, ServerError> {
+        if note.trim().is_empty() {
+            return Err(ServerError::MissingBatchNote);
+        }
+        if note.chars().count() > BatchNoteRequest::MAX_LENGTH {
+            return Err(ServerError::BatchNoteTooLong {
+                max_length: BatchNoteRequest::MAX_LENGTH,
+            });
+        }
+
+        let mut tx = self
+            .database
+            .begin_tx("annotate_batch", IsolationLevel::ReadCommitted)
+            .await?;
+
+        let result = async {
+            if !tx
+                .insert_batch_note(next_root, note, &author.principal)
+                .await?
+            {
+                return Err(ServerError::NoSuchBatch);
+            }
+            // Notes are added next to earlier ones, they replace nothing
+            tx.record_config_change(
+                author,
+                &ConfigChange {
+                    setting: "batch.note".to_string(),
+                    old_value: None,
+                    new_value: Some(serde_json::json!({
+                        "root": next_root,
+                        "note": note,
+                    })),
+                },
+            )
+            .await?;
+
+            Ok::<_, ServerError>(())
+        }
+        .await;
+
+        tx.finish(result).await?;
+
+        info!(
+            ?next_root,
+            principal = %author.principal,
+            "Batch was annotated"
+        );
+
+        self.batch(next_root).await
+    }
+
+    /// Temporarily lifts the deletion limits, e.g. for a planned mass deletion.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if no reason is given or the database malfunctions.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn lift_deletion_limit(
+        &self,
+        reason: &str,
+        duration: std::time::Duration,
+        author: &ChangeAuthor,
+    ) -> Result<LiftDeletionLimitResponse, ServerError> {
+        if reason.trim().is_empty() {
+            return Err(ServerError::MissingLiftReason);
+        }
+
+        let mut tx = self
+            .database
+            .begin_tx("lift_deletion_limit", IsolationLevel::ReadCommitted)
+            .await?;
+
+        let result = async {
+            let lifted_until = tx.lift_deletion_limit(reason, duration).await?;
+            // Lifting adds an override next to earlier ones, it replaces nothing
+            tx.record_config_change(
+                author,
+                &ConfigChange {
+                    setting: "deletion_limit.lifted_until".to_string(),
+                    old_value: None,
+                    new_value: Some(serde_json::json!({
+                        "liftedUntil": lifted_until,
+                        "reason": reason,
+                    })),
+                },
+            )
+            .await?;
+
+            Ok::<_, ServerError>(lifted_until)
+        }
+        .await;
+
+        let lifted_until = tx.finish(result).await?;
+
+        warn!(reason, %lifted_until, "Deletion limits lifted");
+
+        Ok(LiftDeletionLimitResponse { lifted_until })
+    }
+
+    /// Erases the request origins and traces recorded for a commitment. The
+    /// commitment itself stays, in the tree and in the database. Erasing a
+    /// commitment again only redacts data recorded since.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the commitment is unknown or the database
+    /// malfunctions.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn erase_identity(
+        &self,
+        commitment: &Hash,
+    ) -> Result<EraseIdentityResponse, ServerError> {
+        let mut tx = self
+            .database
+            .begin_tx("erase_identity", IsolationLevel::ReadCommitted)
+            .await?;
+
+        let result = async {
+            if !tx.identity_exists(*commitment).await? {
+                return Err(ServerError::IdentityCommitmentNotFound);
+            }
+
+            let redacted_rows = tx.erase_auxiliary_data(commitment).await?;
+            let erased_at = tx
+                .get_identity_erasure(commitment)
+                .await?
+                .context("Missing erasure record")?;
+
+            Ok::<_, ServerError>((redacted_rows, erased_at))
+        }
+        .await;
+
+        let (redacted_rows, erased_at) = tx.finish(result).await?;
+
+        warn!(?commitment, redacted_rows, "Erased identity auxiliary data");
+
+        Ok(EraseIdentityResponse {
+            redacted_rows,
+            erased_at,
+        })
+    }
+
+    /// Returns where the latest requests inserting and deleting a commitment
+    /// came from, as far as they were recorded and not erased.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the commitment is unknown or the database
+    /// malfunctions.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn identity_origin(
+        &self,
+        commitment: &Hash,
+    ) -> Result<IdentityOriginResponse, ServerError> {
+        let mut tx = self
+            .database
+            .begin_tx("identity_origin", IsolationLevel::RepeatableRead)
+            .await?;
+
+        let result = async {
+            if !tx.identity_exists(*commitment).await? {
+                return Err(ServerError::IdentityCommitmentNotFound);
+            }
+
+            let insertion = tx.get_insertion_origin(commitment).await?;
+            let deletion = tx.get_deletion_origin(commitment).await?;
+
+            Ok::<_, ServerError>((insertion, deletion))
+        }
+        .await;
+
+        let (insertion, deletion) = tx.finish(result).await?;
+
+        Ok(IdentityOriginResponse {
+            insertion: insertion.map(Into::into),
+            deletion: deletion.map(Into::into),
+        })
+    }
+
+    /// Removes an identity which has not been inserted into the tree yet.
+    /// Returns `false` if the identity isn't queued for insertion, in which
+    /// case it must go through the regular deletion.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the database malfunctions.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn purge_unprocessed_identity(&self, commitment: &Hash) -> Result<bool, ServerError> {
+        // Repeatable read makes the purge fail rather than silently do nothing
+        // if the identity is concurrently moved into the tree
+        let mut tx = self
+            .database
+            .begin_tx("purge_unprocessed_identity", IsolationLevel::RepeatableRead)
+            .await?;
+
+        let result = async {
+            if tx.get_unprocessed_commitment(commitment).await?.is_none() {
+                return Ok(false);
+            }
+
+            tx.remove_unprocessed_identity(commitment).await?;
+
+            Ok::<_, ServerError>(true)
+        }
+        .await;
+
+        let purged = tx.finish(result).await?;
+
+        if purged {
+            warn!(?commitment, "Purged unprocessed identity");
+        }
+
+        Ok(purged)
+    }
+
+    fn merge_env_provers(
+        prover_urls: &[ProverConfig],
+        existing_provers: &mut HashSet<ProverConfig>,
+    ) -> HashSet<ProverConfig> {
+        let options_set: HashSet<ProverConfig> = prover_urls
+            .iter()
+            .cloned()
+            .map(|opt| ProverConfig {
+                url: opt.url,
+                batch_size: opt.batch_size,
+                max_batch_size: opt.max_batch_size,
+                hmac_secret: opt.hmac_secret,
+                timeout_s: opt.timeout_s,
+                prover_type: opt.prover_type,
+            })
+            .collect();
+
+        let env_provers: HashSet<_> = options_set.difference(existing_provers).cloned().collect();
+
+        for unique in &env_provers {
+            existing_provers.insert(unique.clone());
+        }
+
+        env_provers
+    }
+
+    /// Secrets aren't stored in the database, provers loaded from there get
+    /// the secret of the configured prover with the same batch size and type.
+    fn apply_prover_secrets(
+        prover_urls: &[ProverConfig],
+        provers: HashSet<ProverConfig>,
+    ) -> HashSet<ProverConfig> {
+        provers
+            .into_iter()
+            .map(|prover| {
+                let hmac_secret = prover_urls
+                    .iter()
+                    .find(|configured| **configured == prover)
+                    .and_then(|configured| configured.hmac_secret.clone());
+
+                ProverConfig {
+                    hmac_secret,
+                    ..prover
+                }
+            })
+            .collect()
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the provided batch size already exists.
+    /// Will return `Err` if `probe` is set and the prover can't be reached.
+    /// Will return `Err` if the batch size fails to write to database.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn add_batch_size(
+        &self,
+        url: String,
+        batch_size: usize,
+        timeout_seconds: u64,
+        prover_type: ProverType,
+        probe: bool,
+        author: &ChangeAuthor,
+    ) -> Result<AddBatchSizeResponse, ServerError> {
+        self.prover_repository
+            .add_batch_size(&url, batch_size, timeout_seconds, prover_type, probe)
+            .await?;
+
+        let prover = ProverConfig {
+            url: url.clone(),
+            timeout_s: timeout_seconds,
+            batch_size,
+            max_batch_size: None,
+            hmac_secret: None,
+            prover_type,
+        };
+
+        let stored = async {
+            let mut tx = self
+                .database
+                .begin_tx("add_batch_size", IsolationLevel::ReadCommitted)
+                .await?;
+
+            let result = async {
+                tx.insert_prover_configuration(
+                    batch_size,
+                    url.clone(),
+                    timeout_seconds,
+                    prover_type,
+                )
+                .await?;
+                tx.record_config_change(
+                    author,
+                    &ConfigChange {
+                        setting: prover_setting(batch_size, prover_type),
+                        old_value: None,
+                        new_value: Some(
+                            serde_json::to_value(prover.redacted())
+                                .context("Unserializable prover")?,
+                        ),
+                    },
+                )
+                .await?;
+
+                Ok::<_, ServerError>(())
+            }
+            .await;
+
+            tx.finish(result).await
+        }
+        .await;
+
+        // The prover is only kept if it's stored as well
+        if let Err(err) = stored {
+            self.prover_repository
+                .discard_batch_size(batch_size, prover_type)
+                .await;
+            return Err(err);
+        }
+
+        Ok(AddBatchSizeResponse {
+            url,
+            batch_size,
+            timeout_seconds,
+            prover_type,
+        })
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the requested batch size does not exist.
+    /// Will return `Err` if batch size fails to be removed from database.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn remove_batch_size(
+        &self,
+        batch_size: usize,
+        prover_type: ProverType,
+        author: &ChangeAuthor,
+    ) -> Result<(), ServerError> {
+        let prover = self
+            .prover_repository
+            .remove_batch_size(batch_size, prover_type)
+            .await?;
+
+        let stored = async {
+            let mut tx = self
+                .database
+                .begin_tx("remove_batch_size", IsolationLevel::ReadCommitted)
+                .await?;
+
+            let result = async {
+                tx.remove_prover(batch_size, prover_type).await?;
+                tx.record_config_change(
+                    author,
+                    &ConfigChange {
+                        setting: prover_setting(batch_size, prover_type),
+                        old_value: Some(
+                            serde_json::to_value(prover.redacted())
+                                .context("Unserializable prover")?,
+                        ),
+                        new_value: None,
+                    },
+                )
+                .await?;
+
+                Ok::<_, ServerError>(())
+            }
+            .await;
+
+            tx.finish(result).await
+        }
+        .await;
+
+        // The prover stays in use until it's removed from the database as well
+        if let Err(err) = stored {
+            if let Err(restore_err) = self.prover_repository.restore_batch_size(&prover).await {
+                warn!(error = ?restore_err, "Failed to restore a removed prover");
+            }
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// Runtime settings changed through the admin API, oldest first.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the database query fails.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn config_changes(
+        &self,
+        query: &ConfigChangesQuery,
+    ) -> Result<ConfigChangesResponse, ServerError> {
+        let changes = self
+            .database
+            .get_config_changes(query.after_id, query.limit())
+            .await?;
+
+        Ok(ConfigChangesResponse {
+            changes: changes.into_iter().map(ConfigChangeInfo::from).collect(),
+        })
+    }
+
+    /// Starts an export of every identity inserted so far, the export task
+    /// writes it in the background.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if exports aren't configured.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn start_export(&self) -> Result<ExportInfo, ServerError> {
+        if self.config.exports.is_none() {
+            return Err(ServerError::ExportsDisabled);
+        }
+
+        let export = self.database.insert_export().await?;
+        self.export_requested.notify_one();
+
+        info!(
+            export_id = export.id,
+            up_to_sequence_id = export.up_to_sequence_id,
+            "Export requested"
+        );
+
+        Ok(export.into())
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no export with the given id.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn export(&self, id: i64) -> Result<ExportInfo, ServerError> {
+        let export = self
+            .database
+            .get_export(id)
+            .await?
+            .ok_or(ServerError::NoSuchExport)?;
+
+        Ok(export.into())
+    }
+
+    /// Reads a chunk of an export as newline-delimited JSON.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the chunk hasn't been written yet.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn export_chunk(&self, id: i64, chunk: u64) -> Result<Vec<u8>, ServerError> {
+        let Some(config) = &self.config.exports else {
+            return Err(ServerError::ExportsDisabled);
+        };
+
+        // The file of the chunk after the checkpoint may be half written
+        let export = self
+            .database
+            .get_export(id)
+            .await?
+            .ok_or(ServerError::NoSuchExport)?;
+        if chunk >= export.chunks {
+            return Err(ServerError::NoSuchExport);
+        }
+
+        let contents = tokio::fs::read(chunk_path(&config.directory, id, chunk))
+            .await
+            .context("Failed to read export chunk")?;
+
+        Ok(contents)
+    }
+
+    /// Notified when an export is requested, see
+    /// `task_monitor::tasks::export_identities`.
+    pub fn export_requested(&self) -> &Notify {
+        &self.export_requested
+    }
+
+    /// Registers a webhook which receives root mined and root aging
+    /// notifications. Registering it again replaces its aging threshold.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the url is not a valid http(s) url, or if the aging
+    /// threshold is not a percentage from 1 to 100.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn add_root_subscription(
+        &self,
+        url: &str,
+        aging_threshold_percent: Option<u32>,
+        author: &ChangeAuthor,
+    ) -> Result<(), ServerError> {
+        let url = Url::parse(url).map_err(|_| ServerError::InvalidSubscriptionUrl)?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(ServerError::InvalidSubscriptionUrl);
+        }
+        if aging_threshold_percent.is_some_and(|percent| !(1..=100).contains(&percent)) {
+            return Err(ServerError::InvalidAgingThreshold);
+        }
+
+        let mut tx = self
+            .database
+            .begin_tx("add_root_subscription", IsolationLevel::ReadCommitted)
+            .await?;
+
+        let result = async {
+            let previous = tx.get_root_subscription(url.as_str()).await?;
+            tx.insert_root_subscription(url.as_str(), aging_threshold_percent)
+                .await?;
+            tx.record_config_change(
+                author,
+                &ConfigChange {
+                    setting: ROOT_SUBSCRIPTIONS_SETTING.to_string(),
+                    old_value: previous.map(|subscription| {
+                        subscription_value(
+                            &subscription.url,
+                            subscription.aging_threshold_percent.map(i64::from),
+                        )
+                    }),
+                    new_value: Some(subscription_value(
+                        url.as_str(),
+                        aging_threshold_percent.map(i64::from),
+                    )),
+                },
+            )
+            .await?;
+
+            Ok::<_, ServerError>(())
+        }
+        .await;
+
+        tx.finish(result).await
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no subscription for the given url.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn remove_root_subscription(
+        &self,
+        url: &str,
+        author: &ChangeAuthor,
+    ) -> Result<(), ServerError> {
+        let url = Url::parse(url).map_err(|_| ServerError::InvalidSubscriptionUrl)?;
+
+        let mut tx = self
+            .database
+            .begin_tx("remove_root_subscription", IsolationLevel::ReadCommitted)
+            .await?;
+
+        let result = async {
+            let Some(subscription) = tx.get_root_subscription(url.as_str()).await? else {
+                return Err(ServerError::NoSuchSubscription);
+            };
+            tx.remove_root_subscription(url.as_str()).await?;
+            tx.record_config_change(
+                author,
+                &ConfigChange {
+                    setting: ROOT_SUBSCRIPTIONS_SETTING.to_string(),
+                    old_value: Some(subscription_value(
+                        &subscription.url,
+                        subscription.aging_threshold_percent.map(i64::from),
+                    )),
+                    new_value: None,
+                },
+            )
+            .await?;
+
+            Ok::<_, ServerError>(())
+        }
+        .await;
+
+        tx.finish(result).await
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if something unknown went wrong.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn list_batch_sizes(&self) -> Result<ListBatchSizesResponse, ServerError> {
+        let batches = self.prover_repository.list_batch_sizes().await?;
+
+        Ok(ListBatchSizesResponse::from(batches))
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the tree is not initialized yet.
+    pub fn identity_count(&self) -> Result<IdentityCountResponse, ServerError> {
+        let tree_state = self.tree_state()?;
+
+        Ok(IdentityCountResponse {
+            leaf_count: tree_state.leaf_count(),
+            next_leaf: tree_state.latest_tree().next_leaf(),
+        })
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the tree is not initialized yet.
+    pub fn tree_info(&self) -> Result<TreeInfoResponse, ServerError> {
+        let tree_state = self.tree_state()?;
+
+        Ok(TreeInfoResponse {
+            depth: self.tree_config().tree_depth,
+            next_leaf: tree_state.latest_tree().next_leaf(),
+            root: tree_state.latest_tree().get_root(),
+            mined_root: tree_state.mined_tree().get_root(),
+        })
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the database query fails.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn tree_updates(
+        &self,
+        query: &TreeUpdatesQuery,
+    ) -> Result<TreeUpdatesResponse, ServerError> {
+        // The epoch must be read from the same snapshot as the updates, an
+        // update rewound after reading it would otherwise go unnoticed
+        let mut tx = self
+            .database
+            .begin_tx("tree_updates", IsolationLevel::RepeatableRead)
+            .await?;
+
+        let result = async {
+            let epoch = tx.get_tree_epoch().await?;
+            let updates = tx
+                .get_tree_updates(query.after_sequence_id, query.limit(), query.status)
+                .await?;
+
+            Ok::<_, ServerError>((epoch, updates))
+        }
+        .await;
+
+        let (epoch, updates) = tx.finish(result).await?;
+
+        let next_after_sequence_id = updates
+            .last()
+            .map_or(query.after_sequence_id, |update| update.sequence_id);
+
+        Ok(TreeUpdatesResponse {
+            epoch,
+            updates,
+            next_after_sequence_id,
+        })
+    }
+
+    /// Lists the relayer's transactions together with the batches they
+    /// submitted, or the recorded transactions only if there is no relayer.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the relayer or the database can't be queried, if
+    /// the relayer doesn't know the requested status or if the offset is out of
+    /// range.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn transactions(
+        &self,
+        query: &TransactionsQuery,
+    ) -> Result<TransactionsResponse, ServerError> {
+        let limit = query.limit();
+        // Offsets are passed on to Postgres as a BIGINT
+        let offset = i64::try_from(query.offset).map_err(|_| ServerError::OffsetOutOfRange)?;
+
+        let relayer_transactions = self
+            .identity_processor
+            .relayer_transactions(query.status.as_deref(), limit, query.offset)
+            .await
+            .map_err(|err| match err {
+                TxError::Parse(_) => ServerError::InvalidTransactionStatus,
+                err => ServerError::Other(err.into()),
+            })?;
+
+        let (source, mut transactions) = match relayer_transactions {
+            Some(relayer_transactions) => {
+                let transactions: Vec<_> = relayer_transactions
+                    .into_iter()
+                    .map(|tx| TransactionInfo {
+                        transaction_id: tx.transaction_id,
+                        status: Some(tx.status),
+                        hash: tx.hash,
+                        batches: vec![],
+                    })
+                    .collect();
+
+                (TransactionSource::Relayer, transactions)
+            }
+            None => {
+                let transaction_ids = self.database.get_transaction_ids(limit, offset).await?;
+                let transactions: Vec<_> = transaction_ids
+                    .into_iter()
+                    .map(|transaction_id| TransactionInfo {
+                        transaction_id,
+                        status: None,
+                        hash: None,
+                        batches: vec![],
+                    })
+                    .collect();
+
+                (TransactionSource::Local, transactions)
+            }
+        };
+
+        let transaction_ids: Vec<_> = transactions
+            .iter()
+            .map(|tx| tx.transaction_id.clone())
+            .collect();
+        let mut batches: HashMap<_, Vec<_>> = HashMap::new();
+        for batch in self
+            .database
+            .get_transaction_batches(&transaction_ids)
+            .await?
+        {
+            batches
+                .entry(batch.transaction_id)
+                .or_default()
+                .push(TransactionBatch {
+                    root: batch.batch_next_root,
+                    batch_type: batch.batch_type,
+                    submitted_at: batch.created_at,
+                    note: batch.note,
+                });
+        }
+
+        for tx in &mut transactions {
+            tx.batches = batches.remove(&tx.transaction_id).unwrap_or_default();
+        }
+
+        Ok(TransactionsResponse {
+            source,
+            transactions,
+        })
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the provided index is out of bounds.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn inclusion_proof(
+        &self,
+        commitment: &Hash,
+    ) -> Result<InclusionProofResponse, ServerError> {
+        if self.identity_validator.is_initial_leaf(commitment) {
+            return Err(ServerError::InvalidCommitment);
+        }
+
+        let unprocessed = InclusionProofResponse {
+            status: UnprocessedStatus::New.into(),
+            root: None,
+            proof: None,
+            message: None,
+        };
+
+        // Commitments in the tree are served with a single query, only the
+        // others need the queue to be checked separately
+        let Some(item) = self
+            .database
+            .get_tree_item_with_root_status(commitment)
+            .await?
+        else {
+            return match self.database.get_unprocessed_commitment(commitment).await? {
+                Some(_) => Ok(unprocessed),
+                None => Err(ServerError::IdentityCommitmentNotFound),
+            };
+        };
+
+        if item.unprocessed {
+            return Ok(unprocessed);
+        }
+
+        let item = TreeItem {
+            status: item.root_status,
+            leaf_index: item.leaf_index,
+        };
+        let commitment = *commitment;
+        let proof = self
+            .read_tree(move |tree_state| tree_state.get_proof_for(&item, &commitment))
+            .await?
+            .ok_or(ServerError::InvalidCommitment)?;
+
+        Ok(proof.into())
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the provided proof is invalid.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn verify_semaphore_proof(
+        &self,
+        request: &VerifySemaphoreProofRequest,
+        query: &VerifySemaphoreProofQuery,
+    ) -> Result<VerifySemaphoreProofResponse, ServerError> {
+        self.verify_proof(request, query, false).await
+    }
+
+    /// Verifies the proof, with roots unknown to this instance looked up at the
+    /// external root oracle if `external_roots` is set.
+    async fn verify_proof(
+        &self,
+        request: &VerifySemaphoreProofRequest,
+        query: &VerifySemaphoreProofQuery,
+        external_roots: bool,
+    ) -> Result<VerifySemaphoreProofResponse, ServerError> {
+        if query.max_root_age_seconds.is_some_and(|age| age < 0) {
+            return Err(ServerError::InvalidMaxRootAge);
+        }
+
+        if !self.identity_validator.is_reduced(request.signal_hash) {
+            return Err(ServerError::InvalidSignalHash);
+        }
+
+        if !self
+            .identity_validator
+            .is_reduced(request.external_nullifier_hash)
+        {
+            return Err(ServerError::InvalidExternalNullifierHash);
+        }
+
+        let root_state = match self.root_state(&request.root).await? {
+            None if external_roots => self.external_root_state(&request.root).await,
+            root_state => root_state,
+        };
+        let Some((root_state, db_now)) = root_state else {
+            return Err(ServerError::InvalidRoot);
+        };
+
+        if let Some(max_root_age_seconds) = query.max_root_age_seconds {
+            let max_root_age = Duration::seconds(max_root_age_seconds);
+            self.validate_root_age(max_root_age, &root_state, db_now)?;
+        }
+
+        let checked = self
+            .proof_verifier
+            .verify(request.clone(), self.tree_config().tree_depth)
+            .await;
+
+        match checked {
+            Ok(true) => Ok(root_state.into()),
+            Ok(false) => Err(ServerError::InvalidProof),
+            Err(VerifyError::Overloaded) => Err(ServerError::VerificationOverloaded),
+            Err(err) => {
+                info!(?err, "verify_proof failed with error");
+                Err(ServerError::ProverError)
+            }
+        }
+    }
+
+    /// Same as `verify_semaphore_proof`, but each nullifier hash is only
+    /// accepted once so that a valid proof can't be replayed. Roots unknown to
+    /// this instance are accepted if the external root oracle confirms them.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the proof is invalid, see `verify_semaphore_proof`.
+    /// Will return `Err` if the nullifier hash was already spent.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn verify_semaphore_proof_v2(
+        &self,
+        request: &VerifySemaphoreProofRequest,
+        query: &VerifySemaphoreProofQuery,
+    ) -> Result<VerifySemaphoreProofResponse, ServerError> {
+        let response = self.verify_proof(request, query, true).await?;
+
+        if !self
+            .database
+            .insert_spent_nullifier(&request.nullifier_hash)
+            .await?
+        {
+            return Err(ServerError::NullifierAlreadySpent);
+        }
+
+        self.metrics.nullifiers_spent.inc();
+
+        Ok(response)
+    }
+
+    /// Whether the nullifier hash was spent by `verify_semaphore_proof_v2`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the database malfunctions.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn nullifier_status(
+        &self,
+        nullifier_hash: &Hash,
+    ) -> Result<NullifierStatusResponse, ServerError> {
+        let response = match self.database.get_nullifier_spent_at(nullifier_hash).await? {
+            Some(spent_at) => NullifierStatusResponse {
+                nullifier_hash: Some(*nullifier_hash),
+                spent: true,
+                spent_at: Some(spent_at),
+            },
+            None => NullifierStatusResponse {
+                nullifier_hash: None,
+                spent: false,
+                spent_at: None,
+            },
+        };
+
+        Ok(response)
+    }
+
+    /// The state of `root` together with the current time of the database.
+    ///
+    /// The latest tree is updated before the identities inserted into it are
+    /// committed, so its root can be handed out before it's in the database.
+    /// Such a root is reported as pending as of now, without a database time.
+    async fn root_state(
+        &self,
+        root: &Hash,
+    ) -> Result<Option<(RootItem, Option<DateTime<Utc>>)>, ServerError> {
+        if let Some((root_state, db_now)) = self.database.get_root_state_with_db_time(root).await? {
+            return Ok(Some((root_state, Some(db_now))));
         }
+
+        if self.tree_state()?.get_latest_tree().get_root() != *root {
+            return Ok(None);
+        }
+
+        let root_state = RootItem {
+            root: *root,
+            status: ProcessedStatus::Pending,
+            pending_valid_as_of: Utc::now(),
+            mined_valid_as_of: None,
+        };
+
+        Ok(Some((root_state, None)))
+    }
+
+    /// The state of a root confirmed by the external root oracle, if there is
+    /// one. The root is treated as mined since the oracle's timestamp, and is
+    /// unknown if the oracle fails.
+    async fn external_root_state(&self, root: &Hash) -> Option<(RootItem, Option<DateTime<Utc>>)> {
+        let oracle = self.root_oracle.as_ref()?;
+
+        let valid_as_of = match oracle.root_valid_as_of(root).await {
+            Ok(valid_as_of) => valid_as_of?,
+            Err(err) => {
+                warn!(?err, ?root, "Failed to look up root at the external oracle");
+                return None;
+            }
+        };
+
+        let root_state = RootItem {
+            root: *root,
+            status: ProcessedStatus::Mined,
+            pending_valid_as_of: valid_as_of,
+            mined_valid_as_of: Some(valid_as_of),
+        };
+
+        // The timestamp isn't from the database clock, so the age is computed
+        // against ours
+        Some((root_state, None))
+    }
+
+    /// Runs a read of the trees on the blocking pool. The tree versions are
+    /// behind blocking locks, computing proofs while holding them would stall
+    /// the runtime threads.
+    async fn read_tree<T, F>(&self, read: F) -> Result<T, ServerError>
+    where
+        F: FnOnce(&TreeState) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let tree_state = self.tree_state()?.clone();
+
+        let _permit = self
+            .tree_read_permits
+            .acquire()
+            .await
+            .expect("the semaphore is never closed");
+
+        tokio::task::spawn_blocking(move || read(&tree_state))
+            .await
+            .map_err(|err| ServerError::Other(err.into()))
     }
 
+    /// `db_now` is the current time of the database if the root timestamps
+    /// were set by its clock, which may be skewed against ours. Otherwise the
+    /// age is computed against our clock.
     fn validate_root_age(
         &self,
         max_root_age: Duration,
         root_state: &RootItem,
+        db_now: Option<DateTime<Utc>>,
     ) -> Result<(), ServerError> {
         let tree_state = self.tree_state()?;
         let latest_root = tree_state.get_latest_tree().get_root();
@@ -412,19 +2016,17 @@ impl App {
             _ => (),
         }
 
-        let now = Utc::now();
-        let root_age = if matches!(
-            root_state.status,
-            ProcessedStatus::Pending | ProcessedStatus::Processed
-        ) {
-            now - root_state.pending_valid_as_of
-        } else {
-            let mined_at = root_state
-                .mined_valid_as_of
-                .ok_or(ServerError::InvalidRoot)?;
-            now - mined_at
+        let app_now = Utc::now();
+        let now = match db_now {
+            Some(db_now) => {
+                self.clock_skew.observe(app_now, db_now);
+                db_now
+            }
+            None => app_now,
         };
 
+        let root_age = root_age(root_state, now)?;
+
         warn!("Root age: {root_age:?}");
 
         if root_age > max_root_age {
@@ -434,3 +2036,162 @@ impl App {
         }
     }
 }
+
+/// The age of a root at `now`, by the clock which set its timestamps.
+fn root_age(root_state: &RootItem, now: DateTime<Utc>) -> Result<Duration, ServerError> {
+    if matches!(
+        root_state.status,
+        ProcessedStatus::Pending | ProcessedStatus::Processed
+    ) {
+        return Ok(now - root_state.pending_valid_as_of);
+    }
+
+    let mined_at = root_state
+        .mined_valid_as_of
+        .ok_or(ServerError::InvalidRoot)?;
+
+    Ok(now - mined_at)
+}
+
+/// Logs the skew between the application and database clocks when it crosses
+/// `CLOCK_SKEW_WARNING_THRESHOLD`, rather than on every root age check.
+#[derive(Debug, Default)]
+struct ClockSkew {
+    skewed: AtomicBool,
+}
+
+impl ClockSkew {
+    /// Returns whether the clocks went out of sync or back into it.
+    fn observe(&self, app_now: DateTime<Utc>, db_now: DateTime<Utc>) -> bool {
+        let clock_skew = app_now - db_now;
+        let skewed = clock_skew.abs() > CLOCK_SKEW_WARNING_THRESHOLD;
+        if self.skewed.swap(skewed, Ordering::Relaxed) == skewed {
+            return false;
+        }
+
+        if skewed {
+            warn!(
+                ?clock_skew,
+                "Application clock is skewed against the database"
+            );
+        } else {
+            info!(
+                ?clock_skew,
+                "Application clock is in sync with the database again"
+            );
+        }
+
+        true
+    }
+}
+
+/// Unique across hosts, processes and the apps built within a process, e.g. in
+/// tests.
+fn new_instance_id() -> String {
+    static APPS_BUILT: AtomicUsize = AtomicUsize::new(0);
+
+    let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "localhost".to_owned());
+    format!(
+        "{host}/{}/{}/{}",
+        std::process::id(),
+        Utc::now().timestamp_micros(),
+        APPS_BUILT.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// The name of the prover for `batch_size` and `prover_type` in the config
+/// change log.
+fn prover_setting(batch_size: usize, prover_type: ProverType) -> String {
+    format!("provers.{prover_type}.{batch_size}")
+}
+
+const ROOT_SUBSCRIPTIONS_SETTING: &str = "root_subscriptions";
+
+/// Change log value of a root subscription, the url identifies it.
+fn subscription_value(url: &str, aging_threshold_percent: Option<i64>) -> serde_json::Value {
+    serde_json::json!({
+        "url": url,
+        "agingThresholdPercent": aging_threshold_percent,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send<T: Send>(_: &T) {}
+
+    // Fails to compile if one of the tree reads holds a lock guard across an
+    // await, the futures of the handlers must be `Send`
+    #[allow(dead_code)]
+    fn tree_read_futures_are_send(
+        app: &App,
+        commitment: Hash,
+        request: &VerifySemaphoreProofRequest,
+        query: &VerifySemaphoreProofQuery,
+    ) {
+        assert_send(&app.inclusion_proof(&commitment));
+        assert_send(&app.simulate_insert_identity(commitment));
+        assert_send(&app.verify_semaphore_proof(request, query));
+        assert_send(&app.read_tree(|tree_state| tree_state.leaf_count()));
+    }
+
+    #[test]
+    fn root_age_ignores_application_clock_skew() {
+        let db_now = Utc::now();
+        // The application clock runs 40 seconds ahead of the database
+        let app_now = db_now + Duration::seconds(40);
+
+        let root_state = RootItem {
+            root: Hash::ZERO,
+            status: ProcessedStatus::Mined,
+            pending_valid_as_of: db_now - Duration::seconds(2),
+            mined_valid_as_of: Some(db_now - Duration::seconds(1)),
+        };
+        assert_eq!(root_age(&root_state, db_now).unwrap(), Duration::seconds(1));
+
+        // The skew is only logged when it appears and goes away
+        let clock_skew = ClockSkew::default();
+        assert!(!clock_skew.observe(db_now, db_now));
+        assert!(clock_skew.observe(app_now, db_now));
+        assert!(!clock_skew.observe(app_now, db_now));
+        assert!(clock_skew.observe(db_now + Duration::seconds(1), db_now));
+        assert!(!clock_skew.observe(db_now, db_now));
+    }
+
+    fn prover(batch_size: usize, prover_type: ProverType, url: &str) -> ProverConfig {
+        ProverConfig {
+            url: url.to_string(),
+            timeout_s: 30,
+            batch_size,
+            max_batch_size: None,
+            hmac_secret: None,
+            prover_type,
+        }
+    }
+
+    #[test]
+    fn merge_env_provers_keys_by_batch_size_and_type() {
+        let mut existing_provers =
+            HashSet::from([prover(100, ProverType::Insertion, "http://insertion:3000")]);
+        let env_provers = [
+            prover(100, ProverType::Insertion, "http://moved:3000"),
+            prover(100, ProverType::Deletion, "http://deletion:3000"),
+        ];
+
+        let new_provers = App::merge_env_provers(&env_provers, &mut existing_provers);
+
+        assert_eq!(
+            new_provers,
+            HashSet::from([prover(100, ProverType::Deletion, "http://deletion:3000")])
+        );
+        assert_eq!(existing_provers.len(), 2);
+
+        // The registered insertion prover isn't replaced by the env one
+        let insertion = existing_provers
+            .iter()
+            .find(|prover| prover.prover_type == ProverType::Insertion)
+            .unwrap();
+        assert_eq!(insertion.url, "http://insertion:3000");
+    }
+}
@@ -4,6 +4,7 @@ pub mod scanner;
 
 use anyhow::{anyhow, bail};
 use ethers::providers::Middleware;
+use ethers::types::transaction::eip2718::TypedTransaction;
 use ethers::types::U256;
 use tracing::{error, info, instrument};
 
@@ -91,15 +92,16 @@ impl IdentityManager {
         Ok(identity_manager)
     }
 
+    /// Builds the transaction registering an insertion batch.
     #[instrument(level = "debug", skip(self, identity_commitments, proof_data))]
-    pub async fn register_identities(
+    pub fn register_identities(
         &self,
         start_index: usize,
         pre_root: U256,
         post_root: U256,
-        identity_commitments: Vec<Identity>,
+        identity_commitments: &[Identity],
         proof_data: Proof,
-    ) -> anyhow::Result<TransactionId> {
+    ) -> anyhow::Result<TypedTransaction> {
         let actual_start_index: u32 = start_index.try_into()?;
 
         let proof_points_array: [U256; 8] = proof_data.into();
@@ -109,8 +111,7 @@ impl IdentityManager {
             .collect();
 
         // We want to send the transaction through our ethereum provider rather than
-        // directly now. To that end, we create it, and then send it later, waiting for
-        // it to complete.
+        // directly. To that end, we only create it here.
         let register_identities_transaction = self
             .abi
             .register_identities(
@@ -122,35 +123,35 @@ impl IdentityManager {
             )
             .tx;
 
-        self.ethereum
-            .send_transaction(register_identities_transaction, true)
-            .await
-            .map_err(|tx_err| anyhow!("{}", tx_err.to_string()))
+        Ok(register_identities_transaction)
     }
 
-    // TODO: docs
+    /// Builds the transaction deleting a batch of identities.
     #[instrument(level = "debug")]
-    pub async fn delete_identities(
+    pub fn delete_identities(
         &self,
         deletion_proof: Proof,
         packed_deletion_indices: Vec<u8>,
         pre_root: U256,
         post_root: U256,
-    ) -> anyhow::Result<TransactionId> {
+    ) -> TypedTransaction {
         let proof_points_array: [U256; 8] = deletion_proof.into();
 
-        let delete_identities_transaction = self
-            .abi
+        self.abi
             .delete_identities(
                 proof_points_array,
                 packed_deletion_indices.into(),
                 pre_root,
                 post_root,
             )
-            .tx;
+            .tx
+    }
 
+    /// Sends a transaction built by this identity manager and returns its id.
+    #[instrument(level = "debug", skip_all)]
+    pub async fn send_transaction(&self, tx: TypedTransaction) -> anyhow::Result<TransactionId> {
         self.ethereum
-            .send_transaction(delete_identities_transaction, true)
+            .send_transaction(tx, true)
             .await
             .map_err(|tx_err| anyhow!("{}", tx_err.to_string()))
     }
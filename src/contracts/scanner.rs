@@ -1,10 +1,51 @@
-use ethers::providers::Middleware;
+use std::time::Duration;
+
+use ethers::providers::{Middleware, MiddlewareError};
 use ethers::types::{Address, BlockNumber, Filter, FilterBlockOption, Log, Topic, ValueOrArray};
+use prometheus::{IntCounterVec, IntGaugeVec};
+use tracing::warn;
+
+use crate::metrics::Metrics;
+
+/// EIP-1474 "Limit exceeded"
+const LIMIT_EXCEEDED_ERROR_CODE: i64 = -32005;
+
+/// Fragments of error messages providers return when a `eth_getLogs` range is
+/// too large
+const RANGE_LIMIT_ERROR_MESSAGES: &[&str] = &[
+    "block range",
+    "range too large",
+    "range is too large",
+    "too many blocks",
+    "query returned more than",
+    "exceed maximum block range",
+    "exceeds max block range",
+];
+
+/// Fragments of error messages for requests which didn't reach a healthy
+/// provider and may succeed when retried
+const TRANSIENT_ERROR_MESSAGES: &[&str] = &[
+    "connection refused",
+    "connection reset",
+    "error sending request",
+    "timed out",
+    "timeout",
+    "503",
+    "service unavailable",
+];
 
 pub struct BlockScanner<T> {
     read_provider: T,
     current_block: u64,
+    // The configured window size, never exceeded
+    max_window_size: u64,
+    // The window size currently accepted by the provider
     window_size: u64,
+    label: String,
+    window_size_gauge: Option<IntGaugeVec>,
+    rpc_errors: Option<IntCounterVec>,
+    max_rpc_retries: u32,
+    rpc_retry_interval: Duration,
 
     // How many blocks from the chain head to scan to
     // e.g. if latest block is 20 and offset is set to 3
@@ -20,12 +61,43 @@ where
     pub async fn new_latest(read_provider: T, window_size: u64) -> anyhow::Result<Self> {
         let latest_block = read_provider.get_block_number().await?;
 
-        Ok(Self {
+        Ok(Self::new(read_provider, latest_block.as_u64(), window_size))
+    }
+
+    fn new(read_provider: T, current_block: u64, window_size: u64) -> Self {
+        let scanner = Self {
             read_provider,
-            current_block: latest_block.as_u64(),
+            current_block,
+            max_window_size: window_size,
             window_size,
+            label: "default".to_string(),
+            window_size_gauge: None,
+            rpc_errors: None,
+            max_rpc_retries: 0,
+            rpc_retry_interval: Duration::ZERO,
             chain_head_offset: 0,
-        })
+        };
+
+        scanner.report_window_size();
+
+        scanner
+    }
+
+    /// Reports the effective window size and failed requests through the
+    /// given metrics
+    pub fn with_metrics(mut self, metrics: &Metrics) -> Self {
+        self.window_size_gauge = Some(metrics.scanner_window_size.clone());
+        self.rpc_errors = Some(metrics.eth_rpc_errors.clone());
+        self.report_window_size();
+        self
+    }
+
+    /// Retries transient failures to get the latest block up to `max_retries`
+    /// times, waiting `interval` in between
+    pub fn with_rpc_retries(mut self, max_retries: u32, interval: Duration) -> Self {
+        self.max_rpc_retries = max_retries;
+        self.rpc_retry_interval = interval;
+        self
     }
 
     pub fn with_offset(mut self, chain_head_offset: u64) -> Self {
@@ -33,12 +105,19 @@ where
         self
     }
 
+    /// Sets the label under which the effective window size is reported
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = label.into();
+        self.report_window_size();
+        self
+    }
+
     pub async fn next(
         &mut self,
         address: Option<ValueOrArray<Address>>,
         topics: [Option<Topic>; 4],
     ) -> anyhow::Result<Vec<Log>> {
-        let latest_block = self.read_provider.get_block_number().await?.as_u64();
+        let latest_block = self.latest_block().await?;
         let latest_block = latest_block.saturating_sub(self.chain_head_offset);
 
         if self.current_block >= latest_block {
@@ -46,27 +125,365 @@ where
         }
 
         let from_block = self.current_block;
-        let to_block = latest_block.min(from_block + self.window_size);
 
-        let next_current_block = to_block + 1;
+        loop {
+            let to_block = latest_block.min(from_block + self.window_size);
+
+            let result = self
+                .read_provider
+                .get_logs(&Filter {
+                    block_option: FilterBlockOption::Range {
+                        from_block: Some(BlockNumber::Number(from_block.into())),
+                        to_block: Some(BlockNumber::Number(to_block.into())),
+                    },
+                    address: address.clone(),
+                    topics: topics.clone(),
+                })
+                .await;
+
+            match result {
+                Ok(logs) => {
+                    self.current_block = to_block + 1;
+                    self.grow_window();
+
+                    return Ok(logs);
+                }
+                Err(err) if self.window_size > 1 && is_range_limit_error(&err) => {
+                    self.window_size /= 2;
+                    self.report_window_size();
+
+                    warn!(
+                        scanner = %self.label,
+                        window_size = self.window_size,
+                        ?err,
+                        "Provider rejected block range, shrinking scanning window"
+                    );
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    async fn latest_block(&self) -> anyhow::Result<u64> {
+        let mut retries = 0;
+
+        loop {
+            let err = match self.read_provider.get_block_number().await {
+                Ok(block) => return Ok(block.as_u64()),
+                Err(err) => err,
+            };
+
+            if let Some(rpc_errors) = &self.rpc_errors {
+                rpc_errors.with_label_values(&["get_block_number"]).inc();
+            }
+
+            if retries >= self.max_rpc_retries || !is_transient_error(&err) {
+                return Err(err.into());
+            }
+            retries += 1;
+
+            warn!(
+                scanner = %self.label,
+                retries,
+                max_retries = self.max_rpc_retries,
+                ?err,
+                "Failed to get the latest block, retrying"
+            );
+
+            tokio::time::sleep(self.rpc_retry_interval).await;
+        }
+    }
+
+    // Grows the window back towards the configured size, slowly enough that a
+    // provider limit isn't immediately hit again
+    fn grow_window(&mut self) {
+        if self.window_size < self.max_window_size {
+            let step = (self.window_size / 10).max(1);
+            self.window_size = (self.window_size + step).min(self.max_window_size);
+            self.report_window_size();
+        }
+    }
+
+    fn report_window_size(&self) {
+        let Some(gauge) = &self.window_size_gauge else {
+            return;
+        };
+
+        gauge
+            .with_label_values(&[&self.label])
+            .set(self.window_size.try_into().unwrap_or(i64::MAX));
+    }
+}
+
+fn is_range_limit_error<E: MiddlewareError>(err: &E) -> bool {
+    if let Some(response) = err.as_error_response() {
+        if response.code == LIMIT_EXCEEDED_ERROR_CODE {
+            return true;
+        }
+    }
+
+    let message = err.to_string().to_lowercase();
+
+    RANGE_LIMIT_ERROR_MESSAGES
+        .iter()
+        .any(|fragment| message.contains(fragment))
+}
+
+fn is_transient_error<E: MiddlewareError>(err: &E) -> bool {
+    // The provider answered, retrying won't change its mind
+    if err.as_error_response().is_some() {
+        return false;
+    }
+
+    let message = err.to_string().to_lowercase();
+
+    TRANSIENT_ERROR_MESSAGES
+        .iter()
+        .any(|fragment| message.contains(fragment))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Debug;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use async_trait::async_trait;
+    use ethers::providers::{JsonRpcClient, JsonRpcError, Provider, ProviderError, RpcError};
+    use ethers::types::U64;
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Debug, thiserror::Error)]
+    enum MockError {
+        #[error(transparent)]
+        JsonRpc(JsonRpcError),
+        #[error(transparent)]
+        Serde(#[from] serde_json::Error),
+        #[error("tcp connect error: Connection refused (os error 111)")]
+        ConnectionRefused,
+    }
+
+    impl RpcError for MockError {
+        fn as_error_response(&self) -> Option<&JsonRpcError> {
+            match self {
+                Self::JsonRpc(err) => Some(err),
+                Self::Serde(_) | Self::ConnectionRefused => None,
+            }
+        }
+
+        fn as_serde_error(&self) -> Option<&serde_json::Error> {
+            match self {
+                Self::JsonRpc(_) | Self::ConnectionRefused => None,
+                Self::Serde(err) => Some(err),
+            }
+        }
+    }
+
+    impl From<MockError> for ProviderError {
+        fn from(err: MockError) -> Self {
+            ProviderError::JsonRpcClientError(Box::new(err))
+        }
+    }
+
+    /// A provider which returns a single log for every block and rejects
+    /// `eth_getLogs` ranges spanning more than `max_range` blocks
+    #[derive(Debug)]
+    struct RangeLimitedProvider {
+        latest_block: u64,
+        max_range: u64,
+    }
+
+    #[async_trait]
+    impl JsonRpcClient for RangeLimitedProvider {
+        type Error = MockError;
+
+        async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+        where
+            T: Debug + Serialize + Send + Sync,
+            R: DeserializeOwned + Send,
+        {
+            let response = match method {
+                "eth_blockNumber" => json!(U64::from(self.latest_block)),
+                "eth_getLogs" => {
+                    let params = serde_json::to_value(params)?;
+                    let block = |key: &str| -> u64 {
+                        let hex = params[0][key].as_str().unwrap().trim_start_matches("0x");
+                        u64::from_str_radix(hex, 16).unwrap()
+                    };
+                    let (from_block, to_block) = (block("fromBlock"), block("toBlock"));
+
+                    if to_block - from_block + 1 > self.max_range {
+                        return Err(MockError::JsonRpc(JsonRpcError {
+                            code: LIMIT_EXCEEDED_ERROR_CODE,
+                            message: format!("query exceeds max block range {}", self.max_range),
+                            data: None,
+                        }));
+                    }
+
+                    let logs: Vec<Log> = (from_block..=to_block)
+                        .map(|block_number| Log {
+                            block_number: Some(block_number.into()),
+                            ..Default::default()
+                        })
+                        .collect();
 
-        let from_block = Some(BlockNumber::Number(from_block.into()));
-        let to_block = Some(BlockNumber::Number(to_block.into()));
+                    serde_json::to_value(logs)?
+                }
+                method => unimplemented!("{method}"),
+            };
+
+            Ok(serde_json::from_value::<R>(response)?)
+        }
+    }
+
+    /// A provider which refuses the connection for the first `failures`
+    /// requests of the latest block and has no logs
+    #[derive(Debug)]
+    struct FlakyProvider {
+        failures: AtomicU32,
+    }
+
+    #[async_trait]
+    impl JsonRpcClient for FlakyProvider {
+        type Error = MockError;
+
+        async fn request<T, R>(&self, method: &str, _params: T) -> Result<R, Self::Error>
+        where
+            T: Debug + Serialize + Send + Sync,
+            R: DeserializeOwned + Send,
+        {
+            let response = match method {
+                "eth_blockNumber" => {
+                    let failed = self
+                        .failures
+                        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |failures| {
+                            failures.checked_sub(1)
+                        })
+                        .is_ok();
+                    if failed {
+                        return Err(MockError::ConnectionRefused);
+                    }
+
+                    json!(U64::from(10))
+                }
+                "eth_getLogs" => json!([]),
+                method => unimplemented!("{method}"),
+            };
+
+            Ok(serde_json::from_value::<R>(response)?)
+        }
+    }
+
+    fn flaky_scanner(failures: u32, max_retries: u32) -> BlockScanner<Provider<FlakyProvider>> {
+        let provider = Provider::new(FlakyProvider {
+            failures: AtomicU32::new(failures),
+        });
+
+        BlockScanner::new(provider, 0, 100).with_rpc_retries(max_retries, Duration::ZERO)
+    }
 
-        let logs = self
-            .read_provider
-            .get_logs(&Filter {
-                block_option: FilterBlockOption::Range {
-                    from_block,
-                    to_block,
-                },
-                address,
-                topics,
-            })
-            .await?;
+    fn scanner(
+        latest_block: u64,
+        max_range: u64,
+        window_size: u64,
+    ) -> BlockScanner<Provider<RangeLimitedProvider>> {
+        let provider = Provider::new(RangeLimitedProvider {
+            latest_block,
+            max_range,
+        });
+
+        BlockScanner::new(provider, 0, window_size)
+    }
+
+    async fn scan_all(scanner: &mut BlockScanner<Provider<RangeLimitedProvider>>) -> Vec<u64> {
+        let mut blocks = vec![];
+
+        loop {
+            let logs = scanner.next(None, Default::default()).await.unwrap();
+            if logs.is_empty() {
+                break;
+            }
+
+            blocks.extend(logs.iter().map(|log| log.block_number.unwrap().as_u64()));
+        }
+
+        blocks
+    }
+
+    #[tokio::test]
+    async fn shrinks_window_without_losing_logs() {
+        let mut scanner = scanner(1000, 10, 100);
+
+        let blocks = scan_all(&mut scanner).await;
+
+        assert_eq!(blocks, (0..=1000).collect::<Vec<_>>());
+        // Never returns to the configured window the provider rejects
+        assert!(scanner.window_size <= 10);
+    }
+
+    #[tokio::test]
+    async fn keeps_configured_window_when_accepted() {
+        let mut scanner = scanner(1000, 2000, 100);
+
+        let blocks = scan_all(&mut scanner).await;
+
+        assert_eq!(blocks, (0..=1000).collect::<Vec<_>>());
+        assert_eq!(scanner.window_size, 100);
+    }
+
+    #[tokio::test]
+    async fn grows_window_back_up_to_configured_size() {
+        let mut scanner = scanner(10_000, 2000, 100);
+        scanner.window_size = 5;
+
+        scan_all(&mut scanner).await;
+
+        assert_eq!(scanner.window_size, 100);
+    }
+
+    #[tokio::test]
+    async fn fails_when_window_cannot_shrink() {
+        let provider = Provider::new(RangeLimitedProvider {
+            latest_block: 1000,
+            max_range: 0,
+        });
+        let mut scanner = BlockScanner::new(provider, 0, 1);
+
+        assert!(scanner.next(None, Default::default()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn retries_unavailable_provider() {
+        let metrics = Metrics::default();
+        let mut scanner = flaky_scanner(2, 3).with_metrics(&metrics);
+
+        scanner.next(None, Default::default()).await.unwrap();
+
+        assert_eq!(scanner.current_block, 11);
+        assert_eq!(
+            metrics
+                .eth_rpc_errors
+                .with_label_values(&["get_block_number"])
+                .get(),
+            2
+        );
+    }
 
-        self.current_block = next_current_block;
+    #[tokio::test]
+    async fn fails_when_retries_are_exhausted() {
+        let metrics = Metrics::default();
+        let mut scanner = flaky_scanner(5, 2).with_metrics(&metrics);
 
-        Ok(logs)
+        assert!(scanner.next(None, Default::default()).await.is_err());
+        assert_eq!(
+            metrics
+                .eth_rpc_errors
+                .with_label_values(&["get_block_number"])
+                .get(),
+            3
+        );
     }
 }
@@ -0,0 +1,155 @@
+//! Injection of database faults, to exercise the error paths which a healthy
+//! Postgres never takes. Only built with the `db-fault-injection` feature.
+//!
+//! Faults are injected per [`DbMethods`](super::methods::DbMethods) method and
+//! are raised when the method acquires its connection. They are process wide,
+//! tests injecting them must not run concurrently with other tests of the same
+//! binary.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use sqlx::error::{DatabaseError, ErrorKind};
+
+/// Matches every method.
+pub const ANY_METHOD: &str = "*";
+
+static FAULTS: Lazy<Mutex<Faults>> = Lazy::new(Mutex::default);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fault {
+    /// Fails like a pool which can't reach the database
+    Unavailable,
+    /// Fails with SQLSTATE 40001, as if a serializable transaction conflicted
+    SerializationFailure,
+    /// Delays the call, which then proceeds as usual
+    Latency(Duration),
+}
+
+/// Which calls of a method a fault applies to, counted from the injection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Trigger {
+    /// Only the nth call, starting at 1
+    Nth(usize),
+    /// Every call
+    Always,
+}
+
+#[derive(Default)]
+struct Faults {
+    rules: Vec<Rule>,
+    calls: HashMap<&'static str, usize>,
+}
+
+struct Rule {
+    method: &'static str,
+    fault: Fault,
+    trigger: Trigger,
+    seen: usize,
+}
+
+/// Injects `fault` into the calls of `method` selected by `trigger`, or into
+/// the calls of all methods with [`ANY_METHOD`].
+pub fn inject(method: &'static str, fault: Fault, trigger: Trigger) {
+    FAULTS.lock().unwrap().rules.push(Rule {
+        method,
+        fault,
+        trigger,
+        seen: 0,
+    });
+}
+
+/// Removes all injected faults and resets the call counts.
+pub fn reset() {
+    let mut faults = FAULTS.lock().unwrap();
+
+    faults.rules.clear();
+    faults.calls.clear();
+}
+
+/// The number of calls of `method` since the last reset, including failed
+/// ones.
+#[must_use]
+pub fn calls(method: &str) -> usize {
+    FAULTS
+        .lock()
+        .unwrap()
+        .calls
+        .get(method)
+        .copied()
+        .unwrap_or(0)
+}
+
+pub(super) async fn before_call(method: &'static str) -> Result<(), sqlx::Error> {
+    let faults = {
+        let mut faults = FAULTS.lock().unwrap();
+        *faults.calls.entry(method).or_default() += 1;
+
+        faults
+            .rules
+            .iter_mut()
+            .filter(|rule| rule.method == method || rule.method == ANY_METHOD)
+            .filter_map(|rule| {
+                rule.seen += 1;
+
+                match rule.trigger {
+                    Trigger::Nth(n) if n != rule.seen => None,
+                    _ => Some(rule.fault),
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+
+    for fault in faults {
+        match fault {
+            Fault::Unavailable => return Err(sqlx::Error::PoolTimedOut),
+            Fault::SerializationFailure => {
+                return Err(sqlx::Error::Database(Box::new(SerializationFailure)));
+            }
+            Fault::Latency(latency) => tokio::time::sleep(latency).await,
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug)]
+struct SerializationFailure;
+
+impl fmt::Display for SerializationFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for SerializationFailure {}
+
+impl DatabaseError for SerializationFailure {
+    fn message(&self) -> &str {
+        "could not serialize access due to concurrent update (injected)"
+    }
+
+    fn code(&self) -> Option<Cow<'_, str>> {
+        Some(Cow::Borrowed("40001"))
+    }
+
+    fn as_error(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+        self
+    }
+
+    fn as_error_mut(&mut self) -> &mut (dyn std::error::Error + Send + Sync + 'static) {
+        self
+    }
+
+    fn into_error(self: Box<Self>) -> Box<dyn std::error::Error + Send + Sync + 'static> {
+        self
+    }
+
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
@@ -1,21 +1,52 @@
 use std::collections::HashSet;
+use std::ops::RangeInclusive;
+use std::time::Duration;
 
 use axum::async_trait;
 use chrono::{DateTime, Utc};
-use sqlx::{Acquire, Executor, Postgres, Row};
+use sqlx::{Acquire, Executor, FromRow, Postgres, Row};
 use tracing::instrument;
-
-use super::types::{DeletionEntry, LatestDeletionEntry, LatestInsertionEntry};
-use crate::database::types::{BatchEntry, BatchEntryData, BatchType};
+use url::Url;
+
+use super::types::{
+    DeletionEntry, DeletionIntent, LatestDeletionEntry, LatestInsertionEntry, RequestOrigin,
+    RequestTrace, TreeItemWithRootStatus, TreeUpdateEntry, AUXILIARY_DATA,
+};
+use crate::database::types::{
+    BatchApproval, BatchEntry, BatchEntryData, BatchNoteEntry, BatchType, BulkImportProgress,
+    ChangeAuthor, Commitments, ConfigChange, ConfigChangeEntry, ExportEntry, LeafIndexes,
+    RootNotificationKind, RootSubscription, TransactionBatchEntry,
+};
 use crate::database::Error;
 use crate::identity_tree::{Hash, ProcessedStatus, RootItem, TreeItem, TreeUpdate};
 use crate::prover::identity::Identity;
-use crate::prover::{ProverConfig, ProverType};
+use crate::prover::{prover_label, ProverConfig, ProverType};
 
-const MAX_UNPROCESSED_FETCH_COUNT: i64 = 10_000;
+pub(crate) const MAX_UNPROCESSED_FETCH_COUNT: i64 = 10_000;
 
 #[async_trait]
 pub trait DbMethods<'c>: Acquire<'c, Database = Postgres> + Sized {
+    /// Acquires the connection used by `method`. With the `db-fault-injection`
+    /// feature, the faults injected for the method are raised here.
+    async fn acquire_for(self, method: &'static str) -> Result<Self::Connection, Error> {
+        #[cfg(feature = "db-fault-injection")]
+        super::fault_injection::before_call(method).await?;
+        #[cfg(not(feature = "db-fault-injection"))]
+        let _ = method;
+
+        Ok(self.acquire().await?)
+    }
+
+    /// Checks that the database can be reached.
+    #[instrument(skip(self), level = "debug")]
+    async fn ping(self) -> Result<(), Error> {
+        let mut conn = self.acquire_for("ping").await?;
+
+        sqlx::query("SELECT 1").execute(&mut *conn).await?;
+
+        Ok(())
+    }
+
     #[instrument(skip(self), level = "debug")]
     async fn insert_pending_identity(
         self,
@@ -24,7 +55,7 @@ pub trait DbMethods<'c>: Acquire<'c, Database = Postgres> + Sized {
         root: &Hash,
         pre_root: &Hash,
     ) -> Result<(), Error> {
-        let mut conn = self.acquire().await?;
+        let mut conn = self.acquire_for("insert_pending_identity").await?;
 
         sqlx::query(
             r#"
@@ -45,7 +76,7 @@ pub trait DbMethods<'c>: Acquire<'c, Database = Postgres> + Sized {
 
     #[instrument(skip(self), level = "debug")]
     async fn get_id_by_root(self, root: &Hash) -> Result<Option<usize>, Error> {
-        let mut conn = self.acquire().await?;
+        let mut conn = self.acquire_for("get_id_by_root").await?;
 
         let row = sqlx::query(
             r#"
@@ -71,7 +102,7 @@ pub trait DbMethods<'c>: Acquire<'c, Database = Postgres> + Sized {
     /// This is a composite operation performing multiple queries - it should be ran within a transaction.
     #[instrument(skip(self), level = "debug")]
     async fn mark_root_as_processed(self, root: &Hash) -> Result<(), Error> {
-        let mut conn = self.acquire().await?;
+        let mut conn = self.acquire_for("mark_root_as_processed").await?;
 
         let root_id = conn.get_id_by_root(root).await?;
 
@@ -96,11 +127,12 @@ pub trait DbMethods<'c>: Acquire<'c, Database = Postgres> + Sized {
         .execute(&mut *conn)
         .await?;
 
-        sqlx::query(
+        let result = sqlx::query(
             r#"
             UPDATE identities
             SET    status = $2, mined_at = NULL
             WHERE  id > $1
+            AND    status <> $2
             "#,
         )
         .bind(root_id)
@@ -108,6 +140,10 @@ pub trait DbMethods<'c>: Acquire<'c, Database = Postgres> + Sized {
         .execute(&mut *conn)
         .await?;
 
+        if result.rows_affected() > 0 {
+            conn.bump_tree_epoch().await?;
+        }
+
         Ok(())
     }
 
@@ -116,7 +152,7 @@ pub trait DbMethods<'c>: Acquire<'c, Database = Postgres> + Sized {
     /// This is a composite operation performing multiple queries - it should be ran within a transaction.
     #[instrument(skip(self), level = "debug")]
     async fn mark_root_as_mined(self, root: &Hash) -> Result<(), Error> {
-        let mut conn = self.acquire().await?;
+        let mut conn = self.acquire_for("mark_root_as_mined").await?;
 
         let root_id = conn.get_id_by_root(root).await?;
 
@@ -144,9 +180,9 @@ pub trait DbMethods<'c>: Acquire<'c, Database = Postgres> + Sized {
 
     #[instrument(skip(self), level = "debug")]
     async fn mark_all_as_pending(self) -> Result<(), Error> {
-        let mut conn = self.acquire().await?;
+        let mut conn = self.acquire_for("mark_all_as_pending").await?;
 
-        sqlx::query(
+        let result = sqlx::query(
             r#"
             UPDATE identities
             SET    status = $1, mined_at = NULL
@@ -157,12 +193,163 @@ pub trait DbMethods<'c>: Acquire<'c, Database = Postgres> + Sized {
         .execute(&mut *conn)
         .await?;
 
+        if result.rows_affected() > 0 {
+            conn.bump_tree_epoch().await?;
+        }
+
         Ok(())
     }
 
+    /// Marks every identity inserted after the given sequence id as pending,
+    /// returns the number of identities which were processed or mined
+    #[instrument(skip(self), level = "debug")]
+    async fn invalidate_updates_after(self, sequence_id: usize) -> Result<u64, Error> {
+        let mut conn = self.acquire_for("invalidate_updates_after").await?;
+
+        let result = sqlx::query(
+            r#"
+            UPDATE identities
+            SET    status = $2, mined_at = NULL
+            WHERE  id > $1
+            AND    status <> $2
+            "#,
+        )
+        .bind(sequence_id as i64)
+        .bind(<&str>::from(ProcessedStatus::Pending))
+        .execute(&mut *conn)
+        .await?;
+
+        if result.rows_affected() > 0 {
+            conn.bump_tree_epoch().await?;
+        }
+
+        Ok(result.rows_affected())
+    }
+
+    /// Bumps the epoch of the tree updates feed, must be called whenever
+    /// identities lose their processed or mined status
+    #[instrument(skip(self), level = "debug")]
+    async fn bump_tree_epoch(self) -> Result<u64, Error> {
+        let mut conn = self.acquire_for("bump_tree_epoch").await?;
+
+        let epoch = sqlx::query(
+            r#"
+            INSERT INTO tree_epoch (Lock, epoch, bumped_at)
+            VALUES ('X', 1, CURRENT_TIMESTAMP)
+            ON CONFLICT (Lock)
+            DO UPDATE SET epoch = tree_epoch.epoch + 1, bumped_at = EXCLUDED.bumped_at
+            RETURNING epoch
+            "#,
+        )
+        .fetch_one(&mut *conn)
+        .await?
+        .get::<i64, _>(0);
+
+        Ok(epoch as u64)
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    async fn get_tree_epoch(self) -> Result<u64, Error> {
+        let mut conn = self.acquire_for("get_tree_epoch").await?;
+
+        let epoch = sqlx::query(
+            r#"
+            SELECT epoch
+            FROM tree_epoch
+            WHERE Lock = 'X'
+            "#,
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .map_or(0, |row| row.get::<i64, _>(0));
+
+        Ok(epoch as u64)
+    }
+
+    /// Returns up to `limit` identities inserted after `after_sequence_id`, in
+    /// the order they were inserted in
+    #[instrument(skip(self), level = "debug")]
+    async fn get_tree_updates(
+        self,
+        after_sequence_id: usize,
+        limit: usize,
+        status: Option<ProcessedStatus>,
+    ) -> Result<Vec<TreeUpdateEntry>, Error> {
+        let mut conn = self.acquire_for("get_tree_updates").await?;
+
+        let updates = sqlx::query_as::<_, TreeUpdateEntry>(
+            r#"
+            SELECT
+                id AS sequence_id,
+                leaf_index,
+                commitment AS element,
+                root AS post_root,
+                status,
+                pending_as_of,
+                mined_at
+            FROM identities
+            WHERE id > $1
+            AND   ($3::VARCHAR IS NULL OR status = $3)
+            ORDER BY id ASC
+            LIMIT $2
+            "#,
+        )
+        .bind(after_sequence_id as i64)
+        .bind(limit as i64)
+        .bind(status.map(<&str>::from))
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(updates)
+    }
+
+    /// Whether a recovery to the given root was applied already
+    #[instrument(skip(self), level = "debug")]
+    async fn is_tree_recovery_applied(self, root: &Hash) -> Result<bool, Error> {
+        let mut conn = self.acquire_for("is_tree_recovery_applied").await?;
+
+        let (applied,): (bool,) = sqlx::query_as(
+            r#"
+            SELECT EXISTS (SELECT 1 FROM tree_recoveries WHERE root = $1)
+            "#,
+        )
+        .bind(root)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(applied)
+    }
+
+    /// Records a recovery to the given root. Returns `false` if it was
+    /// recorded already, e.g. by a concurrent recovery.
+    #[instrument(skip(self), level = "debug")]
+    async fn record_tree_recovery(
+        self,
+        root: &Hash,
+        sequence_id: usize,
+        invalidated: u64,
+    ) -> Result<bool, Error> {
+        let mut conn = self.acquire_for("record_tree_recovery").await?;
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO tree_recoveries (root, sequence_id, invalidated)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (root) DO NOTHING
+            "#,
+        )
+        .bind(root)
+        .bind(sequence_id as i64)
+        .bind(invalidated as i64)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
     #[instrument(skip(self), level = "debug")]
     async fn get_next_leaf_index(self) -> Result<usize, Error> {
-        let mut conn = self.acquire().await?;
+        let mut conn = self.acquire_for("get_next_leaf_index").await?;
 
         let row = sqlx::query(
             r#"
@@ -182,7 +369,7 @@ pub trait DbMethods<'c>: Acquire<'c, Database = Postgres> + Sized {
 
     #[instrument(skip(self), level = "debug")]
     async fn get_identity_leaf_index(self, identity: &Hash) -> Result<Option<TreeItem>, Error> {
-        let mut conn = self.acquire().await?;
+        let mut conn = self.acquire_for("get_identity_leaf_index").await?;
 
         let row = sqlx::query(
             r#"
@@ -211,12 +398,42 @@ pub trait DbMethods<'c>: Acquire<'c, Database = Postgres> + Sized {
         Ok(Some(TreeItem { status, leaf_index }))
     }
 
+    /// Looks up the latest entry of a commitment in the tree together with the
+    /// status of its root and whether it's queued again, in one round trip.
+    /// Returns `None` if the commitment isn't in the tree.
+    #[instrument(skip(self), level = "debug")]
+    async fn get_tree_item_with_root_status(
+        self,
+        commitment: &Hash,
+    ) -> Result<Option<TreeItemWithRootStatus>, Error> {
+        let mut conn = self.acquire_for("get_tree_item_with_root_status").await?;
+
+        Ok(sqlx::query_as(
+            r#"
+            SELECT i.id AS sequence_id,
+                   i.leaf_index,
+                   i.commitment AS element,
+                   i.status AS root_status,
+                   EXISTS (
+                       SELECT 1 FROM unprocessed_identities u WHERE u.commitment = $1
+                   ) AS unprocessed
+            FROM   identities i
+            WHERE  i.commitment = $1
+            ORDER BY i.id DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(commitment)
+        .fetch_optional(&mut *conn)
+        .await?)
+    }
+
     #[instrument(skip(self), level = "debug")]
     async fn get_commitments_by_status(
         self,
         status: ProcessedStatus,
     ) -> Result<Vec<TreeUpdate>, Error> {
-        let mut conn = self.acquire().await?;
+        let mut conn = self.acquire_for("get_commitments_by_status").await?;
 
         Ok(sqlx::query_as::<_, TreeUpdate>(
             r#"
@@ -236,7 +453,7 @@ pub trait DbMethods<'c>: Acquire<'c, Database = Postgres> + Sized {
         self,
         statuses: Vec<ProcessedStatus>,
     ) -> Result<Vec<TreeUpdate>, Error> {
-        let mut conn = self.acquire().await?;
+        let mut conn = self.acquire_for("get_commitments_by_statuses").await?;
 
         let statuses: Vec<&str> = statuses.into_iter().map(<&str>::from).collect();
         Ok(sqlx::query_as::<_, TreeUpdate>(
@@ -260,7 +477,9 @@ pub trait DbMethods<'c>: Acquire<'c, Database = Postgres> + Sized {
     where
         I: IntoIterator<Item = usize> + Send,
     {
-        let mut conn = self.acquire().await?;
+        let mut conn = self
+            .acquire_for("get_non_zero_commitments_by_leaf_indexes")
+            .await?;
 
         let leaf_indexes: Vec<i64> = leaf_indexes.into_iter().map(|v| v as i64).collect();
 
@@ -286,7 +505,7 @@ pub trait DbMethods<'c>: Acquire<'c, Database = Postgres> + Sized {
         self,
         status: ProcessedStatus,
     ) -> Result<Option<Hash>, Error> {
-        let mut conn = self.acquire().await?;
+        let mut conn = self.acquire_for("get_latest_root_by_status").await?;
 
         Ok(sqlx::query(
             r#"
@@ -299,9 +518,38 @@ pub trait DbMethods<'c>: Acquire<'c, Database = Postgres> + Sized {
         .map(|r| r.get::<Hash, _>(0)))
     }
 
+    /// How long ago the identities from `first_leaf_index` on were received,
+    /// by the database clock, in leaf order. Identities without a receive
+    /// time are skipped.
+    #[instrument(skip(self), level = "debug")]
+    async fn get_receive_latencies_from_leaf(
+        self,
+        first_leaf_index: usize,
+    ) -> Result<Vec<Duration>, Error> {
+        let mut conn = self.acquire_for("get_receive_latencies_from_leaf").await?;
+
+        let latencies: Vec<(f64,)> = sqlx::query_as(
+            r#"
+            SELECT EXTRACT(EPOCH FROM CURRENT_TIMESTAMP - received_at)::FLOAT8
+            FROM   identities
+            WHERE  leaf_index >= $1
+            AND    received_at IS NOT NULL
+            ORDER BY leaf_index
+            "#,
+        )
+        .bind(first_leaf_index as i64)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(latencies
+            .into_iter()
+            .map(|(secs,)| Duration::from_secs_f64(secs.max(0.0)))
+            .collect())
+    }
+
     #[instrument(skip(self), level = "debug")]
     async fn get_root_state(self, root: &Hash) -> Result<Option<RootItem>, Error> {
-        let mut conn = self.acquire().await?;
+        let mut conn = self.acquire_for("get_root_state").await?;
 
         // This tries really hard to do everything in one query to prevent race
         // conditions.
@@ -323,9 +571,44 @@ pub trait DbMethods<'c>: Acquire<'c, Database = Postgres> + Sized {
         .await?)
     }
 
+    /// Same as [`Self::get_root_state`], together with the current time of the
+    /// database. The age of a root must be computed from this time since the
+    /// timestamps of the root were set by the database clock as well.
+    #[instrument(skip(self), level = "debug")]
+    async fn get_root_state_with_db_time(
+        self,
+        root: &Hash,
+    ) -> Result<Option<(RootItem, DateTime<Utc>)>, Error> {
+        let mut conn = self.acquire_for("get_root_state_with_db_time").await?;
+
+        let row = sqlx::query(
+            r#"
+            SELECT
+                root,
+                status,
+                pending_as_of as pending_valid_as_of,
+                mined_at as mined_valid_as_of,
+                CURRENT_TIMESTAMP as db_now
+            FROM identities
+            WHERE root = $1
+            ORDER BY id
+            LIMIT 1
+            "#,
+        )
+        .bind(root)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        Ok(Some((RootItem::from_row(&row)?, row.try_get("db_now")?)))
+    }
+
     #[instrument(skip(self), level = "debug")]
     async fn get_latest_insertion(self) -> Result<LatestInsertionEntry, Error> {
-        let mut conn = self.acquire().await?;
+        let mut conn = self.acquire_for("get_latest_insertion").await?;
 
         let row = sqlx::query(
             r#"
@@ -349,7 +632,7 @@ pub trait DbMethods<'c>: Acquire<'c, Database = Postgres> + Sized {
 
     #[instrument(skip(self), level = "debug")]
     async fn count_unprocessed_identities(self) -> Result<i32, Error> {
-        let mut conn = self.acquire().await?;
+        let mut conn = self.acquire_for("count_unprocessed_identities").await?;
 
         let (count,): (i64,) = sqlx::query_as(
             r#"
@@ -365,7 +648,7 @@ pub trait DbMethods<'c>: Acquire<'c, Database = Postgres> + Sized {
 
     #[instrument(skip(self), level = "debug")]
     async fn count_pending_identities(self) -> Result<i32, Error> {
-        let mut conn = self.acquire().await?;
+        let mut conn = self.acquire_for("count_pending_identities").await?;
 
         let (count,): (i64,) = sqlx::query_as(
             r#"
@@ -383,18 +666,30 @@ pub trait DbMethods<'c>: Acquire<'c, Database = Postgres> + Sized {
 
     #[instrument(skip(self), level = "debug")]
     async fn get_provers(self) -> Result<HashSet<ProverConfig>, Error> {
-        let mut conn = self.acquire().await?;
+        let mut conn = self.acquire_for("get_provers").await?;
 
-        Ok(sqlx::query_as(
+        let rows: Vec<(i64, String, i64, ProverType, Option<i64>)> = sqlx::query_as(
             r#"
-            SELECT batch_size, url, timeout_s, prover_type
+            SELECT batch_size, url, timeout_s, prover_type, max_batch_size
             FROM provers
             "#,
         )
         .fetch_all(&mut *conn)
-        .await?
-        .into_iter()
-        .collect())
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(
+                |(batch_size, url, timeout_s, prover_type, max_batch_size)| ProverConfig {
+                    url,
+                    timeout_s: timeout_s as u64,
+                    batch_size: batch_size as usize,
+                    max_batch_size: max_batch_size.map(|size| size as usize),
+                    hmac_secret: None,
+                    prover_type,
+                },
+            )
+            .collect())
     }
 
     #[instrument(skip(self, url), level = "debug")]
@@ -405,7 +700,7 @@ pub trait DbMethods<'c>: Acquire<'c, Database = Postgres> + Sized {
         timeout_seconds: u64,
         prover_type: ProverType,
     ) -> Result<(), Error> {
-        let mut conn = self.acquire().await?;
+        let mut conn = self.acquire_for("insert_prover_configuration").await?;
 
         let url = url.to_string();
 
@@ -427,7 +722,7 @@ pub trait DbMethods<'c>: Acquire<'c, Database = Postgres> + Sized {
 
     #[instrument(skip(self), level = "debug")]
     async fn insert_provers(self, provers: HashSet<ProverConfig>) -> Result<(), Error> {
-        let mut conn = self.acquire().await?;
+        let mut conn = self.acquire_for("insert_provers").await?;
 
         if provers.is_empty() {
             return Ok(());
@@ -435,7 +730,7 @@ pub trait DbMethods<'c>: Acquire<'c, Database = Postgres> + Sized {
 
         let mut query_builder = sqlx::QueryBuilder::new(
             r#"
-            INSERT INTO provers (batch_size, url, timeout_s, prover_type)
+            INSERT INTO provers (batch_size, url, timeout_s, prover_type, max_batch_size)
             "#,
         );
 
@@ -443,8 +738,11 @@ pub trait DbMethods<'c>: Acquire<'c, Database = Postgres> + Sized {
             b.push_bind(prover.batch_size as i64)
                 .push_bind(prover.url)
                 .push_bind(prover.timeout_s as i64)
-                .push_bind(prover.prover_type);
+                .push_bind(prover.prover_type)
+                .push_bind(prover.max_batch_size.map(|size| size as i64));
         });
+        // Another instance may have registered the same provers meanwhile
+        query_builder.push(" ON CONFLICT (batch_size, prover_type) DO NOTHING");
 
         let query = query_builder.build();
 
@@ -455,7 +753,7 @@ pub trait DbMethods<'c>: Acquire<'c, Database = Postgres> + Sized {
 
     #[instrument(skip(self), level = "debug")]
     async fn remove_prover(self, batch_size: usize, prover_type: ProverType) -> Result<(), Error> {
-        let mut conn = self.acquire().await?;
+        let mut conn = self.acquire_for("remove_prover").await?;
 
         sqlx::query(
             r#"
@@ -472,405 +770,1693 @@ pub trait DbMethods<'c>: Acquire<'c, Database = Postgres> + Sized {
 
     #[instrument(skip(self), level = "debug")]
     async fn insert_unprocessed_identity(self, identity: Hash) -> Result<Hash, Error> {
-        let mut conn = self.acquire().await?;
+        self.insert_unprocessed_identity_with_origin(identity, &RequestOrigin::default())
+            .await
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    async fn insert_unprocessed_identity_with_origin(
+        self,
+        identity: Hash,
+        origin: &RequestOrigin,
+    ) -> Result<Hash, Error> {
+        let mut conn = self
+            .acquire_for("insert_unprocessed_identity_with_origin")
+            .await?;
 
         sqlx::query(
             r#"
-            INSERT INTO unprocessed_identities (commitment, created_at)
-            VALUES ($1, CURRENT_TIMESTAMP)
+            INSERT INTO unprocessed_identities
+                (commitment, created_at, source_ip, user_agent, trace_id, span_id)
+            VALUES ($1, CURRENT_TIMESTAMP, $2, $3, $4, $5)
             ON CONFLICT DO NOTHING
             "#,
         )
         .bind(identity)
+        .bind(origin.ip.map(|ip| ip.to_string()))
+        .bind(origin.user_agent.as_deref())
+        .bind(origin.trace.as_ref().map(|trace| trace.trace_id.as_str()))
+        .bind(origin.trace.as_ref().map(|trace| trace.span_id.as_str()))
         .execute(&mut *conn)
         .await?;
 
         Ok(identity)
     }
 
-    #[instrument(skip(self), level = "debug")]
-    async fn get_latest_deletion(self) -> Result<LatestDeletionEntry, Error> {
-        let mut conn = self.acquire().await?;
+    /// Inserts many unprocessed identities at once, skipping the ones which are
+    /// already unprocessed or at a leaf of the tree which wasn't deleted.
+    /// Returns the number of inserted identities.
+    #[instrument(skip(self, identities), level = "debug")]
+    async fn insert_unprocessed_identities(self, identities: &[Hash]) -> Result<u64, Error> {
+        let mut conn = self.acquire_for("insert_unprocessed_identities").await?;
 
-        let row =
-            sqlx::query("SELECT deletion_timestamp FROM latest_deletion_root WHERE Lock = 'X';")
-                .fetch_optional(&mut *conn)
-                .await?;
+        let result = sqlx::query(
+            r#"
+            INSERT INTO unprocessed_identities (commitment, created_at)
+            SELECT c, CURRENT_TIMESTAMP
+            FROM UNNEST($1::BYTEA[]) AS c
+            WHERE NOT EXISTS (SELECT 1 FROM identities i WHERE i.commitment = c AND NOT i.deleted)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(Commitments(identities.to_vec()))
+        .execute(&mut *conn)
+        .await?;
 
-        if let Some(row) = row {
-            Ok(LatestDeletionEntry {
-                timestamp: row.get(0),
-            })
-        } else {
-            Ok(LatestDeletionEntry {
-                timestamp: Utc::now(),
-            })
-        }
+        Ok(result.rows_affected())
     }
 
+    /// Returns the recorded request origin of an unprocessed identity.
+    ///
+    /// This is abuse investigation data and must never be surfaced through
+    /// public endpoints.
     #[instrument(skip(self), level = "debug")]
-    async fn update_latest_insertion(
+    async fn get_unprocessed_identity_origin(
         self,
-        insertion_timestamp: DateTime<Utc>,
-    ) -> Result<(), Error> {
-        let mut conn = self.acquire().await?;
+        commitment: &Hash,
+    ) -> Result<Option<RequestOrigin>, Error> {
+        let mut conn = self.acquire_for("get_unprocessed_identity_origin").await?;
 
-        sqlx::query(
+        Ok(sqlx::query_as(
             r#"
-            INSERT INTO latest_insertion_timestamp (Lock, insertion_timestamp)
-            VALUES ('X', $1)
-            ON CONFLICT (Lock)
-            DO UPDATE SET insertion_timestamp = EXCLUDED.insertion_timestamp;
+            SELECT source_ip, user_agent, trace_id, span_id
+            FROM unprocessed_identities
+            WHERE commitment = $1
             "#,
         )
-        .bind(insertion_timestamp)
-        .execute(&mut *conn)
-        .await?;
+        .bind(commitment)
+        .fetch_optional(&mut *conn)
+        .await?)
+    }
 
-        Ok(())
+    /// Returns the origin of the latest request inserting the commitment, from
+    /// the unprocessed identity or, once it was moved, the leaf.
+    ///
+    /// This is abuse investigation data and must never be surfaced through
+    /// public endpoints.
+    #[instrument(skip(self), level = "debug")]
+    async fn get_insertion_origin(self, commitment: &Hash) -> Result<Option<RequestOrigin>, Error> {
+        let mut conn = self.acquire_for("get_insertion_origin").await?;
+
+        Ok(sqlx::query_as(
+            r#"
+            SELECT source_ip, user_agent, trace_id, span_id
+            FROM (
+                SELECT source_ip, user_agent, trace_id, span_id, 0 AS queued_last, 0::BIGINT AS id
+                FROM   unprocessed_identities
+                WHERE  commitment = $1
+                UNION ALL
+                SELECT source_ip, user_agent, trace_id, span_id, 1, id
+                FROM   identities
+                WHERE  commitment = $1
+            ) origins
+            ORDER BY queued_last, id DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(commitment)
+        .fetch_optional(&mut *conn)
+        .await?)
     }
 
+    /// Returns the origin of the latest request deleting the commitment, from
+    /// the queued deletion or, once it was processed, the zeroed leaf.
+    ///
+    /// This is abuse investigation data and must never be surfaced through
+    /// public endpoints.
     #[instrument(skip(self), level = "debug")]
-    async fn update_latest_deletion(self, deletion_timestamp: DateTime<Utc>) -> Result<(), Error> {
-        let mut conn = self.acquire().await?;
+    async fn get_deletion_origin(self, commitment: &Hash) -> Result<Option<RequestOrigin>, Error> {
+        let mut conn = self.acquire_for("get_deletion_origin").await?;
+
+        Ok(sqlx::query_as(
+            r#"
+            SELECT source_ip, user_agent, trace_id, span_id
+            FROM (
+                SELECT source_ip, user_agent, trace_id, span_id, 0 AS queued_last, 0::BIGINT AS id
+                FROM   deletions
+                WHERE  commitment = $1
+                UNION ALL
+                SELECT z.source_ip, z.user_agent, z.trace_id, z.span_id, 1, z.id
+                FROM   identities z
+                JOIN   identities i ON i.leaf_index = z.leaf_index
+                WHERE  i.commitment = $1
+                AND    z.commitment = $2
+            ) origins
+            ORDER BY queued_last, id DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(commitment)
+        .bind(Hash::ZERO)
+        .fetch_optional(&mut *conn)
+        .await?)
+    }
+
+    /// Copies the request origins of unprocessed identities to their rows in
+    /// the identities table. Must run before the unprocessed identities are
+    /// trimmed.
+    #[instrument(skip(self), level = "debug")]
+    async fn copy_unprocessed_request_origins(self) -> Result<(), Error> {
+        let mut conn = self.acquire_for("copy_unprocessed_request_origins").await?;
 
         sqlx::query(
             r#"
-            INSERT INTO latest_deletion_root (Lock, deletion_timestamp)
-            VALUES ('X', $1)
-            ON CONFLICT (Lock)
-            DO UPDATE SET deletion_timestamp = EXCLUDED.deletion_timestamp;
+            UPDATE identities i
+            SET    source_ip = u.source_ip,
+                   user_agent = u.user_agent,
+                   trace_id = u.trace_id,
+                   span_id = u.span_id
+            FROM   unprocessed_identities u
+            WHERE  i.commitment = u.commitment
+            AND    NOT i.deleted
+            AND    (u.source_ip IS NOT NULL
+                    OR u.user_agent IS NOT NULL
+                    OR u.trace_id IS NOT NULL)
             "#,
         )
-        .bind(deletion_timestamp)
         .execute(&mut *conn)
         .await?;
 
         Ok(())
     }
 
-    /// Inserts a new deletion into the deletions table
-    ///
-    /// This method is idempotent and on conflict nothing will happen
+    /// Copies the time unprocessed identities were received to their rows in
+    /// the identities table. Must run before the unprocessed identities are
+    /// trimmed.
     #[instrument(skip(self), level = "debug")]
-    async fn insert_new_deletion(self, leaf_index: usize, identity: &Hash) -> Result<(), Error> {
-        let mut conn = self.acquire().await?;
+    async fn copy_unprocessed_received_at(self) -> Result<(), Error> {
+        let mut conn = self.acquire_for("copy_unprocessed_received_at").await?;
 
         sqlx::query(
             r#"
-            INSERT INTO deletions (leaf_index, commitment)
-            VALUES ($1, $2)
-            ON CONFLICT DO NOTHING
+            UPDATE identities i
+            SET    received_at = u.created_at
+            FROM   unprocessed_identities u
+            WHERE  i.commitment = u.commitment
+            AND    NOT i.deleted
+            AND    i.received_at IS NULL
             "#,
         )
-        .bind(leaf_index as i64)
-        .bind(identity)
         .execute(&mut *conn)
         .await?;
 
         Ok(())
     }
 
-    // TODO: consider using a larger value than i64 for leaf index, ruint should
-    // have postgres compatibility for u256
+    /// Copies the request origins of queued deletions to the zeroed leaves
+    /// which replaced them. Must run before the deletions are removed.
     #[instrument(skip(self), level = "debug")]
-    async fn get_deletions(self) -> Result<Vec<DeletionEntry>, Error> {
-        let mut conn = self.acquire().await?;
+    async fn copy_deletion_request_origins(self) -> Result<(), Error> {
+        let mut conn = self.acquire_for("copy_deletion_request_origins").await?;
 
-        let result = sqlx::query(
+        sqlx::query(
             r#"
-            SELECT *
-            FROM deletions
+            UPDATE identities i
+            SET    source_ip = d.source_ip,
+                   user_agent = d.user_agent,
+                   trace_id = d.trace_id,
+                   span_id = d.span_id
+            FROM   deletions d
+            WHERE  i.leaf_index = d.leaf_index
+            AND    i.commitment = $1
+            AND    (d.source_ip IS NOT NULL
+                    OR d.user_agent IS NOT NULL
+                    OR d.trace_id IS NOT NULL)
             "#,
         )
-        .fetch_all(&mut *conn)
+        .bind(Hash::ZERO)
+        .execute(&mut *conn)
         .await?;
 
-        Ok(result
+        Ok(())
+    }
+
+    /// Returns the traces of the requests which inserted or deleted the given
+    /// leaves, skipping leaves without a recorded trace.
+    #[instrument(skip(self, leaf_indices), level = "debug")]
+    async fn get_request_traces(
+        self,
+        leaf_indices: &[usize],
+        batch_type: BatchType,
+    ) -> Result<Vec<RequestTrace>, Error> {
+        let mut conn = self.acquire_for("get_request_traces").await?;
+
+        let leaf_indices: Vec<i64> = leaf_indices.iter().map(|&i| i as i64).collect();
+
+        let rows: Vec<(String, String)> = sqlx::query_as(
+            r#"
+            SELECT trace_id, span_id
+            FROM   identities
+            WHERE  leaf_index = ANY($1)
+            AND    (commitment = $2) = $3
+            AND    trace_id IS NOT NULL
+            AND    span_id IS NOT NULL
+            ORDER BY leaf_index
+            "#,
+        )
+        .bind(&leaf_indices)
+        .bind(Hash::ZERO)
+        .bind(batch_type == BatchType::Deletion)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(trace_id, span_id)| RequestTrace { trace_id, span_id })
+            .collect())
+    }
+
+    /// Clears the auxiliary data of a commitment in every table listed in
+    /// [`AUXILIARY_DATA`] and records the erasure. Returns the number of rows
+    /// which were redacted, repeated erasures redact nothing.
+    ///
+    /// Should run in a transaction so that the erasure is all or nothing.
+    #[instrument(skip(self), level = "debug")]
+    async fn erase_auxiliary_data(self, commitment: &Hash) -> Result<u64, Error> {
+        let mut conn = self.acquire_for("erase_auxiliary_data").await?;
+
+        let mut redacted_rows = 0;
+        for data in AUXILIARY_DATA {
+            let assignments: Vec<_> = data
+                .columns
+                .iter()
+                .map(|column| format!("{column} = NULL"))
+                .collect();
+            let present: Vec<_> = data
+                .columns
+                .iter()
+                .map(|column| format!("{column} IS NOT NULL"))
+                .collect();
+
+            let result = sqlx::query(&format!(
+                "UPDATE {} SET {} WHERE ({}) AND ({})",
+                data.table,
+                assignments.join(", "),
+                data.rows,
+                present.join(" OR ")
+            ))
+            .bind(commitment)
+            .execute(&mut *conn)
+            .await?;
+
+            redacted_rows += result.rows_affected();
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO identity_erasures (commitment)
+            VALUES ($1)
+            ON CONFLICT (commitment) DO UPDATE SET erased_at = CURRENT_TIMESTAMP
+            "#,
+        )
+        .bind(commitment)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(redacted_rows)
+    }
+
+    /// Returns the auxiliary data columns, as `table.column`, which still
+    /// hold a value for the commitment.
+    #[instrument(skip(self), level = "debug")]
+    async fn get_remaining_auxiliary_data(self, commitment: &Hash) -> Result<Vec<String>, Error> {
+        let mut conn = self.acquire_for("get_remaining_auxiliary_data").await?;
+
+        let mut remaining = vec![];
+        for data in AUXILIARY_DATA {
+            for column in data.columns {
+                let present: bool = sqlx::query(&format!(
+                    "SELECT EXISTS (SELECT 1 FROM {} WHERE ({}) AND {column} IS NOT NULL)",
+                    data.table, data.rows
+                ))
+                .bind(commitment)
+                .fetch_one(&mut *conn)
+                .await?
+                .get(0);
+
+                if present {
+                    remaining.push(format!("{}.{column}", data.table));
+                }
+            }
+        }
+
+        Ok(remaining)
+    }
+
+    /// Returns when the auxiliary data of the commitment was last erased.
+    #[instrument(skip(self), level = "debug")]
+    async fn get_identity_erasure(self, commitment: &Hash) -> Result<Option<DateTime<Utc>>, Error> {
+        let mut conn = self.acquire_for("get_identity_erasure").await?;
+
+        let erased_at: Option<(DateTime<Utc>,)> = sqlx::query_as(
+            r#"
+            SELECT erased_at FROM identity_erasures WHERE commitment = $1
+            "#,
+        )
+        .bind(commitment)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(erased_at.map(|(erased_at,)| erased_at))
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    async fn get_latest_deletion(self) -> Result<LatestDeletionEntry, Error> {
+        let mut conn = self.acquire_for("get_latest_deletion").await?;
+
+        let row =
+            sqlx::query("SELECT deletion_timestamp FROM latest_deletion_root WHERE Lock = 'X';")
+                .fetch_optional(&mut *conn)
+                .await?;
+
+        if let Some(row) = row {
+            Ok(LatestDeletionEntry {
+                timestamp: row.get(0),
+            })
+        } else {
+            Ok(LatestDeletionEntry {
+                timestamp: Utc::now(),
+            })
+        }
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    async fn update_latest_insertion(
+        self,
+        insertion_timestamp: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        let mut conn = self.acquire_for("update_latest_insertion").await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO latest_insertion_timestamp (Lock, insertion_timestamp)
+            VALUES ('X', $1)
+            ON CONFLICT (Lock)
+            DO UPDATE SET insertion_timestamp = EXCLUDED.insertion_timestamp;
+            "#,
+        )
+        .bind(insertion_timestamp)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    async fn update_latest_deletion(self, deletion_timestamp: DateTime<Utc>) -> Result<(), Error> {
+        let mut conn = self.acquire_for("update_latest_deletion").await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO latest_deletion_root (Lock, deletion_timestamp)
+            VALUES ('X', $1)
+            ON CONFLICT (Lock)
+            DO UPDATE SET deletion_timestamp = EXCLUDED.deletion_timestamp;
+            "#,
+        )
+        .bind(deletion_timestamp)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Inserts a new deletion into the deletions table
+    ///
+    /// This method is idempotent and on conflict nothing will happen
+    #[instrument(skip(self), level = "debug")]
+    async fn insert_new_deletion(self, leaf_index: usize, identity: &Hash) -> Result<(), Error> {
+        self.insert_new_deletion_with_origin(leaf_index, identity, &RequestOrigin::default())
+            .await
+    }
+
+    /// Same as [`Self::insert_new_deletion`] but also records where the
+    /// deletion request came from
+    #[instrument(skip(self), level = "debug")]
+    async fn insert_new_deletion_with_origin(
+        self,
+        leaf_index: usize,
+        identity: &Hash,
+        origin: &RequestOrigin,
+    ) -> Result<(), Error> {
+        let mut conn = self.acquire_for("insert_new_deletion_with_origin").await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO deletions (leaf_index, commitment, source_ip, user_agent, trace_id, span_id)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(leaf_index as i64)
+        .bind(identity)
+        .bind(origin.ip.map(|ip| ip.to_string()))
+        .bind(origin.user_agent.as_deref())
+        .bind(origin.trace.as_ref().map(|trace| trace.trace_id.as_str()))
+        .bind(origin.trace.as_ref().map(|trace| trace.span_id.as_str()))
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    // TODO: consider using a larger value than i64 for leaf index, ruint should
+    // have postgres compatibility for u256
+    #[instrument(skip(self), level = "debug")]
+    async fn get_deletions(self) -> Result<Vec<DeletionEntry>, Error> {
+        let mut conn = self.acquire_for("get_deletions").await?;
+
+        let result = sqlx::query(
+            r#"
+            SELECT id, leaf_index, commitment
+            FROM deletions
+            "#,
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(result
             .into_iter()
             .map(|row| DeletionEntry {
-                leaf_index: row.get::<i64, _>(0) as usize,
-                commitment: row.get::<Hash, _>(1),
+                id: row.get::<i64, _>(0),
+                leaf_index: row.get::<i64, _>(1) as usize,
+                commitment: row.get::<Hash, _>(2),
             })
             .collect::<Vec<DeletionEntry>>())
     }
 
-    /// Remove a list of entries from the deletions table
     #[instrument(skip(self), level = "debug")]
-    async fn remove_deletions(self, commitments: &[Hash]) -> Result<(), Error> {
-        let mut conn = self.acquire().await?;
+    async fn count_deletions(self) -> Result<i64, Error> {
+        let mut conn = self.acquire_for("count_deletions").await?;
 
-        let commitments = commitments
-            .iter()
-            .map(|c| c.to_be_bytes())
-            .collect::<Vec<[u8; 32]>>();
+        let (count,): (i64,) = sqlx::query_as(
+            r#"
+            SELECT COUNT(*)
+            FROM deletions
+            "#,
+        )
+        .fetch_one(&mut *conn)
+        .await?;
 
-        sqlx::query("DELETE FROM deletions WHERE commitment = Any($1)")
-            .bind(commitments)
-            .execute(&mut *conn)
-            .await?;
+        Ok(count)
+    }
+
+    /// Returns up to `limit` deletions queued after the deletion with the id
+    /// `after_id`, in the order they were requested.
+    #[instrument(skip(self), level = "debug")]
+    async fn get_deletions_page(
+        self,
+        after_id: i64,
+        limit: usize,
+    ) -> Result<Vec<DeletionEntry>, Error> {
+        let mut conn = self.acquire_for("get_deletions_page").await?;
+
+        let result = sqlx::query(
+            r#"
+            SELECT id, leaf_index, commitment
+            FROM deletions
+            WHERE id > $1
+            ORDER BY id
+            LIMIT $2
+            "#,
+        )
+        .bind(after_id)
+        .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(result
+            .into_iter()
+            .map(|row| DeletionEntry {
+                id: row.get::<i64, _>(0),
+                leaf_index: row.get::<i64, _>(1) as usize,
+                commitment: row.get::<Hash, _>(2),
+            })
+            .collect::<Vec<DeletionEntry>>())
+    }
+
+    /// Counts a deletion towards the current hourly window, unless the window
+    /// already holds `limit` deletions. Returns whether the deletion was
+    /// counted.
+    ///
+    /// Windows are aligned to full hours of the database clock, so that all
+    /// instances share them.
+    #[instrument(skip(self), level = "debug")]
+    async fn record_deletion_in_window(self, limit: u64) -> Result<bool, Error> {
+        let mut conn = self.acquire_for("record_deletion_in_window").await?;
+
+        let row = sqlx::query(
+            r#"
+            INSERT INTO deletion_rate_windows (window_start, deletions)
+            SELECT date_trunc('hour', CURRENT_TIMESTAMP), 1
+            WHERE $1 > 0
+            ON CONFLICT (window_start) DO UPDATE
+            SET    deletions = deletion_rate_windows.deletions + 1
+            WHERE  deletion_rate_windows.deletions < $1
+            RETURNING deletions
+            "#,
+        )
+        .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(row.is_some())
+    }
+
+    /// Lifts the deletion limits until `duration` from now. The reason is kept
+    /// for auditing.
+    #[instrument(skip(self), level = "debug")]
+    async fn lift_deletion_limit(
+        self,
+        reason: &str,
+        duration: std::time::Duration,
+    ) -> Result<DateTime<Utc>, Error> {
+        let mut conn = self.acquire_for("lift_deletion_limit").await?;
+
+        let (lifted_until,): (DateTime<Utc>,) = sqlx::query_as(
+            r#"
+            INSERT INTO deletion_limit_overrides (reason, lifted_until)
+            VALUES ($1, CURRENT_TIMESTAMP + make_interval(secs => $2))
+            RETURNING lifted_until
+            "#,
+        )
+        .bind(reason)
+        .bind(duration.as_secs_f64())
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(lifted_until)
+    }
+
+    /// Appends a runtime setting change to the change log. Called in the
+    /// transaction which applies the change, so that neither is kept without
+    /// the other.
+    #[instrument(skip(self), level = "debug")]
+    async fn record_config_change(
+        self,
+        author: &ChangeAuthor,
+        change: &ConfigChange,
+    ) -> Result<(), Error> {
+        let mut conn = self.acquire_for("record_config_change").await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO config_changes (principal, setting, old_value, new_value, request_id)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(&author.principal)
+        .bind(&change.setting)
+        .bind(change.old_value.as_ref().map(sqlx::types::Json))
+        .bind(change.new_value.as_ref().map(sqlx::types::Json))
+        .bind(&author.request_id)
+        .execute(&mut *conn)
+        .await?;
 
         Ok(())
     }
 
+    /// Changes recorded after the one with `after_id`, oldest first.
     #[instrument(skip(self), level = "debug")]
-    async fn get_unprocessed_commitments(self) -> Result<Vec<Hash>, Error> {
-        let mut conn = self.acquire().await?;
+    async fn get_config_changes(
+        self,
+        after_id: Option<i64>,
+        limit: usize,
+    ) -> Result<Vec<ConfigChangeEntry>, Error> {
+        let mut conn = self.acquire_for("get_config_changes").await?;
+
+        Ok(sqlx::query_as(
+            r#"
+            SELECT id, changed_at, principal, setting, old_value, new_value, request_id
+            FROM config_changes
+            WHERE id > $1
+            ORDER BY id ASC
+            LIMIT $2
+            "#,
+        )
+        .bind(after_id.unwrap_or(0))
+        .bind(limit as i64)
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    async fn is_deletion_limit_lifted(self) -> Result<bool, Error> {
+        let mut conn = self.acquire_for("is_deletion_limit_lifted").await?;
+
+        let (lifted,): (bool,) = sqlx::query_as(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM deletion_limit_overrides
+                WHERE lifted_until > CURRENT_TIMESTAMP
+            )
+            "#,
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(lifted)
+    }
+
+    /// Records a nullifier hash as spent. Returns false if it already was, the
+    /// check and the insert are a single statement so concurrent requests
+    /// can't both spend it.
+    #[instrument(skip(self), level = "debug")]
+    async fn insert_spent_nullifier(self, nullifier_hash: &Hash) -> Result<bool, Error> {
+        let mut conn = self.acquire_for("insert_spent_nullifier").await?;
+
+        let res = sqlx::query(
+            r#"
+            INSERT INTO spent_nullifiers (nullifier_hash)
+            VALUES ($1)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(nullifier_hash)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(res.rows_affected() == 1)
+    }
+
+    /// Returns when the nullifier hash was spent, `None` if it wasn't.
+    #[instrument(skip(self), level = "debug")]
+    async fn get_nullifier_spent_at(
+        self,
+        nullifier_hash: &Hash,
+    ) -> Result<Option<DateTime<Utc>>, Error> {
+        let mut conn = self.acquire_for("get_nullifier_spent_at").await?;
+
+        let spent_at: Option<(DateTime<Utc>,)> =
+            sqlx::query_as("SELECT spent_at FROM spent_nullifiers WHERE nullifier_hash = $1")
+                .bind(nullifier_hash)
+                .fetch_optional(&mut *conn)
+                .await?;
+
+        Ok(spent_at.map(|(spent_at,)| spent_at))
+    }
+
+    /// Remove a list of entries from the deletions table
+    #[instrument(skip(self), level = "debug")]
+    async fn remove_deletions(self, commitments: &[Hash]) -> Result<(), Error> {
+        let mut conn = self.acquire_for("remove_deletions").await?;
+
+        let commitments = commitments
+            .iter()
+            .map(|c| c.to_be_bytes())
+            .collect::<Vec<[u8; 32]>>();
+
+        sqlx::query("DELETE FROM deletions WHERE commitment = Any($1)")
+            .bind(commitments)
+            .execute(&mut *conn)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Records that the deletions are about to be applied to the tree and
+    /// returns the id of the intent.
+    #[instrument(skip(self, deletions), level = "debug")]
+    async fn insert_deletion_intent(self, deletions: &[DeletionEntry]) -> Result<i64, Error> {
+        let mut conn = self.acquire_for("insert_deletion_intent").await?;
+
+        let (leaf_indexes, commitments): (Vec<usize>, Vec<Hash>) = deletions
+            .iter()
+            .map(|d| (d.leaf_index, d.commitment))
+            .unzip();
+
+        let (id,): (i64,) = sqlx::query_as(
+            r#"
+            INSERT INTO deletion_intents (leaf_indexes, commitments)
+            VALUES ($1, $2)
+            RETURNING id
+            "#,
+        )
+        .bind(LeafIndexes(leaf_indexes))
+        .bind(Commitments(commitments))
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(id)
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    async fn complete_deletion_intent(self, id: i64) -> Result<(), Error> {
+        let mut conn = self.acquire_for("complete_deletion_intent").await?;
+
+        sqlx::query("UPDATE deletion_intents SET completed_at = NOW() WHERE id = $1")
+            .bind(id)
+            .execute(&mut *conn)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Returns the deletion intents which were never completed, oldest first.
+    #[instrument(skip(self), level = "debug")]
+    async fn get_incomplete_deletion_intents(self) -> Result<Vec<DeletionIntent>, Error> {
+        let mut conn = self.acquire_for("get_incomplete_deletion_intents").await?;
+
+        Ok(sqlx::query_as(
+            r#"
+            SELECT id, leaf_indexes, commitments
+            FROM   deletion_intents
+            WHERE  completed_at IS NULL
+            ORDER BY id
+            "#,
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Returns those of the leaves which have been zeroed by a deletion.
+    #[instrument(skip(self, leaf_indexes), level = "debug")]
+    async fn get_zeroed_leaves(self, leaf_indexes: &[usize]) -> Result<Vec<usize>, Error> {
+        let mut conn = self.acquire_for("get_zeroed_leaves").await?;
+
+        let leaf_indexes: Vec<i64> = leaf_indexes.iter().map(|&i| i as i64).collect();
+
+        let rows: Vec<(i64,)> = sqlx::query_as(
+            r#"
+            SELECT DISTINCT leaf_index
+            FROM   identities
+            WHERE  leaf_index = ANY($1)
+            AND    commitment = $2
+            ORDER BY leaf_index
+            "#,
+        )
+        .bind(&leaf_indexes)
+        .bind(Hash::ZERO)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(rows.into_iter().map(|(i,)| i as usize).collect())
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    async fn get_unprocessed_commitments(self) -> Result<Vec<Hash>, Error> {
+        let mut conn = self.acquire_for("get_unprocessed_commitments").await?;
+
+        let result: Vec<(Hash,)> = sqlx::query_as(
+            r#"
+            SELECT commitment FROM unprocessed_identities
+            LIMIT $1
+            "#,
+        )
+        .bind(MAX_UNPROCESSED_FETCH_COUNT)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(result.into_iter().map(|(commitment,)| commitment).collect())
+    }
+
+    async fn get_unprocessed_commitment(self, commitment: &Hash) -> Result<Option<Hash>, Error> {
+        let mut conn = self.acquire_for("get_unprocessed_commitment").await?;
+
+        let result = sqlx::query(
+            r#"
+                SELECT commitment FROM unprocessed_identities WHERE commitment = $1
+            "#,
+        )
+        .bind(commitment)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        if let Some(row) = result {
+            return Ok(Some(row.get::<Hash, _>(0)));
+        };
+        Ok(None)
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    async fn remove_unprocessed_identity(self, commitment: &Hash) -> Result<(), Error> {
+        let mut conn = self.acquire_for("remove_unprocessed_identity").await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM unprocessed_identities WHERE commitment = $1
+            "#,
+        )
+        .bind(commitment)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Removes unprocessed identities which are already in the tree at a leaf
+    /// which wasn't deleted, and returns their commitments. Deleted
+    /// commitments queued for insertion again are kept. Safe to call
+    /// repeatedly.
+    #[instrument(skip(self), level = "debug")]
+    async fn trim_unprocessed(self) -> Result<Vec<Hash>, Error> {
+        let mut conn = self.acquire_for("trim_unprocessed").await?;
+
+        let result: Vec<(Hash,)> = sqlx::query_as(
+            r#"
+            DELETE FROM unprocessed_identities u
+            USING identities i
+            WHERE u.commitment = i.commitment
+            AND NOT i.deleted
+            RETURNING u.commitment
+            "#,
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(result.into_iter().map(|(commitment,)| commitment).collect())
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    async fn identity_exists(self, commitment: Hash) -> Result<bool, Error> {
+        let mut conn = self.acquire_for("identity_exists").await?;
+
+        Ok(sqlx::query(
+            r#"
+            select
+            EXISTS (select commitment from unprocessed_identities where commitment = $1) OR
+            EXISTS (select commitment from identities where commitment = $1);
+            "#,
+        )
+        .bind(commitment)
+        .fetch_one(&mut *conn)
+        .await?
+        .get::<bool, _>(0))
+    }
+
+    /// Whether the commitment was deleted from every leaf it was inserted at,
+    /// with all of the deletions mined at least `days` ago by the clock of the
+    /// database. Never the case for commitments queued for insertion.
+    #[instrument(skip(self), level = "debug")]
+    async fn deleted_for_days(self, commitment: Hash, days: u32) -> Result<bool, Error> {
+        let mut conn = self.acquire_for("deleted_for_days").await?;
+
+        Ok(sqlx::query(
+            r#"
+            SELECT
+                NOT EXISTS (SELECT 1 FROM unprocessed_identities WHERE commitment = $1)
+                AND EXISTS (SELECT 1 FROM identities WHERE commitment = $1)
+                AND NOT EXISTS (
+                    SELECT 1
+                    FROM identities inserted
+                    WHERE inserted.commitment = $1
+                    AND NOT EXISTS (
+                        SELECT 1
+                        FROM identities deleted
+                        WHERE deleted.leaf_index = inserted.leaf_index
+                        AND deleted.commitment = $2
+                        AND deleted.id > inserted.id
+                        AND deleted.mined_at <= CURRENT_TIMESTAMP - make_interval(days => $3)
+                    )
+                )
+            "#,
+        )
+        .bind(commitment)
+        .bind(Hash::ZERO)
+        .bind(days as i32)
+        .fetch_one(&mut *conn)
+        .await?
+        .get::<bool, _>(0))
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    async fn insert_new_batch_head(self, next_root: &Hash) -> Result<(), Error> {
+        let mut conn = self.acquire_for("insert_new_batch_head").await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO batches(
+                id,
+                next_root,
+                prev_root,
+                created_at,
+                batch_type,
+                data
+            ) VALUES (DEFAULT, $1, NULL, CURRENT_TIMESTAMP, $2, $3)
+            "#,
+        )
+        .bind(next_root)
+        .bind(BatchType::Insertion)
+        .bind(sqlx::types::Json::from(BatchEntryData {
+            identities: vec![],
+            indexes: vec![],
+        }))
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Stores a batch of `identities`, which must be padded to one of the
+    /// `prover_sizes` registered for its type.
+    #[instrument(skip(self), level = "debug")]
+    async fn insert_new_batch(
+        self,
+        next_root: &Hash,
+        prev_root: &Hash,
+        batch_type: BatchType,
+        prover_sizes: &[RangeInclusive<usize>],
+        identities: &[Identity],
+        indexes: &[usize],
+    ) -> Result<(), Error> {
+        // No prover would take the batch, so don't store it
+        if !prover_sizes
+            .iter()
+            .any(|sizes| sizes.contains(&identities.len()))
+        {
+            return Err(Error::BatchSizeMismatch {
+                expected: prover_sizes.to_vec(),
+                actual: identities.len(),
+            });
+        }
+
+        let mut conn = self.acquire_for("insert_new_batch").await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO batches(
+                id,
+                next_root,
+                prev_root,
+                created_at,
+                batch_type,
+                data
+            ) VALUES (DEFAULT, $1, $2, CURRENT_TIMESTAMP, $3, $4)
+            "#,
+        )
+        .bind(next_root)
+        .bind(prev_root)
+        .bind(batch_type)
+        .bind(sqlx::types::Json::from(BatchEntryData {
+            identities: identities.to_vec(),
+            indexes: indexes.to_vec(),
+        }))
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Records which prover proved the batch with `next_root` and how long it
+    /// took. A batch proven more than once keeps the values of the last proof.
+    ///
+    /// Only the host and port of the prover URL are kept, see
+    /// [`prover_label`].
+    #[instrument(skip(self), level = "debug")]
+    async fn record_batch_proving(
+        self,
+        next_root: &Hash,
+        prover_url: &Url,
+        duration: std::time::Duration,
+    ) -> Result<(), Error> {
+        let mut conn = self.acquire_for("record_batch_proving").await?;
+
+        let duration_ms = i64::try_from(duration.as_millis()).unwrap_or(i64::MAX);
+
+        sqlx::query(
+            r#"
+            UPDATE batches
+            SET prover_url = $2, proving_duration_ms = $3
+            WHERE next_root = $1
+            "#,
+        )
+        .bind(next_root)
+        .bind(prover_label(prover_url))
+        .bind(duration_ms)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    #[cfg(test)]
+    #[instrument(skip(self), level = "debug")]
+    async fn get_next_batch(self, prev_root: &Hash) -> Result<Option<BatchEntry>, Error> {
+        let mut conn = self.acquire_for("get_next_batch").await?;
+
+        let res = sqlx::query_as::<_, BatchEntry>(
+            r#"
+            SELECT
+                id,
+                next_root,
+                prev_root,
+                created_at,
+                batch_type,
+                data
+            FROM batches WHERE prev_root = $1
+            LIMIT 1
+            "#,
+        )
+        .bind(prev_root)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(res)
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    async fn get_latest_batch(self) -> Result<Option<BatchEntry>, Error> {
+        let mut conn = self.acquire_for("get_latest_batch").await?;
+
+        let res = sqlx::query_as::<_, BatchEntry>(
+            r#"
+            SELECT
+                id,
+                next_root,
+                prev_root,
+                created_at,
+                batch_type,
+                data
+            FROM batches
+            ORDER BY id DESC
+            LIMIT 1
+            "#,
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(res)
+    }
+
+    /// Claims up to `limit` of the oldest batches without a transaction for
+    /// `claimer`, for the duration of `lease`, and returns them in submission
+    /// order.
+    ///
+    /// Batches are only claimed up to the first one claimed by another
+    /// instance, so that no batch is ever submitted before its predecessor.
+    /// The rows are only locked while claiming, the claim itself is released
+    /// with [`Self::release_batch_claims`] or expires.
+    #[instrument(skip(self), level = "debug")]
+    async fn claim_next_batches(
+        self,
+        claimer: &str,
+        lease: std::time::Duration,
+        limit: usize,
+    ) -> Result<Vec<BatchEntry>, Error> {
+        let mut conn = self.acquire_for("claim_next_batches").await?;
+
+        let mut res = sqlx::query_as::<_, BatchEntry>(
+            r#"
+            WITH pending AS (
+                SELECT batches.id, batches.claimed_by, batches.claimed_until
+                FROM batches
+                LEFT JOIN transactions ON batches.next_root = transactions.batch_next_root
+                WHERE transactions.batch_next_root IS NULL AND batches.prev_root IS NOT NULL
+                ORDER BY batches.id ASC
+                LIMIT $3
+                FOR UPDATE OF batches
+            ), claimable AS (
+                SELECT id
+                FROM pending
+                WHERE NOT EXISTS (
+                    SELECT 1
+                    FROM pending AS predecessor
+                    WHERE predecessor.id <= pending.id
+                    AND predecessor.claimed_by != $1
+                    AND predecessor.claimed_until > CURRENT_TIMESTAMP
+                )
+            )
+            UPDATE batches
+            SET claimed_by = $1,
+                claimed_until = CURRENT_TIMESTAMP + make_interval(secs => $2)
+            FROM claimable
+            WHERE batches.id = claimable.id
+            RETURNING
+                batches.id,
+                batches.next_root,
+                batches.prev_root,
+                batches.created_at,
+                batches.batch_type,
+                batches.data,
+                batches.approval
+            "#,
+        )
+        .bind(claimer)
+        .bind(lease.as_secs_f64())
+        .bind(limit as i64)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        res.sort_by_key(|batch| batch.id);
+
+        Ok(res)
+    }
+
+    /// Releases the claims of `claimer` on the given batches, returns how many
+    /// of them it still held.
+    #[instrument(skip(self), level = "debug")]
+    async fn release_batch_claims(self, claimer: &str, roots: &[Hash]) -> Result<u64, Error> {
+        let mut conn = self.acquire_for("release_batch_claims").await?;
+
+        let res = sqlx::query(
+            r#"
+            UPDATE batches
+            SET    claimed_by = NULL, claimed_until = NULL
+            WHERE  next_root = ANY($2)
+            AND    claimed_by = $1
+            "#,
+        )
+        .bind(claimer)
+        .bind(Commitments(roots.to_vec()))
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(res.rows_affected())
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    async fn get_batch_head(self) -> Result<Option<BatchEntry>, Error> {
+        let mut conn = self.acquire_for("get_batch_head").await?;
+
+        let res = sqlx::query_as::<_, BatchEntry>(
+            r#"
+            SELECT
+                id,
+                next_root,
+                prev_root,
+                created_at,
+                batch_type,
+                data
+            FROM batches WHERE prev_root IS NULL
+            LIMIT 1
+            "#,
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(res)
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    async fn delete_batches_after_root(self, root: &Hash) -> Result<(), Error> {
+        let mut conn = self.acquire_for("delete_batches_after_root").await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM batches
+            WHERE prev_root = $1
+            "#,
+        )
+        .bind(root)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Puts the batches which haven't been reviewed yet up for approval.
+    #[instrument(skip(self), level = "debug")]
+    async fn mark_batches_awaiting_approval(self, next_roots: &[Hash]) -> Result<(), Error> {
+        let mut conn = self.acquire_for("mark_batches_awaiting_approval").await?;
+
+        sqlx::query(
+            r#"
+            UPDATE batches
+            SET    approval = $2
+            WHERE  next_root = ANY($1)
+            AND    approval IS NULL
+            "#,
+        )
+        .bind(Commitments(next_roots.to_vec()))
+        .bind(BatchApproval::AwaitingApproval)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// The next roots of the batches awaiting approval, in batch order.
+    #[instrument(skip(self), level = "debug")]
+    async fn get_batches_awaiting_approval(self) -> Result<Vec<Hash>, Error> {
+        let mut conn = self.acquire_for("get_batches_awaiting_approval").await?;
+
+        let roots: Vec<(Hash,)> = sqlx::query_as(
+            r#"
+            SELECT next_root
+            FROM   batches
+            WHERE  approval = $1
+            ORDER BY id
+            "#,
+        )
+        .bind(BatchApproval::AwaitingApproval)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(roots.into_iter().map(|(root,)| root).collect())
+    }
+
+    /// Approves or rejects the batch with `next_root` on behalf of `principal`.
+    /// Returns `false` if the batch isn't awaiting approval.
+    #[instrument(skip(self), level = "debug")]
+    async fn decide_batch_approval(
+        self,
+        next_root: &Hash,
+        approval: BatchApproval,
+        principal: &str,
+    ) -> Result<bool, Error> {
+        let mut conn = self.acquire_for("decide_batch_approval").await?;
+
+        let result = sqlx::query(
+            r#"
+            UPDATE batches
+            SET    approval = $2, approval_principal = $4
+            WHERE  next_root = $1
+            AND    approval = $3
+            "#,
+        )
+        .bind(next_root)
+        .bind(approval)
+        .bind(BatchApproval::AwaitingApproval)
+        .bind(principal)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    async fn get_batch(self, next_root: &Hash) -> Result<Option<BatchEntry>, Error> {
+        let mut conn = self.acquire_for("get_batch").await?;
+
+        let batch = sqlx::query_as::<_, BatchEntry>(
+            r#"
+            SELECT
+                id,
+                next_root,
+                prev_root,
+                created_at,
+                batch_type,
+                data,
+                approval,
+                approval_principal,
+                note
+            FROM batches
+            WHERE next_root = $1
+            "#,
+        )
+        .bind(next_root)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(batch)
+    }
+
+    /// Adds a note to the batch with `next_root`, keeping the earlier ones.
+    /// Returns `false` if there is no such batch.
+    #[instrument(skip(self), level = "debug")]
+    async fn insert_batch_note(
+        self,
+        next_root: &Hash,
+        note: &str,
+        principal: &str,
+    ) -> Result<bool, Error> {
+        let mut conn = self.acquire_for("insert_batch_note").await?;
+
+        let result = sqlx::query(
+            r#"
+            WITH annotated AS (
+                UPDATE batches
+                SET    note = $2
+                WHERE  next_root = $1
+                RETURNING next_root
+            )
+            INSERT INTO batch_notes (batch_next_root, note, principal)
+            SELECT next_root, $2, $3 FROM annotated
+            "#,
+        )
+        .bind(next_root)
+        .bind(note)
+        .bind(principal)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Every note added to the batch with `next_root`, oldest first.
+    #[instrument(skip(self), level = "debug")]
+    async fn get_batch_notes(self, next_root: &Hash) -> Result<Vec<BatchNoteEntry>, Error> {
+        let mut conn = self.acquire_for("get_batch_notes").await?;
+
+        Ok(sqlx::query_as::<_, BatchNoteEntry>(
+            r#"
+            SELECT id, note, principal, created_at
+            FROM batch_notes
+            WHERE batch_next_root = $1
+            ORDER BY id ASC
+            "#,
+        )
+        .bind(next_root)
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// The earliest rejected batch, every batch after it is unwound along with
+    /// it.
+    #[instrument(skip(self), level = "debug")]
+    async fn get_first_rejected_batch(self) -> Result<Option<BatchEntry>, Error> {
+        let mut conn = self.acquire_for("get_first_rejected_batch").await?;
+
+        let batch = sqlx::query_as::<_, BatchEntry>(
+            r#"
+            SELECT id, next_root, prev_root, created_at, batch_type, data, approval
+            FROM   batches
+            WHERE  approval = $1
+            ORDER BY id
+            LIMIT 1
+            "#,
+        )
+        .bind(BatchApproval::Rejected)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(batch)
+    }
+
+    /// Removes every identity update after `sequence_id` and queues it again:
+    /// inserted commitments become unprocessed and deletions are queued
+    /// anew. A commitment both inserted and deleted after `sequence_id` is
+    /// dropped altogether, so are deletions queued for the commitments which
+    /// become unprocessed. Returns the number of queued insertions and
+    /// deletions.
+    #[instrument(skip(self), level = "debug")]
+    async fn unwind_updates_after(self, sequence_id: usize) -> Result<(u64, u64), Error> {
+        let mut conn = self.acquire_for("unwind_updates_after").await?;
+
+        let insertions = sqlx::query(
+            r#"
+            INSERT INTO unprocessed_identities (commitment, created_at)
+            SELECT i.commitment, COALESCE(i.received_at, i.pending_as_of)
+            FROM   identities i
+            WHERE  i.id > $1
+            AND    i.commitment <> $2
+            AND    NOT EXISTS (
+                SELECT 1
+                FROM   identities d
+                WHERE  d.id > i.id
+                AND    d.leaf_index = i.leaf_index
+                AND    d.commitment = $2
+            )
+            ORDER BY i.id
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(sequence_id as i64)
+        .bind(Hash::ZERO)
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+
+        // Deletions only need to be queued again if the leaf wasn't empty yet
+        let deletions = sqlx::query(
+            r#"
+            INSERT INTO deletions (leaf_index, commitment)
+            SELECT d.leaf_index, prev.commitment
+            FROM   identities d
+            CROSS JOIN LATERAL (
+                SELECT p.commitment
+                FROM   identities p
+                WHERE  p.leaf_index = d.leaf_index
+                AND    p.id <= $1
+                ORDER BY p.id DESC
+                LIMIT 1
+            ) prev
+            WHERE  d.id > $1
+            AND    d.commitment = $2
+            AND    prev.commitment <> $2
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(sequence_id as i64)
+        .bind(Hash::ZERO)
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
 
-        let result: Vec<(Hash,)> = sqlx::query_as(
+        // The leaves of the queued insertions are freed, so deletions queued for
+        // them would hit whatever ends up there
+        sqlx::query(
             r#"
-            SELECT commitment FROM unprocessed_identities
-            LIMIT $1
+            DELETE FROM deletions d
+            USING  identities i
+            WHERE  i.id > $1
+            AND    d.leaf_index = i.leaf_index
+            AND    d.commitment = i.commitment
             "#,
         )
-        .bind(MAX_UNPROCESSED_FETCH_COUNT)
-        .fetch_all(&mut *conn)
+        .bind(sequence_id as i64)
+        .execute(&mut *conn)
         .await?;
 
-        Ok(result.into_iter().map(|(commitment,)| commitment).collect())
+        let removed = sqlx::query(
+            r#"
+            DELETE FROM identities
+            WHERE  id > $1
+            "#,
+        )
+        .bind(sequence_id as i64)
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+
+        if removed > 0 {
+            conn.bump_tree_epoch().await?;
+        }
+
+        Ok((insertions, deletions))
     }
 
-    async fn get_unprocessed_commitment(self, commitment: &Hash) -> Result<Option<Hash>, Error> {
-        let mut conn = self.acquire().await?;
+    #[instrument(skip(self), level = "debug")]
+    async fn delete_all_batches(self) -> Result<(), Error> {
+        let mut conn = self.acquire_for("delete_all_batches").await?;
 
-        let result = sqlx::query(
+        sqlx::query(
             r#"
-                SELECT commitment FROM unprocessed_identities WHERE commitment = $1
+            DELETE FROM batches
             "#,
         )
-        .bind(commitment)
-        .fetch_optional(&mut *conn)
+        .execute(&mut *conn)
         .await?;
 
-        if let Some(row) = result {
-            return Ok(Some(row.get::<Hash, _>(0)));
-        };
-        Ok(None)
+        Ok(())
     }
 
     #[instrument(skip(self), level = "debug")]
-    async fn remove_unprocessed_identity(self, commitment: &Hash) -> Result<(), Error> {
-        let mut conn = self.acquire().await?;
+    async fn insert_new_transaction(
+        self,
+        transaction_id: &String,
+        batch_next_root: &Hash,
+    ) -> Result<(), Error> {
+        let mut conn = self.acquire_for("insert_new_transaction").await?;
 
         sqlx::query(
             r#"
-            DELETE FROM unprocessed_identities WHERE commitment = $1
+            INSERT INTO transactions(
+                transaction_id,
+                batch_next_root,
+                created_at
+            ) VALUES ($1, $2, CURRENT_TIMESTAMP)
             "#,
         )
-        .bind(commitment)
+        .bind(transaction_id)
+        .bind(batch_next_root)
         .execute(&mut *conn)
         .await?;
 
         Ok(())
     }
 
+    /// Forgets a transaction which failed, so that all the batches it submitted
+    /// are picked up for processing again. Returns their next roots.
     #[instrument(skip(self), level = "debug")]
-    async fn trim_unprocessed(self) -> Result<(), Error> {
-        let mut conn = self.acquire().await?;
+    async fn delete_transaction(self, transaction_id: &str) -> Result<Vec<Hash>, Error> {
+        let mut conn = self.acquire_for("delete_transaction").await?;
 
-        sqlx::query(
+        let roots = sqlx::query_scalar::<_, Hash>(
             r#"
-            DELETE FROM unprocessed_identities u
-            USING identities i
-            WHERE u.commitment = i.commitment
+            DELETE FROM transactions
+            WHERE transaction_id = $1
+            RETURNING batch_next_root
             "#,
         )
-        .execute(&mut *conn)
+        .bind(transaction_id)
+        .fetch_all(&mut *conn)
         .await?;
 
-        Ok(())
+        Ok(roots)
     }
 
+    /// Returns the ids of the recorded transactions, most recent first.
     #[instrument(skip(self), level = "debug")]
-    async fn identity_exists(self, commitment: Hash) -> Result<bool, Error> {
-        let mut conn = self.acquire().await?;
+    async fn get_transaction_ids(self, limit: usize, offset: i64) -> Result<Vec<String>, Error> {
+        let mut conn = self.acquire_for("get_transaction_ids").await?;
 
-        Ok(sqlx::query(
+        let ids = sqlx::query_scalar::<_, String>(
             r#"
-            select
-            EXISTS (select commitment from unprocessed_identities where commitment = $1) OR
-            EXISTS (select commitment from identities where commitment = $1);
+            SELECT transaction_id
+            FROM transactions
+            GROUP BY transaction_id
+            ORDER BY MAX(created_at) DESC, transaction_id DESC
+            LIMIT $1 OFFSET $2
             "#,
         )
-        .bind(commitment)
-        .fetch_one(&mut *conn)
-        .await?
-        .get::<bool, _>(0))
+        .bind(limit as i64)
+        .bind(offset)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(ids)
     }
 
+    /// Returns the batches submitted by any of the given transactions, in
+    /// submission order.
     #[instrument(skip(self), level = "debug")]
-    async fn insert_new_batch_head(self, next_root: &Hash) -> Result<(), Error> {
-        let mut conn = self.acquire().await?;
+    async fn get_transaction_batches(
+        self,
+        transaction_ids: &[String],
+    ) -> Result<Vec<TransactionBatchEntry>, Error> {
+        let mut conn = self.acquire_for("get_transaction_batches").await?;
 
-        sqlx::query(
+        let res = sqlx::query_as::<_, TransactionBatchEntry>(
             r#"
-            INSERT INTO batches(
-                id,
-                next_root,
-                prev_root,
-                created_at,
-                batch_type,
-                data
-            ) VALUES (DEFAULT, $1, NULL, CURRENT_TIMESTAMP, $2, $3)
+            SELECT
+                transactions.transaction_id,
+                transactions.batch_next_root,
+                batches.batch_type,
+                transactions.created_at,
+                batches.note
+            FROM transactions
+            JOIN batches ON batches.next_root = transactions.batch_next_root
+            WHERE transactions.transaction_id = ANY($1)
+            ORDER BY batches.id ASC
             "#,
         )
-        .bind(next_root)
-        .bind(BatchType::Insertion)
-        .bind(sqlx::types::Json::from(BatchEntryData {
-            identities: vec![],
-            indexes: vec![],
-        }))
-        .execute(&mut *conn)
+        .bind(transaction_ids)
+        .fetch_all(&mut *conn)
         .await?;
 
-        Ok(())
+        Ok(res)
     }
 
     #[instrument(skip(self), level = "debug")]
-    async fn insert_new_batch(
+    async fn insert_root_subscription(
         self,
-        next_root: &Hash,
-        prev_root: &Hash,
-        batch_type: BatchType,
-        identities: &[Identity],
-        indexes: &[usize],
+        url: &str,
+        aging_threshold_percent: Option<u32>,
     ) -> Result<(), Error> {
-        let mut conn = self.acquire().await?;
+        let mut conn = self.acquire_for("insert_root_subscription").await?;
 
         sqlx::query(
             r#"
-            INSERT INTO batches(
-                id,
-                next_root,
-                prev_root,
-                created_at,
-                batch_type,
-                data
-            ) VALUES (DEFAULT, $1, $2, CURRENT_TIMESTAMP, $3, $4)
+            INSERT INTO root_subscriptions (url, aging_threshold_percent)
+            VALUES ($1, $2)
+            ON CONFLICT (url) DO UPDATE
+            SET aging_threshold_percent = EXCLUDED.aging_threshold_percent
             "#,
         )
-        .bind(next_root)
-        .bind(prev_root)
-        .bind(batch_type)
-        .bind(sqlx::types::Json::from(BatchEntryData {
-            identities: identities.to_vec(),
-            indexes: indexes.to_vec(),
-        }))
+        .bind(url)
+        .bind(aging_threshold_percent.map(|percent| percent as i32))
         .execute(&mut *conn)
         .await?;
 
         Ok(())
     }
 
-    #[cfg(test)]
+    /// Returns false if there was no subscription for the given url
     #[instrument(skip(self), level = "debug")]
-    async fn get_next_batch(self, prev_root: &Hash) -> Result<Option<BatchEntry>, Error> {
-        let mut conn = self.acquire().await?;
+    async fn remove_root_subscription(self, url: &str) -> Result<bool, Error> {
+        let mut conn = self.acquire_for("remove_root_subscription").await?;
 
-        let res = sqlx::query_as::<_, BatchEntry>(
+        let result = sqlx::query(
             r#"
-            SELECT
-                id,
-                next_root,
-                prev_root,
-                created_at,
-                batch_type,
-                data
-            FROM batches WHERE prev_root = $1
-            LIMIT 1
+            DELETE FROM root_subscriptions WHERE url = $1
             "#,
         )
-        .bind(prev_root)
-        .fetch_optional(&mut *conn)
+        .bind(url)
+        .execute(&mut *conn)
         .await?;
 
-        Ok(res)
+        Ok(result.rows_affected() > 0)
     }
 
+    /// Locks the subscription of the given url until the end of the
+    /// transaction, if there is one.
     #[instrument(skip(self), level = "debug")]
-    async fn get_latest_batch(self) -> Result<Option<BatchEntry>, Error> {
-        let mut conn = self.acquire().await?;
+    async fn get_root_subscription(self, url: &str) -> Result<Option<RootSubscription>, Error> {
+        let mut conn = self.acquire_for("get_root_subscription").await?;
 
-        let res = sqlx::query_as::<_, BatchEntry>(
+        Ok(sqlx::query_as::<_, RootSubscription>(
             r#"
-            SELECT
-                id,
-                next_root,
-                prev_root,
-                created_at,
-                batch_type,
-                data
-            FROM batches
-            ORDER BY id DESC
-            LIMIT 1
+            SELECT id, url, created_at, aging_threshold_percent
+            FROM root_subscriptions
+            WHERE url = $1
+            FOR UPDATE
             "#,
         )
+        .bind(url)
         .fetch_optional(&mut *conn)
-        .await?;
-
-        Ok(res)
+        .await?)
     }
 
     #[instrument(skip(self), level = "debug")]
-    async fn get_next_batch_without_transaction(self) -> Result<Option<BatchEntry>, Error> {
-        let mut conn = self.acquire().await?;
+    async fn get_root_subscriptions(self) -> Result<Vec<RootSubscription>, Error> {
+        let mut conn = self.acquire_for("get_root_subscriptions").await?;
 
-        let res = sqlx::query_as::<_, BatchEntry>(
+        Ok(sqlx::query_as::<_, RootSubscription>(
             r#"
-            SELECT
-                batches.id,
-                batches.next_root,
-                batches.prev_root,
-                batches.created_at,
-                batches.batch_type,
-                batches.data
-            FROM batches
-            LEFT JOIN transactions ON batches.next_root = transactions.batch_next_root
-            WHERE transactions.batch_next_root IS NULL AND batches.prev_root IS NOT NULL
-            ORDER BY batches.id ASC
-            LIMIT 1
+            SELECT id, url, created_at, aging_threshold_percent
+            FROM root_subscriptions
+            ORDER BY id
             "#,
         )
-        .fetch_optional(&mut *conn)
-        .await?;
-
-        Ok(res)
+        .fetch_all(&mut *conn)
+        .await?)
     }
 
     #[instrument(skip(self), level = "debug")]
-    async fn get_batch_head(self) -> Result<Option<BatchEntry>, Error> {
-        let mut conn = self.acquire().await?;
+    async fn root_notification_sent(
+        self,
+        subscription_id: i64,
+        root: &Hash,
+        kind: RootNotificationKind,
+    ) -> Result<bool, Error> {
+        let mut conn = self.acquire_for("root_notification_sent").await?;
 
-        let res = sqlx::query_as::<_, BatchEntry>(
+        Ok(sqlx::query(
             r#"
-            SELECT
-                id,
-                next_root,
-                prev_root,
-                created_at,
-                batch_type,
-                data
-            FROM batches WHERE prev_root IS NULL
-            LIMIT 1
+            SELECT EXISTS (
+                SELECT 1 FROM root_notifications
+                WHERE subscription_id = $1 AND root = $2 AND kind = $3
+            )
             "#,
         )
-        .fetch_optional(&mut *conn)
-        .await?;
-
-        Ok(res)
+        .bind(subscription_id)
+        .bind(root)
+        .bind(kind)
+        .fetch_one(&mut *conn)
+        .await?
+        .get::<bool, _>(0))
     }
 
     #[instrument(skip(self), level = "debug")]
-    async fn delete_batches_after_root(self, root: &Hash) -> Result<(), Error> {
-        let mut conn = self.acquire().await?;
+    async fn insert_root_notification(
+        self,
+        subscription_id: i64,
+        root: &Hash,
+        kind: RootNotificationKind,
+    ) -> Result<(), Error> {
+        let mut conn = self.acquire_for("insert_root_notification").await?;
 
         sqlx::query(
             r#"
-            DELETE FROM batches
-            WHERE prev_root = $1
+            INSERT INTO root_notifications (subscription_id, root, kind)
+            VALUES ($1, $2, $3)
+            ON CONFLICT DO NOTHING
             "#,
         )
+        .bind(subscription_id)
         .bind(root)
+        .bind(kind)
         .execute(&mut *conn)
         .await?;
 
@@ -878,43 +2464,167 @@ pub trait DbMethods<'c>: Acquire<'c, Database = Postgres> + Sized {
     }
 
     #[instrument(skip(self), level = "debug")]
-    async fn delete_all_batches(self) -> Result<(), Error> {
-        let mut conn = self.acquire().await?;
+    async fn get_bulk_import(self, import_id: &str) -> Result<Option<BulkImportProgress>, Error> {
+        let mut conn = self.acquire_for("get_bulk_import").await?;
+
+        Ok(sqlx::query_as::<_, BulkImportProgress>(
+            r#"
+            SELECT import_id, lines_processed, accepted, duplicates, invalid
+            FROM bulk_imports
+            WHERE import_id = $1
+            "#,
+        )
+        .bind(import_id)
+        .fetch_optional(&mut *conn)
+        .await?)
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    async fn update_bulk_import(self, progress: &BulkImportProgress) -> Result<(), Error> {
+        let mut conn = self.acquire_for("update_bulk_import").await?;
 
         sqlx::query(
             r#"
-            DELETE FROM batches
+            INSERT INTO bulk_imports (import_id, lines_processed, accepted, duplicates, invalid)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (import_id) DO UPDATE SET
+                lines_processed = EXCLUDED.lines_processed,
+                accepted = EXCLUDED.accepted,
+                duplicates = EXCLUDED.duplicates,
+                invalid = EXCLUDED.invalid,
+                updated_at = CURRENT_TIMESTAMP
             "#,
         )
+        .bind(&progress.import_id)
+        .bind(progress.lines_processed as i64)
+        .bind(progress.accepted as i64)
+        .bind(progress.duplicates as i64)
+        .bind(progress.invalid as i64)
         .execute(&mut *conn)
         .await?;
 
         Ok(())
     }
 
+    /// Starts an export of every identity inserted so far.
     #[instrument(skip(self), level = "debug")]
-    async fn insert_new_transaction(
+    async fn insert_export(self) -> Result<ExportEntry, Error> {
+        let mut conn = self.acquire_for("insert_export").await?;
+
+        Ok(sqlx::query_as::<_, ExportEntry>(
+            r#"
+            INSERT INTO exports (up_to_sequence_id)
+            SELECT COALESCE(MAX(id), 0) FROM identities
+            RETURNING id, up_to_sequence_id, last_sequence_id, chunks, identities,
+                created_at, completed_at
+            "#,
+        )
+        .fetch_one(&mut *conn)
+        .await?)
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    async fn get_export(self, id: i64) -> Result<Option<ExportEntry>, Error> {
+        let mut conn = self.acquire_for("get_export").await?;
+
+        Ok(sqlx::query_as::<_, ExportEntry>(
+            r#"
+            SELECT id, up_to_sequence_id, last_sequence_id, chunks, identities,
+                created_at, completed_at
+            FROM exports
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await?)
+    }
+
+    /// Claims the oldest export which hasn't completed yet for `claimer`, for
+    /// the duration of `lease`. Exports claimed by other instances are skipped
+    /// until their claim expires.
+    ///
+    /// The row is only locked while claiming, the claim is renewed by every
+    /// checkpoint in [`Self::update_export`] and released on completion.
+    #[instrument(skip(self), level = "debug")]
+    async fn claim_next_export(
         self,
-        transaction_id: &String,
-        batch_next_root: &Hash,
-    ) -> Result<(), Error> {
-        let mut conn = self.acquire().await?;
+        claimer: &str,
+        lease: std::time::Duration,
+    ) -> Result<Option<ExportEntry>, Error> {
+        let mut conn = self.acquire_for("claim_next_export").await?;
 
-        sqlx::query(
+        Ok(sqlx::query_as::<_, ExportEntry>(
             r#"
-            INSERT INTO transactions(
-                transaction_id,
-                batch_next_root,
-                created_at
-            ) VALUES ($1, $2, CURRENT_TIMESTAMP)
+            UPDATE exports
+            SET claimed_by = $1,
+                claimed_until = CURRENT_TIMESTAMP + make_interval(secs => $2)
+            WHERE id = (
+                SELECT id
+                FROM exports
+                WHERE completed_at IS NULL
+                AND (
+                    claimed_by IS NULL
+                    OR claimed_by = $1
+                    OR claimed_until <= CURRENT_TIMESTAMP
+                )
+                ORDER BY id ASC
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, up_to_sequence_id, last_sequence_id, chunks, identities,
+                created_at, completed_at
             "#,
         )
-        .bind(transaction_id)
-        .bind(batch_next_root)
-        .execute(&mut *conn)
-        .await?;
+        .bind(claimer)
+        .bind(lease.as_secs_f64())
+        .fetch_optional(&mut *conn)
+        .await?)
+    }
 
-        Ok(())
+    /// Checkpoints the progress of an export claimed by `claimer`, marking it
+    /// as completed if `completed` is set. The claim is renewed for the
+    /// duration of `lease`, or released on completion.
+    ///
+    /// Returns `None` without checkpointing if another instance took over the
+    /// export.
+    #[instrument(skip(self), level = "debug")]
+    async fn update_export(
+        self,
+        export: &ExportEntry,
+        completed: bool,
+        claimer: &str,
+        lease: std::time::Duration,
+    ) -> Result<Option<ExportEntry>, Error> {
+        let mut conn = self.acquire_for("update_export").await?;
+
+        Ok(sqlx::query_as::<_, ExportEntry>(
+            r#"
+            UPDATE exports SET
+                last_sequence_id = $2,
+                chunks = $3,
+                identities = $4,
+                completed_at = CASE WHEN $5 THEN CURRENT_TIMESTAMP END,
+                claimed_by = CASE WHEN $5 THEN NULL ELSE $6 END,
+                claimed_until = CASE
+                    WHEN $5 THEN NULL
+                    ELSE CURRENT_TIMESTAMP + make_interval(secs => $7)
+                END
+            WHERE id = $1
+            AND claimed_by = $6
+            RETURNING id, up_to_sequence_id, last_sequence_id, chunks, identities,
+                created_at, completed_at
+            "#,
+        )
+        .bind(export.id)
+        .bind(export.last_sequence_id as i64)
+        .bind(export.chunks as i64)
+        .bind(export.identities as i64)
+        .bind(completed)
+        .bind(claimer)
+        .bind(lease.as_secs_f64())
+        .fetch_optional(&mut *conn)
+        .await?)
     }
 }
 
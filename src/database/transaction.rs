@@ -0,0 +1,158 @@
+use std::ops::{Deref, DerefMut};
+use std::time::{Duration, Instant};
+
+use prometheus::IntGauge;
+use sqlx::{Postgres, Transaction};
+use tokio::task::AbortHandle;
+use tracing::{warn, Instrument};
+
+/// A transaction opened by [`super::Database::begin_tx`].
+///
+/// Long-held transactions keep Postgres from vacuuming, so a warning naming
+/// the transaction's label is logged while one is held for longer than the
+/// configured threshold, and once it is finished.
+///
+/// Transactions are finished with [`Tx::commit`], [`Tx::rollback`] or
+/// [`Tx::finish`], the latter rolling back when the body failed. Dropping a
+/// transaction still rolls it back, but is only expected while panicking and
+/// logged as a mistake otherwise.
+pub struct Tx {
+    inner: Option<Transaction<'static, Postgres>>,
+    label: &'static str,
+    opened_at: Instant,
+    slow_threshold: Duration,
+    // The open transactions gauge of the label
+    open_transactions: IntGauge,
+    // Warns while the transaction is held for too long
+    watchdog: AbortHandle,
+}
+
+impl Tx {
+    pub(super) fn new(
+        inner: Transaction<'static, Postgres>,
+        label: &'static str,
+        slow_threshold: Duration,
+        open_transactions: IntGauge,
+    ) -> Self {
+        open_transactions.inc();
+
+        let opened_at = Instant::now();
+        // Logged within the span the transaction was opened in
+        let watchdog = tokio::spawn(
+            async move {
+                loop {
+                    tokio::time::sleep(slow_threshold).await;
+                    warn!(
+                        label,
+                        elapsed = ?opened_at.elapsed(),
+                        threshold = ?slow_threshold,
+                        "Transaction still open"
+                    );
+                }
+            }
+            .in_current_span(),
+        )
+        .abort_handle();
+
+        Self {
+            inner: Some(inner),
+            label,
+            opened_at,
+            slow_threshold,
+            open_transactions,
+            watchdog,
+        }
+    }
+
+    #[must_use]
+    pub fn label(&self) -> &'static str {
+        self.label
+    }
+
+    pub async fn commit(mut self) -> Result<(), sqlx::Error> {
+        self.take().commit().await
+    }
+
+    pub async fn rollback(mut self) -> Result<(), sqlx::Error> {
+        self.take().rollback().await
+    }
+
+    /// Commits the transaction if the body succeeded and rolls it back
+    /// otherwise. A failed rollback is logged, the error of the body is
+    /// returned either way.
+    pub async fn finish<T, E>(mut self, result: Result<T, E>) -> Result<T, E>
+    where
+        E: From<sqlx::Error>,
+    {
+        let tx = self.take();
+
+        match result {
+            Ok(value) => {
+                tx.commit().await?;
+                Ok(value)
+            }
+            Err(err) => {
+                if let Err(rollback_err) = tx.rollback().await {
+                    warn!(
+                        label = self.label,
+                        error = ?rollback_err,
+                        "Failed to roll back transaction"
+                    );
+                }
+                Err(err)
+            }
+        }
+    }
+
+    fn take(&mut self) -> Transaction<'static, Postgres> {
+        self.inner
+            .take()
+            .expect("Transaction is only finished once")
+    }
+}
+
+impl Deref for Tx {
+    type Target = Transaction<'static, Postgres>;
+
+    fn deref(&self) -> &Self::Target {
+        self.inner
+            .as_ref()
+            .expect("Transaction is only finished once")
+    }
+}
+
+impl DerefMut for Tx {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.inner
+            .as_mut()
+            .expect("Transaction is only finished once")
+    }
+}
+
+impl Drop for Tx {
+    fn drop(&mut self) {
+        let label = self.label;
+        let elapsed = self.opened_at.elapsed();
+
+        self.open_transactions.dec();
+        self.watchdog.abort();
+
+        // sqlx rolls back the transaction once the inner one is dropped. While
+        // panicking that's the only way out, otherwise the transaction should
+        // have been finished explicitly.
+        if self.inner.is_some() && !std::thread::panicking() {
+            warn!(
+                label,
+                ?elapsed,
+                "Transaction dropped without commit or rollback"
+            );
+        } else if elapsed > self.slow_threshold {
+            warn!(
+                label,
+                ?elapsed,
+                threshold = ?self.slow_threshold,
+                "Slow transaction"
+            );
+        }
+    }
+}
@@ -1,11 +1,14 @@
+use std::net::IpAddr;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::encode::IsNull;
 use sqlx::error::BoxDynError;
+use sqlx::postgres::PgRow;
 use sqlx::prelude::FromRow;
-use sqlx::{Database, Decode, Encode, Postgres, Type};
+use sqlx::{Database, Decode, Encode, Postgres, Row, Type};
 
-use crate::identity_tree::Hash;
+use crate::identity_tree::{Hash, ProcessedStatus};
 use crate::prover::identity::Identity;
 
 pub struct LatestInsertionEntry {
@@ -16,12 +19,119 @@ pub struct LatestDeletionEntry {
     pub timestamp: DateTime<Utc>,
 }
 
+/// Where an insertion or deletion request came from, kept around for abuse
+/// investigations and to connect the request to the batch which includes it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequestOrigin {
+    pub ip: Option<IpAddr>,
+    pub user_agent: Option<String>,
+    pub trace: Option<RequestTrace>,
+}
+
+/// Reads the `source_ip`, `user_agent`, `trace_id` and `span_id` columns.
+impl<'r> FromRow<'r, PgRow> for RequestOrigin {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        let source_ip: Option<String> = row.try_get("source_ip")?;
+        let trace_id: Option<String> = row.try_get("trace_id")?;
+        let span_id: Option<String> = row.try_get("span_id")?;
+
+        Ok(Self {
+            ip: source_ip.and_then(|ip| ip.parse().ok()),
+            user_agent: row.try_get("user_agent")?,
+            trace: trace_id
+                .zip(span_id)
+                .map(|(trace_id, span_id)| RequestTrace { trace_id, span_id }),
+        })
+    }
+}
+
+/// The trace and span which accepted a request, as lowercase hex.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestTrace {
+    pub trace_id: String,
+    pub span_id: String,
+}
+
+/// Columns holding data about a request next to a commitment. The data is
+/// erased on request while the commitment itself stays in the tree.
+pub struct AuxiliaryData {
+    pub table: &'static str,
+    /// Selects the rows of the commitment bound as `$1`
+    pub rows: &'static str,
+    pub columns: &'static [&'static str],
+}
+
+/// Every column holding auxiliary data, new columns of that kind must be
+/// added here so that erasures cover them.
+pub const AUXILIARY_DATA: &[AuxiliaryData] = &[
+    AuxiliaryData {
+        table: "unprocessed_identities",
+        rows: "commitment = $1",
+        columns: &["source_ip", "user_agent", "trace_id", "span_id"],
+    },
+    AuxiliaryData {
+        table: "deletions",
+        rows: "commitment = $1",
+        columns: &["source_ip", "user_agent", "trace_id", "span_id"],
+    },
+    // Includes the zeroed leaf which replaced a deleted commitment, it carries
+    // the origin of the deletion request
+    AuxiliaryData {
+        table: "identities",
+        rows: "leaf_index IN (SELECT leaf_index FROM identities WHERE commitment = $1)",
+        columns: &["source_ip", "user_agent", "trace_id", "span_id"],
+    },
+];
+
+/// An identity as served by the tree updates feed. Apart from the status and
+/// its timestamps, updates never change once inserted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeUpdateEntry {
+    #[sqlx(try_from = "i64")]
+    pub sequence_id: usize,
+    #[sqlx(try_from = "i64")]
+    pub leaf_index: usize,
+    pub element: Hash,
+    pub post_root: Hash,
+    #[sqlx(try_from = "&'a str")]
+    pub status: ProcessedStatus,
+    pub pending_as_of: DateTime<Utc>,
+    pub mined_at: Option<DateTime<Utc>>,
+}
+
 #[derive(Hash, PartialEq, Eq)]
 pub struct DeletionEntry {
+    pub id: i64,
     pub leaf_index: usize,
     pub commitment: Hash,
 }
 
+/// The latest entry of a commitment in the tree, with the status of the root
+/// it produced.
+#[derive(Debug, Clone, PartialEq, Eq, FromRow)]
+pub struct TreeItemWithRootStatus {
+    #[sqlx(try_from = "i64")]
+    pub sequence_id: usize,
+    #[sqlx(try_from = "i64")]
+    pub leaf_index: usize,
+    pub element: Hash,
+    #[sqlx(try_from = "&'a str")]
+    pub root_status: ProcessedStatus,
+    /// Whether the commitment is also queued to be inserted again, after it
+    /// was deleted
+    pub unprocessed: bool,
+}
+
+/// Deletions taken from the queue which may not have been applied to the
+/// tree yet.
+#[derive(Debug, Clone, PartialEq, Eq, FromRow)]
+pub struct DeletionIntent {
+    pub id: i64,
+    pub leaf_indexes: LeafIndexes,
+    pub commitments: Commitments,
+}
+
 #[derive(Debug, Copy, Clone, sqlx::Type, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 #[sqlx(type_name = "VARCHAR", rename_all = "PascalCase")]
@@ -50,6 +160,96 @@ impl std::fmt::Display for BatchType {
     }
 }
 
+/// Review state of a batch, see `AppConfig::require_batch_approval`.
+#[derive(Debug, Copy, Clone, sqlx::Type, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[sqlx(type_name = "VARCHAR", rename_all = "PascalCase")]
+pub enum BatchApproval {
+    AwaitingApproval,
+    Approved,
+    /// Rejected batches are unwound by the batch creator, together with every
+    /// batch after them
+    Rejected,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, FromRow)]
+pub struct RootSubscription {
+    pub id: i64,
+    pub url: String,
+    pub created_at: DateTime<Utc>,
+    /// Overrides `RootNotificationsConfig::aging_threshold_percent`
+    pub aging_threshold_percent: Option<i32>,
+}
+
+#[derive(Debug, Copy, Clone, sqlx::Type, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[sqlx(type_name = "VARCHAR", rename_all = "PascalCase")]
+pub enum RootNotificationKind {
+    /// A new root has been mined
+    RootMined,
+    /// The latest mined root is about to exceed the max root age
+    RootAging,
+}
+
+/// Who changed runtime settings through the admin API.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangeAuthor {
+    pub principal: String,
+    pub request_id: Option<String>,
+}
+
+/// A runtime setting changed through the admin API. Values are `None` when the
+/// setting didn't exist before or doesn't exist after the change.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigChange {
+    pub setting: String,
+    pub old_value: Option<serde_json::Value>,
+    pub new_value: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, FromRow)]
+pub struct ConfigChangeEntry {
+    pub id: i64,
+    pub changed_at: DateTime<Utc>,
+    pub principal: String,
+    pub setting: String,
+    pub old_value: Option<sqlx::types::Json<serde_json::Value>>,
+    pub new_value: Option<sqlx::types::Json<serde_json::Value>>,
+    pub request_id: Option<String>,
+}
+
+/// The checkpoint of a bulk import, all counts refer to the first
+/// `lines_processed` lines of the imported file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, FromRow)]
+pub struct BulkImportProgress {
+    pub import_id: String,
+    #[sqlx(try_from = "i64")]
+    pub lines_processed: u64,
+    #[sqlx(try_from = "i64")]
+    pub accepted: u64,
+    #[sqlx(try_from = "i64")]
+    pub duplicates: u64,
+    #[sqlx(try_from = "i64")]
+    pub invalid: u64,
+}
+
+/// The checkpoint of an export, the first `chunks` files hold every identity up
+/// to `last_sequence_id`.
+#[derive(Debug, Clone, PartialEq, Eq, FromRow)]
+pub struct ExportEntry {
+    pub id: i64,
+    #[sqlx(try_from = "i64")]
+    pub up_to_sequence_id: usize,
+    #[sqlx(try_from = "i64")]
+    pub last_sequence_id: usize,
+    #[sqlx(try_from = "i64")]
+    pub chunks: u64,
+    #[sqlx(try_from = "i64")]
+    pub identities: u64,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug, Clone, FromRow)]
 pub struct BatchEntry {
     pub id: i64,
@@ -60,6 +260,34 @@ pub struct BatchEntry {
     pub created_at: DateTime<Utc>,
     pub batch_type: BatchType,
     pub data: sqlx::types::Json<BatchEntryData>,
+    /// Only selected where the review state matters
+    #[sqlx(default)]
+    pub approval: Option<BatchApproval>,
+    /// Who approved or rejected the batch, only selected with the approval
+    #[sqlx(default)]
+    pub approval_principal: Option<String>,
+    /// The latest operator note, only selected where notes are shown
+    #[sqlx(default)]
+    pub note: Option<String>,
+}
+
+/// A batch together with the transaction which submitted it.
+#[derive(Debug, Clone, FromRow)]
+pub struct TransactionBatchEntry {
+    pub transaction_id: String,
+    pub batch_next_root: Hash,
+    pub batch_type: BatchType,
+    pub created_at: DateTime<Utc>,
+    pub note: Option<String>,
+}
+
+/// A note an operator added to a batch.
+#[derive(Debug, Clone, PartialEq, Eq, FromRow)]
+pub struct BatchNoteEntry {
+    pub id: i64,
+    pub note: String,
+    pub principal: String,
+    pub created_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
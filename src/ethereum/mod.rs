@@ -7,10 +7,12 @@ use ethers::types::Address;
 pub use read::ReadProvider;
 use tracing::instrument;
 pub use write::TxError;
+pub use write_provider::RelayerTransaction;
 
 use self::write_provider::WriteProvider;
 use crate::config::Config;
 use crate::identity::processor::TransactionId;
+use crate::metrics::Metrics;
 
 pub mod read;
 pub mod write;
@@ -27,7 +29,7 @@ pub struct Ethereum {
 
 impl Ethereum {
     #[instrument(name = "Ethereum::new", level = "debug", skip_all)]
-    pub async fn new(config: &Config) -> anyhow::Result<Self> {
+    pub async fn new(config: &Config, metrics: &Metrics) -> anyhow::Result<Self> {
         let Some(providers_config) = &config.providers else {
             bail!("Providers config is required for Ethereum.");
         };
@@ -36,13 +38,17 @@ impl Ethereum {
             bail!("Relayer config is required for Ethereum.");
         };
 
-        let read_provider =
-            ReadProvider::new(providers_config.primary_network_provider.clone().into()).await?;
+        let read_provider = ReadProvider::new(
+            providers_config.primary_network_provider.clone().into(),
+            metrics,
+        )
+        .await?;
 
         let mut secondary_read_providers = HashMap::new();
 
         for secondary_url in &providers_config.relayed_network_providers.0 {
-            let secondary_read_provider = ReadProvider::new(secondary_url.clone().into()).await?;
+            let secondary_read_provider =
+                ReadProvider::new(secondary_url.clone().into(), metrics).await?;
             secondary_read_providers.insert(
                 secondary_read_provider.chain_id.as_u64(),
                 Arc::new(secondary_read_provider),
@@ -50,7 +56,7 @@ impl Ethereum {
         }
 
         let write_provider: Arc<WriteProvider> =
-            Arc::new(WriteProvider::new(read_provider.clone(), relayer_config).await?);
+            Arc::new(WriteProvider::new(read_provider.clone(), relayer_config, metrics).await?);
 
         Ok(Self {
             read_provider: Arc::new(read_provider),
@@ -87,6 +93,17 @@ impl Ethereum {
         self.write_provider.fetch_pending_transactions().await
     }
 
+    pub async fn list_transactions(
+        &self,
+        status: Option<&str>,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<RelayerTransaction>, TxError> {
+        self.write_provider
+            .list_transactions(status, limit, offset)
+            .await
+    }
+
     pub async fn mine_transaction(&self, tx: TransactionId) -> Result<bool, TxError> {
         self.write_provider.mine_transaction(tx).await
     }
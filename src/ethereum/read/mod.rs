@@ -9,6 +9,7 @@ use tracing::{error, info};
 use url::Url;
 
 use self::rpc_logger::RpcLogger;
+use crate::metrics::Metrics;
 
 pub mod rpc_logger;
 
@@ -21,7 +22,7 @@ pub struct ReadProvider {
 }
 
 impl ReadProvider {
-    pub async fn new(url: Url) -> anyhow::Result<Self> {
+    pub async fn new(url: Url, metrics: &Metrics) -> anyhow::Result<Self> {
         // Connect to the Ethereum provider
         // TODO: Allow multiple providers with failover / broadcast.
         // TODO: Requests don't seem to process in parallel. Check if this is
@@ -35,7 +36,7 @@ impl ReadProvider {
                 "Connecting to provider"
             );
             let transport = Http::new(url);
-            let logger = RpcLogger::new(transport);
+            let logger = RpcLogger::new(transport, metrics);
             let provider = Provider::new(logger);
 
             // Fetch state of the chain.
@@ -1,37 +1,28 @@
 use std::fmt::Debug;
 
-use ::prometheus::{register_histogram, register_int_counter_vec, Histogram, IntCounterVec};
+use ::prometheus::{Histogram, IntCounterVec};
 use async_trait::async_trait;
 use ethers::providers::JsonRpcClient;
-use once_cell::sync::Lazy;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use tracing::instrument;
 
-static REQUESTS: Lazy<IntCounterVec> = Lazy::new(|| {
-    register_int_counter_vec!(
-        "eth_rpc_requests",
-        "Number of Ethereum provider requests made by method.",
-        &["method"]
-    )
-    .unwrap()
-});
-static LATENCY: Lazy<Histogram> = Lazy::new(|| {
-    register_histogram!(
-        "eth_rpc_latency_seconds",
-        "The Ethereum provider latency in seconds."
-    )
-    .unwrap()
-});
+use crate::metrics::Metrics;
 
 #[derive(Debug, Clone)]
 pub struct RpcLogger<Inner> {
     inner: Inner,
+    requests: IntCounterVec,
+    latency: Histogram,
 }
 
 impl<Inner> RpcLogger<Inner> {
-    pub const fn new(inner: Inner) -> Self {
-        Self { inner }
+    pub fn new(inner: Inner, metrics: &Metrics) -> Self {
+        Self {
+            inner,
+            requests: metrics.eth_rpc_requests.clone(),
+            latency: metrics.eth_rpc_latency.clone(),
+        }
     }
 }
 
@@ -49,8 +40,8 @@ where
         T: Debug + Serialize + Send + Sync,
         R: DeserializeOwned + Send,
     {
-        REQUESTS.with_label_values(&[method]).inc();
-        let timer = LATENCY.start_timer();
+        self.requests.with_label_values(&[method]).inc();
+        let timer = self.latency.start_timer();
         let result = self.inner.request(method, params).await;
         timer.observe_duration();
         result
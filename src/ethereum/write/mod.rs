@@ -13,6 +13,9 @@ pub enum TxError {
     #[error("Error fetching transaction from the blockchain: {0}")]
     Fetch(Box<dyn Error + Send + Sync + 'static>),
 
+    #[error("Error listing relayer transactions: {0:?}")]
+    List(anyhow::Error),
+
     #[error("Timeout while sending transaction")]
     SendTimeout,
 
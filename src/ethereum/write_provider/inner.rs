@@ -15,9 +15,27 @@ pub trait Inner: Send + Sync + 'static {
     async fn fetch_pending_transactions(&self) -> Result<Vec<TransactionId>, TxError>;
 
     async fn mine_transaction(&self, tx: TransactionId) -> Result<TransactionResult, TxError>;
+
+    /// Lists the relayer's transactions in the order the relayer returns them.
+    /// Fails with [`TxError::Parse`] if the relayer has no such status.
+    async fn list_transactions(
+        &self,
+        status: Option<&str>,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<RelayerTransaction>, TxError>;
 }
 
 pub struct TransactionResult {
     pub transaction_id: String,
     pub hash: Option<H256>,
 }
+
+/// A transaction as listed by the relayer.
+#[derive(Clone, Debug)]
+pub struct RelayerTransaction {
+    pub transaction_id: TransactionId,
+    pub hash: Option<H256>,
+    /// The status in the relayer's own terms
+    pub status: String,
+}
@@ -7,11 +7,13 @@ use ethers::types::{Address, U64};
 use tracing::{info, warn};
 
 use self::inner::Inner;
+pub use self::inner::RelayerTransaction;
 use self::openzeppelin::OzRelay;
 use self::tx_sitter::TxSitter;
 use super::{ReadProvider, TxError};
 use crate::config::RelayerConfig;
 use crate::identity::processor::TransactionId;
+use crate::metrics::Metrics;
 
 mod error;
 mod inner;
@@ -35,13 +37,17 @@ impl fmt::Debug for WriteProvider {
 }
 
 impl WriteProvider {
-    pub async fn new(read_provider: ReadProvider, config: &RelayerConfig) -> anyhow::Result<Self> {
+    pub async fn new(
+        read_provider: ReadProvider,
+        config: &RelayerConfig,
+        metrics: &Metrics,
+    ) -> anyhow::Result<Self> {
         let address = config.address();
 
         let inner: Arc<dyn Inner> = match config {
             RelayerConfig::OzDefender(oz_config) => {
                 tracing::info!("Initializing OZ Relayer");
-                Arc::new(OzRelay::new(oz_config).await?)
+                Arc::new(OzRelay::new(oz_config, metrics).await?)
             }
             RelayerConfig::TxSitter(tx_sitter_config) => {
                 tracing::info!("Initializing TxSitter");
@@ -68,6 +74,15 @@ impl WriteProvider {
         self.inner.fetch_pending_transactions().await
     }
 
+    pub async fn list_transactions(
+        &self,
+        status: Option<&str>,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<RelayerTransaction>, TxError> {
+        self.inner.list_transactions(status, limit, offset).await
+    }
+
     pub async fn mine_transaction(&self, tx: TransactionId) -> Result<bool, TxError> {
         let oz_transaction_result = self.inner.mine_transaction(tx.clone()).await;
 
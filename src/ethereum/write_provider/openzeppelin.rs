@@ -2,27 +2,18 @@ use std::fmt::Debug;
 use std::time::Duration;
 
 use ethers::types::transaction::eip2718::TypedTransaction;
-use once_cell::sync::Lazy;
 use oz_api::data::transactions::{RelayerTransactionBase, SendBaseTransactionRequest, Status};
 use oz_api::OzApi;
-use prometheus::{register_int_counter_vec, IntCounterVec};
+use prometheus::IntCounterVec;
 use tokio::time::timeout;
 use tracing::{error, info, info_span, Instrument};
 
 use super::error::Error;
-use super::inner::{Inner, TransactionResult};
+use super::inner::{Inner, RelayerTransaction, TransactionResult};
 use crate::config::OzDefenderConfig;
 use crate::ethereum::TxError;
 use crate::identity::processor::TransactionId;
-
-static TX_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
-    register_int_counter_vec!(
-        "eth_tx_count",
-        "The transaction count by bytes4.",
-        &["bytes4"]
-    )
-    .unwrap()
-});
+use crate::metrics::Metrics;
 
 #[derive(Debug)]
 pub struct OzRelay {
@@ -31,10 +22,11 @@ pub struct OzRelay {
     send_timeout: Duration,
     mine_timeout: Duration,
     gas_limit: Option<u64>,
+    tx_count: IntCounterVec,
 }
 
 impl OzRelay {
-    pub async fn new(options: &OzDefenderConfig) -> anyhow::Result<Self> {
+    pub async fn new(options: &OzDefenderConfig, metrics: &Metrics) -> anyhow::Result<Self> {
         let oz_api = if options.oz_api_key.is_empty() && options.oz_api_secret.is_empty() {
             tracing::warn!(
                 "OpenZeppelin Defender API Key and Secret are empty. Connection will operate \
@@ -57,6 +49,7 @@ impl OzRelay {
             send_timeout: options.oz_send_timeout,
             mine_timeout: options.oz_mine_timeout,
             gas_limit: options.oz_gas_limit,
+            tx_count: metrics.eth_tx_count.clone(),
         })
     }
 
@@ -72,6 +65,37 @@ impl OzRelay {
         Ok(transactions)
     }
 
+    /// The relayer API doesn't support offsets, the skipped transactions are
+    /// fetched as well.
+    pub async fn list_transactions(
+        &self,
+        status: Option<&str>,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<RelayerTransaction>, TxError> {
+        let status = status
+            .map(|status| serde_json::from_value::<Status>(status.into()))
+            .transpose()
+            .map_err(|err| TxError::Parse(Box::new(err)))?;
+
+        let transactions = self
+            .oz_api
+            .list_transactions(status, Some(offset.saturating_add(limit)))
+            .await
+            .map_err(|err| TxError::List(err.into()))?;
+
+        Ok(transactions
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(|tx| RelayerTransaction {
+                transaction_id: tx.transaction_id,
+                hash: tx.hash,
+                status: tx.status.to_string(),
+            })
+            .collect())
+    }
+
     async fn mine_transaction_id_unchecked(
         &self,
         id: &str,
@@ -174,7 +198,7 @@ impl OzRelay {
             u32::from_be_bytes(buffer)
         });
         let bytes4 = format!("{bytes4:8x}");
-        TX_COUNT.with_label_values(&[&bytes4]).inc();
+        self.tx_count.with_label_values(&[&bytes4]).inc();
 
         // Send TX to OZ Relay
         let tx_id = timeout(self.send_timeout, self.send_oz_transaction(tx.clone()))
@@ -238,4 +262,13 @@ impl Inner for OzRelay {
             hash: transaction.hash,
         })
     }
+
+    async fn list_transactions(
+        &self,
+        status: Option<&str>,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<RelayerTransaction>, TxError> {
+        self.list_transactions(status, limit, offset).await
+    }
 }
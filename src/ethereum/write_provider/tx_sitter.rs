@@ -7,7 +7,7 @@ use ethers::types::U256;
 use tx_sitter_client::data::{SendTxRequest, TransactionPriority, TxStatus};
 use tx_sitter_client::TxSitterClient;
 
-use super::inner::{Inner, TransactionResult};
+use super::inner::{Inner, RelayerTransaction, TransactionResult};
 use crate::config::TxSitterConfig;
 use crate::ethereum::TxError;
 use crate::identity::processor::TransactionId;
@@ -66,23 +66,34 @@ impl Inner for TxSitter {
             tx.set_gas(gas_limit);
         }
 
+        let to = *tx
+            .to_addr()
+            .context("Tx receiver must be an address")
+            .map_err(TxError::Send)?;
+        let gas_limit = *tx
+            .gas()
+            .context("Missing tx gas limit")
+            .map_err(TxError::Send)?;
+
+        let mut request = SendTxRequest::builder()
+            .to(to)
+            .value(tx.value().copied().unwrap_or(U256::zero()))
+            .gas_limit(gas_limit)
+            .priority(TransactionPriority::Regular);
+
+        if let Some(data) = tx.data() {
+            request = request.data(data.clone());
+        }
+
+        let request = request
+            .build()
+            .context("Invalid transaction")
+            .map_err(TxError::Send)?;
+
         // TODO: Handle only_once
         let tx = self
             .client
-            .send_tx(&SendTxRequest {
-                to: *tx
-                    .to_addr()
-                    .context("Tx receiver must be an address")
-                    .map_err(TxError::Send)?,
-                value: tx.value().copied().unwrap_or(U256::zero()),
-                data: tx.data().cloned(),
-                gas_limit: *tx
-                    .gas()
-                    .context("Missing tx gas limit")
-                    .map_err(TxError::Send)?,
-                priority: TransactionPriority::Regular,
-                tx_id: None,
-            })
+            .send_tx(&request)
             .await
             .context("Error sending transaction")
             .map_err(TxError::Send)?;
@@ -119,4 +130,33 @@ impl Inner for TxSitter {
             .await
             .map_err(|_| TxError::ConfirmationTimeout)?
     }
+
+    async fn list_transactions(
+        &self,
+        status: Option<&str>,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<RelayerTransaction>, TxError> {
+        let status = status
+            .map(|status| serde_json::from_value::<TxStatus>(status.into()))
+            .transpose()
+            .map_err(|err| TxError::Parse(Box::new(err)))?;
+
+        let txs = self
+            .client
+            .get_txs_page(status, limit, offset)
+            .await
+            .map_err(TxError::List)?;
+
+        Ok(txs
+            .into_iter()
+            .map(|tx| RelayerTransaction {
+                transaction_id: tx.tx_id,
+                hash: tx.tx_hash,
+                status: tx
+                    .status
+                    .map_or_else(|| "unsent".to_string(), |status| status.to_string()),
+            })
+            .collect())
+    }
 }
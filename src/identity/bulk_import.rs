@@ -0,0 +1,114 @@
+use crate::identity_tree::Hash;
+
+/// Lines longer than this can't hold a commitment and are rejected before they
+/// are buffered any further.
+pub const MAX_LINE_LENGTH: usize = 1024;
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+#[error("line {line_number} exceeds {MAX_LINE_LENGTH} bytes")]
+pub struct LineTooLong {
+    pub line_number: u64,
+}
+
+/// Splits a streamed newline-delimited body into numbered lines, buffering at
+/// most a single line.
+#[derive(Debug, Default)]
+pub struct LineSplitter {
+    buffer: Vec<u8>,
+    line_number: u64,
+}
+
+impl LineSplitter {
+    /// Feeds the next chunk of the body and returns the lines it completes,
+    /// numbered starting at 1.
+    pub fn push(&mut self, mut chunk: &[u8]) -> Result<Vec<(u64, String)>, LineTooLong> {
+        let mut lines = vec![];
+
+        while let Some(end) = chunk.iter().position(|b| *b == b'\n') {
+            self.extend_buffer(&chunk[..end])?;
+            lines.push(self.take_line());
+            chunk = &chunk[end + 1..];
+        }
+
+        self.extend_buffer(chunk)?;
+
+        Ok(lines)
+    }
+
+    /// Returns the last line if the body doesn't end with a newline.
+    pub fn finish(mut self) -> Option<(u64, String)> {
+        if self.buffer.is_empty() {
+            return None;
+        }
+
+        Some(self.take_line())
+    }
+
+    fn extend_buffer(&mut self, bytes: &[u8]) -> Result<(), LineTooLong> {
+        if self.buffer.len() + bytes.len() > MAX_LINE_LENGTH {
+            return Err(LineTooLong {
+                line_number: self.line_number + 1,
+            });
+        }
+
+        self.buffer.extend_from_slice(bytes);
+
+        Ok(())
+    }
+
+    fn take_line(&mut self) -> (u64, String) {
+        self.line_number += 1;
+        let line = String::from_utf8_lossy(&self.buffer).into_owned();
+        self.buffer.clear();
+
+        (self.line_number, line)
+    }
+}
+
+/// Parses a hex encoded commitment, with or without the `0x` prefix.
+pub fn parse_commitment(line: &str) -> Option<Hash> {
+    let line = line.trim();
+    let hex = line.strip_prefix("0x").unwrap_or(line);
+
+    if hex.is_empty() {
+        return None;
+    }
+
+    Hash::from_str_radix(hex, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_lines_across_chunks() {
+        let mut splitter = LineSplitter::default();
+
+        assert_eq!(splitter.push(b"0x1\n0x").unwrap(), vec![(1, "0x1".into())]);
+        assert_eq!(splitter.push(b"2").unwrap(), vec![]);
+        assert_eq!(
+            splitter.push(b"\n\n0x3").unwrap(),
+            vec![(2, "0x2".into()), (3, String::new())]
+        );
+        assert_eq!(splitter.finish(), Some((4, "0x3".into())));
+    }
+
+    #[test]
+    fn rejects_overlong_lines() {
+        let mut splitter = LineSplitter::default();
+        splitter.push(b"0x1\n").unwrap();
+
+        let err = splitter.push(&[b'a'; MAX_LINE_LENGTH + 1]).unwrap_err();
+
+        assert_eq!(err, LineTooLong { line_number: 2 });
+    }
+
+    #[test]
+    fn parses_commitments() {
+        assert_eq!(parse_commitment("0x2a"), Some(Hash::from(42)));
+        assert_eq!(parse_commitment("2a\r"), Some(Hash::from(42)));
+        assert_eq!(parse_commitment("0x"), None);
+        assert_eq!(parse_commitment("not hex"), None);
+    }
+}
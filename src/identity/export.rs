@@ -0,0 +1,314 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use tokio::fs;
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tracing::info;
+
+use crate::config::ExportsConfig;
+use crate::database::methods::DbMethods as _;
+use crate::database::types::ExportEntry;
+use crate::database::Database;
+
+/// Identities are read in pages of this size, each in a query of its own so
+/// that no transaction is held open while a chunk is written.
+pub const EXPORT_PAGE_SIZE: usize = 1000;
+
+/// The file holding the chunk with the given number, counted from 0.
+pub fn chunk_path(directory: &Path, export_id: i64, chunk: u64) -> PathBuf {
+    directory
+        .join(export_id.to_string())
+        .join(format!("{chunk:06}.ndjson"))
+}
+
+/// The file a chunk is written to until it's complete. It's unique to the
+/// writer, so that an instance which lost its claim on an export never writes
+/// to the same file as the one which took it over.
+fn partial_chunk_path(directory: &Path, export_id: i64, chunk: u64, writer: &str) -> PathBuf {
+    let writer: String = writer
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+
+    chunk_path(directory, export_id, chunk).with_extension(format!("ndjson.{writer}.tmp"))
+}
+
+/// Writes exports chunk by chunk, checkpointing after every chunk.
+///
+/// A chunk is only checkpointed once its file is complete. An export which was
+/// interrupted before the checkpoint rewrites the same file on resumption, so
+/// that no identity ends up in two chunks.
+///
+/// Exports must be claimed by `writer` through
+/// [`DbMethods::claim_next_export`](crate::database::methods::DbMethods::claim_next_export)
+/// before they're written, every checkpoint renews the claim.
+pub struct Exporter<'a> {
+    database: &'a Database,
+    config: &'a ExportsConfig,
+    writer: &'a str,
+}
+
+impl<'a> Exporter<'a> {
+    pub fn new(database: &'a Database, config: &'a ExportsConfig, writer: &'a str) -> Self {
+        Self {
+            database,
+            config,
+            writer,
+        }
+    }
+
+    /// Writes the remaining chunks of an export.
+    pub async fn run(&self, mut export: ExportEntry) -> anyhow::Result<ExportEntry> {
+        self.remove_partial_chunks(export.id).await?;
+
+        while export.completed_at.is_none() {
+            export = self.export_chunk(&export).await?;
+        }
+
+        info!(
+            export_id = export.id,
+            chunks = export.chunks,
+            identities = export.identities,
+            "Export completed"
+        );
+
+        Ok(export)
+    }
+
+    /// Removes the chunks left half written by interrupted writers.
+    async fn remove_partial_chunks(&self, export_id: i64) -> anyhow::Result<()> {
+        let directory = self.config.directory.join(export_id.to_string());
+        let mut entries = match fs::read_dir(&directory).await {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.path().extension().is_some_and(|ext| ext == "tmp") {
+                fs::remove_file(entry.path()).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the next chunk of an export and returns the new checkpoint.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the claim on the export was lost to another
+    /// instance, in which case it's left to that instance.
+    pub async fn export_chunk(&self, export: &ExportEntry) -> anyhow::Result<ExportEntry> {
+        let path = chunk_path(&self.config.directory, export.id, export.chunks);
+        let tmp_path = partial_chunk_path(
+            &self.config.directory,
+            export.id,
+            export.chunks,
+            self.writer,
+        );
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        let mut writer = BufWriter::new(fs::File::create(&tmp_path).await?);
+        let mut last_sequence_id = export.last_sequence_id;
+        let mut written = 0;
+        let mut done = false;
+
+        while !done && written < self.config.chunk_size {
+            let limit = EXPORT_PAGE_SIZE.min(self.config.chunk_size - written);
+            let page = self
+                .database
+                .get_tree_updates(last_sequence_id, limit, None)
+                .await?;
+            done = page.len() < limit;
+
+            for update in page {
+                if update.sequence_id > export.up_to_sequence_id {
+                    done = true;
+                    break;
+                }
+
+                let mut line = serde_json::to_vec(&update)?;
+                line.push(b'\n');
+                writer.write_all(&line).await?;
+
+                last_sequence_id = update.sequence_id;
+                written += 1;
+            }
+
+            done |= last_sequence_id >= export.up_to_sequence_id;
+        }
+
+        writer.flush().await?;
+        let file = writer.into_inner();
+        file.sync_all().await?;
+        drop(file);
+
+        // A chunk holds the same identities whoever writes it, as they only
+        // depend on the last checkpoint. So the rename of a writer which lost
+        // its claim is harmless, only its checkpoint is refused.
+        let (checkpoint, done) = if written == 0 {
+            fs::remove_file(&tmp_path).await?;
+
+            (export.clone(), true)
+        } else {
+            fs::rename(&tmp_path, &path).await?;
+
+            let checkpoint = ExportEntry {
+                last_sequence_id,
+                chunks: export.chunks + 1,
+                identities: export.identities + written as u64,
+                ..export.clone()
+            };
+
+            (checkpoint, done)
+        };
+
+        self.database
+            .update_export(&checkpoint, done, self.writer, self.config.claim_lease)
+            .await?
+            .ok_or_else(|| anyhow!("Export {} was claimed by another instance", export.id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    use testcontainers::clients::Cli;
+
+    use super::*;
+    use crate::config::DatabaseConfig;
+    use crate::database::types::TreeUpdateEntry;
+    use crate::identity_tree::Hash;
+    use crate::metrics::Metrics;
+    use crate::utils::secret::SecretUrl;
+
+    #[tokio::test]
+    async fn interrupted_export_resumes_without_duplicates() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let db_container = postgres_docker_utils::setup(&docker).await?;
+        let url = format!(
+            "postgres://postgres:postgres@{}/database",
+            db_container.address()
+        );
+        let database = Database::new(
+            &DatabaseConfig {
+                database: SecretUrl::from_str(&url)?,
+                migrate: true,
+                max_connections: 1,
+                slow_transaction_threshold: Duration::from_secs(5),
+            },
+            &Metrics::default(),
+        )
+        .await?;
+
+        let commitments: Vec<Hash> = (1..=5).map(Hash::from).collect();
+        let mut pre_root = Hash::from(100);
+        for (leaf_index, commitment) in commitments.iter().enumerate() {
+            let root = Hash::from(101 + leaf_index);
+            database
+                .insert_pending_identity(leaf_index, commitment, &root, &pre_root)
+                .await?;
+            pre_root = root;
+        }
+
+        let directory = tempfile::tempdir()?;
+        let config = ExportsConfig {
+            directory: directory.path().to_path_buf(),
+            chunk_size: 2,
+            claim_lease: Duration::from_secs(60),
+        };
+        let exporter = Exporter::new(&database, &config, "host/1");
+
+        let export = database.insert_export().await?;
+        let claimed = database
+            .claim_next_export("host/1", config.claim_lease)
+            .await?;
+        assert_eq!(claimed.as_ref(), Some(&export));
+        assert_eq!(
+            database
+                .claim_next_export("host/2", config.claim_lease)
+                .await?,
+            None
+        );
+
+        let first = exporter.export_chunk(&export).await?;
+        assert_eq!(first.chunks, 1);
+
+        // The second chunk is written but the export is dropped before its
+        // checkpoint, and the third chunk is left half written
+        exporter.export_chunk(&first).await?;
+        database
+            .update_export(&first, false, "host/1", Duration::ZERO)
+            .await?;
+        fs::write(
+            partial_chunk_path(directory.path(), export.id, 2, "host/1"),
+            b"{\"partial\"",
+        )
+        .await?;
+
+        // Identities inserted after the export was requested aren't part of it
+        database
+            .insert_pending_identity(5, &Hash::from(6), &Hash::from(106), &pre_root)
+            .await?;
+
+        // Another instance takes the export over once the claim expired, the
+        // interrupted writer can't checkpoint it anymore
+        let running = database
+            .claim_next_export("host/2", config.claim_lease)
+            .await?
+            .expect("Expired claim wasn't taken over");
+        assert_eq!(running, first);
+
+        let err = exporter
+            .export_chunk(&first)
+            .await
+            .expect_err("Checkpointed without a claim");
+        assert!(err.to_string().contains("claimed by another instance"));
+
+        let completed = Exporter::new(&database, &config, "host/2")
+            .run(running)
+            .await?;
+
+        assert!(completed.completed_at.is_some());
+        assert_eq!(completed.chunks, 3);
+        assert_eq!(completed.identities, 5);
+        assert_eq!(
+            database
+                .claim_next_export("host/1", config.claim_lease)
+                .await?,
+            None
+        );
+
+        let mut files: Vec<_> = std::fs::read_dir(directory.path().join(export.id.to_string()))?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<_, _>>()?;
+        files.sort();
+        assert_eq!(
+            files,
+            (0..3)
+                .map(|chunk| chunk_path(directory.path(), export.id, chunk))
+                .collect::<Vec<_>>()
+        );
+
+        let mut exported = vec![];
+        for file in files {
+            for line in fs::read_to_string(file).await?.lines() {
+                let update: TreeUpdateEntry = serde_json::from_str(line)?;
+                exported.push(update.element);
+            }
+        }
+        assert_eq!(
+            exported.iter().collect::<HashSet<_>>().len(),
+            exported.len()
+        );
+        assert_eq!(exported, commitments);
+
+        Ok(())
+    }
+}
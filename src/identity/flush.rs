@@ -0,0 +1,68 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use tokio::sync::Notify;
+
+use crate::database::methods::DbMethods as _;
+use crate::database::Database;
+
+const FLUSH_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Shared by the identity processors and the tasks moving identities through
+/// the pipeline. While a flush is in progress the tasks don't wait for full
+/// batches or batch timeouts.
+#[derive(Debug, Default)]
+pub struct FlushSignal {
+    active_flushes: AtomicUsize,
+    wake_up: Notify,
+}
+
+impl FlushSignal {
+    /// Whether queued identities should be processed right away.
+    pub fn is_forced(&self) -> bool {
+        self.active_flushes.load(Ordering::Relaxed) > 0
+    }
+
+    /// Completes when a flush wakes up the tasks.
+    pub async fn notified(&self) {
+        self.wake_up.notified().await;
+    }
+
+    /// Forces processing until there are no unprocessed identities, queued
+    /// deletions or pending identities left.
+    ///
+    /// This never returns while deletions are postponed, e.g. because they
+    /// would produce a duplicate root, callers should apply a timeout.
+    pub async fn flush(&self, database: &Database) -> anyhow::Result<()> {
+        // Stops forcing once the flush completes or is cancelled
+        let _active = ActiveFlush::new(&self.active_flushes);
+
+        loop {
+            self.wake_up.notify_waiters();
+
+            if database.count_unprocessed_identities().await? == 0
+                && database.get_deletions_page(0, 1).await?.is_empty()
+                && database.count_pending_identities().await? == 0
+            {
+                return Ok(());
+            }
+
+            tokio::time::sleep(FLUSH_POLL_INTERVAL).await;
+        }
+    }
+}
+
+struct ActiveFlush<'a>(&'a AtomicUsize);
+
+impl<'a> ActiveFlush<'a> {
+    fn new(active_flushes: &'a AtomicUsize) -> Self {
+        active_flushes.fetch_add(1, Ordering::Relaxed);
+        Self(active_flushes)
+    }
+}
+
+impl Drop for ActiveFlush<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
@@ -1,2 +1,8 @@
+pub mod bulk_import;
+pub mod export;
+pub mod flush;
 pub mod processor;
+pub mod proof_verifier;
+pub mod provable_sla;
+pub mod root_oracle;
 pub mod validator;
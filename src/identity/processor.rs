@@ -1,5 +1,6 @@
 use std::collections::{HashMap, VecDeque};
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 use anyhow::anyhow;
 use async_trait::async_trait;
@@ -8,22 +9,27 @@ use ethers::addressbook::Address;
 use ethers::contract::EthEvent;
 use ethers::middleware::Middleware;
 use ethers::prelude::{Log, Topic, ValueOrArray, U256};
-use tracing::{error, info, instrument};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use tracing::{error, info, instrument, warn};
 
-use crate::config::Config;
+use crate::config::{AppConfig, Config};
 use crate::contracts::abi::{BridgedWorldId, RootAddedFilter, TreeChangeKind, TreeChangedFilter};
 use crate::contracts::scanner::BlockScanner;
 use crate::contracts::IdentityManager;
 use crate::database::methods::DbMethods;
 use crate::database::types::{BatchEntry, BatchType};
 use crate::database::{Database, IsolationLevel};
-use crate::ethereum::{Ethereum, ReadProvider};
+use crate::ethereum::{Ethereum, ReadProvider, RelayerTransaction, TxError};
+use crate::identity::flush::FlushSignal;
+use crate::identity_tree::publication::RootPublisher;
 use crate::identity_tree::{
-    Canonical, Hash, Intermediate, ProcessedStatus, TreeVersion, TreeWithNextVersion,
+    Canonical, Hash, Intermediate, ProcessedStatus, TreeVersion, TreeVersionReadOps,
+    TreeWithNextVersion,
 };
+use crate::metrics::Metrics;
 use crate::prover::identity::Identity;
 use crate::prover::repository::ProverRepository;
-use crate::prover::Prover;
+use crate::prover::{Prover, ProverType};
 use crate::utils::index_packing::pack_indices;
 
 pub type TransactionId = String;
@@ -32,6 +38,10 @@ pub type TransactionId = String;
 pub trait IdentityProcessor: Send + Sync + 'static {
     async fn commit_identities(&self, batch: &BatchEntry) -> anyhow::Result<TransactionId>;
 
+    /// Commits several consecutive batches with a single transaction. A failure
+    /// of that transaction fails all of them.
+    async fn commit_batches(&self, batches: &[BatchEntry]) -> anyhow::Result<TransactionId>;
+
     async fn finalize_identities(
         &self,
         processed_tree: &TreeVersion<Intermediate>,
@@ -45,6 +55,30 @@ pub trait IdentityProcessor: Send + Sync + 'static {
     async fn tree_init_correction(&self, initial_root_hash: &Hash) -> anyhow::Result<()>;
 
     async fn latest_root(&self) -> anyhow::Result<Option<Hash>>;
+
+    /// Balance of the account submitting the batches, `None` if batches aren't
+    /// submitted on chain.
+    async fn relayer_balance(&self) -> anyhow::Result<Option<U256>>;
+
+    /// A page of the relayer's transactions, `None` if batches aren't
+    /// submitted through a relayer.
+    async fn relayer_transactions(
+        &self,
+        status: Option<&str>,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Option<Vec<RelayerTransaction>>, TxError>;
+
+    /// The batch size of the largest insertion prover, `None` without any.
+    async fn current_insertion_batch_size(&self) -> Option<usize>;
+
+    /// The batch size of the largest deletion prover, `None` without any.
+    async fn current_deletion_batch_size(&self) -> Option<usize>;
+
+    /// Processes every queued insertion and deletion right away instead of
+    /// waiting for full batches or batch timeouts, and returns once they are
+    /// all processed.
+    async fn flush(&self) -> anyhow::Result<()>;
 }
 
 pub struct OnChainIdentityProcessor {
@@ -53,6 +87,7 @@ pub struct OnChainIdentityProcessor {
     database: Arc<Database>,
     identity_manager: Arc<IdentityManager>,
     prover_repository: Arc<ProverRepository>,
+    flush_signal: Arc<FlushSignal>,
 
     mainnet_scanner: tokio::sync::Mutex<BlockScanner<Arc<ReadProvider>>>,
     mainnet_address: Address,
@@ -62,33 +97,38 @@ pub struct OnChainIdentityProcessor {
 #[async_trait]
 impl IdentityProcessor for OnChainIdentityProcessor {
     async fn commit_identities(&self, batch: &BatchEntry) -> anyhow::Result<TransactionId> {
-        if batch.batch_type == BatchType::Insertion {
-            let prover = self
-                .prover_repository
-                .get_suitable_insertion_prover(batch.data.0.identities.len())
-                .await?;
+        let transaction = self.prepare_batch(batch).await?;
 
-            info!(
-                num_updates = batch.data.0.identities.len(),
-                batch_size = prover.batch_size(),
-                "Insertion batch",
-            );
+        let transaction_id = self
+            .identity_manager
+            .send_transaction(transaction)
+            .await
+            .map_err(|e| {
+                error!(?e, "Failed to submit batch to contract.");
+                e
+            })?;
 
-            self.insert_identities(&prover, batch).await
-        } else {
-            let prover = self
-                .prover_repository
-                .get_suitable_deletion_prover(batch.data.0.identities.len())
-                .await?;
+        info!(
+            batch_type = ?batch.batch_type,
+            next_root = ?batch.next_root,
+            ?transaction_id,
+            "Batch submitted"
+        );
 
-            info!(
-                num_updates = batch.data.0.identities.len(),
-                batch_size = prover.batch_size(),
-                "Deletion batch"
+        Ok(transaction_id)
+    }
+
+    /// The identity manager contracts take a single batch per transaction,
+    /// `Config::validate` only allows more in offchain mode.
+    async fn commit_batches(&self, batches: &[BatchEntry]) -> anyhow::Result<TransactionId> {
+        let [batch] = batches else {
+            anyhow::bail!(
+                "The identity manager takes a single batch per transaction, got {}",
+                batches.len()
             );
+        };
 
-            self.delete_identities(&prover, batch).await
-        }
+        self.commit_identities(batch).await
     }
 
     async fn finalize_identities(
@@ -140,32 +180,73 @@ impl IdentityProcessor for OnChainIdentityProcessor {
         // in such a case the state will be corrected later in the program
         let mut tx = self
             .database
-            .begin_tx(IsolationLevel::ReadCommitted)
+            .begin_tx("tree_init_correction", IsolationLevel::ReadCommitted)
             .await?;
 
-        // We don't store the initial root in the database, so we have to skip this step
-        // if the contract root hash is equal to initial root hash
-        if root_hash != *initial_root_hash {
-            // Note that we don't have a way of queuing a root here for
-            // finalization. so it's going to stay as "processed"
-            // until the next root is mined. self.database.
-            tx.mark_root_as_processed(&root_hash).await?;
-            tx.delete_batches_after_root(&root_hash).await?; // TODO: We probably shouldn't do this in HA
-        } else {
-            // Db is either empty or we're restarting with a new contract/chain
-            // so we should mark everything as pending
-            tx.mark_all_as_pending().await?;
-            tx.delete_all_batches().await?; // TODO: We probably shouldn't do this in HA
-        }
+        let result = async {
+            // We don't store the initial root in the database, so we have to skip this step
+            // if the contract root hash is equal to initial root hash
+            if root_hash != *initial_root_hash {
+                // Note that we don't have a way of queuing a root here for
+                // finalization. so it's going to stay as "processed"
+                // until the next root is mined. self.database.
+                tx.mark_root_as_processed(&root_hash).await?;
+                tx.delete_batches_after_root(&root_hash).await?; // TODO: We probably shouldn't do this in HA
+            } else {
+                // Db is either empty or we're restarting with a new contract/chain
+                // so we should mark everything as pending
+                tx.mark_all_as_pending().await?;
+                tx.delete_all_batches().await?; // TODO: We probably shouldn't do this in HA
+            }
 
-        tx.commit().await?;
+            anyhow::Ok(())
+        }
+        .await;
 
-        Ok(())
+        tx.finish(result).await
     }
 
     async fn latest_root(&self) -> anyhow::Result<Option<Hash>> {
         Ok(Some(self.identity_manager.latest_root().await?.into()))
     }
+
+    async fn relayer_balance(&self) -> anyhow::Result<Option<U256>> {
+        let balance = self
+            .ethereum
+            .provider()
+            .get_balance(self.ethereum.address(), None)
+            .await?;
+
+        Ok(Some(balance))
+    }
+
+    async fn relayer_transactions(
+        &self,
+        status: Option<&str>,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Option<Vec<RelayerTransaction>>, TxError> {
+        let transactions = self
+            .ethereum
+            .list_transactions(status, limit, offset)
+            .await?;
+
+        Ok(Some(transactions))
+    }
+
+    async fn current_insertion_batch_size(&self) -> Option<usize> {
+        current_batch_size(&self.prover_repository, ProverType::Insertion).await
+    }
+
+    async fn current_deletion_batch_size(&self) -> Option<usize> {
+        current_batch_size(&self.prover_repository, ProverType::Deletion).await
+    }
+
+    async fn flush(&self) -> anyhow::Result<()> {
+        // Identities only stop being pending once the transaction of their
+        // batch is mined and its event was picked up by the finalization
+        self.flush_signal.flush(&self.database).await
+    }
 }
 
 impl OnChainIdentityProcessor {
@@ -175,6 +256,8 @@ impl OnChainIdentityProcessor {
         database: Arc<Database>,
         identity_manager: Arc<IdentityManager>,
         prover_repository: Arc<ProverRepository>,
+        flush_signal: Arc<FlushSignal>,
+        metrics: &Metrics,
     ) -> anyhow::Result<Self> {
         let mainnet_abi = identity_manager.abi();
         let secondary_abis = identity_manager.secondary_abis();
@@ -185,11 +268,14 @@ impl OnChainIdentityProcessor {
                 config.app.scanning_window_size,
             )
             .await?
-            .with_offset(config.app.scanning_chain_head_offset),
+            .with_offset(config.app.scanning_chain_head_offset)
+            .with_rpc_retries(config.app.max_rpc_retries, config.app.time_between_scans)
+            .with_label("mainnet")
+            .with_metrics(metrics),
         );
 
         let secondary_scanners = tokio::sync::Mutex::new(
-            Self::init_secondary_scanners(secondary_abis, config.app.scanning_window_size).await?,
+            Self::init_secondary_scanners(secondary_abis, &config.app, metrics).await?,
         );
 
         let mainnet_address = mainnet_abi.address();
@@ -199,6 +285,7 @@ impl OnChainIdentityProcessor {
             database,
             identity_manager,
             prover_repository,
+            flush_signal,
             mainnet_scanner,
             mainnet_address,
             secondary_scanners,
@@ -207,7 +294,8 @@ impl OnChainIdentityProcessor {
 
     async fn init_secondary_scanners<T>(
         providers: &[BridgedWorldId<T>],
-        scanning_window_size: u64,
+        config: &AppConfig,
+        metrics: &Metrics,
     ) -> anyhow::Result<HashMap<Address, BlockScanner<Arc<T>>>>
     where
         T: Middleware,
@@ -216,30 +304,65 @@ impl OnChainIdentityProcessor {
         let mut secondary_scanners = HashMap::new();
 
         for bridged_abi in providers {
-            let scanner =
-                BlockScanner::new_latest(bridged_abi.client().clone(), scanning_window_size)
-                    .await?;
-
             let address = bridged_abi.address();
 
+            let scanner =
+                BlockScanner::new_latest(bridged_abi.client().clone(), config.scanning_window_size)
+                    .await?
+                    .with_rpc_retries(config.max_rpc_retries, config.time_between_scans)
+                    .with_label(format!("{address:?}"))
+                    .with_metrics(metrics);
+
             secondary_scanners.insert(address, scanner);
         }
 
         Ok(secondary_scanners)
     }
 
+    /// Proves a batch and builds the transaction submitting it.
+    async fn prepare_batch(&self, batch: &BatchEntry) -> anyhow::Result<TypedTransaction> {
+        if batch.batch_type == BatchType::Insertion {
+            let prover = self
+                .prover_repository
+                .acquire_prover(ProverType::Insertion, batch.data.0.identities.len())
+                .await?;
+
+            info!(
+                num_updates = batch.data.0.identities.len(),
+                batch_size = prover.batch_size(),
+                "Insertion batch",
+            );
+
+            self.insert_identities(&prover, batch).await
+        } else {
+            let prover = self
+                .prover_repository
+                .acquire_prover(ProverType::Deletion, batch.data.0.identities.len())
+                .await?;
+
+            info!(
+                num_updates = batch.data.0.identities.len(),
+                batch_size = prover.batch_size(),
+                "Deletion batch"
+            );
+
+            self.delete_identities(&prover, batch).await
+        }
+    }
+
     #[instrument(level = "info", skip_all)]
     async fn insert_identities(
         &self,
         prover: &Prover,
         batch: &BatchEntry,
-    ) -> anyhow::Result<TransactionId> {
+    ) -> anyhow::Result<TypedTransaction> {
         self.validate_merkle_proofs(&batch.data.0.identities)?;
         let start_index = *batch.data.0.indexes.first().expect("Should exist.");
         let pre_root: U256 = batch.prev_root.expect("Should exist.").into();
         let post_root: U256 = batch.next_root.into();
 
         // We prepare the proof before reserving a slot in the pending identities
+        let proving_start = Instant::now();
         let proof = crate::prover::proof::prepare_insertion_proof(
             prover,
             start_index,
@@ -248,40 +371,20 @@ impl OnChainIdentityProcessor {
             post_root,
         )
         .await?;
+        self.record_batch_proving(batch, prover, proving_start)
+            .await;
 
-        info!(
-            start_index,
-            ?pre_root,
-            ?post_root,
-            "Submitting insertion batch"
-        );
-
-        // With all the data prepared we can submit the identities to the on-chain
-        // identity manager and wait for that transaction to be mined.
-        let transaction_id = self
-            .identity_manager
-            .register_identities(
-                start_index,
-                pre_root,
-                post_root,
-                batch.data.0.identities.clone(),
-                proof,
-            )
-            .await
-            .map_err(|e| {
-                error!(?e, "Failed to insert identity to contract.");
-                e
-            })?;
+        info!(start_index, ?pre_root, ?post_root, "Insertion batch proven");
 
-        info!(
+        // With all the data prepared we can build the call to the on-chain identity
+        // manager.
+        self.identity_manager.register_identities(
             start_index,
-            ?pre_root,
-            ?post_root,
-            ?transaction_id,
-            "Insertion batch submitted"
-        );
-
-        Ok(transaction_id)
+            pre_root,
+            post_root,
+            &batch.data.0.identities,
+            proof,
+        )
     }
 
     #[instrument(level = "info", skip_all)]
@@ -289,13 +392,14 @@ impl OnChainIdentityProcessor {
         &self,
         prover: &Prover,
         batch: &BatchEntry,
-    ) -> anyhow::Result<TransactionId> {
+    ) -> anyhow::Result<TypedTransaction> {
         self.validate_merkle_proofs(&batch.data.0.identities)?;
         let pre_root: U256 = batch.prev_root.expect("Should exist.").into();
         let post_root: U256 = batch.next_root.into();
         let deletion_indices: Vec<_> = batch.data.0.indexes.iter().map(|&v| v as u32).collect();
 
         // We prepare the proof before reserving a slot in the pending identities
+        let proving_start = Instant::now();
         let proof = crate::prover::proof::prepare_deletion_proof(
             prover,
             pre_root,
@@ -304,30 +408,33 @@ impl OnChainIdentityProcessor {
             post_root,
         )
         .await?;
+        self.record_batch_proving(batch, prover, proving_start)
+            .await;
 
         let packed_deletion_indices = pack_indices(&deletion_indices);
 
-        info!(?pre_root, ?post_root, "Submitting deletion batch");
+        info!(?pre_root, ?post_root, "Deletion batch proven");
 
-        // With all the data prepared we can submit the identities to the on-chain
-        // identity manager and wait for that transaction to be mined.
-        let transaction_id = self
-            .identity_manager
-            .delete_identities(proof, packed_deletion_indices, pre_root, post_root)
-            .await
-            .map_err(|e| {
-                error!(?e, "Failed to insert identity to contract.");
-                e
-            })?;
-
-        info!(
-            ?pre_root,
-            ?post_root,
-            ?transaction_id,
-            "Deletion batch submitted"
-        );
+        // With all the data prepared we can build the call to the on-chain identity
+        // manager.
+        Ok(self.identity_manager.delete_identities(
+            proof,
+            packed_deletion_indices,
+            pre_root,
+            post_root,
+        ))
+    }
 
-        Ok(transaction_id)
+    /// Keeps the prover and the proving time with the batch for later analysis.
+    /// Failing to do so doesn't fail the batch.
+    async fn record_batch_proving(&self, batch: &BatchEntry, prover: &Prover, start: Instant) {
+        if let Err(err) = self
+            .database
+            .record_batch_proving(&batch.next_root, prover.target_url(), start.elapsed())
+            .await
+        {
+            warn!(?err, next_root = ?batch.next_root, "Failed to record the proving of the batch");
+        }
     }
 
     #[instrument(level = "debug", skip_all)]
@@ -430,10 +537,10 @@ impl OnChainIdentityProcessor {
 
             let mut tx = self
                 .database
-                .begin_tx(IsolationLevel::ReadCommitted)
+                .begin_tx("finalize_mainnet_roots", IsolationLevel::ReadCommitted)
                 .await?;
-            tx.mark_root_as_processed(&post_root.into()).await?;
-            tx.commit().await?;
+            let result = tx.mark_root_as_processed(&post_root.into()).await;
+            tx.finish(result).await?;
 
             info!(?pre_root, ?post_root, ?kind, "Batch mined");
 
@@ -465,10 +572,10 @@ impl OnChainIdentityProcessor {
 
             let mut tx = self
                 .database
-                .begin_tx(IsolationLevel::ReadCommitted)
+                .begin_tx("finalize_secondary_roots", IsolationLevel::ReadCommitted)
                 .await?;
-            tx.mark_root_as_mined(&root.into()).await?;
-            tx.commit().await?;
+            let result = tx.mark_root_as_mined(&root.into()).await;
+            tx.finish(result).await?;
 
             mined_tree.apply_updates_up_to(root.into());
 
@@ -515,6 +622,9 @@ impl OnChainIdentityProcessor {
 pub struct OffChainIdentityProcessor {
     committed_batches: Arc<Mutex<VecDeque<BatchEntry>>>,
     database: Arc<Database>,
+    prover_repository: Arc<ProverRepository>,
+    flush_signal: Arc<FlushSignal>,
+    root_publisher: Option<RootPublisher>,
 }
 
 #[async_trait]
@@ -524,6 +634,15 @@ impl IdentityProcessor for OffChainIdentityProcessor {
         Ok(batch.id.to_string())
     }
 
+    async fn commit_batches(&self, batches: &[BatchEntry]) -> anyhow::Result<TransactionId> {
+        let mut transaction_id = TransactionId::default();
+        for batch in batches {
+            transaction_id = self.commit_identities(batch).await?;
+        }
+
+        Ok(transaction_id)
+    }
+
     async fn finalize_identities(
         &self,
         processed_tree: &TreeVersion<Intermediate>,
@@ -541,26 +660,44 @@ impl IdentityProcessor for OffChainIdentityProcessor {
 
             let mut tx = self
                 .database
-                .begin_tx(IsolationLevel::ReadCommitted)
+                .begin_tx("finalize_identities", IsolationLevel::ReadCommitted)
                 .await?;
 
-            let root_state = tx.get_root_state(&batch.next_root).await?;
-            if root_state.is_none() {
-                // If root is not in identities table we can't mark it as processed or mined.
-                // It happens sometimes as we do not have atomic operation for database and tree
-                // insertion.
-                // TODO: check if this is still possible after HA being done
-                tx.commit().await?;
-                return Ok(());
-            }
+            let result = async {
+                let root_state = tx.get_root_state(&batch.next_root).await?;
+                if root_state.is_none() {
+                    // If root is not in identities table we can't mark it as processed or mined.
+                    // It happens sometimes as we do not have atomic operation for database and
+                    // tree insertion.
+                    // TODO: check if this is still possible after HA being done
+                    return anyhow::Ok(false);
+                }
+
+                tx.mark_root_as_processed(&batch.next_root).await?;
+                tx.mark_root_as_mined(&batch.next_root).await?;
 
-            tx.mark_root_as_processed(&batch.next_root).await?;
-            tx.mark_root_as_mined(&batch.next_root).await?;
+                Ok(true)
+            }
+            .await;
 
-            tx.commit().await?;
+            if !tx.finish(result).await? {
+                return Ok(());
+            }
 
             processed_tree.apply_updates_up_to(batch.next_root);
             mined_tree.apply_updates_up_to(batch.next_root);
+
+            // Roots are processed and mined at once, so only the final state is
+            // published
+            if let Some(root_publisher) = &self.root_publisher {
+                root_publisher
+                    .publish(
+                        batch.next_root,
+                        ProcessedStatus::Mined,
+                        mined_tree.next_leaf(),
+                    )
+                    .await?;
+            }
         }
     }
 
@@ -582,22 +719,25 @@ impl IdentityProcessor for OffChainIdentityProcessor {
         // in such a case the state will be corrected later in the program
         let mut tx = self
             .database
-            .begin_tx(IsolationLevel::ReadCommitted)
+            .begin_tx("tree_init_correction", IsolationLevel::ReadCommitted)
             .await?;
 
-        let root_hash = tx
-            .get_latest_root_by_status(ProcessedStatus::Processed)
-            .await?;
+        let result = async {
+            let root_hash = tx
+                .get_latest_root_by_status(ProcessedStatus::Processed)
+                .await?;
 
-        if let Some(root_hash) = root_hash {
-            // This deletion is required as when tree is being initialized we do not read batches.
-            // Just restarting from last processed tree.
-            tx.delete_batches_after_root(&root_hash).await?; // TODO: We probably shouldn't do this in HA
-        }
+            if let Some(root_hash) = root_hash {
+                // This deletion is required as when tree is being initialized we do not read
+                // batches. Just restarting from last processed tree.
+                tx.delete_batches_after_root(&root_hash).await?; // TODO: We probably shouldn't do this in HA
+            }
 
-        tx.commit().await?;
+            anyhow::Ok(())
+        }
+        .await;
 
-        Ok(())
+        tx.finish(result).await
     }
 
     async fn latest_root(&self) -> anyhow::Result<Option<Hash>> {
@@ -606,13 +746,48 @@ impl IdentityProcessor for OffChainIdentityProcessor {
             .get_latest_root_by_status(ProcessedStatus::Mined)
             .await?)
     }
+
+    async fn relayer_balance(&self) -> anyhow::Result<Option<U256>> {
+        Ok(None)
+    }
+
+    async fn relayer_transactions(
+        &self,
+        _status: Option<&str>,
+        _limit: usize,
+        _offset: usize,
+    ) -> Result<Option<Vec<RelayerTransaction>>, TxError> {
+        Ok(None)
+    }
+
+    async fn current_insertion_batch_size(&self) -> Option<usize> {
+        current_batch_size(&self.prover_repository, ProverType::Insertion).await
+    }
+
+    async fn current_deletion_batch_size(&self) -> Option<usize> {
+        current_batch_size(&self.prover_repository, ProverType::Deletion).await
+    }
+
+    async fn flush(&self) -> anyhow::Result<()> {
+        // Committed batches are finalized on the next finalization cycle, which
+        // the flush wakes up as well
+        self.flush_signal.flush(&self.database).await
+    }
 }
 
 impl OffChainIdentityProcessor {
-    pub async fn new(database: Arc<Database>) -> anyhow::Result<Self> {
+    pub async fn new(
+        database: Arc<Database>,
+        prover_repository: Arc<ProverRepository>,
+        flush_signal: Arc<FlushSignal>,
+        root_publisher: Option<RootPublisher>,
+    ) -> anyhow::Result<Self> {
         Ok(OffChainIdentityProcessor {
             committed_batches: Arc::new(Mutex::new(Default::default())),
             database,
+            prover_repository,
+            flush_signal,
+            root_publisher,
         })
     }
 
@@ -622,3 +797,60 @@ impl OffChainIdentityProcessor {
         committed_batches.push_back(batch_entry);
     }
 }
+
+async fn current_batch_size(
+    prover_repository: &ProverRepository,
+    prover_type: ProverType,
+) -> Option<usize> {
+    let batch_size = match prover_type {
+        ProverType::Insertion => prover_repository.max_insertion_batch_size().await,
+        ProverType::Deletion => prover_repository.max_deletion_batch_size().await,
+    };
+
+    // The prover maps report 0 when empty
+    (batch_size > 0).then_some(batch_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prover::{ProverConfig, ProverMap};
+
+    #[tokio::test]
+    async fn current_batch_size_is_the_largest_prover() -> anyhow::Result<()> {
+        let mut insertion_provers = ProverMap::default();
+        for (port, batch_size) in [(3001, 3), (3002, 10)] {
+            insertion_provers.add(
+                batch_size,
+                Prover::new(
+                    &ProverConfig {
+                        url: format!("http://prover:{port}"),
+                        timeout_s: 30,
+                        batch_size,
+                        max_batch_size: None,
+                        hmac_secret: None,
+                        prover_type: ProverType::Insertion,
+                    },
+                    &Metrics::default(),
+                )?,
+            );
+        }
+        let prover_repository = ProverRepository::new(
+            insertion_provers,
+            ProverMap::default(),
+            1,
+            &Metrics::default(),
+        );
+
+        assert_eq!(
+            current_batch_size(&prover_repository, ProverType::Insertion).await,
+            Some(10)
+        );
+        assert_eq!(
+            current_batch_size(&prover_repository, ProverType::Deletion).await,
+            None
+        );
+
+        Ok(())
+    }
+}
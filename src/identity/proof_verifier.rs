@@ -0,0 +1,158 @@
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use prometheus::Histogram;
+use semaphore::protocol::{verify_proof, ProofError};
+use thiserror::Error;
+use tokio::sync::oneshot;
+use tracing::error;
+
+use crate::metrics::Metrics;
+use crate::server::data::VerifySemaphoreProofRequest;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+#[derive(Debug, Error)]
+pub enum VerifyError {
+    #[error("all proof verification workers are busy")]
+    Overloaded,
+    #[error("proof verification failed: {0:?}")]
+    Proof(ProofError),
+    #[error("proof verification worker panicked")]
+    Panicked,
+}
+
+/// Verifies semaphore proofs on threads of its own, so that the pairing checks
+/// don't hold up the async runtime. At most `queue_size` proofs wait for a
+/// worker, more are rejected rather than queued.
+pub struct ProofVerifier {
+    jobs: SyncSender<Job>,
+    queue_wait: Histogram,
+    verification: Histogram,
+}
+
+impl ProofVerifier {
+    /// # Errors
+    ///
+    /// Will return `Err` if the worker threads can't be spawned.
+    pub fn new(threads: usize, queue_size: usize, metrics: &Metrics) -> anyhow::Result<Self> {
+        let (jobs, queue) = sync_channel::<Job>(queue_size);
+        let queue = Arc::new(Mutex::new(queue));
+
+        for i in 0..threads.max(1) {
+            let queue = queue.clone();
+            thread::Builder::new()
+                .name(format!("proof-verifier-{i}"))
+                .spawn(move || work(&queue))?;
+        }
+
+        Ok(Self {
+            jobs,
+            queue_wait: metrics.proof_verification_queue_wait.clone(),
+            verification: metrics.proof_verification.clone(),
+        })
+    }
+
+    /// Whether the proof is valid for the request's root, nullifier and
+    /// signal at the given tree depth.
+    pub async fn verify(
+        &self,
+        request: VerifySemaphoreProofRequest,
+        tree_depth: usize,
+    ) -> Result<bool, VerifyError> {
+        let (result_tx, result_rx) = oneshot::channel();
+        let queued_at = Instant::now();
+        let queue_wait = self.queue_wait.clone();
+        let verification = self.verification.clone();
+
+        let job: Job = Box::new(move || {
+            queue_wait.observe(queued_at.elapsed().as_secs_f64());
+
+            let timer = verification.start_timer();
+            let checked = verify_proof(
+                request.root,
+                request.nullifier_hash,
+                request.signal_hash,
+                request.external_nullifier_hash,
+                &request.proof,
+                tree_depth,
+            );
+            timer.observe_duration();
+
+            // The request is gone if it timed out while queued
+            let _ = result_tx.send(checked);
+        });
+
+        match self.jobs.try_send(job) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => return Err(VerifyError::Overloaded),
+            // Workers only exit once the verifier is dropped
+            Err(TrySendError::Disconnected(_)) => unreachable!("proof verifier workers exited"),
+        }
+
+        match result_rx.await {
+            Ok(checked) => checked.map_err(VerifyError::Proof),
+            Err(_) => Err(VerifyError::Panicked),
+        }
+    }
+}
+
+fn work(queue: &Mutex<Receiver<Job>>) {
+    loop {
+        // The lock is released before the job runs, other workers take the
+        // next job meanwhile
+        let job = queue.lock().unwrap().recv();
+        let Ok(job) = job else {
+            return;
+        };
+
+        if catch_unwind(AssertUnwindSafe(job)).is_err() {
+            error!("Proof verification panicked");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+
+    use ethers::types::U256;
+    use semaphore::protocol::Proof;
+    use semaphore::Field;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn rejects_proofs_while_all_workers_are_busy() {
+        let verifier = ProofVerifier::new(1, 0, &Metrics::default()).unwrap();
+
+        // Without a queue, the send only returns once the worker took the job
+        let (release, released) = channel::<()>();
+        verifier
+            .jobs
+            .send(Box::new(move || {
+                let _ = released.recv();
+            }))
+            .unwrap();
+
+        let request = VerifySemaphoreProofRequest {
+            root: Field::ZERO,
+            signal_hash: Field::ZERO,
+            nullifier_hash: Field::ZERO,
+            external_nullifier_hash: Field::ZERO,
+            proof: Proof(
+                (U256::zero(), U256::zero()),
+                ([U256::zero(); 2], [U256::zero(); 2]),
+                (U256::zero(), U256::zero()),
+            ),
+        };
+
+        let result = verifier.verify(request, 20).await;
+        assert!(matches!(result, Err(VerifyError::Overloaded)));
+
+        release.send(()).unwrap();
+    }
+}
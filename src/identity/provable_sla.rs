@@ -0,0 +1,167 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use prometheus::{Histogram, IntGauge};
+
+use crate::config::ProvableSlaConfig;
+use crate::metrics::Metrics;
+
+/// Samples kept at once, so a burst of insertions can't grow the window
+/// without bounds. The oldest samples are dropped first.
+const MAX_SAMPLES: usize = 100_000;
+
+/// Tracks how long identities take to become provable in offchain mode, and
+/// whether that's within the SLA if one is configured. See
+/// `OffchainModeConfig::provable_sla`.
+pub struct ProvableSla {
+    config: Option<ProvableSlaConfig>,
+    samples: Mutex<VecDeque<(Instant, Duration)>>,
+    insert_to_provable: Histogram,
+    sla_breached: IntGauge,
+}
+
+impl ProvableSla {
+    pub fn new(config: Option<ProvableSlaConfig>, metrics: &Metrics) -> Self {
+        metrics.sla_breached.set(0);
+
+        Self {
+            config,
+            samples: Mutex::default(),
+            insert_to_provable: metrics.insert_to_provable.clone(),
+            sla_breached: metrics.sla_breached.clone(),
+        }
+    }
+
+    /// Records the latency of identities which just became provable.
+    pub fn observe(&self, latencies: impl IntoIterator<Item = Duration>) {
+        let now = Instant::now();
+        let mut samples = self.samples.lock().unwrap();
+
+        for latency in latencies {
+            self.insert_to_provable.observe(latency.as_secs_f64());
+            if self.config.is_some() {
+                samples.push_back((now, latency));
+            }
+        }
+
+        while samples.len() > MAX_SAMPLES {
+            samples.pop_front();
+        }
+
+        self.evaluate(&mut samples, now);
+    }
+
+    /// Whether the p95 latency over the window currently exceeds the SLA. An
+    /// empty window never breaches it, neither does a missing SLA.
+    pub fn is_breached(&self) -> bool {
+        let mut samples = self.samples.lock().unwrap();
+
+        self.evaluate(&mut samples, Instant::now())
+    }
+
+    /// Drops the samples which left the window and updates the `sla_breached`
+    /// gauge. Called periodically, as the window drains even when no identity
+    /// becomes provable.
+    pub fn refresh(&self) {
+        self.is_breached();
+    }
+
+    fn evaluate(&self, samples: &mut VecDeque<(Instant, Duration)>, now: Instant) -> bool {
+        let Some(config) = &self.config else {
+            return false;
+        };
+
+        while samples
+            .front()
+            .is_some_and(|(observed_at, _)| now.duration_since(*observed_at) > config.window)
+        {
+            samples.pop_front();
+        }
+
+        let breached = p95(samples.iter().map(|(_, latency)| *latency))
+            .is_some_and(|p95| p95 > config.max_latency);
+        self.sla_breached.set(breached.into());
+
+        breached
+    }
+}
+
+/// The nearest-rank 95th percentile.
+fn p95(latencies: impl Iterator<Item = Duration>) -> Option<Duration> {
+    let mut latencies: Vec<_> = latencies.collect();
+    if latencies.is_empty() {
+        return None;
+    }
+
+    latencies.sort_unstable();
+    let rank = (latencies.len() * 95).div_ceil(100);
+
+    Some(latencies[rank - 1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sla(max_latency: u64) -> ProvableSla {
+        ProvableSla::new(
+            Some(ProvableSlaConfig {
+                max_latency: Duration::from_secs(max_latency),
+                window: Duration::from_secs(600),
+            }),
+            &Metrics::default(),
+        )
+    }
+
+    #[test]
+    fn p95_is_nearest_rank() {
+        let secs = |range: std::ops::RangeInclusive<u64>| range.map(Duration::from_secs);
+
+        assert_eq!(p95(std::iter::empty()), None);
+        assert_eq!(p95(secs(7..=7)), Some(Duration::from_secs(7)));
+        assert_eq!(p95(secs(1..=100)), Some(Duration::from_secs(95)));
+        assert_eq!(p95(secs(1..=10).rev()), Some(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn breach_requires_p95_above_sla() {
+        let sla = sla(10);
+        assert!(!sla.is_breached());
+
+        // 5% of slow identities are tolerated
+        sla.observe((0..95).map(|_| Duration::from_secs(1)));
+        sla.observe((0..5).map(|_| Duration::from_secs(60)));
+        assert!(!sla.is_breached());
+
+        sla.observe([Duration::from_secs(60)]);
+        assert!(sla.is_breached());
+    }
+
+    #[test]
+    fn gauge_clears_once_the_window_drains() {
+        let metrics = Metrics::default();
+        let sla = ProvableSla::new(
+            Some(ProvableSlaConfig {
+                max_latency: Duration::from_secs(10),
+                window: Duration::from_millis(50),
+            }),
+            &metrics,
+        );
+
+        sla.observe([Duration::from_secs(60)]);
+        assert_eq!(metrics.sla_breached.get(), 1);
+
+        std::thread::sleep(Duration::from_millis(100));
+        sla.refresh();
+        assert_eq!(metrics.sla_breached.get(), 0);
+    }
+
+    #[test]
+    fn never_breached_without_sla() {
+        let sla = ProvableSla::new(None, &Metrics::default());
+
+        sla.observe([Duration::from_secs(3600)]);
+        assert!(!sla.is_breached());
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+use prometheus::IntCounterVec;
+use serde::{Deserialize, Serialize};
+
+use crate::config::ExternalRootOracleConfig;
+use crate::identity_tree::Hash;
+use crate::metrics::Metrics;
+
+/// Answers kept at once, the roots are chosen by the callers of the verify
+/// endpoints.
+const MAX_CACHED_ROOTS: usize = 10_000;
+
+/// The oracle's answer for a root.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RootOracleResponse {
+    pub known: bool,
+    /// When the root became valid, required for known roots
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+struct CachedAnswer {
+    valid_as_of: Option<DateTime<Utc>>,
+    fetched_at: Instant,
+}
+
+/// Client of a trusted external source of roots, e.g. roots bridged to chains
+/// this instance doesn't serve. See `AppConfig::external_root_oracle`.
+pub struct RootOracle {
+    client: reqwest::Client,
+    config: ExternalRootOracleConfig,
+    cache: Mutex<HashMap<Hash, CachedAnswer>>,
+    lookups: IntCounterVec,
+}
+
+impl RootOracle {
+    pub fn new(config: &ExternalRootOracleConfig, metrics: &Metrics) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder().timeout(config.timeout).build()?;
+
+        Ok(Self {
+            client,
+            config: config.clone(),
+            cache: Mutex::default(),
+            lookups: metrics.root_oracle_lookups.clone(),
+        })
+    }
+
+    /// When `root` became valid according to the oracle, `None` if the oracle
+    /// doesn't know it. Answers are cached, failures aren't.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the oracle can't be reached or gives an invalid
+    /// answer.
+    pub async fn root_valid_as_of(&self, root: &Hash) -> anyhow::Result<Option<DateTime<Utc>>> {
+        let cached = self
+            .cache
+            .lock()
+            .unwrap()
+            .get(root)
+            .filter(|cached| cached.fetched_at.elapsed() < self.config.cache_ttl)
+            .map(|cached| cached.valid_as_of);
+        if let Some(valid_as_of) = cached {
+            return Ok(valid_as_of);
+        }
+
+        let valid_as_of = match self.fetch(root).await {
+            Ok(valid_as_of) => valid_as_of,
+            Err(err) => {
+                self.lookups.with_label_values(&["error"]).inc();
+                return Err(err);
+            }
+        };
+
+        let result = if valid_as_of.is_some() {
+            "confirmed"
+        } else {
+            "unknown"
+        };
+        self.lookups.with_label_values(&[result]).inc();
+
+        let mut cache = self.cache.lock().unwrap();
+        cache.retain(|_, cached| cached.fetched_at.elapsed() < self.config.cache_ttl);
+        if cache.len() < MAX_CACHED_ROOTS {
+            cache.insert(
+                *root,
+                CachedAnswer {
+                    valid_as_of,
+                    fetched_at: Instant::now(),
+                },
+            );
+        }
+
+        Ok(valid_as_of)
+    }
+
+    async fn fetch(&self, root: &Hash) -> anyhow::Result<Option<DateTime<Utc>>> {
+        let url = self.config.url.join(&format!("root/{root:#x}"))?;
+
+        let response: RootOracleResponse = self
+            .client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        match response {
+            RootOracleResponse {
+                known: true,
+                timestamp: None,
+            } => anyhow::bail!("Root oracle knows {root:#x} but didn't say since when"),
+            RootOracleResponse {
+                known: true,
+                timestamp,
+            } => Ok(timestamp),
+            RootOracleResponse { known: false, .. } => Ok(None),
+        }
+    }
+}
@@ -23,6 +23,11 @@ impl IdentityValidator {
         }
     }
 
+    /// Returns true iff commitment is a valid element of the BN254 scalar field
+    /// (i.e. < SNARK_SCALAR_FIELD_SIZE)
+    ///
+    /// Unreduced values would alias a reduced commitment once they enter the
+    /// circuits, so they must be rejected before insertion.
     pub fn is_reduced(&self, commitment: Hash) -> bool {
         commitment.lt(&self.snark_scalar_field)
     }
@@ -31,3 +36,43 @@ impl IdentityValidator {
         *commitment == self.initial_leaf_value
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validator() -> IdentityValidator {
+        IdentityValidator {
+            snark_scalar_field: Hash::from(MODULUS),
+            initial_leaf_value: Field::ZERO,
+        }
+    }
+
+    #[test]
+    fn zero_is_reduced() {
+        assert!(validator().is_reduced(Hash::ZERO));
+    }
+
+    #[test]
+    fn modulus_minus_one_is_reduced() {
+        assert!(validator().is_reduced(MODULUS - Hash::from(1)));
+    }
+
+    #[test]
+    fn modulus_is_not_reduced() {
+        assert!(!validator().is_reduced(MODULUS));
+    }
+
+    #[test]
+    fn max_value_is_not_reduced() {
+        assert!(!validator().is_reduced(Hash::MAX));
+    }
+
+    #[test]
+    fn arbitrary_commitment_is_reduced() {
+        let commitment =
+            uint!(0x0a1b2c3d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f60718293a4b5c6d7e8f9_U256);
+
+        assert!(validator().is_reduced(commitment));
+    }
+}
@@ -0,0 +1,210 @@
+//! Compaction of the cache file backing the dense prefix of the mined tree.
+//!
+//! The cache is a fixed size file which is only ever updated in place, so every
+//! block of it stays allocated on disk, even those only holding empty or
+//! deleted leaves. Compaction rebuilds it from the mined identities in the
+//! database, writes it as a sparse file without the blocks that are all zeroes
+//! and swaps the new file in once its root checks out.
+
+use std::fs::{self, File};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use fs2::FileExt;
+use semaphore::poseidon_tree::LazyPoseidonTree;
+use tracing::{info, instrument, warn};
+
+use crate::config::TreeConfig;
+use crate::database::methods::DbMethods;
+use crate::database::Database;
+use crate::identity_tree::initializer::{check_leaf_indexes, leaves_from_updates};
+use crate::identity_tree::{CanonicalTreeBuilder, Hash, ProcessedStatus, TreeVersionReadOps};
+use crate::utils::tree_updates::dedup_tree_updates;
+
+#[derive(Debug)]
+pub struct CompactionReport {
+    /// Root of the mined tree, the same before and after the compaction
+    pub root: Hash,
+    /// Non-zero leaves in the compacted tree
+    pub leaf_count: usize,
+    /// Bytes allocated on disk, `None` if there was no cache file to compact
+    pub size_before: Option<u64>,
+    /// Bytes allocated on disk
+    pub size_after: u64,
+}
+
+/// Lock on the cache file, shared by every sequencer using it and taken
+/// exclusively by the compaction.
+///
+/// The lock is held on a separate file next to the cache, as the cache itself
+/// is replaced by the compaction.
+#[derive(Debug)]
+pub struct TreeCacheLock {
+    _file: File,
+}
+
+impl TreeCacheLock {
+    /// Held by a sequencer for as long as it has the cache mapped.
+    pub fn shared(cache_file: &str) -> anyhow::Result<Self> {
+        let path = suffixed_path(Path::new(cache_file), ".lock");
+        let file = File::create(&path)?;
+        file.try_lock_shared()
+            .with_context(|| format!("Tree cache {cache_file} is being compacted"))?;
+
+        Ok(Self { _file: file })
+    }
+
+    fn exclusive(cache_file: &Path) -> anyhow::Result<Self> {
+        let path = suffixed_path(cache_file, ".lock");
+        let file = File::create(&path)?;
+        file.try_lock_exclusive().with_context(|| {
+            format!(
+                "Tree cache {} is in use, stop the sequencer before compacting it",
+                cache_file.display()
+            )
+        })?;
+
+        Ok(Self { _file: file })
+    }
+}
+
+/// Rebuilds the cache file of the mined tree and atomically replaces the
+/// current one with it. The rebuilt tree must have the latest mined root in
+/// the database, otherwise the current cache file is left untouched.
+///
+/// Fails if a sequencer is using the cache file, it would keep the replaced
+/// file mapped and not see the compacted one until it is restarted.
+#[instrument(skip_all)]
+pub async fn compact_tree_cache(
+    database: &Database,
+    config: &TreeConfig,
+) -> anyhow::Result<CompactionReport> {
+    let mut mined_items = database
+        .get_commitments_by_status(ProcessedStatus::Mined)
+        .await?;
+    mined_items.sort_by_key(|item| item.leaf_index);
+    let mined_items = dedup_tree_updates(mined_items);
+
+    if !config.allow_leaf_gaps {
+        check_leaf_indexes(&mined_items)?;
+    }
+
+    let expected_root = match database
+        .get_latest_root_by_status(ProcessedStatus::Mined)
+        .await?
+    {
+        Some(root) => root,
+        None => LazyPoseidonTree::new(config.tree_depth, config.initial_leaf_value).root(),
+    };
+
+    let cache_file = PathBuf::from(&config.cache_file);
+    let _lock = TreeCacheLock::exclusive(&cache_file)?;
+
+    let built_file = suffixed_path(&cache_file, ".build");
+    let compacted_file = suffixed_path(&cache_file, ".compact");
+    let size_before = allocated_size(&cache_file)?;
+
+    // Left behind by an interrupted compaction
+    for path in [&built_file, &compacted_file] {
+        if allocated_size(path)?.is_some() {
+            warn!(?path, "Removing stale compacted cache file");
+            fs::remove_file(path)?;
+        }
+    }
+
+    info!(
+        mined_items = mined_items.len(),
+        path = ?built_file,
+        "Rebuilding the tree cache"
+    );
+
+    let leaves = leaves_from_updates(config.initial_leaf_value, mined_items);
+    let tree_config = config.clone();
+    let path = built_file.to_string_lossy().into_owned();
+    let (root, leaf_count) = tokio::task::spawn_blocking(move || {
+        let (mined, _) = CanonicalTreeBuilder::new(
+            tree_config.tree_depth,
+            tree_config.dense_tree_prefix_depth,
+            tree_config.tree_gc_threshold,
+            tree_config.initial_leaf_value,
+            &leaves,
+            &path,
+        )
+        .seal();
+
+        (mined.get_root(), mined.leaf_count())
+    })
+    .await?;
+
+    if root != expected_root {
+        fs::remove_file(&built_file)?;
+        anyhow::bail!(
+            "Compacted tree has root {root:#x} but the latest mined root is {expected_root:#x}, \
+             the cache file was left as is"
+        );
+    }
+
+    // The tree is unmapped by now, so its file can be copied
+    copy_sparse(&built_file, &compacted_file)?;
+    fs::remove_file(&built_file)?;
+    fs::rename(&compacted_file, &cache_file)?;
+
+    let size_after = allocated_size(&cache_file)?.unwrap_or_default();
+
+    info!(
+        ?root,
+        leaf_count, size_before, size_after, "Tree cache compacted"
+    );
+
+    Ok(CompactionReport {
+        root,
+        leaf_count,
+        size_before,
+        size_after,
+    })
+}
+
+/// Copies `from` to `to`, leaving holes for the blocks that are all zeroes, and
+/// makes sure the copy is on disk.
+fn copy_sparse(from: &Path, to: &Path) -> std::io::Result<()> {
+    const BLOCK_SIZE: usize = 4096;
+
+    let mut source = File::open(from)?;
+    let len = source.metadata()?.len();
+    let mut target = File::create(to)?;
+
+    let mut block = vec![0; BLOCK_SIZE];
+    loop {
+        let read = source.read(&mut block)?;
+        if read == 0 {
+            break;
+        }
+
+        if block[..read].iter().all(|byte| *byte == 0) {
+            target.seek(SeekFrom::Current(read as i64))?;
+        } else {
+            target.write_all(&block[..read])?;
+        }
+    }
+
+    // Trailing holes aren't written at all
+    target.set_len(len)?;
+    target.sync_all()
+}
+
+fn suffixed_path(cache_file: &Path, suffix: &str) -> PathBuf {
+    let mut path = cache_file.as_os_str().to_owned();
+    path.push(suffix);
+    path.into()
+}
+
+fn allocated_size(path: &Path) -> std::io::Result<Option<u64>> {
+    match fs::metadata(path) {
+        // Blocks are counted in units of 512 bytes, whatever the file system
+        Ok(metadata) => Ok(Some(metadata.blocks() * 512)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
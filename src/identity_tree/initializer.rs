@@ -1,23 +1,67 @@
 use std::sync::Arc;
 use std::time::Instant;
 
+use prometheus::IntCounter;
 use semaphore::poseidon_tree::LazyPoseidonTree;
-use tracing::{info, instrument, warn};
+use thiserror::Error;
+use tracing::{error, info, instrument, warn};
 
 use crate::config::TreeConfig;
 use crate::database::methods::DbMethods;
-use crate::database::Database;
+use crate::database::{Database, IsolationLevel};
 use crate::identity::processor::IdentityProcessor;
 use crate::identity_tree::{
     CanonicalTreeBuilder, Hash, ProcessedStatus, TreeState, TreeUpdate, TreeVersionReadOps,
     TreeWithNextVersion,
 };
+use crate::metrics::Metrics;
 use crate::utils::tree_updates::dedup_tree_updates;
 
+/// The mined identities don't cover every leaf index up to the last one, the
+/// tree built from them would not match the tree on chain.
+#[derive(Debug, Error)]
+#[error("Mined identities skip from leaf index {expected} to {found}")]
+pub struct LeafIndexGap {
+    pub expected: usize,
+    pub found: usize,
+}
+
+/// Checks that sorted and deduplicated updates start at leaf 0 and have no
+/// gaps.
+pub(super) fn check_leaf_indexes(updates: &[TreeUpdate]) -> Result<(), LeafIndexGap> {
+    for (expected, update) in updates.iter().enumerate() {
+        if update.leaf_index != expected {
+            return Err(LeafIndexGap {
+                expected,
+                found: update.leaf_index,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// The leaves of the tree holding the sorted `updates`, leaves without an
+/// update keep the initial value.
+pub(super) fn leaves_from_updates(initial_leaf_value: Hash, updates: Vec<TreeUpdate>) -> Vec<Hash> {
+    let Some(max_leaf) = updates.last().map(|item| item.leaf_index) else {
+        return vec![];
+    };
+
+    let mut leaves = vec![initial_leaf_value; max_leaf + 1];
+    for item in updates {
+        leaves[item.leaf_index] = item.element;
+    }
+
+    leaves
+}
+
 pub struct TreeInitializer {
     pub database: Arc<Database>,
     pub identity_processor: Arc<dyn IdentityProcessor>,
     pub config: TreeConfig,
+    pub offchain_mode: bool,
+    tree_sync_gaps: IntCounter,
 }
 
 impl TreeInitializer {
@@ -25,11 +69,15 @@ impl TreeInitializer {
         database: Arc<Database>,
         identity_processor: Arc<dyn IdentityProcessor>,
         config: TreeConfig,
+        offchain_mode: bool,
+        metrics: &Metrics,
     ) -> Self {
         Self {
             database,
             identity_processor,
             config,
+            offchain_mode,
+            tree_sync_gaps: metrics.tree_sync_gaps.clone(),
         }
     }
 
@@ -42,6 +90,12 @@ impl TreeInitializer {
         let initial_root_hash =
             LazyPoseidonTree::new(self.config.tree_depth, self.config.initial_leaf_value).root();
 
+        let mut force_cache_purge = self.config.force_cache_purge;
+        if let Some(root) = self.config.recover_to_root {
+            // The cached tree may contain the rolled back identities
+            force_cache_purge |= self.recover_to_root(&root).await?;
+        }
+
         self.identity_processor
             .tree_init_correction(&initial_root_hash)
             .await?;
@@ -49,7 +103,7 @@ impl TreeInitializer {
         let timer = Instant::now();
         info!("Tree state initialization started");
         let tree_state = self
-            .restore_or_initialize_tree(initial_root_hash, self.config.force_cache_purge)
+            .restore_or_initialize_tree(initial_root_hash, force_cache_purge)
             .await?;
         info!("Tree state initialization took: {:?}", timer.elapsed());
 
@@ -75,11 +129,92 @@ impl TreeInitializer {
         }
     }
 
+    /// Rolls the database back to `root`: every identity inserted after it
+    /// becomes pending again and the batches after it are dropped, so that the
+    /// identities are batched and processed anew.
+    ///
+    /// Refuses to roll back roots which were already mined on chain, the
+    /// contract would reject the identities batched again.
+    ///
+    /// A recovery is recorded in the database and applied only once, so that
+    /// leaving the option set doesn't roll the tree back on every restart.
+    /// Returns whether the recovery was applied now.
+    #[instrument(skip(self))]
+    async fn recover_to_root(&self, root: &Hash) -> anyhow::Result<bool> {
+        if self.database.is_tree_recovery_applied(root).await? {
+            warn!(
+                ?root,
+                "Recovery to this root was applied already, ignoring it. Unset the recovery \
+                 option to silence this warning."
+            );
+            return Ok(false);
+        }
+
+        let Some(sequence_id) = self.database.get_id_by_root(root).await? else {
+            anyhow::bail!("Cannot recover to root {root:#x}, it's not in the database");
+        };
+
+        if !self.offchain_mode {
+            if let Some(contract_root) = self.identity_processor.latest_root().await? {
+                let contract_sequence_id = self.database.get_id_by_root(&contract_root).await?;
+
+                if contract_sequence_id.is_some_and(|id| id > sequence_id) {
+                    anyhow::bail!(
+                        "Cannot recover to root {root:#x}, the contract is already at the newer \
+                         root {contract_root:#x}"
+                    );
+                }
+            }
+        }
+
+        let previous_batch_root = self
+            .database
+            .get_latest_batch()
+            .await?
+            .map(|batch| batch.next_root);
+
+        // Other instances of the sequencer must not be running during a recovery,
+        // so the isolation level is of no concern here
+        let mut tx = self
+            .database
+            .begin_tx("recover_to_root", IsolationLevel::ReadCommitted)
+            .await?;
+
+        let result = async {
+            let invalidated = tx.invalidate_updates_after(sequence_id).await?;
+            // Deleting the next batch cascades to all of the batches after it
+            tx.delete_batches_after_root(root).await?;
+
+            if !tx
+                .record_tree_recovery(root, sequence_id, invalidated)
+                .await?
+            {
+                anyhow::bail!("Recovery to root {root:#x} was applied concurrently");
+            }
+
+            anyhow::Ok(invalidated)
+        }
+        .await;
+
+        let invalidated = tx.finish(result).await?;
+
+        warn!(
+            ?root,
+            ?previous_batch_root,
+            sequence_id,
+            invalidated_identities = invalidated,
+            "RECOVERY: rolled the tree back to an earlier root, the identities inserted after it \
+             will be batched again"
+        );
+
+        Ok(true)
+    }
+
     #[instrument(skip(self))]
     async fn restore_or_initialize_tree(
         &self,
         initial_root_hash: Hash,
-        force_cache_purge: bool,
+        mut force_cache_purge: bool,
     ) -> anyhow::Result<TreeState> {
         info!("Getting mined commitments from DB");
         let mut mined_items = self
@@ -93,6 +228,24 @@ impl TreeInitializer {
 
         info!("Retrieved {} mined commitments from DB", mined_items.len());
 
+        if let Err(gap) = check_leaf_indexes(&mined_items) {
+            self.tree_sync_gaps.inc();
+            error!(
+                expected_leaf_index = gap.expected,
+                found_leaf_index = gap.found,
+                "Mined identities have a gap in their leaf indexes"
+            );
+
+            if !self.config.allow_leaf_gaps {
+                return Err(gap.into());
+            }
+
+            warn!("Leaf index gaps are allowed, the missing leaves are left empty");
+            // Restoring from the cache relies on the leaf index of every
+            // update matching its position
+            force_cache_purge = true;
+        }
+
         if !force_cache_purge {
             info!(
                 "Attempting to restore tree from cache (file={})",
@@ -249,18 +402,7 @@ impl TreeInitializer {
 
         let initial_leaf_value = self.config.initial_leaf_value;
 
-        let initial_leaves = if mined_items.is_empty() {
-            vec![]
-        } else {
-            let max_leaf = mined_items.last().map(|item| item.leaf_index).unwrap();
-            let mut leaves = vec![initial_leaf_value; max_leaf + 1];
-
-            for item in mined_items {
-                leaves[item.leaf_index] = item.element;
-            }
-
-            leaves
-        };
+        let initial_leaves = leaves_from_updates(initial_leaf_value, mined_items);
 
         let tree_depth = self.config.tree_depth;
         let dense_tree_prefix_depth = self.config.dense_tree_prefix_depth;
@@ -337,12 +479,27 @@ impl TreeInitializer {
 
 #[cfg(test)]
 mod test {
+    use std::str::FromStr;
+    use std::sync::Arc;
+    use std::time::Duration;
+
     use ethers::prelude::rand;
     use ethers::types::U256;
     use ruint::Uint;
-
+    use testcontainers::clients::Cli;
+
+    use super::LeafIndexGap;
+    use crate::config::{DatabaseConfig, TreeConfig};
+    use crate::database::methods::DbMethods;
+    use crate::database::Database;
+    use crate::identity::flush::FlushSignal;
+    use crate::identity::processor::OffChainIdentityProcessor;
     use crate::identity_tree::initializer::TreeInitializer;
-    use crate::identity_tree::TreeUpdate;
+    use crate::identity_tree::{Hash, TreeUpdate, TreeVersionReadOps};
+    use crate::metrics::Metrics;
+    use crate::prover::map::ProverMap;
+    use crate::prover::repository::ProverRepository;
+    use crate::utils::secret::SecretUrl;
 
     pub fn generate_test_identities_with_index(identity_count: usize) -> Vec<TreeUpdate> {
         let mut identities = vec![];
@@ -429,4 +586,96 @@ mod test {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn mined_leaf_gaps_are_refused_unless_allowed() -> anyhow::Result<()> {
+        let metrics = Metrics::default();
+        let docker = Cli::default();
+        let db_container = postgres_docker_utils::setup(&docker).await?;
+        let url = format!(
+            "postgres://postgres:postgres@{}/database",
+            db_container.address()
+        );
+        let database = Arc::new(
+            Database::new(
+                &DatabaseConfig {
+                    database: SecretUrl::from_str(&url)?,
+                    migrate: true,
+                    max_connections: 1,
+                    slow_transaction_threshold: Duration::from_secs(5),
+                },
+                &metrics,
+            )
+            .await?,
+        );
+
+        // Leaf 1 is missing
+        let mut pre_root = Hash::ZERO;
+        for leaf_index in [0, 2, 3] {
+            let root = Hash::from(leaf_index + 1);
+            database
+                .insert_pending_identity(
+                    leaf_index,
+                    &Hash::from(leaf_index + 100),
+                    &root,
+                    &pre_root,
+                )
+                .await?;
+            pre_root = root;
+        }
+        database.mark_root_as_mined(&pre_root).await?;
+
+        let identity_processor = Arc::new(
+            OffChainIdentityProcessor::new(
+                database.clone(),
+                Arc::new(ProverRepository::new(
+                    ProverMap::default(),
+                    ProverMap::default(),
+                    1,
+                    &metrics,
+                )),
+                Arc::new(FlushSignal::default()),
+                None,
+            )
+            .await?,
+        );
+
+        let temp_dir = tempfile::tempdir()?;
+        let initializer = |allow_leaf_gaps| {
+            TreeInitializer::new(
+                database.clone(),
+                identity_processor.clone(),
+                TreeConfig {
+                    tree_depth: 10,
+                    dense_tree_prefix_depth: 3,
+                    tree_gc_threshold: 10_000,
+                    cache_file: temp_dir.path().join("tree").to_str().unwrap().to_string(),
+                    force_cache_purge: false,
+                    initial_leaf_value: Hash::ZERO,
+                    recover_to_root: None,
+                    allow_leaf_gaps,
+                },
+                true,
+                &metrics,
+            )
+        };
+
+        let err = initializer(false)
+            .restore_or_initialize_tree(Hash::ZERO, true)
+            .await
+            .err()
+            .expect("Gap was not detected");
+        let gap = err.downcast_ref::<LeafIndexGap>().unwrap();
+        assert_eq!((gap.expected, gap.found), (1, 2));
+
+        let tree_state = initializer(true)
+            .restore_or_initialize_tree(Hash::ZERO, true)
+            .await?;
+        let mined_tree = tree_state.get_mined_tree();
+        assert_eq!(mined_tree.next_leaf(), 4);
+        assert_eq!(mined_tree.get_leaf(1), Hash::ZERO);
+        assert_eq!(mined_tree.get_leaf(2), Hash::from(102));
+
+        Ok(())
+    }
 }
@@ -1,4 +1,5 @@
 use std::cmp::min;
+use std::collections::HashSet;
 use std::sync::{Arc, Mutex, MutexGuard};
 
 use chrono::Utc;
@@ -10,13 +11,16 @@ use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
 use tracing::{info, warn};
 
+use crate::utils::batch_type::BatchType;
+
+pub mod compaction;
 pub mod initializer;
-mod status;
+pub mod publication;
 
 pub type PoseidonTree<Version> = LazyMerkleTree<PoseidonHash, Version>;
 pub type Hash = <PoseidonHash as Hasher>::Hash;
 
-pub use self::status::{ProcessedStatus, Status, UnknownStatus, UnprocessedStatus};
+pub use signup_sequencer_types::{ProcessedStatus, Status, UnknownStatus, UnprocessedStatus};
 
 #[derive(Clone, Eq, PartialEq, Hash, Debug, FromRow)]
 pub struct TreeUpdate {
@@ -60,6 +64,22 @@ pub struct InclusionProof {
     pub message: Option<String>,
 }
 
+impl Eq for InclusionProof {}
+
+impl std::hash::Hash for InclusionProof {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.status.hash(state);
+        self.root.hash(state);
+        // The merkle proof doesn't implement `Hash`, its serialized form is
+        // equal iff the proofs are
+        self.proof
+            .as_ref()
+            .map(|proof| serde_json::to_vec(proof).expect("proofs are serializable"))
+            .hash(state);
+        self.message.hash(state);
+    }
+}
+
 /// Additional data held by the canonical tree version. It includes data
 /// necessary to control garbage collection.
 pub struct CanonicalTreeMetadata {
@@ -96,11 +116,13 @@ impl AllowedTreeVersionMarker for lazy_merkle_tree::Derived {
 }
 
 /// Underlying data structure for a tree version. It holds the tree itself, the
-/// next leaf (only used in the latest tree), a pointer to the next version (if
-/// exists) and the metadata specified by the version marker.
+/// next leaf (only used in the latest tree), the number of non-zero leaves, a
+/// pointer to the next version (if exists) and the metadata specified by the
+/// version marker.
 struct TreeVersionData<V: AllowedTreeVersionMarker> {
     tree: PoseidonTree<V>,
     next_leaf: usize,
+    non_zero_count: usize,
     next: Option<TreeVersion<AnyDerived>>,
     metadata: V::Metadata,
 }
@@ -137,6 +159,16 @@ where
         self.tree.get_leaf(leaf)
     }
 
+    /// Keeps the count of non-zero leaves in sync when a leaf changes from
+    /// `previous` to `element`.
+    fn track_leaf_change(&mut self, previous: Hash, element: Hash) {
+        match (previous == Hash::ZERO, element == Hash::ZERO) {
+            (true, false) => self.non_zero_count += 1,
+            (false, true) => self.non_zero_count = self.non_zero_count.saturating_sub(1),
+            _ => {}
+        }
+    }
+
     /// Gets the proof of the given leaf index element
     fn get_proof(&self, leaf: usize) -> (Hash, Proof) {
         let proof = self.tree.proof(leaf);
@@ -150,28 +182,36 @@ where
             return Vec::new();
         };
 
-        let next = next.get_data();
-
-        let first_is_zero = match next.metadata.diff.first() {
-            Some(first) => first.update.element == Hash::ZERO,
+        let batch_type = match next.get_data().metadata.diff.first() {
+            Some(first) if first.update.element == Hash::ZERO => BatchType::Deletion,
+            Some(_) => BatchType::Insertion,
             None => return vec![],
         };
 
-        // Gets the next contiguous of insertion or deletion updates from the diff
-        let should_take = |elem: &&AppliedTreeUpdate| {
-            if first_is_zero {
-                // If first is zero, we should take only consecutive zeros
-                elem.update.element == Hash::ZERO
-            } else {
-                // If first is not zero, we should take only non-zeros
-                elem.update.element != Hash::ZERO
-            }
+        self.peek_next_updates_of_type(maximum_update_count, batch_type)
+    }
+
+    /// Same as [`Self::peek_next_updates`], but returns nothing unless the
+    /// next updates are of the given type. Updates are applied in order, so
+    /// later updates of that type are never skipped to.
+    fn peek_next_updates_of_type(
+        &self,
+        maximum_update_count: usize,
+        batch_type: BatchType,
+    ) -> Vec<AppliedTreeUpdate> {
+        let Some(next) = self.next.as_ref() else {
+            return Vec::new();
         };
 
+        let next = next.get_data();
+
+        // Deletions replace leaves with zeros, insertions never insert them
+        let is_deletion = batch_type.is_deletion();
+
         next.metadata
             .diff
             .iter()
-            .take_while(should_take)
+            .take_while(|elem| (elem.update.element == Hash::ZERO) == is_deletion)
             .take(maximum_update_count)
             .cloned()
             .collect()
@@ -213,6 +253,9 @@ where
 
 impl BasicTreeOps for TreeVersionData<lazy_merkle_tree::Canonical> {
     fn update(&mut self, leaf_index: usize, element: Hash) {
+        let previous = self.tree.get_leaf(leaf_index);
+        self.track_leaf_change(previous, element);
+
         take_mut::take(&mut self.tree, |tree| {
             tree.update_with_mutation(leaf_index, &element)
         });
@@ -256,6 +299,8 @@ impl BasicTreeOps for TreeVersionData<lazy_merkle_tree::Canonical> {
             self.metadata.count_since_last_flatten = 0;
             let next = &self.next;
             if let Some(next) = next {
+                // A derived tree is rooted at this canonical tree and starts with
+                // no pending updates, `rebuild_on` replays the diff on top of it.
                 next.get_data().rebuild_on(self.tree.derived());
             }
             info!("Tree versions rebuilt");
@@ -279,6 +324,9 @@ impl TreeVersionData<lazy_merkle_tree::Derived> {
 
 impl BasicTreeOps for TreeVersionData<lazy_merkle_tree::Derived> {
     fn update(&mut self, leaf_index: usize, element: Hash) {
+        let previous = self.tree.get_leaf(leaf_index);
+        self.track_leaf_change(previous, element);
+
         let updated_tree = self.tree.update(leaf_index, &element);
 
         self.tree = updated_tree.clone();
@@ -311,6 +359,13 @@ impl BasicTreeOps for TreeVersionData<lazy_merkle_tree::Derived> {
     fn apply_diffs(&mut self, mut diffs: Vec<AppliedTreeUpdate>) {
         let last = diffs.last().cloned();
 
+        let mut previous_tree = self.tree.clone();
+        for diff in &diffs {
+            let previous = previous_tree.get_leaf(diff.update.leaf_index);
+            self.track_leaf_change(previous, diff.update.element);
+            previous_tree = diff.result.clone();
+        }
+
         self.metadata.diff.append(&mut diffs);
 
         if let Some(last) = last {
@@ -404,6 +459,9 @@ pub trait TreeVersionReadOps {
     fn get_root(&self) -> Hash;
     /// Returns the next free leaf.
     fn next_leaf(&self) -> usize;
+    /// Returns the number of non-zero leaves. Unlike `next_leaf` this doesn't
+    /// include leaves which have been deleted.
+    fn leaf_count(&self) -> usize;
     /// Returns the given leaf value, the root of the tree and the proof
     fn get_leaf_and_proof(&self, leaf: usize) -> (Hash, Hash, Proof);
     /// Returns the merkle proof and element at the given leaf.
@@ -424,6 +482,10 @@ where
         self.get_data().next_leaf
     }
 
+    fn leaf_count(&self) -> usize {
+        self.get_data().non_zero_count
+    }
+
     fn get_leaf_and_proof(&self, leaf: usize) -> (Hash, Hash, Proof) {
         let tree = self.get_data();
 
@@ -477,6 +539,75 @@ impl TreeVersion<Latest> {
         output
     }
 
+    /// Computes the roots, proofs and leaf indices `append_many` would return
+    /// without modifying the tree. The lock is only held while taking a
+    /// snapshot of the tree.
+    #[must_use]
+    pub fn simulate_append_many(&self, identities: &[Hash]) -> Vec<(Hash, Proof, usize)> {
+        let (mut tree, next_leaf) = self.snapshot();
+
+        let mut output = Vec::with_capacity(identities.len());
+
+        for (idx, identity) in identities.iter().enumerate() {
+            let leaf_index = next_leaf + idx;
+
+            tree = tree.update(leaf_index, identity);
+
+            output.push((tree.root(), tree.proof(leaf_index), leaf_index));
+        }
+
+        output
+    }
+
+    /// Same as `simulate_append_many`, but only returns the entries of the
+    /// identities at the given positions in `identities`. Proofs of all other
+    /// identities are never computed.
+    #[must_use]
+    pub fn simulate_append_with_proofs_for(
+        &self,
+        identities: &[Hash],
+        positions: &[usize],
+    ) -> Vec<(Hash, Proof, usize)> {
+        let positions: HashSet<usize> = positions.iter().copied().collect();
+        let (mut tree, next_leaf) = self.snapshot();
+
+        let mut output = Vec::with_capacity(positions.len());
+
+        for (idx, identity) in identities.iter().enumerate() {
+            let leaf_index = next_leaf + idx;
+
+            tree = tree.update(leaf_index, identity);
+
+            if positions.contains(&idx) {
+                output.push((tree.root(), tree.proof(leaf_index), leaf_index));
+            }
+        }
+
+        output
+    }
+
+    /// Clones the tree and its next free leaf, the lock is only held while
+    /// doing so.
+    fn snapshot(&self) -> (PoseidonTree<Derived>, usize) {
+        let data = self.get_data();
+        (data.tree.clone(), data.next_leaf)
+    }
+
+    /// Returns the root after each deletion of `delete_many`, without
+    /// deleting anything from the tree.
+    #[must_use]
+    pub fn simulate_delete_many(&self, leaf_indices: &[usize]) -> Vec<Hash> {
+        let (mut tree, _) = self.snapshot();
+
+        leaf_indices
+            .iter()
+            .map(|leaf_index| {
+                tree = tree.update(*leaf_index, &Hash::ZERO);
+                tree.root()
+            })
+            .collect()
+    }
+
     // pub fn append(&self, identity: Hash)
 
     /// Deletes many identities from the tree, returns a list with the root
@@ -519,6 +650,11 @@ where
 /// only allow peeking and applying updates from the successor.
 pub trait TreeWithNextVersion {
     fn peek_next_updates(&self, maximum_update_count: usize) -> Vec<AppliedTreeUpdate>;
+    fn peek_next_updates_of_type(
+        &self,
+        maximum_update_count: usize,
+        batch_type: BatchType,
+    ) -> Vec<AppliedTreeUpdate>;
     fn apply_updates_up_to(&self, root: Hash) -> usize;
 }
 
@@ -531,6 +667,15 @@ where
         self.get_data().peek_next_updates(maximum_update_count)
     }
 
+    fn peek_next_updates_of_type(
+        &self,
+        maximum_update_count: usize,
+        batch_type: BatchType,
+    ) -> Vec<AppliedTreeUpdate> {
+        self.get_data()
+            .peek_next_updates_of_type(maximum_update_count, batch_type)
+    }
+
     fn apply_updates_up_to(&self, root: Hash) -> usize {
         self.get_data().apply_updates_up_to(root)
     }
@@ -596,22 +741,98 @@ impl TreeState {
         &self.mined
     }
 
-    #[must_use]
-    pub fn get_proof_for(&self, item: &TreeItem) -> (Field, InclusionProof) {
-        let (leaf, root, proof) = match item.status {
-            ProcessedStatus::Pending => self.latest.get_leaf_and_proof(item.leaf_index),
-            ProcessedStatus::Processed => self.processed.get_leaf_and_proof(item.leaf_index),
-            ProcessedStatus::Mined => self.mined.get_leaf_and_proof(item.leaf_index),
+    /// Drops every update after `root` from the batching and latest trees, as
+    /// if the identities inserted or deleted after it were never picked up.
+    /// `root` must be the root of the processed tree or of an update still
+    /// pending in the batching tree. Both trees are left untouched otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `root` is not one of those roots.
+    pub fn rewind_batching_to(&self, root: Hash) -> anyhow::Result<()> {
+        // Locks are taken in version order, like when applying updates
+        let processed = self.processed.get_data();
+        let mut batching = self.batching.get_data();
+        let mut latest = self.latest.get_data();
+
+        let kept_updates = if processed.tree.root() == root {
+            0
+        } else {
+            batching
+                .metadata
+                .diff
+                .iter()
+                .position(|update| update.result.root() == root)
+                .map(|index| index + 1)
+                .ok_or_else(|| anyhow::anyhow!("Root {root:#x} is not in the batching tree"))?
         };
 
-        let proof = InclusionProof {
-            status: item.status.into(),
-            root: Some(root),
-            proof: Some(proof),
-            message: None,
-        };
+        batching.metadata.diff.truncate(kept_updates);
+
+        let mut tree = processed.tree.clone();
+        let mut next_leaf = processed.next_leaf;
+        let mut non_zero_count = processed.non_zero_count;
+        for update in &batching.metadata.diff {
+            let previous = tree.get_leaf(update.update.leaf_index);
+            match (previous == Hash::ZERO, update.update.element == Hash::ZERO) {
+                (true, false) => non_zero_count += 1,
+                (false, true) => non_zero_count = non_zero_count.saturating_sub(1),
+                _ => {}
+            }
+            if update.update.element != Hash::ZERO {
+                next_leaf = update.update.leaf_index + 1;
+            }
+            tree = update.result.clone();
+        }
+
+        for data in [&mut *batching, &mut *latest] {
+            data.tree = tree.clone();
+            data.next_leaf = next_leaf;
+            data.non_zero_count = non_zero_count;
+        }
+        latest.metadata.diff.clear();
 
-        (leaf, proof)
+        Ok(())
+    }
+
+    /// Returns the number of identities in the latest tree, excluding deleted
+    /// ones.
+    #[must_use]
+    pub fn leaf_count(&self) -> usize {
+        self.latest.leaf_count()
+    }
+
+    /// Returns the inclusion proof of the commitment at the item's leaf index
+    /// from the most settled tree version containing it, checking the mined,
+    /// processed, batching and latest trees in that order.
+    ///
+    /// The status of the item in the database can be ahead of the tree
+    /// versions, e.g. an identity can be marked as processed before the
+    /// processed tree caught up. The tree version the proof is served from
+    /// decides the status of the proof instead.
+    #[must_use]
+    pub fn get_proof_for(&self, item: &TreeItem, commitment: &Hash) -> Option<InclusionProof> {
+        let versions: [(&dyn TreeVersionReadOps, ProcessedStatus); 4] = [
+            (&self.mined, ProcessedStatus::Mined),
+            (&self.processed, ProcessedStatus::Processed),
+            (&self.batching, ProcessedStatus::Pending),
+            (&self.latest, ProcessedStatus::Pending),
+        ];
+
+        versions.into_iter().find_map(|(version, status)| {
+            if item.leaf_index >= version.next_leaf() {
+                return None;
+            }
+
+            let (leaf, root, proof) = version.get_leaf_and_proof(item.leaf_index);
+
+            (leaf == *commitment).then(|| InclusionProof {
+                status: status.into(),
+                root: Some(root),
+                proof: Some(proof),
+                message: None,
+            })
+        })
     }
 }
 
@@ -659,9 +880,14 @@ impl CanonicalTreeBuilder {
             flatten_threshold: flattening_threshold,
             count_since_last_flatten: 0,
         };
+        let non_zero_count = initial_leaves_in_dense
+            .iter()
+            .filter(|leaf| **leaf != Hash::ZERO)
+            .count();
         let mut builder = Self(TreeVersionData {
             tree,
             next_leaf: initial_leaves_in_dense_count,
+            non_zero_count,
             metadata,
             next: None,
         });
@@ -711,9 +937,13 @@ impl CanonicalTreeBuilder {
             count_since_last_flatten: 0,
         };
         let next_leaf = last_index.map(|v| v + 1).unwrap_or(0);
+        let non_zero_count = (0..next_leaf)
+            .filter(|leaf_index| tree.get_leaf(*leaf_index) != Hash::ZERO)
+            .count();
         let mut builder = Self(TreeVersionData {
             tree,
             next_leaf,
+            non_zero_count,
             metadata,
             next: None,
         });
@@ -742,10 +972,17 @@ impl CanonicalTreeBuilder {
     /// Seals this version and returns a builder for the next version.
     #[must_use]
     pub fn seal(self) -> (TreeVersion<Canonical>, DerivedTreeBuilder<Canonical>) {
+        // Rooted at the sealed tree, without any pending updates of its own
         let next_tree = self.0.tree.derived();
         let next_leaf = self.0.next_leaf;
+        let non_zero_count = self.0.non_zero_count;
         let sealed = TreeVersion(Arc::new(Mutex::new(self.0)));
-        let next = DerivedTreeBuilder::<Canonical>::new(next_tree, next_leaf, sealed.clone());
+        let next = DerivedTreeBuilder::<Canonical>::new(
+            next_tree,
+            next_leaf,
+            non_zero_count,
+            sealed.clone(),
+        );
         (sealed, next)
     }
 }
@@ -762,6 +999,7 @@ impl<P: Version> DerivedTreeBuilder<P> {
     const fn new<Prev: Version>(
         tree: PoseidonTree<lazy_merkle_tree::Derived>,
         next_leaf: usize,
+        non_zero_count: usize,
         prev: TreeVersion<Prev>,
     ) -> DerivedTreeBuilder<Prev> {
         let metadata = DerivedTreeMetadata { diff: vec![] };
@@ -770,6 +1008,7 @@ impl<P: Version> DerivedTreeBuilder<P> {
             current: TreeVersionData {
                 tree,
                 next_leaf,
+                non_zero_count,
                 metadata,
                 next: None,
             },
@@ -788,8 +1027,9 @@ impl<P: Version> DerivedTreeBuilder<P> {
     ) -> (TreeVersion<Intermediate>, DerivedTreeBuilder<Intermediate>) {
         let next_tree = self.current.tree.clone();
         let next_leaf = self.current.next_leaf;
+        let non_zero_count = self.current.non_zero_count;
         let sealed = TreeVersion(Arc::new(Mutex::new(self.current)));
-        let next = Self::new(next_tree, next_leaf, sealed.clone());
+        let next = Self::new(next_tree, next_leaf, non_zero_count, sealed.clone());
         self.prev.get_data().next = Some(sealed.as_derived());
         (sealed, next)
     }
@@ -806,7 +1046,20 @@ impl<P: Version> DerivedTreeBuilder<P> {
 #[cfg(test)]
 mod tests {
 
-    use super::{CanonicalTreeBuilder, Hash, TreeWithNextVersion};
+    use std::collections::HashSet;
+    use std::time::Instant;
+
+    use semaphore::merkle_tree::Hasher;
+    use semaphore::poseidon_tree::PoseidonHash;
+
+    use super::{
+        CanonicalTreeBuilder, Hash, InclusionProof, ProcessedStatus, TreeItem, TreeState,
+        TreeVersionReadOps, TreeWithNextVersion,
+    };
+    use crate::config::default;
+    use crate::database::methods::MAX_UNPROCESSED_FETCH_COUNT;
+    use crate::server::data::InclusionProofResponse;
+    use crate::utils::batch_type::BatchType;
 
     #[test]
     fn test_peek_next_updates() {
@@ -855,4 +1108,369 @@ mod tests {
 
         assert_eq!(next_updates.len(), 3);
     }
+
+    #[test]
+    fn test_peek_next_updates_of_type() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let (canonical_tree, processed_builder) = CanonicalTreeBuilder::new(
+            10,
+            10,
+            0,
+            Hash::ZERO,
+            &[],
+            temp_dir.path().join("testfile").to_str().unwrap(),
+        )
+        .seal();
+        let processed_tree = processed_builder.seal();
+
+        // Insertions and deletions interleaved
+        let first_insertions = processed_tree.append_many(&[Hash::from(1), Hash::from(2)]);
+        let first_deletions = processed_tree.delete_many(&[0]);
+        let _ = processed_tree.append_many(&[Hash::from(3), Hash::from(4), Hash::from(5)]);
+        let _ = processed_tree.delete_many(&[1, 2]);
+
+        let insertions = canonical_tree.peek_next_updates_of_type(10, BatchType::Insertion);
+        assert_eq!(insertions.len(), 2);
+        assert!(insertions.iter().all(|u| u.update.element != Hash::ZERO));
+        assert!(canonical_tree
+            .peek_next_updates_of_type(10, BatchType::Deletion)
+            .is_empty());
+
+        canonical_tree.apply_updates_up_to(first_insertions.last().unwrap().0);
+
+        let deletions = canonical_tree.peek_next_updates_of_type(10, BatchType::Deletion);
+        assert_eq!(deletions.len(), 1);
+        assert_eq!(deletions[0].update.leaf_index, 0);
+        assert!(canonical_tree
+            .peek_next_updates_of_type(10, BatchType::Insertion)
+            .is_empty());
+
+        canonical_tree.apply_updates_up_to(first_deletions.last().unwrap().0);
+
+        let insertions = canonical_tree.peek_next_updates_of_type(2, BatchType::Insertion);
+        assert_eq!(
+            insertions
+                .iter()
+                .map(|u| u.update.element)
+                .collect::<Vec<_>>(),
+            vec![Hash::from(3), Hash::from(4)]
+        );
+        assert_eq!(
+            canonical_tree
+                .peek_next_updates_of_type(10, BatchType::Insertion)
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_leaf_count() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let (canonical_tree, processed_builder) = CanonicalTreeBuilder::new(
+            10,
+            10,
+            0,
+            Hash::ZERO,
+            &[Hash::from(1), Hash::from(2)],
+            temp_dir.path().join("testfile").to_str().unwrap(),
+        )
+        .seal();
+        let processed_tree = processed_builder.seal();
+        assert_eq!(processed_tree.leaf_count(), 2);
+
+        let insertion_updates =
+            processed_tree.append_many(&[Hash::from(3), Hash::from(4), Hash::from(5)]);
+        let deletion_updates = processed_tree.delete_many(&[0, 3]);
+
+        assert_eq!(processed_tree.next_leaf(), 5);
+        assert_eq!(processed_tree.leaf_count(), 3);
+
+        // Deleting an already deleted leaf doesn't change the count
+        let _ = processed_tree.delete_many(&[0]);
+        assert_eq!(processed_tree.leaf_count(), 3);
+
+        canonical_tree.apply_updates_up_to(insertion_updates.last().unwrap().0);
+        assert_eq!(canonical_tree.leaf_count(), 5);
+
+        canonical_tree.apply_updates_up_to(deletion_updates.last().unwrap().0);
+        assert_eq!(canonical_tree.leaf_count(), 3);
+    }
+
+    #[test]
+    fn inclusion_proofs_can_be_stored_in_sets() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let (canonical_tree, _) = CanonicalTreeBuilder::new(
+            10,
+            10,
+            0,
+            Hash::ZERO,
+            &[Hash::from(1), Hash::from(2)],
+            temp_dir.path().join("testfile").to_str().unwrap(),
+        )
+        .seal();
+
+        let inclusion_proof = |leaf_index| {
+            let (root, proof) = canonical_tree.get_proof(leaf_index);
+
+            InclusionProof {
+                status: ProcessedStatus::Mined.into(),
+                root: Some(root),
+                proof: Some(proof),
+                message: None,
+            }
+        };
+
+        assert_eq!(inclusion_proof(0), inclusion_proof(0));
+        assert_ne!(inclusion_proof(0), inclusion_proof(1));
+
+        let proofs: HashSet<_> = [inclusion_proof(0), inclusion_proof(0), inclusion_proof(1)]
+            .into_iter()
+            .collect();
+
+        assert_eq!(proofs.len(), 2);
+        assert!(proofs.contains(&inclusion_proof(1)));
+    }
+
+    #[test]
+    fn simulate_append_with_proofs_for_matches_per_item_simulation() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let (_canonical, latest_builder) = CanonicalTreeBuilder::new(
+            20,
+            10,
+            0,
+            Hash::ZERO,
+            &[Hash::from(1)],
+            temp_dir.path().join("testfile").to_str().unwrap(),
+        )
+        .seal();
+        let latest = latest_builder.seal();
+
+        // A full queue of unprocessed identities ahead of the simulated one
+        let queued = MAX_UNPROCESSED_FETCH_COUNT as usize;
+        let identities: Vec<_> = (2..queued as u64 + 3).map(Hash::from).collect();
+
+        let start = Instant::now();
+        let selected = latest.simulate_append_with_proofs_for(&identities, &[0, queued]);
+        let elapsed = start.elapsed();
+
+        // Simulated inserts must be answered well within the default admin
+        // request timeout
+        assert!(
+            elapsed < default::serve_timeout() / 3,
+            "Simulating {} appends took {elapsed:?}",
+            identities.len()
+        );
+
+        let simulated = latest.simulate_append_many(&identities);
+        assert_eq!(
+            selected,
+            vec![simulated[0].clone(), simulated[queued].clone()]
+        );
+
+        // Nothing was appended
+        assert_eq!(latest.next_leaf(), 1);
+        assert_ne!(latest.get_root(), selected[1].0);
+    }
+
+    #[test]
+    fn simulate_delete_many_matches_delete_many() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let identities: Vec<_> = (1..5).map(Hash::from).collect();
+        let (_canonical, latest_builder) = CanonicalTreeBuilder::new(
+            10,
+            10,
+            0,
+            Hash::ZERO,
+            &identities,
+            temp_dir.path().join("testfile").to_str().unwrap(),
+        )
+        .seal();
+        let latest = latest_builder.seal();
+        let initial_root = latest.get_root();
+
+        let simulated = latest.simulate_delete_many(&[1, 3]);
+
+        // Nothing was deleted
+        assert_eq!(latest.get_root(), initial_root);
+
+        let deleted: Vec<_> = latest
+            .delete_many(&[1, 3])
+            .into_iter()
+            .map(|(root, _)| root)
+            .collect();
+        assert_eq!(simulated, deleted);
+    }
+
+    fn tree_state(path: &str) -> TreeState {
+        let (mined, processed_builder) =
+            CanonicalTreeBuilder::new(10, 10, 0, Hash::ZERO, &[Hash::from(1)], path).seal();
+        let (processed, batching_builder) = processed_builder.seal_and_continue();
+        let (batching, latest_builder) = batching_builder.seal_and_continue();
+        let latest = latest_builder.seal();
+
+        TreeState::new(mined, processed, batching, latest)
+    }
+
+    #[test]
+    fn rewind_batching_to_drops_later_updates() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let tree_state = tree_state(temp_dir.path().join("testfile").to_str().unwrap());
+        let initial_root = tree_state.processed_tree().get_root();
+
+        let updates =
+            tree_state
+                .latest_tree()
+                .append_many(&[Hash::from(2), Hash::from(3), Hash::from(4)]);
+        tree_state.batching_tree().apply_updates_up_to(updates[1].0);
+
+        tree_state.rewind_batching_to(updates[0].0).unwrap();
+
+        for version in [
+            tree_state.batching_tree() as &dyn TreeVersionReadOps,
+            tree_state.latest_tree(),
+        ] {
+            assert_eq!(version.get_root(), updates[0].0);
+            assert_eq!(version.next_leaf(), 2);
+            assert_eq!(version.leaf_count(), 2);
+        }
+        assert!(tree_state.batching_tree().peek_next_updates(10).is_empty());
+
+        // The rewound leaves can be appended again
+        let updates = tree_state.latest_tree().append_many(&[Hash::from(5)]);
+        assert_eq!(updates[0].2, 2);
+        assert_eq!(tree_state.batching_tree().peek_next_updates(10).len(), 1);
+
+        tree_state.rewind_batching_to(initial_root).unwrap();
+        assert_eq!(tree_state.latest_tree().get_root(), initial_root);
+        assert_eq!(tree_state.latest_tree().next_leaf(), 1);
+
+        // Unknown roots are refused
+        assert!(tree_state.rewind_batching_to(updates[0].0).is_err());
+        assert_eq!(tree_state.latest_tree().get_root(), initial_root);
+    }
+
+    #[test]
+    fn test_get_proof_for_prefers_most_settled_version() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let tree_state = tree_state(temp_dir.path().join("testfile").to_str().unwrap());
+
+        let updates = tree_state
+            .latest_tree()
+            .append_many(&[Hash::from(2), Hash::from(3)]);
+
+        // Pick the first identity for a batch, leaving the second one in the
+        // latest tree only
+        let batching_root = updates[0].0;
+        tree_state
+            .batching_tree()
+            .apply_updates_up_to(batching_root);
+
+        let proof_for = |leaf_index, status, commitment| {
+            tree_state.get_proof_for(&TreeItem { status, leaf_index }, &Hash::from(commitment))
+        };
+
+        let mined = proof_for(0, ProcessedStatus::Mined, 1).unwrap();
+        assert_eq!(mined.status, ProcessedStatus::Mined.into());
+        assert_eq!(mined.root, Some(tree_state.mined_tree().get_root()));
+
+        // Served from the batching tree, even though the database claims the
+        // identity was already processed
+        let batched = proof_for(1, ProcessedStatus::Processed, 2).unwrap();
+        assert_eq!(batched.status, ProcessedStatus::Pending.into());
+        assert_eq!(batched.root, Some(batching_root));
+        assert_eq!(batched.proof.unwrap().root(Hash::from(2)), batching_root);
+
+        let latest = proof_for(2, ProcessedStatus::Pending, 3).unwrap();
+        assert_eq!(latest.status, ProcessedStatus::Pending.into());
+        assert_eq!(latest.root, Some(tree_state.latest_tree().get_root()));
+
+        assert_eq!(proof_for(1, ProcessedStatus::Pending, 3), None);
+        assert_eq!(proof_for(3, ProcessedStatus::Pending, 4), None);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_proofs_verify_while_appending() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let tree_state = tree_state(temp_dir.path().join("testfile").to_str().unwrap());
+
+        // Leaf `i` holds the commitment `i + 1`
+        let writer = tokio::task::spawn_blocking({
+            let tree_state = tree_state.clone();
+            move || {
+                for commitment in 2..=200 {
+                    tree_state
+                        .latest_tree()
+                        .append_many(&[Hash::from(commitment)]);
+                }
+            }
+        });
+
+        let readers = (0..32).map(|_| {
+            let tree_state = tree_state.clone();
+            tokio::task::spawn_blocking(move || {
+                for leaf_index in 0..tree_state.latest_tree().next_leaf() {
+                    let commitment = Hash::from(leaf_index + 1);
+                    let proof = tree_state
+                        .get_proof_for(
+                            &TreeItem {
+                                status: ProcessedStatus::Pending,
+                                leaf_index,
+                            },
+                            &commitment,
+                        )
+                        .expect("appended leaves have a proof");
+
+                    assert_eq!(proof.proof.unwrap().root(commitment), proof.root.unwrap());
+                }
+            })
+        });
+
+        for reader in futures::future::join_all(readers).await {
+            reader.unwrap();
+        }
+        writer.await.unwrap();
+
+        assert_eq!(tree_state.latest_tree().next_leaf(), 200);
+    }
+
+    #[test]
+    fn served_proofs_verify_with_wire_types() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let tree_state = tree_state(temp_dir.path().join("testfile").to_str().unwrap());
+
+        tree_state
+            .latest_tree()
+            .append_many(&[Hash::from(2), Hash::from(3)]);
+
+        let proof = tree_state
+            .get_proof_for(
+                &TreeItem {
+                    status: ProcessedStatus::Pending,
+                    leaf_index: 2,
+                },
+                &Hash::from(3),
+            )
+            .unwrap();
+        let json = serde_json::to_string(&InclusionProofResponse::from(proof)).unwrap();
+
+        // What a client without the sequencer's dependencies sees
+        let response: signup_sequencer_types::InclusionProofResponse =
+            serde_json::from_str(&json).unwrap();
+        let proof = response.proof.unwrap();
+
+        assert_eq!(proof.leaf_index(), 2);
+        assert!(proof.verify(
+            Hash::from(3),
+            response.root.unwrap(),
+            PoseidonHash::hash_node
+        ));
+        assert!(!proof.verify(
+            Hash::from(2),
+            response.root.unwrap(),
+            PoseidonHash::hash_node
+        ));
+    }
 }
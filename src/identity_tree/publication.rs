@@ -0,0 +1,258 @@
+//! Publication of the latest root for consumers which can't query the
+//! sequencer or a chain, e.g. in offchain mode.
+//!
+//! The document is signed, readers should use [`read_published_root`] which
+//! only returns documents with a valid signature by the expected signer.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use ethers::signers::{LocalWallet, Signer};
+use ethers::types::{Address, Bytes};
+use ethers::utils::hash_message;
+use serde::{Deserialize, Serialize};
+use signup_sequencer_types::signature::{published_root_message, verify_signature};
+use tokio::io::AsyncWriteExt;
+
+use super::{Hash, ProcessedStatus};
+use crate::config::RootPublicationConfig;
+
+/// The published document.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublishedRoot {
+    pub root: Hash,
+    pub status: ProcessedStatus,
+    /// The next free leaf of the tree with this root
+    pub tree_size: usize,
+    pub timestamp: DateTime<Utc>,
+    /// The address of the key the document was signed with
+    pub signer: Address,
+    /// EIP-191 signature of [`PublishedRoot::signed_message`]
+    pub signature: Bytes,
+}
+
+impl PublishedRoot {
+    /// The message covered by the signature, every other field is part of it.
+    #[must_use]
+    pub fn signed_message(&self) -> String {
+        published_root_message(self.root, self.status, self.tree_size, self.timestamp)
+    }
+
+    /// Checks that the document was signed by its signer.
+    pub fn verify(&self) -> anyhow::Result<()> {
+        verify_signature(
+            self.signed_message().as_bytes(),
+            &self.signature,
+            &self.signer.0,
+        )
+        .context("Invalid signature")
+    }
+}
+
+/// Writes the signed document to the configured file, replacing it
+/// atomically so that readers never see a partial write.
+pub struct RootPublisher {
+    path: PathBuf,
+    wallet: LocalWallet,
+}
+
+impl RootPublisher {
+    pub fn new(config: &RootPublicationConfig) -> anyhow::Result<Self> {
+        let wallet = LocalWallet::from_bytes(config.signing_key.expose().as_bytes())
+            .context("Invalid root publication signing key")?;
+
+        Ok(Self {
+            path: config.path.clone(),
+            wallet,
+        })
+    }
+
+    /// The address readers should expect as the signer.
+    #[must_use]
+    pub fn signer(&self) -> Address {
+        self.wallet.address()
+    }
+
+    pub async fn publish(
+        &self,
+        root: Hash,
+        status: ProcessedStatus,
+        tree_size: usize,
+    ) -> anyhow::Result<PublishedRoot> {
+        let timestamp = Utc::now();
+        let message = published_root_message(root, status, tree_size, timestamp);
+        let signature = self.wallet.sign_hash(hash_message(message))?;
+
+        let document = PublishedRoot {
+            root,
+            status,
+            tree_size,
+            timestamp,
+            signer: self.wallet.address(),
+            signature: signature.to_vec().into(),
+        };
+
+        // The contents and the rename are synced, so that a crash can't leave an
+        // empty or missing document behind
+        let tmp_path = self.path.with_extension("tmp");
+        write_synced(&tmp_path, &serde_json::to_vec_pretty(&document)?)
+            .await
+            .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+        tokio::fs::rename(&tmp_path, &self.path)
+            .await
+            .with_context(|| format!("Failed to replace {}", self.path.display()))?;
+        sync_parent(&self.path)
+            .await
+            .with_context(|| format!("Failed to sync the directory of {}", self.path.display()))?;
+
+        Ok(document)
+    }
+}
+
+async fn write_synced(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut file = tokio::fs::File::create(path).await?;
+    file.write_all(contents).await?;
+    file.sync_all().await
+}
+
+async fn sync_parent(path: &Path) -> std::io::Result<()> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    tokio::fs::File::open(parent).await?.sync_all().await
+}
+
+/// Reads a published root and verifies that it was signed by
+/// `expected_signer`. Unsigned documents and documents signed by any other key
+/// are rejected.
+pub async fn read_published_root(
+    path: impl AsRef<Path>,
+    expected_signer: Address,
+) -> anyhow::Result<PublishedRoot> {
+    let path = path.as_ref();
+    let contents = tokio::fs::read(path)
+        .await
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let document: PublishedRoot = serde_json::from_slice(&contents)?;
+
+    anyhow::ensure!(
+        document.signer == expected_signer,
+        "Root was published by {:?} instead of {expected_signer:?}",
+        document.signer
+    );
+
+    document.verify()?;
+
+    Ok(document)
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::H256;
+
+    use super::*;
+
+    fn publisher(path: PathBuf) -> RootPublisher {
+        publisher_with_key(path, 1)
+    }
+
+    fn publisher_with_key(path: PathBuf, key: u64) -> RootPublisher {
+        RootPublisher::new(&RootPublicationConfig {
+            path,
+            signing_key: H256::from_low_u64_be(key).into(),
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn published_root_updates_and_verifies() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let path = temp_dir.path().join("root.json");
+        let publisher = publisher(path.clone());
+
+        publisher
+            .publish(Hash::from(1), ProcessedStatus::Processed, 3)
+            .await?;
+
+        let document = read_published_root(&path, publisher.signer()).await?;
+        assert_eq!(document.root, Hash::from(1));
+        assert_eq!(document.status, ProcessedStatus::Processed);
+        assert_eq!(document.tree_size, 3);
+
+        let published = publisher
+            .publish(Hash::from(2), ProcessedStatus::Mined, 6)
+            .await?;
+
+        let document = read_published_root(&path, publisher.signer()).await?;
+        assert_eq!(document, published);
+        assert!(!path.with_extension("tmp").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn tampered_root_is_rejected() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let path = temp_dir.path().join("root.json");
+        let publisher = publisher(path.clone());
+
+        let mut document = publisher
+            .publish(Hash::from(1), ProcessedStatus::Mined, 3)
+            .await?;
+        document.tree_size = 4;
+        tokio::fs::write(&path, serde_json::to_vec(&document)?).await?;
+
+        assert!(read_published_root(&path, publisher.signer())
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unsigned_root_is_rejected() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let path = temp_dir.path().join("root.json");
+        let publisher = publisher(path.clone());
+
+        let document = publisher
+            .publish(Hash::from(1), ProcessedStatus::Mined, 3)
+            .await?;
+        let mut unsigned = serde_json::to_value(&document)?;
+        unsigned.as_object_mut().unwrap().remove("signature");
+        tokio::fs::write(&path, serde_json::to_vec(&unsigned)?).await?;
+
+        assert!(read_published_root(&path, publisher.signer())
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn root_of_another_signer_is_rejected() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let path = temp_dir.path().join("root.json");
+        let expected = publisher(path.clone());
+        let other = publisher_with_key(path.clone(), 2);
+
+        // Validly signed, but by the wrong key
+        other
+            .publish(Hash::from(1), ProcessedStatus::Mined, 3)
+            .await?;
+        assert!(read_published_root(&path, other.signer()).await.is_ok());
+        assert!(read_published_root(&path, expected.signer()).await.is_err());
+
+        // Claiming the expected signer doesn't help without its signature
+        let mut document = read_published_root(&path, other.signer()).await?;
+        document.signer = expected.signer();
+        tokio::fs::write(&path, serde_json::to_vec(&document)?).await?;
+        assert!(read_published_root(&path, expected.signer()).await.is_err());
+
+        Ok(())
+    }
+}
@@ -1,17 +1,24 @@
 #![doc = include_str!("../Readme.md")]
 #![warn(clippy::cargo)]
 #![allow(clippy::multiple_crate_versions, clippy::too_many_arguments)]
+// The trees are behind blocking locks, see `App::read_tree`
+#![deny(clippy::await_holding_lock)]
 
 pub mod app;
 pub mod config;
 mod contracts;
-mod database;
+pub mod database;
 mod ethereum;
 
 mod identity;
 pub mod identity_tree;
+pub mod metrics;
 pub mod prover;
 pub mod server;
 pub mod shutdown;
 pub mod task_monitor;
 pub mod utils;
+
+pub use identity::root_oracle::RootOracleResponse;
+pub use identity::validator::MODULUS;
+pub use signup_sequencer_types as types;
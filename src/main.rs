@@ -8,9 +8,13 @@
 
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use semaphore::Field;
 use signup_sequencer::app::App;
-use signup_sequencer::config::{load_config, ServiceConfig};
+use signup_sequencer::config::{load_config, Config, ServiceConfig};
+use signup_sequencer::database::Database;
+use signup_sequencer::identity_tree::compaction::compact_tree_cache;
+use signup_sequencer::metrics::Metrics;
 use signup_sequencer::server;
 use signup_sequencer::shutdown::Shutdown;
 use signup_sequencer::task_monitor::TaskMonitor;
@@ -22,6 +26,22 @@ use telemetry_batteries::tracing::TracingShutdownHandle;
 struct Args {
     /// Path to the optional config file
     config: Option<PathBuf>,
+
+    /// Rewind the tree to this root on startup, overrides
+    /// `tree.recover_to_root`
+    #[clap(long)]
+    recover_to_root: Option<Field>,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+enum Command {
+    /// Rebuild the tree cache file from the mined identities in the database,
+    /// dropping the deleted leaves it still holds. The sequencer must be
+    /// stopped while it runs.
+    CompactTreeCache,
 }
 
 #[tokio::main]
@@ -33,10 +53,18 @@ async fn main() -> eyre::Result<()> {
 }
 
 async fn sequencer_app(args: Args) -> anyhow::Result<()> {
-    let config = load_config(args.config.as_deref())?;
+    let mut config = load_config(args.config.as_deref())?;
+
+    if let Some(root) = args.recover_to_root {
+        config.tree.recover_to_root = Some(root);
+    }
 
     let _tracing_shutdown_handle = init_telemetry(&config.service)?;
 
+    if let Some(Command::CompactTreeCache) = args.command {
+        return compact_cache(&config).await;
+    }
+
     let shutdown = Shutdown::spawn(config.app.shutdown_timeout, config.app.shutdown_delay);
 
     let version = env!("GIT_VERSION");
@@ -62,6 +90,21 @@ async fn sequencer_app(args: Args) -> anyhow::Result<()> {
     Ok(())
 }
 
+async fn compact_cache(config: &Config) -> anyhow::Result<()> {
+    let database = Database::new(&config.database, &Metrics::default()).await?;
+    let report = compact_tree_cache(&database, &config.tree).await?;
+
+    println!("Root: {:#x}", report.root);
+    println!("Leaves: {}", report.leaf_count);
+    match report.size_before {
+        Some(size) => println!("Allocated before: {size} bytes"),
+        None => println!("Allocated before: no cache file"),
+    }
+    println!("Allocated after: {} bytes", report.size_after);
+
+    Ok(())
+}
+
 fn init_telemetry(service: &ServiceConfig) -> anyhow::Result<TracingShutdownHandle> {
     if let Some(ref datadog) = service.datadog {
         Ok(DatadogBattery::init(
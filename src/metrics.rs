@@ -0,0 +1,377 @@
+use prometheus::core::Collector;
+use prometheus::{
+    exponential_buckets, linear_buckets, Counter, Gauge, Histogram, HistogramOpts, HistogramVec,
+    IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry, DEFAULT_BUCKETS,
+};
+
+/// The metrics of a single app.
+///
+/// They're registered into the registry the app is built with, the global one
+/// for the binary. Apps sharing a process, e.g. in tests, each need a registry
+/// of their own, registering the same metric twice fails.
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+
+    // App
+    pub(crate) nullifiers_spent: IntCounter,
+    pub(crate) commitments_reinserted: IntCounter,
+    pub(crate) insertion_duration: HistogramVec,
+
+    // API
+    pub(crate) api_requests: Counter,
+    pub(crate) api_response_status: IntCounterVec,
+    pub(crate) api_latency: Histogram,
+    pub(crate) v1_requests: IntCounterVec,
+
+    // Database
+    pub(crate) database_version: IntGaugeVec,
+    pub(crate) open_transactions: IntGaugeVec,
+
+    // Ethereum
+    pub(crate) eth_rpc_requests: IntCounterVec,
+    pub(crate) eth_rpc_latency: Histogram,
+    pub(crate) eth_tx_count: IntCounterVec,
+    pub(crate) eth_rpc_errors: IntCounterVec,
+    pub(crate) scanner_window_size: IntGaugeVec,
+
+    // Provers
+    pub(crate) total_proving_time: HistogramVec,
+    pub(crate) prover_proving_time: HistogramVec,
+    pub(crate) prover_in_flight: IntGaugeVec,
+
+    // Identities
+    pub(crate) proof_verification_queue_wait: Histogram,
+    pub(crate) proof_verification: Histogram,
+    pub(crate) root_oracle_lookups: IntCounterVec,
+    pub(crate) insert_to_provable: Histogram,
+    pub(crate) sla_breached: IntGauge,
+    pub(crate) tree_sync_gaps: IntCounter,
+
+    // Tasks
+    pub(crate) pending_identities: Gauge,
+    pub(crate) unprocessed_identities: Gauge,
+    pub(crate) tree_leaf_count: Gauge,
+    pub(crate) submitted_batch_sizes: Histogram,
+    pub(crate) batches_awaiting_approval: IntGauge,
+    pub(crate) recovered_deletion_intents: IntCounter,
+    pub(crate) recovered_deletions: IntCounterVec,
+    pub(crate) canary_ok: IntGauge,
+    pub(crate) relayer_balance: Gauge,
+    pub(crate) batching_paused: IntGauge,
+}
+
+impl Metrics {
+    /// Registers the metrics into `registry`, their names prefixed with
+    /// `namespace` if given.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if one of the metrics is already registered.
+    pub fn new(registry: Registry, namespace: Option<&str>) -> prometheus::Result<Self> {
+        let namespace = namespace.unwrap_or_default();
+        let opts = |name: &str, help: &str| Opts::new(name, help).namespace(namespace);
+        let histogram_opts = |name: &str, help: &str, buckets: Vec<f64>| {
+            HistogramOpts::new(name, help)
+                .namespace(namespace)
+                .buckets(buckets)
+        };
+        let register = |collector: Box<dyn Collector>| registry.register(collector);
+
+        let nullifiers_spent = IntCounter::with_opts(opts(
+            "nullifiers_spent_total",
+            "Nullifier hashes spent by verifying a proof through the v2 endpoint",
+        ))?;
+        let commitments_reinserted = IntCounter::with_opts(opts(
+            "commitments_reinserted_total",
+            "Deleted commitments queued for insertion again",
+        ))?;
+        let insertion_duration = HistogramVec::new(
+            histogram_opts(
+                "identity_insertion_processing_duration_seconds",
+                "Time from receiving an insertion request until the identity is queued in the \
+                 database, by outcome",
+                vec![0.001, 0.002, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0],
+            ),
+            &["status"],
+        )?;
+
+        let api_requests =
+            Counter::with_opts(opts("api_requests", "Number of requests received."))?;
+        let api_response_status = IntCounterVec::new(
+            opts("api_response_status", "The API responses by status code."),
+            &["status_code"],
+        )?;
+        let api_latency = Histogram::with_opts(histogram_opts(
+            "api_latency_seconds",
+            "The API latency in seconds.",
+            DEFAULT_BUCKETS.to_vec(),
+        ))?;
+        let v1_requests = IntCounterVec::new(
+            opts("v1_requests_total", "Requests to the deprecated v1 API"),
+            &["endpoint"],
+        )?;
+
+        let database_version = IntGaugeVec::new(
+            opts(
+                "sequencer_database_version",
+                "Major version of the connected Postgres server",
+            ),
+            &["major"],
+        )?;
+        let open_transactions = IntGaugeVec::new(
+            opts(
+                "database_open_transactions",
+                "Number of currently open database transactions",
+            ),
+            &["label"],
+        )?;
+
+        let eth_rpc_requests = IntCounterVec::new(
+            opts(
+                "eth_rpc_requests",
+                "Number of Ethereum provider requests made by method.",
+            ),
+            &["method"],
+        )?;
+        let eth_rpc_latency = Histogram::with_opts(histogram_opts(
+            "eth_rpc_latency_seconds",
+            "The Ethereum provider latency in seconds.",
+            DEFAULT_BUCKETS.to_vec(),
+        ))?;
+        let eth_tx_count = IntCounterVec::new(
+            opts("eth_tx_count", "The transaction count by bytes4."),
+            &["bytes4"],
+        )?;
+        let eth_rpc_errors = IntCounterVec::new(
+            opts(
+                "ethereum_rpc_errors_total",
+                "Failed Ethereum provider requests, including retried ones.",
+            ),
+            &["error_type"],
+        )?;
+        let scanner_window_size = IntGaugeVec::new(
+            opts(
+                "scanner_effective_window_size",
+                "The block range currently used by a log scanner",
+            ),
+            &["scanner"],
+        )?;
+
+        let total_proving_time = HistogramVec::new(
+            histogram_opts(
+                "total_proving_time",
+                "The time to generate a proof in seconds. Includes preparing the data for the \
+                 prover",
+                exponential_buckets(0.1, 1.5, 25)?,
+            ),
+            &["prover", "batch_type", "batch_size"],
+        )?;
+        let prover_proving_time = HistogramVec::new(
+            histogram_opts(
+                "prover_proving_time",
+                "Only the time between sending a request and receiving the proof",
+                exponential_buckets(0.1, 1.5, 25)?,
+            ),
+            &["prover", "batch_type", "batch_size"],
+        )?;
+        let prover_in_flight = IntGaugeVec::new(
+            opts(
+                "prover_in_flight",
+                "Number of proofs currently requested from a prover",
+            ),
+            &["url"],
+        )?;
+
+        let proof_verification_queue_wait = Histogram::with_opts(histogram_opts(
+            "proof_verification_queue_wait_seconds",
+            "Time a semaphore proof waits for a verification worker",
+            exponential_buckets(0.0001, 2.0, 20)?,
+        ))?;
+        let proof_verification = Histogram::with_opts(histogram_opts(
+            "proof_verification_seconds",
+            "Time spent verifying a semaphore proof on a worker",
+            exponential_buckets(0.0005, 2.0, 16)?,
+        ))?;
+        let root_oracle_lookups = IntCounterVec::new(
+            opts(
+                "root_oracle_lookups_total",
+                "Lookups of unknown roots at the external root oracle, by result",
+            ),
+            &["result"],
+        )?;
+        let insert_to_provable = Histogram::with_opts(histogram_opts(
+            "insert_to_provable_seconds",
+            "Time from receiving an identity until its inclusion proof is served, in offchain mode",
+            exponential_buckets(0.5, 1.5, 25)?,
+        ))?;
+        let sla_breached = IntGauge::with_opts(opts(
+            "sla_breached",
+            "1 while the p95 insert to provable latency over the window exceeds the SLA",
+        ))?;
+        let tree_sync_gaps = IntCounter::with_opts(opts(
+            "tree_sync_gap_total",
+            "Tree initializations which found a gap in the leaf indexes of the mined identities",
+        ))?;
+
+        let pending_identities = Gauge::with_opts(opts(
+            "pending_identities",
+            "Identities not submitted on-chain",
+        ))?;
+        let unprocessed_identities = Gauge::with_opts(opts(
+            "unprocessed_identities",
+            "Identities not processed by identity committer",
+        ))?;
+        let tree_leaf_count = Gauge::with_opts(opts(
+            "tree_leaf_count",
+            "Identities in the latest tree, excluding deleted ones",
+        ))?;
+        let submitted_batch_sizes = Histogram::with_opts(histogram_opts(
+            "submitted_batch_sizes",
+            "Submitted batch size",
+            linear_buckets(1.0, 1.0, 100)?,
+        ))?;
+        let batches_awaiting_approval = IntGauge::with_opts(opts(
+            "batches_awaiting_approval",
+            "Batches held back from submission until they're approved",
+        ))?;
+        let recovered_deletion_intents = IntCounter::with_opts(opts(
+            "deletion_intents_recovered_total",
+            "Deletion intents left incomplete by an interrupted deletion and recovered",
+        ))?;
+        let recovered_deletions = IntCounterVec::new(
+            opts(
+                "deletion_intents_recovered_deletions_total",
+                "Deletions of recovered intents, by whether they had been applied to the tree or \
+                 were released back to the queue",
+            ),
+            &["outcome"],
+        )?;
+        let canary_ok = IntGauge::with_opts(opts(
+            "canary_ok",
+            "Whether the startup canary identity was processed and its proof verified",
+        ))?;
+        let relayer_balance = Gauge::with_opts(opts(
+            "relayer_balance_wei",
+            "Balance of the account submitting the batches",
+        ))?;
+        let batching_paused = IntGauge::with_opts(opts(
+            "batching_paused",
+            "Whether batch submission is paused due to a low relayer balance",
+        ))?;
+
+        register(Box::new(nullifiers_spent.clone()))?;
+        register(Box::new(commitments_reinserted.clone()))?;
+        register(Box::new(insertion_duration.clone()))?;
+        register(Box::new(api_requests.clone()))?;
+        register(Box::new(api_response_status.clone()))?;
+        register(Box::new(api_latency.clone()))?;
+        register(Box::new(v1_requests.clone()))?;
+        register(Box::new(database_version.clone()))?;
+        register(Box::new(open_transactions.clone()))?;
+        register(Box::new(eth_rpc_requests.clone()))?;
+        register(Box::new(eth_rpc_latency.clone()))?;
+        register(Box::new(eth_tx_count.clone()))?;
+        register(Box::new(eth_rpc_errors.clone()))?;
+        register(Box::new(scanner_window_size.clone()))?;
+        register(Box::new(total_proving_time.clone()))?;
+        register(Box::new(prover_proving_time.clone()))?;
+        register(Box::new(prover_in_flight.clone()))?;
+        register(Box::new(proof_verification_queue_wait.clone()))?;
+        register(Box::new(proof_verification.clone()))?;
+        register(Box::new(root_oracle_lookups.clone()))?;
+        register(Box::new(insert_to_provable.clone()))?;
+        register(Box::new(sla_breached.clone()))?;
+        register(Box::new(tree_sync_gaps.clone()))?;
+        register(Box::new(pending_identities.clone()))?;
+        register(Box::new(unprocessed_identities.clone()))?;
+        register(Box::new(tree_leaf_count.clone()))?;
+        register(Box::new(submitted_batch_sizes.clone()))?;
+        register(Box::new(batches_awaiting_approval.clone()))?;
+        register(Box::new(recovered_deletion_intents.clone()))?;
+        register(Box::new(recovered_deletions.clone()))?;
+        register(Box::new(canary_ok.clone()))?;
+        register(Box::new(relayer_balance.clone()))?;
+        register(Box::new(batching_paused.clone()))?;
+
+        Ok(Self {
+            registry,
+            nullifiers_spent,
+            commitments_reinserted,
+            insertion_duration,
+            api_requests,
+            api_response_status,
+            api_latency,
+            v1_requests,
+            database_version,
+            open_transactions,
+            eth_rpc_requests,
+            eth_rpc_latency,
+            eth_tx_count,
+            eth_rpc_errors,
+            scanner_window_size,
+            total_proving_time,
+            prover_proving_time,
+            prover_in_flight,
+            proof_verification_queue_wait,
+            proof_verification,
+            root_oracle_lookups,
+            insert_to_provable,
+            sla_breached,
+            tree_sync_gaps,
+            pending_identities,
+            unprocessed_identities,
+            tree_leaf_count,
+            submitted_batch_sizes,
+            batches_awaiting_approval,
+            recovered_deletion_intents,
+            recovered_deletions,
+            canary_ok,
+            relayer_balance,
+            batching_paused,
+        })
+    }
+
+    /// The registry the metrics are registered into.
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+}
+
+/// Metrics registered into a registry of their own, for components used
+/// outside of an app, e.g. by tools and tests.
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new(Registry::new(), None).expect("a new registry has no metrics registered")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn namespace_prefixes_metric_names() {
+        let metrics = Metrics::new(Registry::new(), Some("staging")).unwrap();
+        metrics.pending_identities.set(1.0);
+
+        let names: Vec<_> = metrics
+            .registry()
+            .gather()
+            .into_iter()
+            .map(|family| family.get_name().to_string())
+            .collect();
+
+        assert!(names.contains(&"staging_pending_identities".to_string()));
+        assert!(names.iter().all(|name| name.starts_with("staging_")));
+    }
+
+    #[test]
+    fn metrics_are_registered_once_per_registry() {
+        let registry = Registry::new();
+
+        assert!(Metrics::new(registry.clone(), None).is_ok());
+        assert!(Metrics::new(registry.clone(), None).is_err());
+        assert!(Metrics::new(registry, Some("other")).is_ok());
+    }
+}
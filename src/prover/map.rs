@@ -1,9 +1,12 @@
 use std::collections::HashSet;
+use std::ops::RangeInclusive;
 
+use crate::metrics::Metrics;
 use crate::prover::{Prover, ProverConfig, ProverType};
 use crate::utils::min_map::MinMap;
 
-/// A map that contains a prover for each batch size.
+/// A map that contains a prover for each batch size. Ranged provers are
+/// registered for the smallest batch size they take.
 ///
 /// Provides utility methods for getting the appropriate provers
 #[derive(Debug, Default)]
@@ -12,9 +15,20 @@ pub struct ProverMap {
 }
 
 impl ProverMap {
-    /// Get the smallest prover that can handle the given batch size.
-    pub fn get(&self, batch_size: usize) -> Option<&Prover> {
-        self.map.get(batch_size)
+    /// Get the prover for a batch of `num_identities`.
+    ///
+    /// Provers which take the batch as is come first, the one for exactly that
+    /// size before ranged ones. Otherwise it's the smallest prover the batch
+    /// can be padded for.
+    pub fn get(&self, num_identities: usize) -> Option<&Prover> {
+        let fitting = self
+            .map
+            .iter()
+            .map(|(_, prover)| prover)
+            .filter(|prover| prover.fits(num_identities))
+            .min_by_key(|prover| prover.max_batch_size() - prover.batch_size());
+
+        fitting.or_else(|| self.map.get(num_identities))
     }
 
     /// Registers the provided `prover` for the given `batch_size` in the map.
@@ -36,7 +50,19 @@ impl ProverMap {
     }
 
     pub fn max_batch_size(&self) -> usize {
-        self.map.max_key().unwrap_or(0)
+        self.map
+            .iter()
+            .map(|(_, prover)| prover.max_batch_size())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The batch sizes each prover takes, a single one unless it's ranged.
+    pub fn batch_sizes(&self) -> Vec<RangeInclusive<usize>> {
+        self.map
+            .iter()
+            .map(|(_, prover)| prover.batch_size()..=prover.max_batch_size())
+            .collect()
     }
 
     pub fn batch_size_exists(&self, batch_size: usize) -> bool {
@@ -44,21 +70,14 @@ impl ProverMap {
     }
 
     pub fn as_configuration_vec(&self) -> Vec<ProverConfig> {
-        self.map
-            .iter()
-            .map(|(k, v)| ProverConfig {
-                url: v.url(),
-                timeout_s: v.timeout_s(),
-                batch_size: *k,
-                prover_type: v.prover_type(),
-            })
-            .collect()
+        self.map.iter().map(|(_, prover)| prover.config()).collect()
     }
 }
 
 /// Builds an insertion prover map from the provided configuration.
 pub fn initialize_prover_maps(
     db_provers: HashSet<ProverConfig>,
+    metrics: &Metrics,
 ) -> anyhow::Result<(ProverMap, ProverMap)> {
     let mut insertion_map = ProverMap::default();
     let mut deletion_map = ProverMap::default();
@@ -66,11 +85,17 @@ pub fn initialize_prover_maps(
     for prover in db_provers {
         match prover.prover_type {
             ProverType::Insertion => {
-                insertion_map.add(prover.batch_size, Prover::from_prover_conf(&prover)?);
+                insertion_map.add(
+                    prover.batch_size,
+                    Prover::from_prover_conf(&prover, metrics)?,
+                );
             }
 
             ProverType::Deletion => {
-                deletion_map.add(prover.batch_size, Prover::from_prover_conf(&prover)?);
+                deletion_map.add(
+                    prover.batch_size,
+                    Prover::from_prover_conf(&prover, metrics)?,
+                );
             }
         }
     }
@@ -1,22 +1,41 @@
+use std::collections::HashMap;
+use std::ops::{Deref, RangeInclusive};
+use std::sync::{Arc, Mutex};
+
 use anyhow::anyhow;
-use tokio::sync::{RwLock, RwLockReadGuard};
+use prometheus::IntGauge;
+use tokio::sync::{OwnedSemaphorePermit, RwLock, RwLockReadGuard, Semaphore};
 use tracing::warn;
 
+use crate::metrics::Metrics;
 use crate::prover::{Prover, ProverConfig, ProverMap, ProverType};
 
 pub struct ProverRepository {
     insertion_prover_map: RwLock<ProverMap>,
     deletion_prover_map: RwLock<ProverMap>,
+    max_in_flight: usize,
+    /// Keyed by URL, a prover registered for several batch sizes is still a
+    /// single instance
+    in_flight: Mutex<HashMap<String, Arc<Semaphore>>>,
+    metrics: Metrics,
 }
 
 impl ProverRepository {
-    pub fn new(insertion_prover_map: ProverMap, deletion_prover_map: ProverMap) -> Self {
+    pub fn new(
+        insertion_prover_map: ProverMap,
+        deletion_prover_map: ProverMap,
+        max_in_flight: usize,
+        metrics: &Metrics,
+    ) -> Self {
         let insertion_prover_map = RwLock::new(insertion_prover_map);
         let deletion_prover_map = RwLock::new(deletion_prover_map);
 
         Self {
             insertion_prover_map,
             deletion_prover_map,
+            max_in_flight: max_in_flight.max(1),
+            in_flight: Mutex::new(HashMap::new()),
+            metrics: metrics.clone(),
         }
     }
 
@@ -26,7 +45,27 @@ impl ProverRepository {
         batch_size: usize,
         timeout_seconds: u64,
         prover_type: ProverType,
+        probe: bool,
     ) -> Result<(), crate::server::error::Error> {
+        let prover = Prover::new(
+            &ProverConfig {
+                url: url.to_string(),
+                batch_size,
+                max_batch_size: None,
+                hmac_secret: None,
+                prover_type,
+                timeout_s: timeout_seconds,
+            },
+            &self.metrics,
+        )?;
+
+        // Probe before locking the map, an unresponsive prover would
+        // otherwise stall proof generation until the timeout
+        if probe && !prover.is_reachable().await {
+            warn!(url = prover.url(), "Prover is unreachable");
+            return Err(crate::server::error::Error::ProverUnreachable);
+        }
+
         let mut map = match prover_type {
             ProverType::Insertion => self.insertion_prover_map.write().await,
             ProverType::Deletion => self.deletion_prover_map.write().await,
@@ -36,13 +75,6 @@ impl ProverRepository {
             return Err(crate::server::error::Error::BatchSizeAlreadyExists);
         }
 
-        let prover = Prover::new(&ProverConfig {
-            url: url.to_string(),
-            batch_size,
-            prover_type,
-            timeout_s: timeout_seconds,
-        })?;
-
         map.add(batch_size, prover);
 
         Ok(())
@@ -52,25 +84,59 @@ impl ProverRepository {
     ///
     /// Will return `Err` if the batch size requested for removal doesn't exist
     /// in the prover map.
+    /// Returns the configuration of the removed prover.
     pub async fn remove_batch_size(
         &self,
         batch_size: usize,
         prover_type: ProverType,
-    ) -> Result<(), crate::server::error::Error> {
+    ) -> Result<ProverConfig, crate::server::error::Error> {
         let mut map = match prover_type {
             ProverType::Insertion => self.insertion_prover_map.write().await,
             ProverType::Deletion => self.deletion_prover_map.write().await,
         };
 
+        if !map.batch_size_exists(batch_size) {
+            return Err(crate::server::error::Error::NoSuchBatchSize);
+        }
+
         if map.len() == 1 {
             warn!("Attempting to remove the last batch size.");
             return Err(crate::server::error::Error::CannotRemoveLastBatchSize);
         }
 
-        match map.remove(batch_size) {
-            Some(_) => Ok(()),
-            None => Err(crate::server::error::Error::NoSuchBatchSize),
-        }
+        let prover = map
+            .remove(batch_size)
+            .ok_or(crate::server::error::Error::NoSuchBatchSize)?;
+
+        Ok(prover.config())
+    }
+
+    /// Undoes `add_batch_size`, unlike `remove_batch_size` this also removes
+    /// the last batch size.
+    pub async fn discard_batch_size(&self, batch_size: usize, prover_type: ProverType) {
+        let mut map = match prover_type {
+            ProverType::Insertion => self.insertion_prover_map.write().await,
+            ProverType::Deletion => self.deletion_prover_map.write().await,
+        };
+
+        map.remove(batch_size);
+    }
+
+    /// Undoes `remove_batch_size` with the configuration it returned.
+    pub async fn restore_batch_size(
+        &self,
+        prover: &ProverConfig,
+    ) -> Result<(), crate::server::error::Error> {
+        let restored = Prover::new(prover, &self.metrics)?;
+
+        let mut map = match prover.prover_type {
+            ProverType::Insertion => self.insertion_prover_map.write().await,
+            ProverType::Deletion => self.deletion_prover_map.write().await,
+        };
+
+        map.add(prover.batch_size, restored);
+
+        Ok(())
     }
 
     pub async fn list_batch_sizes(&self) -> Result<Vec<ProverConfig>, crate::server::error::Error> {
@@ -93,6 +159,14 @@ impl ProverRepository {
         self.deletion_prover_map.read().await.len() > 0
     }
 
+    /// The batch sizes currently taken by the provers of `prover_type`.
+    pub async fn batch_sizes(&self, prover_type: ProverType) -> Vec<RangeInclusive<usize>> {
+        match prover_type {
+            ProverType::Insertion => self.insertion_prover_map.read().await.batch_sizes(),
+            ProverType::Deletion => self.deletion_prover_map.read().await.batch_sizes(),
+        }
+    }
+
     pub async fn max_insertion_batch_size(&self) -> usize {
         self.insertion_prover_map.read().await.max_batch_size()
     }
@@ -108,7 +182,7 @@ impl ProverRepository {
         Ok(self
             .get_suitable_deletion_prover(num_identities)
             .await?
-            .batch_size())
+            .padded_batch_size(num_identities))
     }
 
     pub async fn get_suitable_insertion_batch_size(
@@ -118,7 +192,7 @@ impl ProverRepository {
         Ok(self
             .get_suitable_insertion_prover(num_identities)
             .await?
-            .batch_size())
+            .padded_batch_size(num_identities))
     }
 
     pub async fn get_suitable_insertion_prover(
@@ -148,4 +222,239 @@ impl ProverRepository {
             )),
         }
     }
+
+    /// Acquires the prover for a batch, waiting while it is busy with other
+    /// batches. The prover is released when the guard is dropped.
+    pub async fn acquire_prover(
+        &self,
+        prover_type: ProverType,
+        num_identities: usize,
+    ) -> anyhow::Result<ProverGuard> {
+        // The map must not stay locked while waiting, that would block
+        // changes to the batch sizes
+        let prover = match prover_type {
+            ProverType::Insertion => self.get_suitable_insertion_prover(num_identities).await?,
+            ProverType::Deletion => self.get_suitable_deletion_prover(num_identities).await?,
+        }
+        .clone();
+
+        let semaphore = self
+            .in_flight
+            .lock()
+            .unwrap()
+            .entry(prover.url().to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(self.max_in_flight)))
+            .clone();
+
+        let permit = semaphore.acquire_owned().await?;
+
+        let in_flight = self
+            .metrics
+            .prover_in_flight
+            .with_label_values(&[prover.url()]);
+
+        Ok(ProverGuard::new(prover, permit, in_flight))
+    }
+}
+
+/// A prover acquired for a batch, see [`ProverRepository::acquire_prover`].
+pub struct ProverGuard {
+    prover: Prover,
+    _permit: OwnedSemaphorePermit,
+    in_flight: IntGauge,
+}
+
+impl ProverGuard {
+    fn new(prover: Prover, permit: OwnedSemaphorePermit, in_flight: IntGauge) -> Self {
+        in_flight.inc();
+
+        Self {
+            prover,
+            _permit: permit,
+            in_flight,
+        }
+    }
+}
+
+impl Deref for ProverGuard {
+    type Target = Prover;
+
+    fn deref(&self) -> &Self::Target {
+        &self.prover
+    }
+}
+
+// Also runs if the batch panics or its future is cancelled
+impl Drop for ProverGuard {
+    fn drop(&mut self) {
+        self.in_flight.dec();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    const BUSY_TIMEOUT: Duration = Duration::from_millis(100);
+
+    fn prover(url: &str, batch_size: usize) -> Prover {
+        Prover::new(
+            &ProverConfig {
+                url: url.to_string(),
+                timeout_s: 30,
+                batch_size,
+                max_batch_size: None,
+                hmac_secret: None,
+                prover_type: ProverType::Insertion,
+            },
+            &Metrics::default(),
+        )
+        .unwrap()
+    }
+
+    fn ranged_prover(url: &str, batch_size: usize, max_batch_size: usize) -> Prover {
+        Prover::new(
+            &ProverConfig {
+                url: url.to_string(),
+                timeout_s: 30,
+                batch_size,
+                max_batch_size: Some(max_batch_size),
+                hmac_secret: None,
+                prover_type: ProverType::Insertion,
+            },
+            &Metrics::default(),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn batches_on_one_prover_are_serialized() -> anyhow::Result<()> {
+        let mut map = ProverMap::default();
+        map.add(3, prover("http://prover:3001", 3));
+        let repository = ProverRepository::new(map, ProverMap::default(), 1, &Metrics::default());
+
+        let first = repository.acquire_prover(ProverType::Insertion, 3).await?;
+
+        assert!(
+            tokio::time::timeout(
+                BUSY_TIMEOUT,
+                repository.acquire_prover(ProverType::Insertion, 2)
+            )
+            .await
+            .is_err(),
+            "A busy prover must not be handed out again"
+        );
+
+        let second = repository.acquire_prover(ProverType::Insertion, 3);
+        tokio::pin!(second);
+        assert!(tokio::time::timeout(BUSY_TIMEOUT, &mut second)
+            .await
+            .is_err());
+
+        drop(first);
+
+        let second = tokio::time::timeout(BUSY_TIMEOUT, second).await??;
+        assert_eq!(second.batch_size(), 3);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn prover_is_released_when_a_batch_panics() -> anyhow::Result<()> {
+        let mut map = ProverMap::default();
+        map.add(3, prover("http://prover:3001", 3));
+        let repository = Arc::new(ProverRepository::new(
+            map,
+            ProverMap::default(),
+            1,
+            &Metrics::default(),
+        ));
+
+        let result = tokio::spawn({
+            let repository = repository.clone();
+            async move {
+                let _prover = repository.acquire_prover(ProverType::Insertion, 3).await;
+                panic!("Proving failed");
+            }
+        })
+        .await;
+        assert!(result.is_err());
+
+        tokio::time::timeout(
+            BUSY_TIMEOUT,
+            repository.acquire_prover(ProverType::Insertion, 3),
+        )
+        .await??;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn batches_on_different_provers_run_in_parallel() -> anyhow::Result<()> {
+        let mut map = ProverMap::default();
+        map.add(3, prover("http://prover:3001", 3));
+        map.add(10, prover("http://prover:3002", 10));
+        let repository = ProverRepository::new(map, ProverMap::default(), 1, &Metrics::default());
+
+        let (small, large) = tokio::time::timeout(BUSY_TIMEOUT, async {
+            tokio::try_join!(
+                repository.acquire_prover(ProverType::Insertion, 3),
+                repository.acquire_prover(ProverType::Insertion, 10),
+            )
+        })
+        .await??;
+
+        assert_eq!(small.batch_size(), 3);
+        assert_eq!(large.batch_size(), 10);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn ranged_provers_take_batches_without_padding() -> anyhow::Result<()> {
+        let mut map = ProverMap::default();
+        map.add(3, prover("http://exact:3001", 3));
+        map.add(10, prover("http://exact:3002", 10));
+        map.add(1, ranged_prover("http://ranged:3003", 1, 8));
+        map.add(12, ranged_prover("http://ranged:3004", 12, 16));
+        let repository = ProverRepository::new(map, ProverMap::default(), 1, &Metrics::default());
+
+        let selection = |num_identities| async move {
+            let prover = repository
+                .get_suitable_insertion_prover(num_identities)
+                .await
+                .ok()?
+                .url()
+                .to_owned();
+            let batch_size = repository
+                .get_suitable_insertion_batch_size(num_identities)
+                .await
+                .ok()?;
+
+            Some((prover, batch_size))
+        };
+
+        // An exact prover of the right size comes first
+        assert_eq!(selection(3).await, Some(("http://exact:3001/".into(), 3)));
+        // Odd sizes go to the ranged prover unpadded
+        assert_eq!(selection(7).await, Some(("http://ranged:3003/".into(), 7)));
+        assert_eq!(selection(2).await, Some(("http://ranged:3003/".into(), 2)));
+        // Beyond its range the batch is padded to the next prover
+        assert_eq!(selection(9).await, Some(("http://exact:3002/".into(), 10)));
+        assert_eq!(
+            selection(11).await,
+            Some(("http://ranged:3004/".into(), 12))
+        );
+        assert_eq!(
+            selection(16).await,
+            Some(("http://ranged:3004/".into(), 16))
+        );
+        assert_eq!(selection(17).await, None);
+
+        assert_eq!(repository.max_insertion_batch_size().await, 16);
+
+        Ok(())
+    }
 }
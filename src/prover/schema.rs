@@ -0,0 +1,114 @@
+//! Validation against the JSON schemas of the prover API in
+//! `schemas/prover`. Only the keywords those schemas use are supported.
+
+use regex::Regex;
+use serde_json::Value;
+
+pub const INSERTION_PROOF_INPUT: &str =
+    include_str!("../../schemas/prover/insertion_proof_input.schema.json");
+pub const PROOF: &str = include_str!("../../schemas/prover/proof.schema.json");
+
+/// Validates `value` against `schema`, returning a description of every
+/// violation.
+pub fn validate(schema: &str, value: &Value) -> Vec<String> {
+    let root: Value = serde_json::from_str(schema).expect("schema is valid JSON");
+
+    let mut errors = vec![];
+    validate_at(&root, &root, value, "$", &mut errors);
+    errors
+}
+
+fn validate_at(root: &Value, schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) {
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        let pointer = reference
+            .strip_prefix('#')
+            .expect("only local references are supported");
+        let schema = root.pointer(pointer).expect("reference resolves");
+        return validate_at(root, schema, value, path, errors);
+    }
+
+    let expected_type = schema.get("type").and_then(Value::as_str);
+    let type_matches = match expected_type {
+        Some("object") => value.is_object(),
+        Some("array") => value.is_array(),
+        Some("string") => value.is_string(),
+        Some("integer") => value.is_u64() || value.is_i64(),
+        Some(other) => panic!("unsupported type {other}"),
+        None => true,
+    };
+    if !type_matches {
+        errors.push(format!("{path}: expected {}", expected_type.unwrap()));
+        return;
+    }
+
+    match value {
+        Value::Object(object) => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+
+            for required in schema
+                .get("required")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+            {
+                let required = required.as_str().unwrap();
+                if !object.contains_key(required) {
+                    errors.push(format!("{path}: missing property {required}"));
+                }
+            }
+
+            for (key, field) in object {
+                let field_path = format!("{path}.{key}");
+                match properties.and_then(|properties| properties.get(key)) {
+                    Some(field_schema) => {
+                        validate_at(root, field_schema, field, &field_path, errors);
+                    }
+                    None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                        errors.push(format!("{field_path}: unexpected property"));
+                    }
+                    None => (),
+                }
+            }
+        }
+        Value::Array(items) => {
+            let len = items.len() as u64;
+            if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+                if len < min {
+                    errors.push(format!("{path}: expected at least {min} items"));
+                }
+            }
+            if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+                if len > max {
+                    errors.push(format!("{path}: expected at most {max} items"));
+                }
+            }
+
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    validate_at(root, item_schema, item, &format!("{path}[{i}]"), errors);
+                }
+            }
+        }
+        Value::String(string) => {
+            if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+                if !Regex::new(pattern).unwrap().is_match(string) {
+                    errors.push(format!("{path}: {string:?} doesn't match {pattern}"));
+                }
+            }
+        }
+        Value::Number(number) => {
+            let number = number.as_f64().unwrap();
+            if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+                if number < min {
+                    errors.push(format!("{path}: below the minimum of {min}"));
+                }
+            }
+            if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+                if number > max {
+                    errors.push(format!("{path}: above the maximum of {max}"));
+                }
+            }
+        }
+        _ => (),
+    }
+}
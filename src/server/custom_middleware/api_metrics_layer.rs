@@ -1,36 +1,22 @@
-use axum::extract::Request;
+use axum::extract::{Request, State};
 use axum::http::StatusCode;
 use axum::middleware::Next;
 use axum::response::Response;
-use once_cell::sync::Lazy;
-use prometheus::{
-    opts, register_counter, register_histogram, register_int_counter_vec, Counter, Histogram,
-    IntCounterVec,
-};
 
-static REQUESTS: Lazy<Counter> =
-    Lazy::new(|| register_counter!(opts!("api_requests", "Number of requests received.")).unwrap());
+use crate::metrics::Metrics;
 
-static STATUS: Lazy<IntCounterVec> = Lazy::new(|| {
-    register_int_counter_vec!(
-        "api_response_status",
-        "The API responses by status code.",
-        &["status_code"]
-    )
-    .unwrap()
-});
-
-static LATENCY: Lazy<Histogram> = Lazy::new(|| {
-    register_histogram!("api_latency_seconds", "The API latency in seconds.").unwrap()
-});
-
-pub async fn middleware(request: Request, next: Next) -> Result<Response, StatusCode> {
-    let _timer = LATENCY.start_timer(); // Observes on drop
-    REQUESTS.inc();
+pub async fn middleware(
+    State(metrics): State<Metrics>,
+    request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let _timer = metrics.api_latency.start_timer(); // Observes on drop
+    metrics.api_requests.inc();
 
     let response = next.run(request).await;
 
-    STATUS
+    metrics
+        .api_response_status
         .with_label_values(&[response.status().as_str()])
         .inc();
 
@@ -0,0 +1,199 @@
+use std::collections::{HashMap, VecDeque};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::extract::{ConnectInfo, MatchedPath, Request, State};
+use axum::http::{HeaderName, HeaderValue};
+use axum::middleware::Next;
+use axum::response::Response;
+use chrono::{NaiveDate, NaiveTime};
+use prometheus::IntCounterVec;
+use tracing::info;
+
+use crate::metrics::Metrics;
+use crate::server::origin::client_ip;
+
+const DEPRECATION: HeaderName = HeaderName::from_static("deprecation");
+const SUNSET: HeaderName = HeaderName::from_static("sunset");
+
+/// Each caller of the v1 API is logged at most once within this interval
+const CALLER_LOG_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Upper bound on the callers remembered at once, the oldest ones are
+/// forgotten first
+const MAX_LOGGED_CALLERS: usize = 10_000;
+
+/// Marks responses of the v1 API as deprecated, see RFC 8594, and keeps track
+/// of who still calls it.
+pub struct V1Deprecation {
+    sunset: Option<HeaderValue>,
+    trusted_proxies: Vec<IpAddr>,
+    logged_callers: Mutex<LoggedCallers>,
+    requests: IntCounterVec,
+}
+
+#[derive(Default)]
+struct LoggedCallers {
+    logged_at: HashMap<IpAddr, Instant>,
+    /// Callers in the order they were logged in, so that the stale ones can be
+    /// evicted from the front
+    order: VecDeque<(IpAddr, Instant)>,
+}
+
+impl V1Deprecation {
+    pub fn new(
+        sunset_date: Option<NaiveDate>,
+        trusted_proxies: Vec<IpAddr>,
+        metrics: &Metrics,
+    ) -> Self {
+        let sunset = sunset_date.map(|date| {
+            let http_date = date
+                .and_time(NaiveTime::MIN)
+                .and_utc()
+                .format("%a, %d %b %Y %H:%M:%S GMT");
+
+            HeaderValue::from_str(&http_date.to_string()).expect("HTTP dates are valid headers")
+        });
+
+        Self {
+            sunset,
+            trusted_proxies,
+            logged_callers: Mutex::default(),
+            requests: metrics.v1_requests.clone(),
+        }
+    }
+
+    /// Whether `caller` wasn't logged within the interval, in which case it's
+    /// recorded as logged now.
+    fn should_log(&self, caller: IpAddr, now: Instant) -> bool {
+        let mut logged_callers = self.logged_callers.lock().unwrap();
+        let is_recent = |logged_at: &Instant| now.duration_since(*logged_at) < CALLER_LOG_INTERVAL;
+
+        if logged_callers.logged_at.get(&caller).is_some_and(is_recent) {
+            return false;
+        }
+
+        // Callers which stopped calling, or the oldest ones once the map is full,
+        // are evicted whenever a caller is logged
+        while let Some(&(oldest, logged_at)) = logged_callers.order.front() {
+            if is_recent(&logged_at) && logged_callers.logged_at.len() < MAX_LOGGED_CALLERS {
+                break;
+            }
+
+            logged_callers.order.pop_front();
+            if logged_callers.logged_at.get(&oldest) == Some(&logged_at) {
+                logged_callers.logged_at.remove(&oldest);
+            }
+        }
+
+        logged_callers.logged_at.insert(caller, now);
+        logged_callers.order.push_back((caller, now));
+
+        true
+    }
+}
+
+pub async fn middleware(
+    State(deprecation): State<Arc<V1Deprecation>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let endpoint = request.extensions().get::<MatchedPath>().map_or_else(
+        || request.uri().path().to_owned(),
+        |path| path.as_str().to_owned(),
+    );
+    deprecation
+        .requests
+        .with_label_values(&[endpoint.as_str()])
+        .inc();
+
+    let caller = client_ip(peer.ip(), request.headers(), &deprecation.trusted_proxies);
+    if deprecation.should_log(caller, Instant::now()) {
+        info!(%caller, %endpoint, "Deprecated v1 API called");
+    }
+
+    let mut response = next.run(request).await;
+
+    let headers = response.headers_mut();
+    headers.insert(DEPRECATION, HeaderValue::from_static("true"));
+    if let Some(sunset) = &deprecation.sunset {
+        headers.insert(SUNSET, sunset.clone());
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    const CALLER: [u8; 4] = [203, 0, 113, 7];
+    const OTHER_CALLER: [u8; 4] = [198, 51, 100, 1];
+
+    fn deprecation() -> Arc<V1Deprecation> {
+        let sunset_date = NaiveDate::from_ymd_opt(2025, 6, 30).unwrap();
+
+        Arc::new(V1Deprecation::new(
+            Some(sunset_date),
+            vec![],
+            &Metrics::default(),
+        ))
+    }
+
+    #[test]
+    fn callers_are_logged_once_per_interval() {
+        let deprecation = deprecation();
+        let caller = IpAddr::from(CALLER);
+        let other_caller = IpAddr::from(OTHER_CALLER);
+        let start = Instant::now();
+
+        assert!(deprecation.should_log(caller, start));
+        assert!(!deprecation.should_log(caller, start + Duration::from_secs(60)));
+        assert!(deprecation.should_log(other_caller, start + Duration::from_secs(60)));
+
+        let next_day = start + CALLER_LOG_INTERVAL;
+        assert!(deprecation.should_log(caller, next_day));
+        assert!(!deprecation.should_log(caller, next_day));
+    }
+
+    #[test]
+    fn stale_callers_are_evicted() {
+        let deprecation = deprecation();
+        let start = Instant::now();
+
+        deprecation.should_log(IpAddr::from(CALLER), start);
+        deprecation.should_log(IpAddr::from(OTHER_CALLER), start + CALLER_LOG_INTERVAL);
+
+        let logged_callers = deprecation.logged_callers.lock().unwrap();
+        assert_eq!(
+            logged_callers.logged_at.keys().collect::<Vec<_>>(),
+            vec![&IpAddr::from(OTHER_CALLER)]
+        );
+        assert_eq!(logged_callers.order.len(), 1);
+    }
+
+    #[test]
+    fn oldest_callers_are_evicted_once_full() {
+        let deprecation = deprecation();
+        let start = Instant::now();
+        let caller = |i: usize| IpAddr::from(Ipv4Addr::from(i as u32));
+
+        for i in 0..=MAX_LOGGED_CALLERS {
+            assert!(deprecation.should_log(caller(i), start));
+        }
+
+        {
+            let logged_callers = deprecation.logged_callers.lock().unwrap();
+            assert_eq!(logged_callers.logged_at.len(), MAX_LOGGED_CALLERS);
+            assert_eq!(logged_callers.order.len(), MAX_LOGGED_CALLERS);
+        }
+
+        // The first caller was forgotten, the last one is still remembered
+        assert!(deprecation.should_log(caller(0), start));
+        assert!(!deprecation.should_log(caller(MAX_LOGGED_CALLERS), start));
+    }
+}
@@ -11,9 +11,15 @@ use hyper::{body::Body as _, Method};
 use telemetry_batteries::tracing::{trace_from_headers, trace_to_headers};
 use tracing::{error, info, info_span, warn, Instrument};
 
+use crate::server::BULK_IMPORT_PATH;
+
 // 1 MiB
 const MAX_REQUEST_BODY_SIZE: u64 = 1024 * 1024;
 
+/// Routes whose request bodies are streamed to the handler instead of being
+/// buffered and logged, they can be far larger than `MAX_REQUEST_BODY_SIZE`.
+const STREAMED_BODY_PATHS: &[&str] = &[BULK_IMPORT_PATH];
+
 pub async fn middleware(request: Request, next: Next) -> Result<Response, StatusCode> {
     let (parts, body) = request.into_parts();
 
@@ -21,7 +27,9 @@ pub async fn middleware(request: Request, next: Next) -> Result<Response, Status
     let request_method = parts.method.clone();
     let request_query = parts.uri.query().map(ToString::to_string);
 
-    if let Method::GET = request_method {
+    let streamed = STREAMED_BODY_PATHS.contains(&uri_path.as_str());
+
+    if request_method == Method::GET || streamed {
         let span = info_span!("request", ?uri_path, ?request_method, ?request_query);
 
         async {
@@ -34,7 +42,7 @@ pub async fn middleware(request: Request, next: Next) -> Result<Response, Status
                 "Processing request"
             );
 
-            let body = Body::empty();
+            let body = if streamed { body } else { Body::empty() };
             let request = Request::from_parts(parts, body);
 
             let response = next.run(request).await;
@@ -1,4 +1,5 @@
 pub mod api_metrics_layer;
+pub mod deprecation_layer;
 pub mod logging_layer;
 pub mod remove_auth_layer;
 pub mod timeout_layer;
@@ -3,15 +3,103 @@ use std::time::Duration;
 use axum::extract::{Request, State};
 use axum::http::StatusCode;
 use axum::middleware::Next;
-use axum::response::Response;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeoutResponse {
+    pub error_id: String,
+    pub error_message: String,
+}
 
 pub async fn middleware(
     State(timeout_duration): State<Duration>,
     request: Request,
     next: Next,
-) -> Result<Response, StatusCode> {
+) -> Response {
     match tokio::time::timeout(timeout_duration, next.run(request)).await {
-        Ok(response) => Ok(response),
-        Err(_elapsed) => Err(StatusCode::REQUEST_TIMEOUT),
+        Ok(response) => response,
+        Err(_elapsed) => (
+            StatusCode::REQUEST_TIMEOUT,
+            Json(TimeoutResponse {
+                error_id: "request_timeout".to_string(),
+                error_message: format!(
+                    "The request did not complete within {}",
+                    humantime::format_duration(timeout_duration)
+                ),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use axum::routing::get;
+    use axum::{middleware, Router};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    const HANDLER_DURATION: Duration = Duration::from_millis(200);
+
+    async fn slow_handler() -> &'static str {
+        tokio::time::sleep(HANDLER_DURATION).await;
+        "done"
+    }
+
+    async fn spawn_server() -> SocketAddr {
+        let read_routes =
+            Router::new()
+                .route("/read", get(slow_handler))
+                .layer(middleware::from_fn_with_state(
+                    HANDLER_DURATION / 4,
+                    super::middleware,
+                ));
+        let write_routes =
+            Router::new()
+                .route("/write", get(slow_handler))
+                .layer(middleware::from_fn_with_state(
+                    HANDLER_DURATION * 4,
+                    super::middleware,
+                ));
+        let router = read_routes.merge(write_routes);
+
+        let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .await
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            axum::serve(listener, router).await.unwrap();
+        });
+
+        addr
+    }
+
+    #[tokio::test]
+    async fn read_timeout_returns_structured_error() {
+        let addr = spawn_server().await;
+
+        let response = reqwest::get(format!("http://{addr}/read")).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::REQUEST_TIMEOUT);
+
+        let body: TimeoutResponse = response.json().await.unwrap();
+        assert_eq!(body.error_id, "request_timeout");
+    }
+
+    #[tokio::test]
+    async fn write_timeout_allows_slower_handlers() {
+        let addr = spawn_server().await;
+
+        let response = reqwest::get(format!("http://{addr}/write")).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.text().await.unwrap(), "done");
     }
 }
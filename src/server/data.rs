@@ -1,23 +1,138 @@
+use std::net::IpAddr;
+
 use chrono::Utc;
+use ethers::types::H256;
 use hyper::StatusCode;
 use semaphore::protocol::Proof;
 use semaphore::Field;
 use serde::{Deserialize, Serialize};
 
+use crate::database::types::{
+    BatchApproval, BatchNoteEntry, BatchType, ConfigChangeEntry, ExportEntry, RequestOrigin,
+    TreeUpdateEntry,
+};
 use crate::identity_tree::{Hash, InclusionProof, ProcessedStatus, RootItem, Status};
 use crate::prover::{ProverConfig, ProverType};
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
-pub struct InclusionProofResponse {
-    pub status: Status,
-    pub root: Option<Field>,
-    pub proof: Option<semaphore::poseidon_tree::Proof>,
-    pub message: Option<String>,
-}
+pub type InclusionProofResponse =
+    signup_sequencer_types::InclusionProofResponse<semaphore::poseidon_tree::Proof>;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ListBatchSizesResponse(pub Vec<ProverConfig>);
 
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidateInsertResponse {
+    pub insertable: bool,
+    /// Why the commitment would be rejected, if it would be
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<InsertRejection>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InsertRejection {
+    /// The commitment is the initial leaf of the tree
+    InvalidCommitment,
+    /// The commitment is not an element of the scalar field
+    UnreducedCommitment,
+    /// The commitment is queued, in the tree or was deleted from it
+    DuplicateCommitment,
+    /// No insertion provers are configured
+    NoInsertionProvers,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulateInsertResponse {
+    /// The root of the latest tree once the commitment is appended
+    pub root: Field,
+    pub leaf_index: usize,
+    pub proof: semaphore::poseidon_tree::Proof,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityCountResponse {
+    /// Number of identities in the latest tree, deleted ones excluded
+    pub leaf_count: usize,
+    pub next_leaf: usize,
+}
+
+/// Query of `GET /v2/tree/updates`
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TreeUpdatesQuery {
+    /// The cursor, starts from the first update if not set
+    #[serde(default)]
+    pub after_sequence_id: usize,
+    /// Capped at [`TreeUpdatesQuery::MAX_LIMIT`]
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// Only returns updates with this status
+    #[serde(default)]
+    pub status: Option<ProcessedStatus>,
+}
+
+impl TreeUpdatesQuery {
+    pub const DEFAULT_LIMIT: usize = 100;
+    pub const MAX_LIMIT: usize = 1000;
+
+    #[must_use]
+    pub fn limit(&self) -> usize {
+        self.limit
+            .unwrap_or(Self::DEFAULT_LIMIT)
+            .min(Self::MAX_LIMIT)
+    }
+}
+
+/// A page of the tree updates feed.
+///
+/// Updates are delivered at least once: identities which lose their processed
+/// or mined status, e.g. when the sequencer rewinds to an earlier root, keep
+/// their sequence id and are delivered again with their new status. Every
+/// such rewind bumps `epoch`, consumers seeing a new epoch must resync from
+/// the start or from an update they know to be mined.
+///
+/// Identities are mined in sequence order, so the cursor over updates
+/// filtered by the mined status only moves forward within an epoch.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeUpdatesResponse {
+    pub epoch: u64,
+    pub updates: Vec<TreeUpdateEntry>,
+    /// Cursor of the next page, the requested cursor if there are no updates
+    pub next_after_sequence_id: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeInfoResponse {
+    pub depth: usize,
+    pub next_leaf: usize,
+    /// Root of the latest tree, including pending identities
+    pub root: Hash,
+    /// Root of the tree with mined identities only
+    pub mined_root: Hash,
+}
+
+/// Invalid lines reported by line number per bulk import request, the others
+/// are only counted
+pub const MAX_REPORTED_INVALID_LINES: usize = 100;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkImportResponse {
+    pub import_id: String,
+    /// Counts cover every request made with this import id
+    pub lines_processed: u64,
+    pub accepted: u64,
+    pub duplicates: u64,
+    pub invalid: u64,
+    /// Line numbers of the first invalid lines found by this request, up to
+    /// `MAX_REPORTED_INVALID_LINES`
+    pub invalid_lines: Vec<u64>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct VerifySemaphoreProofResponse {
     pub root: Field,
@@ -45,6 +160,28 @@ pub struct AddBatchSizeRequest {
     pub timeout_seconds: u64,
     // TODO: add docs
     pub prover_type: ProverType,
+    /// Reject the prover if its URL can't be reached.
+    #[serde(default)]
+    pub probe: bool,
+}
+
+/// Query of `POST /addBatchSize`
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AddBatchSizeQuery {
+    /// Same as [`AddBatchSizeRequest::probe`], for clients which can't extend
+    /// the body
+    #[serde(default)]
+    pub verify: bool,
+}
+
+/// The prover registered by an `/addBatchSize` request.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddBatchSizeResponse {
+    pub url: String,
+    pub batch_size: usize,
+    pub timeout_seconds: u64,
+    pub prover_type: ProverType,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -57,6 +194,18 @@ pub struct RemoveBatchSizeRequest {
     pub prover_type: ProverType,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+pub struct RootSubscriptionRequest {
+    /// The webhook url root notifications are delivered to.
+    pub url: String,
+    /// Overrides the configured percentage of the max root age after which
+    /// the subscriber is warned about an aging root, from 1 to 100.
+    #[serde(default)]
+    pub aging_threshold_percent: Option<u32>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 #[serde(deny_unknown_fields)]
@@ -83,6 +232,24 @@ pub struct VerifySemaphoreProofQuery {
     pub max_root_age_seconds: Option<i64>,
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+pub struct MetricsQuery {
+    #[serde(default)]
+    pub format: MetricsFormat,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetricsFormat {
+    /// The classic Prometheus text exposition format
+    #[default]
+    Text,
+    /// The OpenMetrics text format
+    OpenMetrics,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 #[serde(deny_unknown_fields)]
@@ -91,6 +258,304 @@ pub struct DeletionRequest {
     pub identity_commitment: Hash,
 }
 
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletionQuery {
+    /// Purge the identity if it hasn't been inserted into the tree yet. Only
+    /// accepted on the admin route.
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+pub struct LiftDeletionLimitRequest {
+    /// Why the limits are lifted, kept for auditing.
+    pub reason: String,
+    /// How long the limits stay lifted.
+    #[serde(with = "humantime_serde")]
+    pub duration: std::time::Duration,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LiftDeletionLimitResponse {
+    pub lifted_until: chrono::DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminStatusResponse {
+    pub ready: bool,
+    /// Batch submission is paused while the relayer balance is too low
+    pub batching_paused: bool,
+    /// Identities take longer than the offchain mode SLA to become provable
+    pub sla_breached: bool,
+    /// Next roots of the batches held back until they're approved, in the
+    /// order they're submitted in
+    pub batches_awaiting_approval: Vec<Hash>,
+}
+
+/// Query of `GET /v2/admin/transactions`
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TransactionsQuery {
+    /// Only lists transactions with this status, in the relayer's terms.
+    /// Ignored without a relayer.
+    #[serde(default)]
+    pub status: Option<String>,
+    /// Capped at [`TransactionsQuery::MAX_LIMIT`]
+    #[serde(default)]
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub offset: usize,
+}
+
+impl TransactionsQuery {
+    pub const DEFAULT_LIMIT: usize = 50;
+    pub const MAX_LIMIT: usize = 500;
+
+    #[must_use]
+    pub fn limit(&self) -> usize {
+        self.limit
+            .unwrap_or(Self::DEFAULT_LIMIT)
+            .min(Self::MAX_LIMIT)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TransactionSource {
+    /// Listed by the relayer
+    Relayer,
+    /// Listed from the transactions recorded by the sequencer, e.g. in offchain
+    /// mode
+    Local,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionsResponse {
+    pub source: TransactionSource,
+    pub transactions: Vec<TransactionInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionInfo {
+    pub transaction_id: String,
+    /// Only known to the relayer
+    pub status: Option<String>,
+    pub hash: Option<H256>,
+    /// The batches the transaction submitted, empty for transactions the
+    /// sequencer didn't record, e.g. ones sent by another service
+    pub batches: Vec<TransactionBatch>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionBatch {
+    /// The root after the batch
+    pub root: Hash,
+    pub batch_type: BatchType,
+    pub submitted_at: chrono::DateTime<Utc>,
+    /// The latest operator note
+    pub note: Option<String>,
+}
+
+/// Body of `PATCH /v2/admin/batches/:root`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchNoteRequest {
+    /// At most [`BatchNoteRequest::MAX_LENGTH`] characters
+    pub note: String,
+}
+
+impl BatchNoteRequest {
+    pub const MAX_LENGTH: usize = 1000;
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchResponse {
+    /// The root after the batch
+    pub root: Hash,
+    pub prev_root: Option<Hash>,
+    pub batch_type: BatchType,
+    pub created_at: chrono::DateTime<Utc>,
+    /// Only set for batches which reached the submission stage while batch
+    /// approval was required
+    pub approval: Option<BatchApproval>,
+    /// The admin who approved or rejected the batch
+    pub approval_principal: Option<String>,
+    /// The latest operator note
+    pub note: Option<String>,
+    /// Every note added to the batch, oldest first
+    pub notes: Vec<BatchNoteInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchNoteInfo {
+    pub note: String,
+    /// The admin who added the note, as forwarded by the admin ingress, or
+    /// their address
+    pub principal: String,
+    pub created_at: chrono::DateTime<Utc>,
+}
+
+impl From<BatchNoteEntry> for BatchNoteInfo {
+    fn from(entry: BatchNoteEntry) -> Self {
+        Self {
+            note: entry.note,
+            principal: entry.principal,
+            created_at: entry.created_at,
+        }
+    }
+}
+
+/// Query of `GET /v2/admin/config-changes`
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ConfigChangesQuery {
+    /// Capped at [`ConfigChangesQuery::MAX_LIMIT`]
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// Only lists changes recorded after the one with this id
+    #[serde(default)]
+    pub after_id: Option<i64>,
+}
+
+impl ConfigChangesQuery {
+    pub const DEFAULT_LIMIT: usize = 50;
+    pub const MAX_LIMIT: usize = 500;
+
+    #[must_use]
+    pub fn limit(&self) -> usize {
+        self.limit
+            .unwrap_or(Self::DEFAULT_LIMIT)
+            .min(Self::MAX_LIMIT)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigChangesResponse {
+    /// Oldest first, the id of the last one is the `after_id` of the next page
+    pub changes: Vec<ConfigChangeInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigChangeInfo {
+    pub id: i64,
+    pub changed_at: chrono::DateTime<Utc>,
+    /// The admin who made the change, as forwarded by the admin ingress, or
+    /// their address
+    pub principal: String,
+    pub setting: String,
+    /// `None` if the setting didn't exist before
+    pub old_value: Option<serde_json::Value>,
+    /// `None` if the setting was removed
+    pub new_value: Option<serde_json::Value>,
+    pub request_id: Option<String>,
+}
+
+impl From<ConfigChangeEntry> for ConfigChangeInfo {
+    fn from(entry: ConfigChangeEntry) -> Self {
+        Self {
+            id: entry.id,
+            changed_at: entry.changed_at,
+            principal: entry.principal,
+            setting: entry.setting,
+            old_value: entry.old_value.map(|value| value.0),
+            new_value: entry.new_value.map(|value| value.0),
+            request_id: entry.request_id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportInfo {
+    pub id: i64,
+    pub created_at: chrono::DateTime<Utc>,
+    /// `None` while the export is running
+    pub completed_at: Option<chrono::DateTime<Utc>>,
+    /// The export holds every identity up to this one
+    pub up_to_sequence_id: usize,
+    pub last_sequence_id: usize,
+    pub identities: u64,
+    /// Download paths of the chunks written so far, each is newline-delimited
+    /// JSON in the format of the tree updates feed
+    pub chunks: Vec<String>,
+}
+
+impl From<ExportEntry> for ExportInfo {
+    fn from(entry: ExportEntry) -> Self {
+        Self {
+            id: entry.id,
+            created_at: entry.created_at,
+            completed_at: entry.completed_at,
+            up_to_sequence_id: entry.up_to_sequence_id,
+            last_sequence_id: entry.last_sequence_id,
+            identities: entry.identities,
+            chunks: (0..entry.chunks)
+                .map(|chunk| format!("/v2/admin/exports/{}/chunks/{chunk}", entry.id))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EraseIdentityResponse {
+    /// Number of rows which held auxiliary data, zero if it was erased before
+    pub redacted_rows: u64,
+    pub erased_at: chrono::DateTime<Utc>,
+}
+
+/// Where the latest requests inserting and deleting a commitment came from.
+/// Only served on admin routes, see `RequestOrigin`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityOriginResponse {
+    pub insertion: Option<RequestOriginInfo>,
+    pub deletion: Option<RequestOriginInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestOriginInfo {
+    pub source_ip: Option<IpAddr>,
+    pub user_agent: Option<String>,
+    pub trace_id: Option<String>,
+    pub span_id: Option<String>,
+}
+
+impl From<RequestOrigin> for RequestOriginInfo {
+    fn from(origin: RequestOrigin) -> Self {
+        let (trace_id, span_id) = origin
+            .trace
+            .map(|trace| (trace.trace_id, trace.span_id))
+            .unzip();
+
+        Self {
+            source_ip: origin.ip,
+            user_agent: origin.user_agent,
+            trace_id,
+            span_id,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkImportQuery {
+    /// Identifies the import across requests, lines already processed under
+    /// this id are skipped.
+    pub import_id: String,
+}
+
 impl From<InclusionProof> for InclusionProofResponse {
     fn from(value: InclusionProof) -> Self {
         Self {
@@ -139,6 +604,18 @@ impl From<RootItem> for VerifySemaphoreProofResponse {
     }
 }
 
+/// Whether a nullifier hash was spent by `/v2/verifySemaphoreProof`. Only the
+/// `spent` field is set for nullifiers which weren't.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct NullifierStatusResponse {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nullifier_hash: Option<Hash>,
+    pub spent: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spent_at: Option<chrono::DateTime<Utc>>,
+}
+
 impl ToResponseCode for VerifySemaphoreProofResponse {
     fn to_response_code(&self) -> StatusCode {
         StatusCode::OK
@@ -1,9 +1,12 @@
 use anyhow::Error as EyreError;
 use axum::response::IntoResponse;
+use axum::Json;
 use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use crate::database;
+use crate::identity::bulk_import::LineTooLong;
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -19,6 +22,10 @@ pub enum Error {
     InvalidRoot,
     #[error("invalid semaphore proof")]
     InvalidProof,
+    #[error("Too many proofs are waiting for verification, try again later.")]
+    VerificationOverloaded,
+    #[error("The nullifier hash of this proof was already spent.")]
+    NullifierAlreadySpent,
     #[error("provided identity index out of bounds")]
     IndexOutOfBounds,
     #[error("provided identity commitment not found")]
@@ -27,16 +34,30 @@ pub enum Error {
     InvalidCommitment,
     #[error("provided identity commitment is not in reduced form")]
     UnreducedCommitment,
+    #[error("provided signal hash is not in reduced form")]
+    InvalidSignalHash,
+    #[error("provided external nullifier hash is not in reduced form")]
+    InvalidExternalNullifierHash,
     #[error("provided identity commitment is already included")]
     DuplicateCommitment,
     #[error("Root mismatch between tree and contract.")]
     RootMismatch,
     #[error("Root provided in semaphore proof is too old.")]
     RootTooOld,
+    #[error("The max root age must not be negative.")]
+    InvalidMaxRootAge,
     #[error("Identity is already queued for deletion.")]
     IdentityQueuedForDeletion,
     #[error("Identity has already been deleted.")]
     IdentityAlreadyDeleted,
+    #[error("Forced deletions are only allowed through the admin route.")]
+    ForcedDeletionNotAllowed,
+    #[error("Deletion rate exceeded, try again later.")]
+    DeletionRateExceeded,
+    #[error("A reason is required to lift the deletion limits.")]
+    MissingLiftReason,
+    #[error(transparent)]
+    BulkImportLineTooLong(#[from] LineTooLong),
     #[error("invalid JSON request: {0}")]
     InvalidSerialization(#[from] serde_json::Error),
     #[error(transparent)]
@@ -55,16 +76,44 @@ pub enum Error {
     FailedToInsert,
     #[error("The provided batch size already exists")]
     BatchSizeAlreadyExists,
+    #[error("The prover could not be reached")]
+    ProverUnreachable,
     #[error("The requested batch size does not exist")]
     NoSuchBatchSize,
+    #[error("The requested batch does not exist")]
+    NoSuchBatch,
+    #[error("A batch note must not be empty")]
+    MissingBatchNote,
+    #[error("A batch note must be at most {max_length} characters long")]
+    BatchNoteTooLong { max_length: usize },
+    #[error("The requested batch is not awaiting approval")]
+    NoSuchBatchAwaitingApproval,
     #[error("The last batch size cannot be removed")]
     CannotRemoveLastBatchSize,
     #[error("Identity Manager had no provers on point of identity insertion.")]
     NoProversOnIdInsert,
     #[error("Identity Manager had no provers on point of identity deletion.")]
     NoProversOnIdDeletion,
+    #[error("The provided subscription url is invalid")]
+    InvalidSubscriptionUrl,
+    #[error("The requested subscription does not exist")]
+    NoSuchSubscription,
+    #[error("The aging threshold must be a percentage from 1 to 100")]
+    InvalidAgingThreshold,
+    #[error("Exports are not configured")]
+    ExportsDisabled,
+    #[error("The requested export does not exist")]
+    NoSuchExport,
+    #[error("The relayer has no such transaction status")]
+    InvalidTransactionStatus,
+    #[error("The requested offset is out of range")]
+    OffsetOutOfRange,
     #[error(transparent)]
     Sqlx(#[from] sqlx::Error),
+    #[error("The startup canary failed, the sequencer is not ready.")]
+    NotReady,
+    #[error("The database is unavailable.")]
+    DatabaseUnavailable,
     #[error("The tree is uninitialized. Try again in a few moments.")]
     TreeStateUninitialized,
     #[error(transparent)]
@@ -75,23 +124,99 @@ impl Error {
     fn to_status_code(&self) -> StatusCode {
         match self {
             Self::InvalidMethod => StatusCode::METHOD_NOT_ALLOWED,
-            Self::InvalidPath | Self::IdentityCommitmentNotFound => StatusCode::NOT_FOUND,
+            Self::InvalidPath
+            | Self::IdentityCommitmentNotFound
+            | Self::NoSuchSubscription
+            | Self::NoSuchBatchSize
+            | Self::NoSuchBatch
+            | Self::NoSuchBatchAwaitingApproval
+            | Self::ExportsDisabled
+            | Self::NoSuchExport => StatusCode::NOT_FOUND,
             Self::InvalidContentType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
-            Self::IndexOutOfBounds | Self::InvalidCommitment | Self::InvalidSerialization(_) => {
-                StatusCode::BAD_REQUEST
-            }
+            Self::ForcedDeletionNotAllowed => StatusCode::FORBIDDEN,
+            Self::IndexOutOfBounds
+            | Self::InvalidCommitment
+            | Self::InvalidSerialization(_)
+            | Self::InvalidSubscriptionUrl
+            | Self::InvalidAgingThreshold
+            | Self::InvalidMaxRootAge
+            | Self::InvalidSignalHash
+            | Self::InvalidExternalNullifierHash
+            | Self::InvalidTransactionStatus
+            | Self::OffsetOutOfRange
+            | Self::BulkImportLineTooLong(_)
+            | Self::MissingLiftReason
+            | Self::MissingBatchNote
+            | Self::BatchNoteTooLong { .. } => StatusCode::BAD_REQUEST,
             Self::IdentityAlreadyDeleted
             | Self::IdentityQueuedForDeletion
-            | Self::DuplicateCommitment => StatusCode::CONFLICT,
+            | Self::DuplicateCommitment
+            | Self::BatchSizeAlreadyExists
+            | Self::CannotRemoveLastBatchSize
+            | Self::NullifierAlreadySpent => StatusCode::CONFLICT,
+            Self::ProverUnreachable => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::DeletionRateExceeded => StatusCode::TOO_MANY_REQUESTS,
+            Self::NotReady | Self::DatabaseUnavailable | Self::VerificationOverloaded => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            _ if self.is_database_unavailable() => StatusCode::SERVICE_UNAVAILABLE,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
+
+    fn sqlx_error(&self) -> Option<&sqlx::Error> {
+        match self {
+            Self::Sqlx(err) | Self::Database(database::Error::InternalError(err)) => Some(err),
+            _ => None,
+        }
+    }
+
+    /// Whether the database couldn't be reached, as opposed to a query failing
+    fn is_database_unavailable(&self) -> bool {
+        matches!(self, Self::DatabaseUnavailable)
+            || matches!(
+                self.sqlx_error(),
+                Some(sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed | sqlx::Error::Io(_))
+            )
+    }
+
+    /// A stable identifier for errors that automation needs to tell apart.
+    /// These errors are returned as an [`ErrorResponse`].
+    fn error_id(&self) -> Option<&'static str> {
+        match self {
+            Self::BatchSizeAlreadyExists => Some("batch_size_exists"),
+            Self::ProverUnreachable => Some("prover_unreachable"),
+            Self::NoSuchBatchSize => Some("no_such_batch_size"),
+            Self::CannotRemoveLastBatchSize => Some("last_prover"),
+            Self::VerificationOverloaded => Some("verification_overloaded"),
+            Self::DeletionRateExceeded => Some("deletion_rate_exceeded"),
+            _ if self.is_database_unavailable() => Some("database_unavailable"),
+            Self::Database(_) | Self::Sqlx(_) => Some("database_error"),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorResponse {
+    pub error_id: String,
+    pub error_message: String,
 }
 
 impl IntoResponse for Error {
     fn into_response(self) -> axum::response::Response {
         let status_code = self.to_status_code();
 
+        if let Some(error_id) = self.error_id() {
+            let body = ErrorResponse {
+                error_id: error_id.to_string(),
+                error_message: self.to_string(),
+            };
+
+            return (status_code, Json(body)).into_response();
+        }
+
         let body = if let Self::Other(err) = self {
             format!("{err}")
         } else {
@@ -101,3 +226,26 @@ impl IntoResponse for Error {
         (status_code, body).into_response()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn error_responses_round_trip() {
+        let response = Error::NoSuchBatchSize.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+
+        // Clients match on the field names, v1 and v2 share them
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["errorId"], "no_such_batch_size");
+        assert_eq!(json["errorMessage"], Error::NoSuchBatchSize.to_string());
+
+        let error: ErrorResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(serde_json::to_value(&error).unwrap(), json);
+    }
+}
@@ -0,0 +1,137 @@
+use ethers::utils::keccak256;
+use hyper::header::IF_NONE_MATCH;
+use hyper::HeaderMap;
+
+use crate::identity_tree::{Hash, ProcessedStatus, TreeState, TreeVersionReadOps};
+
+/// How long clients may reuse a proof against a root that's no longer pending.
+const SETTLED_PROOF_MAX_AGE: &str = "public, max-age=60";
+
+/// An entity tag for responses derived from the in-memory trees only.
+///
+/// Covers the root of every tree version, so it changes with any update to the
+/// tree.
+pub fn tree_etag(tree_state: &TreeState) -> String {
+    let roots = [
+        tree_state.mined_tree().get_root(),
+        tree_state.processed_tree().get_root(),
+        tree_state.batching_tree().get_root(),
+        tree_state.latest_tree().get_root(),
+    ];
+
+    let mut preimage = Vec::with_capacity(32 * roots.len());
+    for root in &roots {
+        preimage.extend_from_slice(&root.to_be_bytes::<32>());
+    }
+
+    quoted_hash(&preimage)
+}
+
+/// An entity tag for the inclusion proof of `commitment` against `root`.
+///
+/// The proof itself only depends on the commitment and the root, the status
+/// is covered as well since a root keeps its proofs as it gets mined.
+pub fn proof_etag(commitment: &Hash, root: &Hash, status: ProcessedStatus) -> String {
+    let status: &str = status.into();
+
+    let mut preimage = Vec::with_capacity(64 + status.len());
+    preimage.extend_from_slice(&commitment.to_be_bytes::<32>());
+    preimage.extend_from_slice(&root.to_be_bytes::<32>());
+    preimage.extend_from_slice(status.as_bytes());
+
+    quoted_hash(&preimage)
+}
+
+/// The `Cache-Control` directive for a proof against a root of `status`.
+///
+/// Proofs against pending roots are revalidated on every request, as the root
+/// may still be replaced.
+pub fn proof_cache_control(status: ProcessedStatus) -> &'static str {
+    match status {
+        ProcessedStatus::Pending => "no-cache",
+        ProcessedStatus::Processed | ProcessedStatus::Mined => SETTLED_PROOF_MAX_AGE,
+    }
+}
+
+fn quoted_hash(preimage: &[u8]) -> String {
+    let hash: String = keccak256(preimage)
+        .iter()
+        .take(16)
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+
+    format!("\"{hash}\"")
+}
+
+/// Whether the `If-None-Match` header of a request lists `etag`.
+pub fn if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::header::HeaderValue;
+
+    use super::*;
+
+    fn headers(if_none_match: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(IF_NONE_MATCH, HeaderValue::from_str(if_none_match).unwrap());
+        headers
+    }
+
+    #[test]
+    fn if_none_match_lists() {
+        let etag = "\"abc\"";
+
+        assert!(if_none_match(&headers("\"abc\""), etag));
+        assert!(if_none_match(&headers("\"xyz\", W/\"abc\""), etag));
+        assert!(if_none_match(&headers("*"), etag));
+
+        assert!(!if_none_match(&headers("\"xyz\""), etag));
+        assert!(!if_none_match(&headers("abc"), etag));
+        assert!(!if_none_match(&HeaderMap::new(), etag));
+    }
+
+    #[test]
+    fn proof_etag_covers_commitment_root_and_status() {
+        let commitment = Hash::from(1);
+        let root = Hash::from(2);
+        let etag = proof_etag(&commitment, &root, ProcessedStatus::Pending);
+
+        assert_eq!(
+            proof_etag(&commitment, &root, ProcessedStatus::Pending),
+            etag
+        );
+        assert!(etag.starts_with('"') && etag.ends_with('"'));
+
+        assert_ne!(
+            proof_etag(&Hash::from(3), &root, ProcessedStatus::Pending),
+            etag
+        );
+        assert_ne!(
+            proof_etag(&commitment, &Hash::from(3), ProcessedStatus::Pending),
+            etag
+        );
+        assert_ne!(proof_etag(&commitment, &root, ProcessedStatus::Mined), etag);
+    }
+
+    #[test]
+    fn only_settled_proofs_are_cached() {
+        assert_eq!(proof_cache_control(ProcessedStatus::Pending), "no-cache");
+        assert_eq!(
+            proof_cache_control(ProcessedStatus::Processed),
+            "public, max-age=60"
+        );
+        assert_eq!(
+            proof_cache_control(ProcessedStatus::Mined),
+            "public, max-age=60"
+        );
+    }
+}
@@ -0,0 +1,351 @@
+use std::io::Write;
+
+use prometheus::proto::{Metric, MetricFamily, MetricType};
+use prometheus::Encoder;
+use tracing::warn;
+
+use crate::config::PrometheusOutputConfig;
+
+pub const OPENMETRICS_FORMAT: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Encodes metric families in the OpenMetrics 1.0 text format.
+///
+/// Unlike the Prometheus text format, counter families are named without the
+/// `_total` suffix their samples carry, histograms always end with a `+Inf`
+/// bucket and the exposition is terminated by an EOF marker.
+#[derive(Debug, Default)]
+pub struct OpenMetricsEncoder;
+
+impl Encoder for OpenMetricsEncoder {
+    fn encode<W: Write>(
+        &self,
+        metric_families: &[MetricFamily],
+        writer: &mut W,
+    ) -> prometheus::Result<()> {
+        for family in metric_families {
+            let metric_type = family.get_field_type();
+            let name = match metric_type {
+                MetricType::COUNTER => family
+                    .get_name()
+                    .strip_suffix("_total")
+                    .unwrap_or(family.get_name()),
+                _ => family.get_name(),
+            };
+            let type_name = match metric_type {
+                MetricType::COUNTER => "counter",
+                MetricType::GAUGE => "gauge",
+                MetricType::HISTOGRAM => "histogram",
+                MetricType::SUMMARY => "summary",
+                MetricType::UNTYPED => "unknown",
+            };
+
+            writeln!(writer, "# TYPE {name} {type_name}")?;
+            if !family.get_help().is_empty() {
+                writeln!(writer, "# HELP {name} {}", escape(family.get_help()))?;
+            }
+
+            for metric in family.get_metric() {
+                match metric_type {
+                    MetricType::COUNTER => {
+                        let value = metric.get_counter().get_value();
+                        write_sample(writer, name, "_total", metric, None, value)?;
+                    }
+                    MetricType::GAUGE => {
+                        let value = metric.get_gauge().get_value();
+                        write_sample(writer, name, "", metric, None, value)?;
+                    }
+                    MetricType::UNTYPED => {
+                        let value = metric.get_untyped().get_value();
+                        write_sample(writer, name, "", metric, None, value)?;
+                    }
+                    MetricType::HISTOGRAM => {
+                        let histogram = metric.get_histogram();
+                        let count = histogram.get_sample_count() as f64;
+
+                        let mut inf_seen = false;
+                        for bucket in histogram.get_bucket() {
+                            let upper_bound = bucket.get_upper_bound();
+                            inf_seen |= upper_bound == f64::INFINITY;
+                            write_sample(
+                                writer,
+                                name,
+                                "_bucket",
+                                metric,
+                                Some(("le", &format_bound(upper_bound))),
+                                bucket.get_cumulative_count() as f64,
+                            )?;
+                        }
+                        if !inf_seen {
+                            write_sample(
+                                writer,
+                                name,
+                                "_bucket",
+                                metric,
+                                Some(("le", "+Inf")),
+                                count,
+                            )?;
+                        }
+
+                        write_sample(writer, name, "_count", metric, None, count)?;
+                        let sum = histogram.get_sample_sum();
+                        write_sample(writer, name, "_sum", metric, None, sum)?;
+                    }
+                    MetricType::SUMMARY => {
+                        let summary = metric.get_summary();
+
+                        for quantile in summary.get_quantile() {
+                            write_sample(
+                                writer,
+                                name,
+                                "",
+                                metric,
+                                Some(("quantile", &format_bound(quantile.get_quantile()))),
+                                quantile.get_value(),
+                            )?;
+                        }
+
+                        let count = summary.get_sample_count() as f64;
+                        write_sample(writer, name, "_count", metric, None, count)?;
+                        let sum = summary.get_sample_sum();
+                        write_sample(writer, name, "_sum", metric, None, sum)?;
+                    }
+                }
+            }
+        }
+
+        writeln!(writer, "# EOF")?;
+
+        Ok(())
+    }
+
+    fn format_type(&self) -> &str {
+        OPENMETRICS_FORMAT
+    }
+}
+
+fn write_sample(
+    writer: &mut impl Write,
+    name: &str,
+    suffix: &str,
+    metric: &Metric,
+    extra_label: Option<(&str, &str)>,
+    value: f64,
+) -> std::io::Result<()> {
+    write!(writer, "{name}{suffix}")?;
+
+    let labels = metric
+        .get_label()
+        .iter()
+        .map(|label| (label.get_name(), label.get_value()))
+        .chain(extra_label);
+    let mut separator = "{";
+    for (label, label_value) in labels {
+        write!(writer, "{separator}{label}=\"{}\"", escape(label_value))?;
+        separator = ",";
+    }
+    if separator == "," {
+        write!(writer, "}}")?;
+    }
+
+    writeln!(writer, " {}", format_value(value))
+}
+
+fn format_value(value: f64) -> String {
+    if value == f64::INFINITY {
+        "+Inf".to_owned()
+    } else if value == f64::NEG_INFINITY {
+        "-Inf".to_owned()
+    } else {
+        // NaN is formatted as `NaN` already
+        value.to_string()
+    }
+}
+
+/// Bucket bounds and quantiles are label values, which OpenMetrics expects in
+/// a canonical form, e.g. `1.0` rather than `1`.
+fn format_bound(value: f64) -> String {
+    if value.is_finite() && value.fract() == 0.0 {
+        format!("{value:.1}")
+    } else {
+        format_value(value)
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('"', "\\\"")
+}
+
+/// Applies the output limits of `config` to gathered metric families.
+pub fn filter_metric_families(
+    metric_families: Vec<MetricFamily>,
+    config: &PrometheusOutputConfig,
+) -> Vec<MetricFamily> {
+    let mut metric_families: Vec<_> = metric_families
+        .into_iter()
+        .filter_map(|mut family| {
+            if config.strip_zero_valued_vectors && family.get_field_type() == MetricType::COUNTER {
+                let metrics: Vec<_> = family
+                    .take_metric()
+                    .into_iter()
+                    .filter(|metric| {
+                        metric.get_label().is_empty() || metric.get_counter().get_value() != 0.0
+                    })
+                    .collect();
+
+                // The encoder refuses families without any samples
+                if metrics.is_empty() {
+                    return None;
+                }
+
+                family.set_metric(metrics.into());
+            }
+
+            Some(family)
+        })
+        .collect();
+
+    if metric_families.len() > config.max_families {
+        warn!(
+            total = metric_families.len(),
+            max_families = config.max_families,
+            "Truncating metrics output"
+        );
+        metric_families.truncate(config.max_families);
+    }
+
+    metric_families
+}
+
+#[cfg(test)]
+mod tests {
+    use prometheus::{
+        Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge, Opts, Registry,
+    };
+
+    use super::*;
+
+    fn registry() -> Registry {
+        let registry = Registry::new();
+
+        let requests = IntCounterVec::new(
+            Opts::new("requests_total", "Requests, by route"),
+            &["route"],
+        )
+        .unwrap();
+        requests.with_label_values(&["/insert"]).inc_by(2);
+        requests.with_label_values(&["/delete"]);
+        registry.register(Box::new(requests)).unwrap();
+
+        let unused =
+            IntCounterVec::new(Opts::new("unused", "Never incremented"), &["route"]).unwrap();
+        unused.with_label_values(&["/insert"]);
+        registry.register(Box::new(unused)).unwrap();
+
+        let restarts = IntCounter::new("restarts", "Restarts").unwrap();
+        registry.register(Box::new(restarts)).unwrap();
+
+        let pending = IntGauge::new("pending", "Pending \"identities\"").unwrap();
+        pending.set(3);
+        registry.register(Box::new(pending)).unwrap();
+
+        let latency =
+            Histogram::with_opts(HistogramOpts::new("latency", "Latency").buckets(vec![1.0]))
+                .unwrap();
+        latency.observe(0.5);
+        registry.register(Box::new(latency)).unwrap();
+
+        registry
+    }
+
+    fn names(metric_families: &[MetricFamily]) -> Vec<&str> {
+        metric_families
+            .iter()
+            .map(|family| family.get_name())
+            .collect()
+    }
+
+    #[test]
+    fn strips_zero_valued_label_sets() {
+        let config = PrometheusOutputConfig {
+            strip_zero_valued_vectors: true,
+            max_families: 100,
+        };
+
+        let metric_families = filter_metric_families(registry().gather(), &config);
+
+        // Families only holding zero valued label sets are dropped, unlabelled
+        // counters are kept at zero
+        assert_eq!(
+            names(&metric_families),
+            ["latency", "pending", "requests_total", "restarts"]
+        );
+        let requests = &metric_families[2];
+        assert_eq!(requests.get_metric().len(), 1);
+        assert_eq!(
+            requests.get_metric()[0].get_label()[0].get_value(),
+            "/insert"
+        );
+    }
+
+    #[test]
+    fn keeps_zero_valued_label_sets_unless_configured() {
+        let config = PrometheusOutputConfig {
+            strip_zero_valued_vectors: false,
+            max_families: 100,
+        };
+
+        let metric_families = filter_metric_families(registry().gather(), &config);
+
+        assert_eq!(metric_families.len(), 5);
+        assert_eq!(metric_families[2].get_metric().len(), 2);
+    }
+
+    #[test]
+    fn caps_families() {
+        let config = PrometheusOutputConfig {
+            strip_zero_valued_vectors: false,
+            max_families: 2,
+        };
+
+        let metric_families = filter_metric_families(registry().gather(), &config);
+
+        assert_eq!(names(&metric_families), ["latency", "pending"]);
+    }
+
+    #[test]
+    fn encodes_openmetrics() {
+        let config = PrometheusOutputConfig {
+            strip_zero_valued_vectors: true,
+            max_families: 100,
+        };
+        let metric_families = filter_metric_families(registry().gather(), &config);
+
+        let mut buffer = vec![];
+        OpenMetricsEncoder
+            .encode(&metric_families, &mut buffer)
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "# TYPE latency histogram\n\
+             # HELP latency Latency\n\
+             latency_bucket{le=\"1.0\"} 1\n\
+             latency_bucket{le=\"+Inf\"} 1\n\
+             latency_count 1\n\
+             latency_sum 0.5\n\
+             # TYPE pending gauge\n\
+             # HELP pending Pending \\\"identities\\\"\n\
+             pending 3\n\
+             # TYPE requests counter\n\
+             # HELP requests Requests, by route\n\
+             requests_total{route=\"/insert\"} 2\n\
+             # TYPE restarts counter\n\
+             # HELP restarts Restarts\n\
+             restarts_total 0\n\
+             # EOF\n"
+        );
+    }
+}
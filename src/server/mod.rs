@@ -1,32 +1,51 @@
 pub mod error;
 
+use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
 
 use axum::body::Body;
-use axum::extract::{Query, State};
-use axum::response::Response;
-use axum::routing::{get, post};
+use axum::extract::{ConnectInfo, Path, Query, State};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post};
 use axum::{middleware, Json, Router};
+use axum_server::tls_rustls::RustlsConfig;
 use error::Error;
-use hyper::header::CONTENT_TYPE;
-use hyper::StatusCode;
-use prometheus::{Encoder, TextEncoder};
+use hyper::header::{CACHE_CONTROL, CONTENT_TYPE, ETAG};
+use hyper::{HeaderMap, StatusCode};
+use prometheus::{Encoder, TextEncoder, TEXT_FORMAT};
 use tokio::net::TcpListener;
 use tower_http::catch_panic::{CatchPanicLayer, ResponseForPanic};
 use tracing::info;
 
+use self::custom_middleware::deprecation_layer::V1Deprecation;
+use self::etag::{if_none_match, proof_cache_control, proof_etag, tree_etag};
+use self::exposition::{filter_metric_families, OpenMetricsEncoder, OPENMETRICS_FORMAT};
+use self::origin::{change_author, request_origin};
 use crate::app::App;
 use crate::config::ServerConfig;
+use crate::database::methods::DbMethods as _;
+use crate::database::types::BatchApproval;
+use crate::identity_tree::{Hash, Status};
 use crate::shutdown::Shutdown;
 
 mod custom_middleware;
 pub mod data;
+mod etag;
+mod exposition;
+mod origin;
 
 use self::data::{
-    AddBatchSizeRequest, DeletionRequest, InclusionProofRequest, InclusionProofResponse,
-    InsertCommitmentRequest, ListBatchSizesResponse, RemoveBatchSizeRequest, ToResponseCode,
-    VerifySemaphoreProofQuery, VerifySemaphoreProofRequest, VerifySemaphoreProofResponse,
+    AddBatchSizeQuery, AddBatchSizeRequest, AddBatchSizeResponse, AdminStatusResponse,
+    BatchNoteRequest, BatchResponse, BulkImportQuery, BulkImportResponse, ConfigChangesQuery,
+    ConfigChangesResponse, DeletionQuery, DeletionRequest, EraseIdentityResponse, ExportInfo,
+    IdentityCountResponse, IdentityOriginResponse, InclusionProofRequest, InclusionProofResponse,
+    InsertCommitmentRequest, LiftDeletionLimitRequest, LiftDeletionLimitResponse,
+    ListBatchSizesResponse, MetricsFormat, MetricsQuery, NullifierStatusResponse,
+    RemoveBatchSizeRequest, RootSubscriptionRequest, SimulateInsertResponse, ToResponseCode,
+    TransactionsQuery, TransactionsResponse, TreeUpdatesQuery, TreeUpdatesResponse,
+    ValidateInsertResponse, VerifySemaphoreProofQuery, VerifySemaphoreProofRequest,
+    VerifySemaphoreProofResponse,
 };
 
 async fn inclusion_proof(
@@ -40,16 +59,86 @@ async fn inclusion_proof(
     Ok((result.to_response_code(), Json(result)))
 }
 
+/// Only proofs are tagged, the response for commitments which aren't in the
+/// tree yet changes without their root changing.
+async fn inclusion_proof_v2(
+    State(app): State<Arc<App>>,
+    Path(commitment): Path<Hash>,
+    headers: HeaderMap,
+) -> Result<Response, Error> {
+    let result = app.inclusion_proof(&commitment).await?;
+    let status = result.to_response_code();
+
+    let (Some(root), Status::Processed(root_status)) = (result.root, result.status) else {
+        return Ok((status, Json(result)).into_response());
+    };
+    if result.proof.is_none() {
+        return Ok((status, Json(result)).into_response());
+    }
+
+    let caching = [
+        (ETAG, proof_etag(&commitment, &root, root_status)),
+        (CACHE_CONTROL, proof_cache_control(root_status).to_owned()),
+    ];
+    if if_none_match(&headers, &caching[0].1) {
+        return Ok((StatusCode::NOT_MODIFIED, caching).into_response());
+    }
+
+    Ok((status, caching, Json(result)).into_response())
+}
+
+async fn tree_info(State(app): State<Arc<App>>, headers: HeaderMap) -> Result<Response, Error> {
+    let etag = tree_etag(app.tree_state()?);
+    if if_none_match(&headers, &etag) {
+        return Ok((StatusCode::NOT_MODIFIED, [(ETAG, etag)]).into_response());
+    }
+
+    let result = app.tree_info()?;
+
+    Ok(([(ETAG, etag)], Json(result)).into_response())
+}
+
+async fn tree_updates(
+    State(app): State<Arc<App>>,
+    Query(query): Query<TreeUpdatesQuery>,
+) -> Result<Json<TreeUpdatesResponse>, Error> {
+    let result = app.tree_updates(&query).await?;
+
+    Ok(Json(result))
+}
+
 async fn insert_identity(
     State(app): State<Arc<App>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(insert_identity_request): Json<InsertCommitmentRequest>,
 ) -> Result<(), Error> {
-    app.insert_identity(insert_identity_request.identity_commitment)
+    let origin = request_origin(peer.ip(), &headers, &app.server_config().trusted_proxies.0);
+
+    app.insert_identity(insert_identity_request.identity_commitment, origin)
         .await?;
 
     Ok(())
 }
 
+async fn simulate_insert_identity(
+    State(app): State<Arc<App>>,
+    Path(commitment): Path<Hash>,
+) -> Result<Json<SimulateInsertResponse>, Error> {
+    let result = app.simulate_insert_identity(commitment).await?;
+
+    Ok(Json(result))
+}
+
+async fn validate_insert_identity(
+    State(app): State<Arc<App>>,
+    Path(commitment): Path<Hash>,
+) -> Result<Json<ValidateInsertResponse>, Error> {
+    let result = app.validate_insert_identity(commitment).await?;
+
+    Ok(Json(result))
+}
+
 async fn verify_semaphore_proof(
     State(app): State<Arc<App>>,
     Query(verify_semaphore_proof_query): Query<VerifySemaphoreProofQuery>,
@@ -65,39 +154,180 @@ async fn verify_semaphore_proof(
     Ok((result.to_response_code(), Json(result)))
 }
 
+/// Malformed hashes are rejected by the `Path` extractor with a 400.
+async fn nullifier_status(
+    State(app): State<Arc<App>>,
+    Path(nullifier_hash): Path<Hash>,
+) -> Result<Json<NullifierStatusResponse>, Error> {
+    let result = app.nullifier_status(&nullifier_hash).await?;
+
+    Ok(Json(result))
+}
+
+async fn verify_semaphore_proof_v2(
+    State(app): State<Arc<App>>,
+    Query(verify_semaphore_proof_query): Query<VerifySemaphoreProofQuery>,
+    Json(verify_semaphore_proof_request): Json<VerifySemaphoreProofRequest>,
+) -> Result<(StatusCode, Json<VerifySemaphoreProofResponse>), Error> {
+    let result = app
+        .verify_semaphore_proof_v2(
+            &verify_semaphore_proof_request,
+            &verify_semaphore_proof_query,
+        )
+        .await?;
+
+    Ok((result.to_response_code(), Json(result)))
+}
+
 async fn add_batch_size(
     State(app): State<Arc<App>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Query(query): Query<AddBatchSizeQuery>,
     Json(req): Json<AddBatchSizeRequest>,
-) -> Result<(), Error> {
-    app.add_batch_size(
-        req.url,
-        req.batch_size,
-        req.timeout_seconds,
-        req.prover_type,
-    )
-    .await?;
+) -> Result<(StatusCode, Json<AddBatchSizeResponse>), Error> {
+    let author = change_author(peer.ip(), &headers, &app.server_config().trusted_proxies.0);
 
-    Ok(())
+    let result = app
+        .add_batch_size(
+            req.url,
+            req.batch_size,
+            req.timeout_seconds,
+            req.prover_type,
+            req.probe || query.verify,
+            &author,
+        )
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(result)))
 }
 
 async fn delete_identity(
     State(app): State<Arc<App>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Query(query): Query<DeletionQuery>,
     Json(req): Json<DeletionRequest>,
-) -> Result<(), Error> {
-    app.delete_identity(&req.identity_commitment).await?;
-    Ok(())
+) -> Result<StatusCode, Error> {
+    if query.force {
+        return Err(Error::ForcedDeletionNotAllowed);
+    }
+
+    let origin = request_origin(peer.ip(), &headers, &app.server_config().trusted_proxies.0);
+
+    app.delete_identity(&req.identity_commitment, origin)
+        .await?;
+    Ok(StatusCode::OK)
+}
+
+/// Purges the commitment if `force` is set and it's still queued for
+/// insertion, otherwise queues a regular deletion.
+async fn admin_delete_identity(
+    State(app): State<Arc<App>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(commitment): Path<Hash>,
+    Query(query): Query<DeletionQuery>,
+) -> Result<StatusCode, Error> {
+    if query.force && app.purge_unprocessed_identity(&commitment).await? {
+        return Ok(StatusCode::ACCEPTED);
+    }
+
+    let origin = request_origin(peer.ip(), &headers, &app.server_config().trusted_proxies.0);
+
+    app.delete_identity(&commitment, origin).await?;
+    Ok(StatusCode::OK)
+}
+
+async fn erase_identity(
+    State(app): State<Arc<App>>,
+    Path(commitment): Path<Hash>,
+) -> Result<Json<EraseIdentityResponse>, Error> {
+    let result = app.erase_identity(&commitment).await?;
+
+    Ok(Json(result))
+}
+
+async fn identity_origin(
+    State(app): State<Arc<App>>,
+    Path(commitment): Path<Hash>,
+) -> Result<Json<IdentityOriginResponse>, Error> {
+    let result = app.identity_origin(&commitment).await?;
+
+    Ok(Json(result))
+}
+
+async fn lift_deletion_limit(
+    State(app): State<Arc<App>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(req): Json<LiftDeletionLimitRequest>,
+) -> Result<Json<LiftDeletionLimitResponse>, Error> {
+    let author = change_author(peer.ip(), &headers, &app.server_config().trusted_proxies.0);
+
+    let result = app
+        .lift_deletion_limit(&req.reason, req.duration, &author)
+        .await?;
+
+    Ok(Json(result))
+}
+
+/// Bulk import bodies are streamed, see the logging layer
+pub(crate) const BULK_IMPORT_PATH: &str = "/v2/admin/bulk-import";
+
+async fn bulk_import(
+    State(app): State<Arc<App>>,
+    Query(query): Query<BulkImportQuery>,
+    body: Body,
+) -> Result<Json<BulkImportResponse>, Error> {
+    let result = app
+        .bulk_import(query.import_id, body.into_data_stream())
+        .await?;
+
+    Ok(Json(result))
 }
 
 async fn remove_batch_size(
     State(app): State<Arc<App>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(req): Json<RemoveBatchSizeRequest>,
 ) -> Result<(), Error> {
-    app.remove_batch_size(req.batch_size, req.prover_type)
+    let author = change_author(peer.ip(), &headers, &app.server_config().trusted_proxies.0);
+
+    app.remove_batch_size(req.batch_size, req.prover_type, &author)
         .await?;
 
     Ok(())
 }
 
+async fn add_root_subscription(
+    State(app): State<Arc<App>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(req): Json<RootSubscriptionRequest>,
+) -> Result<(), Error> {
+    let author = change_author(peer.ip(), &headers, &app.server_config().trusted_proxies.0);
+
+    app.add_root_subscription(&req.url, req.aging_threshold_percent, &author)
+        .await?;
+
+    Ok(())
+}
+
+async fn remove_root_subscription(
+    State(app): State<Arc<App>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(req): Json<RootSubscriptionRequest>,
+) -> Result<(), Error> {
+    let author = change_author(peer.ip(), &headers, &app.server_config().trusted_proxies.0);
+
+    app.remove_root_subscription(&req.url, &author).await?;
+
+    Ok(())
+}
+
 async fn list_batch_sizes(
     State(app): State<Arc<App>>,
 ) -> Result<(StatusCode, Json<ListBatchSizesResponse>), Error> {
@@ -106,22 +336,163 @@ async fn list_batch_sizes(
     Ok((result.to_response_code(), Json(result)))
 }
 
+async fn identity_count(State(app): State<Arc<App>>) -> Result<Json<IdentityCountResponse>, Error> {
+    Ok(Json(app.identity_count()?))
+}
+
 async fn health() -> Result<(), Error> {
     Ok(())
 }
 
-async fn metrics() -> Result<Response<Body>, Error> {
-    let encoder = TextEncoder::new();
+const READY_DATABASE_TIMEOUT: Duration = Duration::from_secs(2);
+
+async fn ready(State(app): State<Arc<App>>) -> Result<(), Error> {
+    if !app.is_ready() {
+        return Err(Error::NotReady);
+    }
+
+    // Don't wait for the pool's acquire timeout if the database is down
+    let ping = tokio::time::timeout(READY_DATABASE_TIMEOUT, app.database.ping()).await;
+    if !matches!(ping, Ok(Ok(()))) {
+        return Err(Error::DatabaseUnavailable);
+    }
+
+    Ok(())
+}
+
+async fn admin_status(State(app): State<Arc<App>>) -> Result<Json<AdminStatusResponse>, Error> {
+    Ok(Json(AdminStatusResponse {
+        ready: app.is_ready(),
+        batching_paused: app.is_batching_paused(),
+        sla_breached: app.is_sla_breached(),
+        batches_awaiting_approval: app.database.get_batches_awaiting_approval().await?,
+    }))
+}
+
+async fn get_batch(
+    State(app): State<Arc<App>>,
+    Path(next_root): Path<Hash>,
+) -> Result<Json<BatchResponse>, Error> {
+    Ok(Json(app.batch(&next_root).await?))
+}
+
+async fn annotate_batch(
+    State(app): State<Arc<App>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(next_root): Path<Hash>,
+    Json(req): Json<BatchNoteRequest>,
+) -> Result<Json<BatchResponse>, Error> {
+    let author = change_author(peer.ip(), &headers, &app.server_config().trusted_proxies.0);
+
+    let result = app.annotate_batch(&next_root, &req.note, &author).await?;
+
+    Ok(Json(result))
+}
+
+async fn approve_batch(
+    State(app): State<Arc<App>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(next_root): Path<Hash>,
+) -> Result<(), Error> {
+    let author = change_author(peer.ip(), &headers, &app.server_config().trusted_proxies.0);
+
+    app.decide_batch_approval(&next_root, BatchApproval::Approved, &author)
+        .await
+}
+
+async fn reject_batch(
+    State(app): State<Arc<App>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(next_root): Path<Hash>,
+) -> Result<StatusCode, Error> {
+    let author = change_author(peer.ip(), &headers, &app.server_config().trusted_proxies.0);
+
+    app.decide_batch_approval(&next_root, BatchApproval::Rejected, &author)
+        .await?;
+
+    // The batch is unwound in the background
+    Ok(StatusCode::ACCEPTED)
+}
+
+async fn list_config_changes(
+    State(app): State<Arc<App>>,
+    Query(query): Query<ConfigChangesQuery>,
+) -> Result<Json<ConfigChangesResponse>, Error> {
+    let result = app.config_changes(&query).await?;
+
+    Ok(Json(result))
+}
+
+async fn start_export(
+    State(app): State<Arc<App>>,
+) -> Result<(StatusCode, Json<ExportInfo>), Error> {
+    let result = app.start_export().await?;
+
+    // The export is written in the background
+    Ok((StatusCode::ACCEPTED, Json(result)))
+}
+
+async fn get_export(
+    State(app): State<Arc<App>>,
+    Path(id): Path<i64>,
+) -> Result<Json<ExportInfo>, Error> {
+    Ok(Json(app.export(id).await?))
+}
+
+const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+async fn get_export_chunk(
+    State(app): State<Arc<App>>,
+    Path((id, chunk)): Path<(i64, u64)>,
+) -> Result<Response<Body>, Error> {
+    let contents = app.export_chunk(id, chunk).await?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, NDJSON_CONTENT_TYPE)
+        .body(Body::from(contents))?)
+}
+
+async fn list_transactions(
+    State(app): State<Arc<App>>,
+    Query(query): Query<TransactionsQuery>,
+) -> Result<Json<TransactionsResponse>, Error> {
+    let result = app.transactions(&query).await?;
+
+    Ok(Json(result))
+}
+
+async fn metrics(
+    State(app): State<Arc<App>>,
+    Query(query): Query<MetricsQuery>,
+) -> Result<Response<Body>, Error> {
+    let metric_families = filter_metric_families(
+        app.metrics().registry().gather(),
+        &app.server_config().prometheus_output,
+    );
 
-    let metric_families = prometheus::gather();
     let mut buffer = vec![];
-    encoder
-        .encode(&metric_families, &mut buffer)
-        .map_err(|e| Error::Other(e.into()))?;
+    let format_type = match query.format {
+        MetricsFormat::Text => {
+            TextEncoder::new()
+                .encode(&metric_families, &mut buffer)
+                .map_err(|e| Error::Other(e.into()))?;
+            TEXT_FORMAT
+        }
+        MetricsFormat::OpenMetrics => {
+            OpenMetricsEncoder
+                .encode(&metric_families, &mut buffer)
+                .map_err(|e| Error::Other(e.into()))?;
+            OPENMETRICS_FORMAT
+        }
+    };
 
     let response = Response::builder()
         .status(200)
-        .header(CONTENT_TYPE, encoder.format_type())
+        .header(CONTENT_TYPE, format_type)
         .body(Body::from(buffer))?;
 
     Ok(response)
@@ -136,7 +507,7 @@ pub async fn run(app: Arc<App>, config: ServerConfig, shutdown: Shutdown) -> any
     info!("Will listen on {}", config.address);
     let listener = TcpListener::bind(config.address).await?;
 
-    bind_from_listener(app, config.serve_timeout, listener, shutdown).await?;
+    bind_from_listener(app, &config, listener, shutdown).await?;
 
     Ok(())
 }
@@ -165,31 +536,127 @@ impl ResponseForPanic for PanicHandler {
 /// if the server fails to bind to the given address.
 pub async fn bind_from_listener(
     app: Arc<App>,
-    serve_timeout: Duration,
+    config: &ServerConfig,
     listener: TcpListener,
     shutdown: Shutdown,
 ) -> anyhow::Result<()> {
-    let router = Router::new()
-        // Operate on identity commitments
+    let v1_deprecation_layer = middleware::from_fn_with_state(
+        Arc::new(V1Deprecation::new(
+            config.v1_sunset_date,
+            config.trusted_proxies.0.clone(),
+            app.metrics(),
+        )),
+        custom_middleware::deprecation_layer::middleware,
+    );
+
+    let v1_read_routes = Router::new()
         .route("/verifySemaphoreProof", post(verify_semaphore_proof))
         .route("/inclusionProof", post(inclusion_proof))
-        .route("/insertIdentity", post(insert_identity))
-        .route("/deleteIdentity", post(delete_identity))
-        // Operate on batch sizes
-        .route("/addBatchSize", post(add_batch_size))
-        .route("/removeBatchSize", post(remove_batch_size))
         .route("/listBatchSizes", get(list_batch_sizes))
+        .layer(v1_deprecation_layer.clone());
+
+    let read_routes = Router::new()
+        .merge(v1_read_routes)
+        .route("/v2/verifySemaphoreProof", post(verify_semaphore_proof_v2))
+        .route(
+            "/v2/semaphore-proof/nullifiers/:nullifier_hash",
+            get(nullifier_status),
+        )
+        .route(
+            "/v2/identities/:commitment/inclusion-proof",
+            get(inclusion_proof_v2),
+        )
+        .route(
+            "/v2/identities/:commitment/validate",
+            get(validate_insert_identity),
+        )
+        .route("/v2/identities/count", get(identity_count))
+        .route("/v2/tree/info", get(tree_info))
+        .route("/v2/tree/updates", get(tree_updates))
         // Health check, return 200 OK
         .route("/health", get(health))
+        // Readiness check, fails once the startup canary failed
+        .route("/ready", get(ready))
         .route("/metrics", get(metrics))
-        .layer(middleware::from_fn(
-            custom_middleware::api_metrics_layer::middleware,
-        ))
-        .layer(CatchPanicLayer::custom(PanicHandler {}))
         .layer(middleware::from_fn_with_state(
-            serve_timeout,
+            config.read_timeout(),
+            custom_middleware::timeout_layer::middleware,
+        ));
+
+    // Both write routes are part of the v1 API
+    let write_routes = Router::new()
+        .route("/insertIdentity", post(insert_identity))
+        .route("/deleteIdentity", post(delete_identity))
+        .layer(v1_deprecation_layer)
+        .layer(middleware::from_fn_with_state(
+            config.write_timeout(),
             custom_middleware::timeout_layer::middleware,
+        ));
+
+    let admin_routes = Router::new()
+        .route("/addBatchSize", post(add_batch_size))
+        .route("/removeBatchSize", post(remove_batch_size))
+        .route(
+            "/v2/admin/identities/:commitment",
+            delete(admin_delete_identity),
+        )
+        .route("/admin/liftDeletionLimit", post(lift_deletion_limit))
+        .route("/v2/admin/status", get(admin_status))
+        .route("/v2/admin/transactions", get(list_transactions))
+        .route("/v2/admin/config-changes", get(list_config_changes))
+        .route("/v2/admin/exports", post(start_export))
+        .route("/v2/admin/exports/:id", get(get_export))
+        .route(
+            "/v2/admin/batches/:root",
+            get(get_batch).patch(annotate_batch),
+        )
+        .route("/v2/admin/batches/:root/approve", post(approve_batch))
+        .route("/v2/admin/batches/:root/reject", post(reject_batch))
+        .route(
+            "/v2/admin/identities/:commitment/erase",
+            post(erase_identity),
+        )
+        // Abuse investigation data, never to be served on the read routes
+        .route(
+            "/v2/admin/identities/:commitment/origin",
+            get(identity_origin),
+        )
+        .route(
+            "/v2/admin/identities/:commitment/simulate",
+            post(simulate_insert_identity),
+        )
+        // Operate on root notification subscriptions, like the batch size
+        // routes these must only be reachable through the authenticated
+        // admin ingress
+        .route(
+            "/v2/admin/subscriptions",
+            post(add_root_subscription).delete(remove_root_subscription),
+        )
+        .layer(middleware::from_fn_with_state(
+            config.admin_timeout(),
+            custom_middleware::timeout_layer::middleware,
+        ));
+
+    // Bulk imports stream arbitrarily large bodies and checkpoint their
+    // progress, so they run without a timeout
+    let bulk_import_routes = Router::new().route(BULK_IMPORT_PATH, post(bulk_import));
+
+    // Export chunks hold up to `ExportsConfig::chunk_size` identities each, the
+    // admin timeout is meant for much smaller responses
+    let export_download_routes =
+        Router::new().route("/v2/admin/exports/:id/chunks/:chunk", get(get_export_chunk));
+
+    let router = Router::new()
+        .merge(read_routes)
+        .merge(write_routes)
+        .merge(admin_routes)
+        .merge(bulk_import_routes)
+        .merge(export_download_routes)
+        .layer(middleware::from_fn_with_state(
+            app.metrics().clone(),
+            custom_middleware::api_metrics_layer::middleware,
         ))
+        .layer(CatchPanicLayer::custom(PanicHandler {}))
         .layer(middleware::from_fn(
             custom_middleware::logging_layer::middleware,
         ))
@@ -200,11 +667,32 @@ pub async fn bind_from_listener(
 
     let _shutdown_handle = shutdown.handle();
 
-    let server = axum::serve(listener, router).with_graceful_shutdown(async move {
-        shutdown.await_shutdown_begin().await;
-    });
-
-    server.await?;
+    let service = router.into_make_service_with_connect_info::<SocketAddr>();
+
+    if let Some(tls) = &config.tls {
+        let rustls_config = RustlsConfig::from_pem_file(&tls.cert_path, &tls.key_path).await?;
+
+        let handle = axum_server::Handle::new();
+        tokio::spawn({
+            let handle = handle.clone();
+            async move {
+                shutdown.await_shutdown_begin().await;
+                handle.graceful_shutdown(None);
+            }
+        });
+
+        info!("Serving over TLS");
+        axum_server::from_tcp_rustls(listener.into_std()?, rustls_config)
+            .handle(handle)
+            .serve(service)
+            .await?;
+    } else {
+        axum::serve(listener, service)
+            .with_graceful_shutdown(async move {
+                shutdown.await_shutdown_begin().await;
+            })
+            .await?;
+    }
 
     info!("Server gracefully shutdown");
 
@@ -0,0 +1,203 @@
+use std::net::IpAddr;
+
+use hyper::header::USER_AGENT;
+use hyper::HeaderMap;
+
+use crate::database::types::{ChangeAuthor, RequestOrigin};
+use crate::utils::trace_links::current_request_trace;
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+const X_FORWARDED_USER: &str = "x-forwarded-user";
+const X_REQUEST_ID: &str = "x-request-id";
+
+/// Longer user agents are truncated before being stored
+const MAX_USER_AGENT_LEN: usize = 256;
+
+/// Builds the [`RequestOrigin`] of a request received from `peer`.
+pub fn request_origin(
+    peer: IpAddr,
+    headers: &HeaderMap,
+    trusted_proxies: &[IpAddr],
+) -> RequestOrigin {
+    let user_agent = headers
+        .get(USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(|user_agent| user_agent.chars().take(MAX_USER_AGENT_LEN).collect());
+
+    RequestOrigin {
+        ip: Some(client_ip(peer, headers, trusted_proxies)),
+        user_agent,
+        trace: current_request_trace(),
+    }
+}
+
+/// Builds the [`ChangeAuthor`] of an admin request received from `peer`.
+///
+/// The principal is the user the admin ingress authenticated, which is only
+/// trusted from the trusted proxies like `X-Forwarded-For`, or otherwise the
+/// client's address. The request id falls back to the trace id.
+pub fn change_author(
+    peer: IpAddr,
+    headers: &HeaderMap,
+    trusted_proxies: &[IpAddr],
+) -> ChangeAuthor {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_owned)
+    };
+
+    let forwarded_user = header(X_FORWARDED_USER).filter(|_| trusted_proxies.contains(&peer));
+    let principal =
+        forwarded_user.unwrap_or_else(|| client_ip(peer, headers, trusted_proxies).to_string());
+    let request_id =
+        header(X_REQUEST_ID).or_else(|| current_request_trace().map(|trace| trace.trace_id));
+
+    ChangeAuthor {
+        principal,
+        request_id,
+    }
+}
+
+/// Determines the address of the client which made the request.
+///
+/// `X-Forwarded-For` is only consulted when the direct peer is one of the
+/// trusted proxies. In that case the header is walked from right to left and
+/// the first address that isn't a trusted proxy is returned - anything further
+/// left could have been supplied by the client itself.
+pub fn client_ip(peer: IpAddr, headers: &HeaderMap, trusted_proxies: &[IpAddr]) -> IpAddr {
+    if !trusted_proxies.contains(&peer) {
+        return peer;
+    }
+
+    let forwarded_for = headers
+        .get_all(X_FORWARDED_FOR)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect::<Vec<_>>();
+
+    let mut client = peer;
+    for hop in forwarded_for.into_iter().rev() {
+        let Ok(hop) = hop.parse::<IpAddr>() else {
+            // A malformed entry means we can't trust anything beyond it
+            break;
+        };
+
+        client = hop;
+
+        if !trusted_proxies.contains(&hop) {
+            break;
+        }
+    }
+
+    client
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::header::HeaderValue;
+
+    use super::*;
+
+    const PROXY: [u8; 4] = [10, 0, 0, 1];
+    const SECOND_PROXY: [u8; 4] = [10, 0, 0, 2];
+    const CLIENT: [u8; 4] = [203, 0, 113, 7];
+    const SPOOFED: [u8; 4] = [198, 51, 100, 1];
+
+    fn headers(forwarded_for: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            X_FORWARDED_FOR,
+            HeaderValue::from_str(forwarded_for).unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn ignores_header_without_trusted_proxies() {
+        let peer = IpAddr::from(CLIENT);
+
+        let ip = client_ip(peer, &headers("198.51.100.1"), &[]);
+
+        assert_eq!(ip, peer);
+    }
+
+    #[test]
+    fn ignores_header_from_untrusted_peer() {
+        let peer = IpAddr::from(CLIENT);
+
+        let ip = client_ip(peer, &headers("198.51.100.1"), &[PROXY.into()]);
+
+        assert_eq!(ip, peer);
+    }
+
+    #[test]
+    fn uses_header_from_trusted_proxy() {
+        let ip = client_ip(PROXY.into(), &headers("203.0.113.7"), &[PROXY.into()]);
+
+        assert_eq!(ip, IpAddr::from(CLIENT));
+    }
+
+    #[test]
+    fn skips_chained_trusted_proxies() {
+        let ip = client_ip(
+            PROXY.into(),
+            &headers("198.51.100.1, 203.0.113.7, 10.0.0.2"),
+            &[PROXY.into(), SECOND_PROXY.into()],
+        );
+
+        assert_eq!(ip, IpAddr::from(CLIENT));
+        assert_ne!(ip, IpAddr::from(SPOOFED));
+    }
+
+    #[test]
+    fn stops_at_malformed_entry() {
+        let ip = client_ip(
+            PROXY.into(),
+            &headers("203.0.113.7, garbage, 10.0.0.2"),
+            &[PROXY.into(), SECOND_PROXY.into()],
+        );
+
+        assert_eq!(ip, IpAddr::from(SECOND_PROXY));
+    }
+
+    #[test]
+    fn falls_back_to_peer_without_header() {
+        let ip = client_ip(PROXY.into(), &HeaderMap::new(), &[PROXY.into()]);
+
+        assert_eq!(ip, IpAddr::from(PROXY));
+    }
+
+    #[test]
+    fn captures_truncated_user_agent() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            USER_AGENT,
+            HeaderValue::from_str(&"a".repeat(1000)).unwrap(),
+        );
+
+        let origin = request_origin(CLIENT.into(), &headers, &[]);
+
+        assert_eq!(origin.ip, Some(IpAddr::from(CLIENT)));
+        assert_eq!(origin.user_agent.unwrap().len(), MAX_USER_AGENT_LEN);
+    }
+
+    #[test]
+    fn forwarded_user_is_only_trusted_from_proxies() {
+        let mut headers = HeaderMap::new();
+        headers.insert(X_FORWARDED_USER, HeaderValue::from_static("alice"));
+        headers.insert(X_REQUEST_ID, HeaderValue::from_static("req-1"));
+
+        let author = change_author(PROXY.into(), &headers, &[PROXY.into()]);
+        assert_eq!(author.principal, "alice");
+        assert_eq!(author.request_id.as_deref(), Some("req-1"));
+
+        let author = change_author(CLIENT.into(), &headers, &[PROXY.into()]);
+        assert_eq!(author.principal, "203.0.113.7");
+    }
+}
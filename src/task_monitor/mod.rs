@@ -1,18 +1,18 @@
+use std::future::Future;
 use std::sync::Arc;
 use std::time::Duration;
 
 use futures::stream::FuturesUnordered;
 use futures::StreamExt;
-use once_cell::sync::Lazy;
-use prometheus::{linear_buckets, register_gauge, register_histogram, Gauge, Histogram};
-use tokio::select;
 use tokio::sync::{mpsc, Mutex, Notify};
-use tokio::task::JoinHandle;
+use tokio::task::{AbortHandle, JoinHandle};
 use tracing::{error, info, instrument, warn};
 
 use crate::app::App;
 use crate::database::methods::DbMethods as _;
 use crate::database::Database;
+use crate::identity_tree::TreeVersionReadOps;
+use crate::metrics::Metrics;
 use crate::shutdown::Shutdown;
 
 pub mod tasks;
@@ -23,41 +23,34 @@ const FINALIZE_IDENTITIES_BACKOFF: Duration = Duration::from_secs(5);
 const QUEUE_MONITOR_BACKOFF: Duration = Duration::from_secs(5);
 const INSERT_IDENTITIES_BACKOFF: Duration = Duration::from_secs(5);
 const DELETE_IDENTITIES_BACKOFF: Duration = Duration::from_secs(5);
-
-static PENDING_IDENTITIES: Lazy<Gauge> = Lazy::new(|| {
-    register_gauge!("pending_identities", "Identities not submitted on-chain").unwrap()
-});
-
-static UNPROCESSED_IDENTITIES: Lazy<Gauge> = Lazy::new(|| {
-    register_gauge!(
-        "unprocessed_identities",
-        "Identities not processed by identity committer"
-    )
-    .unwrap()
-});
-
-static BATCH_SIZES: Lazy<Histogram> = Lazy::new(|| {
-    register_histogram!(
-        "submitted_batch_sizes",
-        "Submitted batch size",
-        linear_buckets(f64::from(1), f64::from(1), 100).unwrap()
-    )
-    .unwrap()
-});
+const ROOT_NOTIFICATIONS_BACKOFF: Duration = Duration::from_secs(5);
+const CANARY_BACKOFF: Duration = Duration::from_secs(5);
+const RELAYER_BALANCE_BACKOFF: Duration = Duration::from_secs(5);
+const EXPORT_BACKOFF: Duration = Duration::from_secs(5);
 
 /// A task manager for all long running tasks
 ///
 /// It's assumed that there is only one instance at a time.
 /// Spawning multiple `TaskManagers` will result in undefined behavior,
 /// including data duplication.
-pub struct TaskMonitor;
+pub struct TaskMonitor {
+    /// Handles of the monitored tasks, used to cancel them in `stop`
+    abort_handles: Vec<AbortHandle>,
+    /// Handle of the task watching the monitored tasks, it returns once all of
+    /// them have
+    monitor: JoinHandle<()>,
+}
 
 impl TaskMonitor {
     /// Initialize and run the task monitor
     #[instrument(level = "debug", skip_all)]
-    pub async fn init(main_app: Arc<App>, shutdown: Shutdown) {
+    pub async fn init(main_app: Arc<App>, shutdown: Shutdown) -> Self {
+        // Spawn the tasks onto the runtime the app was created with, which
+        // isn't necessarily the one this is called from
+        let _runtime = main_app.runtime().enter();
+
         let (monitored_txs_sender, monitored_txs_receiver) =
-            mpsc::channel(main_app.clone().config.app.monitored_txs_capacity);
+            mpsc::channel(main_app.config().app.monitored_txs_capacity);
 
         let monitored_txs_sender = Arc::new(monitored_txs_sender);
         let monitored_txs_receiver = Arc::new(Mutex::new(monitored_txs_receiver));
@@ -103,14 +96,20 @@ impl TaskMonitor {
         // Process identities
         let base_next_batch_notify = Arc::new(Notify::new());
 
+        // Serializes changes to the latest tree, rejected batches are unwound
+        // under it as well
+        let pending_insertion_mutex = Arc::new(Mutex::new(()));
+
         // Create batches
         let app = main_app.clone();
         let next_batch_notify = base_next_batch_notify.clone();
         let wake_up_notify = base_wake_up_notify.clone();
+        let insertion_mutex = pending_insertion_mutex.clone();
 
         let create_batches = move || {
             tasks::create_batches::create_batches(
                 app.clone(),
+                insertion_mutex.clone(),
                 next_batch_notify.clone(),
                 wake_up_notify.clone(),
             )
@@ -153,8 +152,6 @@ impl TaskMonitor {
         );
         handles.push(monitor_txs_handle);
 
-        let pending_insertion_mutex = Arc::new(Mutex::new(()));
-
         // Insert identities
         let app = main_app.clone();
         let wake_up_notify = base_wake_up_notify.clone();
@@ -192,58 +189,151 @@ impl TaskMonitor {
         );
         handles.push(delete_identities_handle);
 
-        tokio::spawn(Self::monitor_shutdown(handles, shutdown.clone()));
+        // Insert the canary identity, the task returns once it's verified
+        if main_app.config().service.canary.is_some() {
+            let app = main_app.clone();
+            let canary = move || tasks::canary::run_canary(app.clone());
+            let canary_handle = crate::utils::spawn_with_backoff_cancel_on_shutdown(
+                canary,
+                CANARY_BACKOFF,
+                shutdown.clone(),
+            );
+            handles.push(canary_handle);
+        }
+
+        // Pause batch submission while the relayer can't pay for it
+        if main_app.config().app.min_relayer_balance_gwei.is_some() {
+            let app = main_app.clone();
+            let monitor_relayer_balance =
+                move || tasks::monitor_relayer_balance::monitor_relayer_balance(app.clone());
+            let monitor_relayer_balance_handle =
+                crate::utils::spawn_with_backoff_cancel_on_shutdown(
+                    monitor_relayer_balance,
+                    RELAYER_BALANCE_BACKOFF,
+                    shutdown.clone(),
+                );
+            handles.push(monitor_relayer_balance_handle);
+        }
+
+        // Write exports requested through the admin API
+        if main_app.config().exports.is_some() {
+            let app = main_app.clone();
+            let export_identities =
+                move || tasks::export_identities::export_identities(app.clone());
+            let export_identities_handle = crate::utils::spawn_with_backoff_cancel_on_shutdown(
+                export_identities,
+                EXPORT_BACKOFF,
+                shutdown.clone(),
+            );
+            handles.push(export_identities_handle);
+        }
+
+        // Notify root subscribers
+        let app = main_app.clone();
+        let notify_root_subscribers =
+            move || tasks::notify_root_subscribers::notify_root_subscribers(app.clone());
+        let notify_root_subscribers_handle = crate::utils::spawn_with_backoff_cancel_on_shutdown(
+            notify_root_subscribers,
+            ROOT_NOTIFICATIONS_BACKOFF,
+            shutdown.clone(),
+        );
+        handles.push(notify_root_subscribers_handle);
+
+        let abort_handles = handles.iter().map(JoinHandle::abort_handle).collect();
+        let monitor = tokio::spawn(Self::monitor_tasks(handles, shutdown));
+
+        Self {
+            abort_handles,
+            monitor,
+        }
     }
 
-    async fn monitor_shutdown(mut handles: FuturesUnordered<JoinHandle<()>>, shutdown: Shutdown) {
-        select! {
-            // Wait for the shutdown signal
-            _ = shutdown.await_shutdown_begin() => {
-             }
-            // Or wait for a task to panic
-            _ = Self::await_task_panic(&mut handles, shutdown.clone()) => {}
-        };
+    /// Cancels the monitored tasks and waits for them to finish, without
+    /// signalling a shutdown to the rest of the app.
+    pub fn stop(self) -> impl Future<Output = ()> {
+        for handle in &self.abort_handles {
+            handle.abort();
+        }
+
+        async move {
+            if let Err(error) = self.monitor.await {
+                error!(?error, "task monitor panicked");
+            }
+        }
     }
 
-    async fn await_task_panic(handles: &mut FuturesUnordered<JoinHandle<()>>, shutdown: Shutdown) {
+    async fn monitor_tasks(mut handles: FuturesUnordered<JoinHandle<()>>, shutdown: Shutdown) {
+        let mut stopped = false;
+
         while let Some(result) = handles.next().await {
-            if !shutdown.is_shutting_down() {
-                match result {
-                    Ok(_) => {
-                        info!("task exited");
-                    }
-                    Err(error) => {
-                        error!(?error, "task panicked");
-                        // Instruct the rest of the app to shutdown
-                        shutdown.clone().shutdown();
-                        return;
-                    }
+            match result {
+                // Cancelled by `stop`
+                Err(error) if error.is_cancelled() => {
+                    stopped = true;
+                }
+                _ if shutdown.is_shutting_down() => {}
+                Ok(()) => {
+                    info!("task exited");
+                }
+                Err(error) => {
+                    error!(?error, "task panicked");
+                    // Instruct the rest of the app to shutdown
+                    shutdown.shutdown();
                 }
             }
         }
-        warn!("all tasks have returned unexpectedly");
+
+        if !stopped && !shutdown.is_shutting_down() {
+            warn!("all tasks have returned unexpectedly");
+        }
     }
 
-    async fn log_pending_identities_count(database: &Database) -> anyhow::Result<()> {
+    async fn log_pending_identities_count(
+        database: &Database,
+        metrics: &Metrics,
+    ) -> anyhow::Result<()> {
         let identities = database.count_pending_identities().await?;
-        PENDING_IDENTITIES.set(f64::from(identities));
+        metrics.pending_identities.set(f64::from(identities));
         Ok(())
     }
 
-    async fn log_unprocessed_identities_count(database: &Database) -> anyhow::Result<()> {
+    async fn log_unprocessed_identities_count(
+        database: &Database,
+        metrics: &Metrics,
+    ) -> anyhow::Result<()> {
         let identities = database.count_unprocessed_identities().await?;
-        UNPROCESSED_IDENTITIES.set(f64::from(identities));
+        metrics.unprocessed_identities.set(f64::from(identities));
         Ok(())
     }
 
-    async fn log_identities_queues(database: &Database) -> anyhow::Result<()> {
-        TaskMonitor::log_unprocessed_identities_count(database).await?;
-        TaskMonitor::log_pending_identities_count(database).await?;
+    async fn log_identities_queues(app: &App) -> anyhow::Result<()> {
+        TaskMonitor::log_unprocessed_identities_count(&app.database, app.metrics()).await?;
+        TaskMonitor::log_pending_identities_count(&app.database, app.metrics()).await?;
         Ok(())
     }
 
     #[allow(clippy::cast_precision_loss)]
-    fn log_batch_size(size: usize) {
-        BATCH_SIZES.observe(size as f64);
+    fn log_tree_leaf_count(app: &App) {
+        // The tree may not be initialized yet
+        if let Ok(tree_state) = app.tree_state() {
+            let mut leaf_count = tree_state.leaf_count();
+
+            // The canary isn't a real user
+            if let (Some(leaf_index), Some(canary)) =
+                (app.canary_leaf(), &app.config().service.canary)
+            {
+                let leaf = tree_state.latest_tree().get_leaf(leaf_index);
+                if leaf == tasks::canary::canary_commitment(canary) {
+                    leaf_count -= 1;
+                }
+            }
+
+            app.metrics().tree_leaf_count.set(leaf_count as f64);
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn log_batch_size(metrics: &Metrics, size: usize) {
+        metrics.submitted_batch_sizes.observe(size as f64);
     }
 }
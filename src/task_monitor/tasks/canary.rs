@@ -0,0 +1,137 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use semaphore::hash_to_field;
+use tokio::time;
+use tracing::{error, info, warn};
+
+use crate::config::CanaryConfig;
+use crate::database::methods::DbMethods as _;
+use crate::database::types::RequestOrigin;
+use crate::identity_tree::{Hash, ProcessedStatus, Status};
+use crate::server::error::Error as ServerError;
+use crate::task_monitor::App;
+
+const CANARY_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The canary commitment of an environment. It's a valid field element and
+/// stays the same as long as the seed does.
+#[must_use]
+pub fn canary_commitment(config: &CanaryConfig) -> Hash {
+    hash_to_field(format!("signup-sequencer canary {}", config.seed).as_bytes())
+}
+
+/// Inserts the canary identity, or reuses it if it was inserted by a previous
+/// run, and waits until its inclusion proof can be verified.
+///
+/// If that doesn't happen within the configured timeout the app is reported as
+/// not ready.
+pub async fn run_canary(app: Arc<App>) -> anyhow::Result<()> {
+    let Some(config) = app.config().service.canary.clone() else {
+        return Ok(());
+    };
+
+    if !app.config().offchain_mode.enabled && !config.allow_onchain {
+        warn!("Canary is only enabled in offchain mode unless allow_onchain is set, skipping");
+        return Ok(());
+    }
+
+    app.metrics().canary_ok.set(0);
+
+    let commitment = canary_commitment(&config);
+
+    match time::timeout(config.timeout, check_canary(&app, commitment)).await {
+        Ok(Ok(leaf_index)) => {
+            info!(?commitment, leaf_index, "Canary identity verified");
+
+            app.set_canary_failed(false);
+            app.metrics().canary_ok.set(1);
+        }
+        Ok(Err(error)) => {
+            error!(?commitment, ?error, "Canary identity failed");
+
+            app.set_canary_failed(true);
+        }
+        Err(_) => {
+            error!(
+                ?commitment,
+                timeout = ?config.timeout,
+                "Canary identity was not processed in time"
+            );
+
+            app.set_canary_failed(true);
+        }
+    }
+
+    Ok(())
+}
+
+async fn check_canary(app: &App, commitment: Hash) -> anyhow::Result<usize> {
+    while app.tree_state().is_err() {
+        time::sleep(CANARY_POLL_INTERVAL).await;
+    }
+
+    match app
+        .insert_identity(commitment, RequestOrigin::default())
+        .await
+    {
+        Ok(()) => info!(?commitment, "Inserted canary identity"),
+        Err(ServerError::DuplicateCommitment) => {
+            info!(?commitment, "Canary identity already present");
+        }
+        Err(error) => return Err(error.into()),
+    }
+
+    loop {
+        let response = app.inclusion_proof(&commitment).await?;
+
+        if let Status::Processed(ProcessedStatus::Processed | ProcessedStatus::Mined) =
+            response.status
+        {
+            let root = response.root.context("Missing root")?;
+            let proof = response.proof.context("Missing proof")?;
+
+            anyhow::ensure!(
+                proof.root(commitment) == root,
+                "Inclusion proof doesn't match root {root}"
+            );
+
+            break;
+        }
+
+        time::sleep(CANARY_POLL_INTERVAL).await;
+    }
+
+    let item = app
+        .database
+        .get_identity_leaf_index(&commitment)
+        .await?
+        .context("Canary identity is not in the database")?;
+
+    app.set_canary_leaf(item.leaf_index);
+
+    Ok(item.leaf_index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::validator::MODULUS;
+
+    #[test]
+    fn canary_commitment_is_stable_per_seed() {
+        let config = |seed: &str| CanaryConfig {
+            seed: seed.to_string(),
+            timeout: Duration::from_secs(1),
+            allow_onchain: false,
+        };
+
+        let commitment = canary_commitment(&config("staging"));
+
+        assert_eq!(commitment, canary_commitment(&config("staging")));
+        assert_ne!(commitment, canary_commitment(&config("production")));
+        assert!(commitment < MODULUS);
+        assert_ne!(commitment, Hash::ZERO);
+    }
+}
@@ -6,7 +6,7 @@ use semaphore::merkle_tree::Proof;
 use semaphore::poseidon_tree::{Branch, PoseidonHash};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::Notify;
+use tokio::sync::{Mutex, Notify};
 use tokio::time::MissedTickBehavior;
 use tokio::{select, time};
 use tracing::instrument;
@@ -14,14 +14,17 @@ use tracing::instrument;
 use crate::app::App;
 use crate::database;
 use crate::database::methods::DbMethods as _;
-use crate::database::Database;
+use crate::database::{Database, IsolationLevel};
 use crate::identity_tree::{
     AppliedTreeUpdate, Hash, Intermediate, TreeVersion, TreeVersionReadOps, TreeWithNextVersion,
 };
+use crate::metrics::Metrics;
 use crate::prover::identity::Identity;
 use crate::prover::repository::ProverRepository;
+use crate::prover::ProverType;
 use crate::task_monitor::TaskMonitor;
 use crate::utils::batch_type::BatchType;
+use crate::utils::trace_links::link_request_traces;
 
 /// The number of seconds either side of the timer tick to treat as enough to
 /// trigger a forced batch insertion.
@@ -29,6 +32,7 @@ const DEBOUNCE_THRESHOLD_SECS: i64 = 1;
 
 pub async fn create_batches(
     app: Arc<App>,
+    pending_insertions_mutex: Arc<Mutex<()>>,
     next_batch_notify: Arc<Notify>,
     wake_up_notify: Arc<Notify>,
 ) -> anyhow::Result<()> {
@@ -63,6 +67,14 @@ pub async fn create_batches(
             () = wake_up_notify.notified() => {
                 tracing::trace!("Identity batch insertion woken due to request");
             },
+
+            () = app.flush_signal().notified() => {
+                tracing::info!("Identity batch insertion woken due to flush");
+            },
+        }
+
+        if app.config().app.require_batch_approval {
+            unwind_rejected_batch(&app, &pending_insertions_mutex).await?;
         }
 
         let Some(batch_type) = determine_batch_type(app.tree_state()?.batching_tree()) else {
@@ -70,15 +82,19 @@ pub async fn create_batches(
         };
 
         let batch_size = if batch_type.is_deletion() {
-            app.prover_repository.max_deletion_batch_size().await
+            app.identity_processor.current_deletion_batch_size().await
         } else {
-            app.prover_repository.max_insertion_batch_size().await
+            app.identity_processor.current_insertion_batch_size().await
+        };
+        let Some(batch_size) = batch_size else {
+            tracing::warn!(?batch_type, "No prover for the next batch. Waiting.");
+            continue;
         };
 
         let updates = app
             .tree_state()?
             .batching_tree()
-            .peek_next_updates(batch_size);
+            .peek_next_updates_of_type(batch_size, batch_type);
 
         if updates.is_empty() {
             tracing::trace!("No updates found. Waiting.");
@@ -90,6 +106,7 @@ pub async fn create_batches(
             commit_identities(
                 &app.database,
                 &app.prover_repository,
+                app.metrics(),
                 app.tree_state()?.batching_tree(),
                 &next_batch_notify,
                 &updates,
@@ -98,7 +115,7 @@ pub async fn create_batches(
         } else {
             let current_time = Utc::now();
             let batch_insertion_timeout =
-                chrono::Duration::from_std(app.config.app.batch_insertion_timeout)?;
+                chrono::Duration::from_std(app.config().app.batch_insertion_timeout)?;
 
             let timeout_batch_time = last_batch_time
                 + batch_insertion_timeout
@@ -106,12 +123,13 @@ pub async fn create_batches(
 
             let batch_time_elapsed = current_time >= timeout_batch_time;
 
-            // If the batch size is full or if the insertion time has elapsed
-            // process the batch
-            if updates.len() >= batch_size || batch_time_elapsed {
+            // If the batch size is full, the insertion time has elapsed or we're
+            // flushing, process the batch
+            if updates.len() >= batch_size || batch_time_elapsed || app.flush_signal().is_forced() {
                 commit_identities(
                     &app.database,
                     &app.prover_repository,
+                    app.metrics(),
                     app.tree_state()?.batching_tree(),
                     &next_batch_notify,
                     &updates,
@@ -148,6 +166,7 @@ pub async fn create_batches(
                     commit_identities(
                         &app.database,
                         &app.prover_repository,
+                        app.metrics(),
                         app.tree_state()?.batching_tree(),
                         &next_batch_notify,
                         &updates,
@@ -181,9 +200,75 @@ async fn ensure_batch_chain_initialized(app: &Arc<App>) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Unwinds the earliest rejected batch and every batch and identity update
+/// after it, the updates are queued to be inserted or deleted again.
+async fn unwind_rejected_batch(
+    app: &App,
+    pending_insertions_mutex: &Mutex<()>,
+) -> anyhow::Result<()> {
+    let Some(batch) = app.database.get_first_rejected_batch().await? else {
+        return Ok(());
+    };
+    let prev_root = batch
+        .prev_root
+        .context("The head of the batch chain can't be rejected")?;
+
+    // The insertion and deletion tasks must not touch the latest tree meanwhile
+    let _guard = pending_insertions_mutex.lock().await;
+
+    let mut tx = app
+        .database
+        .begin_tx("unwind_rejected_batch", IsolationLevel::ReadCommitted)
+        .await?;
+
+    let result = async {
+        let sequence_id = match tx.get_id_by_root(&prev_root).await? {
+            Some(sequence_id) => sequence_id,
+            // The root the batch chain starts at may not belong to an identity, in
+            // which case every identity comes after it
+            None if tx.get_batch_head().await?.map(|head| head.next_root) == Some(prev_root) => 0,
+            None => {
+                anyhow::bail!("Root {prev_root:#x} of the rejected batch is not in the database")
+            }
+        };
+
+        let (insertions, deletions) = tx.unwind_updates_after(sequence_id).await?;
+        // Deleting the rejected batch cascades to all of the batches after it
+        tx.delete_batches_after_root(&prev_root).await?;
+
+        app.tree_state()?.rewind_batching_to(prev_root)?;
+
+        anyhow::Ok((insertions, deletions))
+    }
+    .await;
+
+    let (insertions, deletions) = match result {
+        Ok(unwound) => unwound,
+        Err(err) => {
+            tx.rollback().await?;
+            return Err(err);
+        }
+    };
+
+    tx.commit()
+        .await
+        .expect("Committing the unwind failed - tree will be out of sync");
+
+    tracing::warn!(
+        rejected_root = ?batch.next_root,
+        ?prev_root,
+        insertions,
+        deletions,
+        "Unwound rejected batch, its updates and the ones after it are queued again"
+    );
+
+    Ok(())
+}
+
 async fn commit_identities(
     database: &Database,
     prover_repository: &Arc<ProverRepository>,
+    metrics: &Metrics,
     batching_tree: &TreeVersion<Intermediate>,
     next_batch_notify: &Arc<Notify>,
     updates: &[AppliedTreeUpdate],
@@ -204,6 +289,8 @@ async fn commit_identities(
 
         insert_identities(
             database,
+            prover_repository,
+            metrics,
             batching_tree,
             next_batch_notify,
             updates,
@@ -219,6 +306,8 @@ async fn commit_identities(
 
         delete_identities(
             database,
+            prover_repository,
+            metrics,
             batching_tree,
             next_batch_notify,
             updates,
@@ -231,6 +320,8 @@ async fn commit_identities(
 #[instrument(level = "info", skip_all)]
 pub async fn insert_identities(
     database: &Database,
+    prover_repository: &ProverRepository,
+    metrics: &Metrics,
     batching_tree: &TreeVersion<Intermediate>,
     next_batch_notify: &Arc<Notify>,
     updates: &[AppliedTreeUpdate],
@@ -240,6 +331,11 @@ pub async fn insert_identities(
 
     let pre_root = batching_tree.get_root();
     let mut insertion_indices: Vec<_> = updates.iter().map(|f| f.update.leaf_index).collect();
+
+    let request_traces = database
+        .get_request_traces(&insertion_indices, database::types::BatchType::Insertion)
+        .await?;
+    link_request_traces(&request_traces);
     let mut commitments: Vec<U256> = updates
         .iter()
         .map(|update| update.update.element.into())
@@ -314,12 +410,15 @@ pub async fn insert_identities(
         "Submitting insertion batch to DB"
     );
 
-    // With all the data prepared we can submit the batch to database.
+    // With all the data prepared we can submit the batch to database. The
+    // provers are looked up again, they may have changed since the batch size
+    // was picked.
     database
         .insert_new_batch(
             &post_root,
             &pre_root,
             database::types::BatchType::Insertion,
+            &prover_repository.batch_sizes(ProverType::Insertion).await,
             &identity_commitments,
             &insertion_indices,
         )
@@ -334,7 +433,7 @@ pub async fn insert_identities(
 
     next_batch_notify.notify_one();
 
-    TaskMonitor::log_batch_size(updates.len());
+    TaskMonitor::log_batch_size(metrics, updates.len());
 
     batching_tree.apply_updates_up_to(post_root);
 
@@ -365,8 +464,11 @@ fn assert_updates_are_consecutive(updates: &[AppliedTreeUpdate]) {
     }
 }
 
+#[instrument(level = "info", skip_all)]
 pub async fn delete_identities(
     database: &Database,
+    prover_repository: &ProverRepository,
+    metrics: &Metrics,
     batching_tree: &TreeVersion<Intermediate>,
     next_batch_notify: &Arc<Notify>,
     updates: &[AppliedTreeUpdate],
@@ -377,6 +479,11 @@ pub async fn delete_identities(
 
     let mut deletion_indices: Vec<_> = updates.iter().map(|f| f.update.leaf_index).collect();
 
+    let request_traces = database
+        .get_request_traces(&deletion_indices, database::types::BatchType::Deletion)
+        .await?;
+    link_request_traces(&request_traces);
+
     let commitments = batching_tree.commitments_by_indices(deletion_indices.iter().copied());
     let mut commitments: Vec<U256> = commitments.into_iter().map(U256::from).collect();
 
@@ -440,12 +547,15 @@ pub async fn delete_identities(
 
     tracing::info!(?pre_root, ?post_root, "Submitting deletion batch to DB");
 
-    // With all the data prepared we can submit the batch to database.
+    // With all the data prepared we can submit the batch to database. The
+    // provers are looked up again, they may have changed since the batch size
+    // was picked.
     database
         .insert_new_batch(
             &post_root,
             &pre_root,
             database::types::BatchType::Deletion,
+            &prover_repository.batch_sizes(ProverType::Deletion).await,
             &identity_commitments,
             &deletion_indices,
         )
@@ -455,7 +565,7 @@ pub async fn delete_identities(
 
     next_batch_notify.notify_one();
 
-    TaskMonitor::log_batch_size(updates.len());
+    TaskMonitor::log_batch_size(metrics, updates.len());
 
     batching_tree.apply_updates_up_to(post_root);
 
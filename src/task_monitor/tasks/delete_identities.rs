@@ -7,12 +7,17 @@ use chrono::Utc;
 use tokio::sync::{Mutex, Notify};
 use tokio::time::MissedTickBehavior;
 use tokio::{select, time};
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::app::App;
 use crate::database::methods::DbMethods;
 use crate::database::types::DeletionEntry;
+use crate::database::{Database, IsolationLevel};
 use crate::identity_tree::{Hash, TreeVersionReadOps};
+use crate::metrics::Metrics;
+
+/// The maximum number of deletions read from the database at once
+const DELETIONS_PAGE_SIZE: usize = 1000;
 
 // Deletion here differs from insert_identites task. This is because two
 // different flows are created for both tasks. Due to how our prover works
@@ -27,8 +32,14 @@ pub async fn delete_identities(
 ) -> anyhow::Result<()> {
     info!("Starting deletion processor.");
 
-    let batch_deletion_timeout = chrono::Duration::from_std(app.config.app.batch_deletion_timeout)
-        .context("Invalid batch deletion timeout duration")?;
+    let batch_deletion_timeout =
+        chrono::Duration::from_std(app.config().app.batch_deletion_timeout)
+            .context("Invalid batch deletion timeout duration")?;
+
+    {
+        let _guard = pending_insertions_mutex.lock().await;
+        recover_deletion_intents(&app.database, app.metrics()).await?;
+    }
 
     let mut timer = time::interval(Duration::from_secs(5));
     timer.set_missed_tick_behavior(MissedTickBehavior::Skip);
@@ -38,80 +49,278 @@ pub async fn delete_identities(
             _ = timer.tick() => {
                 info!("Deletion processor woken due to timeout");
             }
-        }
 
-        let deletions = app.database.get_deletions().await?;
-        if deletions.is_empty() {
-            continue;
+            () = app.flush_signal().notified() => {
+                info!("Deletion processor woken due to flush");
+            }
         }
 
-        let last_deletion_timestamp = app.database.get_latest_deletion().await?.timestamp;
+        let page_size = DELETIONS_PAGE_SIZE.max(app.config().app.min_batch_deletion_size);
+        let mut after_id = 0;
+
+        // Deletions are processed in pages so that a large backlog isn't loaded
+        // into memory at once
+        loop {
+            let deletions = app.database.get_deletions_page(after_id, page_size).await?;
+            let Some(last_id) = deletions.iter().map(|d| d.id).max() else {
+                break;
+            };
+
+            let processed = delete_page(
+                &app,
+                &pending_insertions_mutex,
+                batch_deletion_timeout,
+                deletions,
+            )
+            .await?;
+            if !processed {
+                break;
+            }
 
-        // If the minimum deletions batch size is not reached and the deletion time
-        // interval has not elapsed then we can skip
-        if deletions.len() < app.config.app.min_batch_deletion_size
-            && Utc::now() - last_deletion_timestamp <= batch_deletion_timeout
-        {
-            continue;
+            after_id = last_id;
+            wake_up_notify.notify_one();
         }
+    }
+}
+
+/// Deletes the identities of a page of deletions from the tree. Returns
+/// `false` if the deletions are postponed.
+async fn delete_page(
+    app: &App,
+    pending_insertions_mutex: &Mutex<()>,
+    batch_deletion_timeout: chrono::Duration,
+    deletions: Vec<DeletionEntry>,
+) -> anyhow::Result<bool> {
+    let last_deletion_timestamp = app.database.get_latest_deletion().await?.timestamp;
+
+    // If the minimum deletions batch size is not reached and the deletion time
+    // interval has not elapsed then we can skip, unless we're flushing
+    if deletions.len() < app.config().app.min_batch_deletion_size
+        && Utc::now() - last_deletion_timestamp <= batch_deletion_timeout
+        && !app.flush_signal().is_forced()
+    {
+        return Ok(false);
+    }
+
+    // Dedup deletion entries
+    let deletions = deletions.into_iter().collect::<HashSet<DeletionEntry>>();
+    let mut deletions = deletions.into_iter().collect::<Vec<DeletionEntry>>();
+
+    // Check if the deletion batch could potentially create:
+    // - duplicate root on the tree when inserting to identities
+    // - duplicate root on batch
+    // Such situation may happen only when deletions are done from the last inserted leaf in
+    // decreasing order (each next leaf is decreased by 1) - same root for identities, or when
+    // deletions are going to create same tree state - continuous deletions.
+    // To avoid such situation we sort then in ascending order and only check the scenario when
+    // they are continuous ending with last leaf index
+    deletions.sort_by(|d1, d2| d1.leaf_index.cmp(&d2.leaf_index));
 
-        // Dedup deletion entries
-        let deletions = deletions.into_iter().collect::<HashSet<DeletionEntry>>();
-        let mut deletions = deletions.into_iter().collect::<Vec<DeletionEntry>>();
-
-        // Check if the deletion batch could potentially create:
-        // - duplicate root on the tree when inserting to identities
-        // - duplicate root on batch
-        // Such situation may happen only when deletions are done from the last inserted leaf in
-        // decreasing order (each next leaf is decreased by 1) - same root for identities, or when
-        // deletions are going to create same tree state - continuous deletions.
-        // To avoid such situation we sort then in ascending order and only check the scenario when
-        // they are continuous ending with last leaf index
-        deletions.sort_by(|d1, d2| d1.leaf_index.cmp(&d2.leaf_index));
-
-        if let Some(last_leaf_index) = app.tree_state()?.latest_tree().next_leaf().checked_sub(1) {
-            let indices_are_continuous = deletions
-                .windows(2)
-                .all(|w| w[1].leaf_index == w[0].leaf_index + 1);
-
-            if indices_are_continuous && deletions.last().unwrap().leaf_index == last_leaf_index {
-                tracing::warn!(
-                    "Deletion batch could potentially create a duplicate root batch. Deletion \
+    if let Some(last_leaf_index) = app.tree_state()?.latest_tree().next_leaf().checked_sub(1) {
+        let indices_are_continuous = deletions
+            .windows(2)
+            .all(|w| w[1].leaf_index == w[0].leaf_index + 1);
+
+        if indices_are_continuous && deletions.last().unwrap().leaf_index == last_leaf_index {
+            tracing::warn!(
+                "Deletion batch could potentially create a duplicate root batch. Deletion \
                  batch will be postponed"
-                );
-                continue;
-            }
+            );
+            return Ok(false);
+        }
+    }
+
+    let (leaf_indices, previous_commitments): (Vec<usize>, Vec<Hash>) = deletions
+        .iter()
+        .map(|d| (d.leaf_index, d.commitment))
+        .unzip();
+
+    let _guard = pending_insertions_mutex.lock().await;
+
+    // The deletions are applied to the latest tree only once they are
+    // committed, so that a failed write can't leave the tree ahead of the
+    // database
+    let mut pre_root = app.tree_state()?.latest_tree().get_root();
+    let roots = app
+        .tree_state()?
+        .latest_tree()
+        .simulate_delete_many(&leaf_indices);
+
+    let mut tx = app
+        .database
+        .begin_tx("delete_identities", IsolationLevel::ReadCommitted)
+        .await?;
+
+    let result = async {
+        let intent_id = tx.insert_deletion_intent(&deletions).await?;
+
+        // Insert the new items into pending identities
+        for (root, leaf_index) in roots.iter().zip(&leaf_indices) {
+            tx.insert_pending_identity(*leaf_index, &Hash::ZERO, root, &pre_root)
+                .await?;
+            pre_root = *root;
         }
 
-        let (leaf_indices, previous_commitments): (Vec<usize>, Vec<Hash>) = deletions
+        tx.copy_deletion_request_origins().await?;
+
+        // Remove the previous commitments from the deletions table
+        tx.remove_deletions(&previous_commitments).await?;
+
+        tx.complete_deletion_intent(intent_id).await?;
+
+        anyhow::Ok(())
+    }
+    .await;
+
+    tx.finish(result).await?;
+
+    // Delete the commitments at the target leaf indices in the latest tree
+    let data = app.tree_state()?.latest_tree().delete_many(&leaf_indices);
+
+    assert_eq!(
+        data.last().map(|(root, _)| root),
+        roots.last(),
+        "Deleting from the latest tree doesn't match the committed roots"
+    );
+
+    Ok(true)
+}
+
+/// Completes the intents of deletions which were interrupted, e.g. by a crash.
+/// Deletions whose leaves were zeroed in the database are taken off the queue,
+/// the others stay queued to be deleted again. The zeroed leaves are part of
+/// the tree once it's initialized, so deleting them again would duplicate the
+/// deletion.
+async fn recover_deletion_intents(database: &Database, metrics: &Metrics) -> anyhow::Result<()> {
+    for intent in database.get_incomplete_deletion_intents().await? {
+        let leaf_indexes = &intent.leaf_indexes.0;
+        let zeroed_leaves = database.get_zeroed_leaves(leaf_indexes).await?;
+
+        let applied: Vec<Hash> = leaf_indexes
             .iter()
-            .map(|d| (d.leaf_index, d.commitment))
-            .unzip();
+            .zip(&intent.commitments.0)
+            .filter(|(leaf_index, _)| zeroed_leaves.contains(leaf_index))
+            .map(|(_, commitment)| *commitment)
+            .collect();
+        let released = leaf_indexes.len() - applied.len();
 
-        let _guard = pending_insertions_mutex.lock().await;
+        warn!(
+            intent_id = intent.id,
+            applied = applied.len(),
+            released,
+            "Recovering an interrupted deletion"
+        );
+
+        database.copy_deletion_request_origins().await?;
+        database.remove_deletions(&applied).await?;
+        database.complete_deletion_intent(intent.id).await?;
+
+        metrics.recovered_deletion_intents.inc();
+        metrics
+            .recovered_deletions
+            .with_label_values(&["applied"])
+            .inc_by(applied.len() as u64);
+        metrics
+            .recovered_deletions
+            .with_label_values(&["released"])
+            .inc_by(released as u64);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
 
-        let mut pre_root = app.tree_state()?.latest_tree().get_root();
-        // Delete the commitments at the target leaf indices in the latest tree,
-        // generating the proof for each update
-        let data = app.tree_state()?.latest_tree().delete_many(&leaf_indices);
+    use testcontainers::clients::Cli;
 
-        assert_eq!(
-            data.len(),
-            leaf_indices.len(),
-            "Length mismatch when appending identities to tree"
+    use super::*;
+    use crate::config::DatabaseConfig;
+    use crate::utils::secret::SecretUrl;
+
+    #[tokio::test]
+    async fn recovery_neither_loses_nor_duplicates_deletions() -> anyhow::Result<()> {
+        let metrics = Metrics::default();
+        let docker = Cli::default();
+        let db_container = postgres_docker_utils::setup(&docker).await?;
+        let url = format!(
+            "postgres://postgres:postgres@{}/database",
+            db_container.address()
         );
+        let database = Database::new(
+            &DatabaseConfig {
+                database: SecretUrl::from_str(&url)?,
+                migrate: true,
+                max_connections: 1,
+                slow_transaction_threshold: Duration::from_secs(5),
+            },
+            &metrics,
+        )
+        .await?;
 
-        // Insert the new items into pending identities
-        let items = data.into_iter().zip(leaf_indices);
-        for ((root, _proof), leaf_index) in items {
-            app.database
+        let commitments: Vec<Hash> = (1..=3).map(Hash::from).collect();
+        let mut pre_root = Hash::from(100);
+        for (leaf_index, commitment) in commitments.iter().enumerate() {
+            let root = Hash::from(101 + leaf_index);
+            database
+                .insert_pending_identity(leaf_index, commitment, &root, &pre_root)
+                .await?;
+            database.insert_new_deletion(leaf_index, commitment).await?;
+            pre_root = root;
+        }
+
+        // Interrupted after the first two leaves were zeroed
+        let deletions = database.get_deletions_page(0, 10).await?;
+        let intent_id = database.insert_deletion_intent(&deletions).await?;
+        for leaf_index in 0..2 {
+            let root = Hash::from(201 + leaf_index);
+            database
                 .insert_pending_identity(leaf_index, &Hash::ZERO, &root, &pre_root)
                 .await?;
             pre_root = root;
         }
 
-        // Remove the previous commitments from the deletions table
-        app.database.remove_deletions(&previous_commitments).await?;
-        wake_up_notify.notify_one();
+        recover_deletion_intents(&database, &metrics).await?;
+
+        // The deletion which wasn't applied is back in the queue, alone
+        let queued: Vec<usize> = database
+            .get_deletions()
+            .await?
+            .into_iter()
+            .map(|d| d.leaf_index)
+            .collect();
+        assert_eq!(queued, vec![2]);
+        assert!(database.get_incomplete_deletion_intents().await?.is_empty());
+        assert_eq!(database.get_zeroed_leaves(&[0, 1, 2]).await?, vec![0, 1]);
+
+        // Interrupted after the queue was updated but before completion
+        let deletions = database.get_deletions_page(0, 10).await?;
+        let intent_id_2 = database.insert_deletion_intent(&deletions).await?;
+        assert_ne!(intent_id, intent_id_2);
+        database
+            .insert_pending_identity(2, &Hash::ZERO, &Hash::from(203), &pre_root)
+            .await?;
+        database.remove_deletions(&[commitments[2]]).await?;
+
+        recover_deletion_intents(&database, &metrics).await?;
+        recover_deletion_intents(&database, &metrics).await?;
+
+        assert!(database.get_deletions().await?.is_empty());
+        assert!(database.get_incomplete_deletion_intents().await?.is_empty());
+
+        let zeroed_rows: (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM identities WHERE commitment = $1")
+                .bind(Hash::ZERO)
+                .fetch_one(&*database)
+                .await?;
+        assert_eq!(zeroed_rows.0, 3);
+
+        let recovered = &metrics.recovered_deletions;
+        assert_eq!(metrics.recovered_deletion_intents.get(), 2);
+        assert_eq!(recovered.with_label_values(&["applied"]).get(), 3);
+        assert_eq!(recovered.with_label_values(&["released"]).get(), 1);
+
+        Ok(())
     }
 }
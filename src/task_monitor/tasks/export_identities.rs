@@ -0,0 +1,39 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::time;
+
+use crate::app::App;
+use crate::database::methods::DbMethods as _;
+use crate::identity::export::Exporter;
+
+/// Exports requested through another instance are picked up after at most
+/// this long.
+const EXPORT_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Runs requested exports one after the other, resuming the ones interrupted
+/// by a restart first. Returns right away if exports aren't configured.
+///
+/// Every export is claimed before it's written, so that it's only written by
+/// one instance at a time.
+pub async fn export_identities(app: Arc<App>) -> anyhow::Result<()> {
+    let Some(config) = &app.config().exports else {
+        return Ok(());
+    };
+    let exporter = Exporter::new(&app.database, config, app.instance_id());
+
+    loop {
+        while let Some(export) = app
+            .database
+            .claim_next_export(app.instance_id(), config.claim_lease)
+            .await?
+        {
+            exporter.run(export).await?;
+        }
+
+        tokio::select! {
+            () = app.export_requested().notified() => {}
+            () = time::sleep(EXPORT_POLL_INTERVAL) => {}
+        }
+    }
+}
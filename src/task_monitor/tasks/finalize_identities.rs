@@ -1,5 +1,7 @@
 use std::sync::Arc;
 
+use tokio::select;
+
 use crate::app::App;
 
 pub async fn finalize_roots(app: Arc<App>) -> anyhow::Result<()> {
@@ -11,6 +13,9 @@ pub async fn finalize_roots(app: Arc<App>) -> anyhow::Result<()> {
             )
             .await?;
 
-        tokio::time::sleep(app.config.app.time_between_scans).await;
+        select! {
+            () = tokio::time::sleep(app.config().app.time_between_scans) => {}
+            () = app.flush_signal().notified() => {}
+        }
     }
 }
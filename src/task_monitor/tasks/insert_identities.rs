@@ -31,6 +31,10 @@ pub async fn insert_identities(
             _ = timer.tick() => {
                 info!("Insertion processor woken due to timeout.");
             }
+
+            () = app.flush_signal().notified() => {
+                info!("Insertion processor woken due to flush.");
+            }
         }
 
         // get commits from database
@@ -43,30 +47,55 @@ pub async fn insert_identities(
         let _guard = pending_insertions_mutex.lock().await;
         let latest_tree = app.tree_state()?.latest_tree();
 
-        let mut tx = app.database.begin_tx(IsolationLevel::ReadCommitted).await?;
+        let mut tx = app
+            .database
+            .begin_tx("insert_identities", IsolationLevel::ReadCommitted)
+            .await?;
+
+        let result = async {
+            let next_leaf = latest_tree.next_leaf();
+            let mut pre_root = latest_tree.get_root();
 
-        let next_leaf = latest_tree.next_leaf();
-        let mut pre_root = latest_tree.get_root();
+            let next_db_index = tx.get_next_leaf_index().await?;
+            assert_eq!(
+                next_leaf, next_db_index,
+                "Database and tree are out of sync. Next leaf index in tree is: {next_leaf}, in database: {next_db_index}"
+            );
 
-        let next_db_index = tx.get_next_leaf_index().await?;
-        assert_eq!(
-            next_leaf, next_db_index,
-            "Database and tree are out of sync. Next leaf index in tree is: {next_leaf}, in database: {next_db_index}"
-        );
+            for (idx, identity) in unprocessed.iter().enumerate() {
+                let leaf_idx = next_leaf + idx;
+                latest_tree.update(leaf_idx, *identity);
+                let root = latest_tree.get_root();
 
-        for (idx, identity) in unprocessed.iter().enumerate() {
-            let leaf_idx = next_leaf + idx;
-            latest_tree.update(leaf_idx, *identity);
-            let root = latest_tree.get_root();
+                tx.insert_pending_identity(leaf_idx, identity, &root, &pre_root)
+                    .await
+                    .expect("Failed to insert identity - tree will be out of sync");
+
+                pre_root = root;
+            }
 
-            tx.insert_pending_identity(leaf_idx, identity, &root, &pre_root)
-                .await
-                .expect("Failed to insert identity - tree will be out of sync");
+            tx.copy_unprocessed_request_origins().await?;
+            tx.copy_unprocessed_received_at().await?;
+            tx.trim_unprocessed().await?;
 
-            pre_root = root;
+            // Only tracked in offchain mode
+            let latencies = if app.provable_sla().is_some() {
+                tx.get_receive_latencies_from_leaf(next_leaf).await?
+            } else {
+                vec![]
+            };
+
+            anyhow::Ok(latencies)
         }
+        .await;
 
-        tx.trim_unprocessed().await?;
+        let latencies = match result {
+            Ok(latencies) => latencies,
+            Err(err) => {
+                tx.rollback().await?;
+                return Err(err);
+            }
+        };
 
         // TODO: This works only while we're not operating in an HA context
         //       when HA is introduced we need to increase the tx serialization level
@@ -75,6 +104,11 @@ pub async fn insert_identities(
             .await
             .expect("Committing insert failed - tree will be out of sync");
 
+        // Inclusion proofs of the identities are served from now on
+        if let Some(provable_sla) = app.provable_sla() {
+            provable_sla.observe(latencies);
+        }
+
         // Notify the identity processing task, that there are new identities
         wake_up_notify.notify_one();
     }
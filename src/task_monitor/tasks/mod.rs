@@ -1,7 +1,11 @@
+pub mod canary;
 pub mod create_batches;
 pub mod delete_identities;
+pub mod export_identities;
 pub mod finalize_identities;
 pub mod insert_identities;
 pub mod monitor_queue;
+pub mod monitor_relayer_balance;
 pub mod monitor_txs;
+pub mod notify_root_subscribers;
 pub mod process_batches;
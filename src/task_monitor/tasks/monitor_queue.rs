@@ -15,6 +15,8 @@ pub async fn monitor_queue(app: Arc<App>) -> anyhow::Result<()> {
         timer.tick().await;
         info!("Monitor queue woken due to timeout.");
 
-        TaskMonitor::log_identities_queues(&app.database).await?;
+        TaskMonitor::log_identities_queues(&app).await?;
+        TaskMonitor::log_tree_leaf_count(&app);
+        app.refresh_provable_sla();
     }
 }
@@ -0,0 +1,49 @@
+use std::sync::Arc;
+
+use ethers::types::U256;
+use tokio::time;
+use tokio::time::MissedTickBehavior;
+use tracing::{error, info};
+
+use crate::task_monitor::App;
+
+const WEI_PER_GWEI: u64 = 1_000_000_000;
+
+/// Pauses batch submission while the relayer balance is below the configured
+/// minimum, so that no proofs are generated for transactions which can't be
+/// paid for. Returns right away in off-chain mode.
+pub async fn monitor_relayer_balance(app: Arc<App>) -> anyhow::Result<()> {
+    let Some(min_balance_gwei) = app.config().app.min_relayer_balance_gwei else {
+        return Ok(());
+    };
+    let min_balance = U256::from(min_balance_gwei) * U256::from(WEI_PER_GWEI);
+
+    let mut timer = time::interval(app.config().app.relayer_balance_check_interval);
+    timer.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        timer.tick().await;
+
+        let Some(balance) = app.identity_processor.relayer_balance().await? else {
+            return Ok(());
+        };
+        app.metrics()
+            .relayer_balance
+            .set(u128::try_from(balance).map_or(f64::MAX, |balance| balance as f64));
+
+        let paused = balance < min_balance;
+        if paused {
+            error!(
+                %balance,
+                %min_balance,
+                "Relayer balance is below the minimum, batch submission is paused until it's \
+                 topped up"
+            );
+        } else if app.is_batching_paused() {
+            info!(%balance, "Relayer was topped up, resuming batch submission");
+        }
+
+        app.set_batching_paused(paused);
+        app.metrics().batching_paused.set(i64::from(paused));
+    }
+}
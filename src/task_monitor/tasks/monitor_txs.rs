@@ -1,8 +1,10 @@
 use std::sync::Arc;
 
 use tokio::sync::{mpsc, Mutex};
+use tracing::error;
 
 use crate::app::App;
+use crate::database::methods::DbMethods as _;
 use crate::identity::processor::TransactionId;
 
 pub async fn monitor_txs(
@@ -12,11 +14,16 @@ pub async fn monitor_txs(
     let mut monitored_txs_receiver = monitored_txs_receiver.lock().await;
 
     while let Some(tx) = monitored_txs_receiver.recv().await {
-        assert!(
-            (app.identity_processor.mine_transaction(tx.clone()).await?),
-            "Failed to mine transaction: {}",
-            tx
-        );
+        if !app.identity_processor.mine_transaction(tx.clone()).await? {
+            // All batches of the transaction reverted together, they are retried
+            // together as well
+            let roots = app.database.delete_transaction(&tx).await?;
+            error!(
+                ?tx,
+                ?roots,
+                "Transaction failed, its batches will be retried"
+            );
+        }
     }
 
     Ok(())
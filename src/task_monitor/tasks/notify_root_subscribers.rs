@@ -0,0 +1,140 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::time;
+use tokio::time::MissedTickBehavior;
+use tracing::{info, warn};
+
+use crate::app::App;
+use crate::config::RootNotificationsConfig;
+use crate::database::methods::DbMethods as _;
+use crate::database::types::{RootNotificationKind, RootSubscription};
+use crate::database::Database;
+use crate::identity_tree::{Hash, ProcessedStatus};
+
+/// The payload delivered to subscribers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RootNotification {
+    pub event: RootNotificationKind,
+    pub root: Hash,
+    pub mined_at: DateTime<Utc>,
+    /// When the root exceeds the configured max root age
+    pub expires_at: DateTime<Utc>,
+}
+
+pub async fn notify_root_subscribers(app: Arc<App>) -> anyhow::Result<()> {
+    let config = &app.config().root_notifications;
+    let client = reqwest::Client::builder()
+        .timeout(config.webhook_timeout)
+        .build()?;
+
+    let mut timer = time::interval(config.interval);
+    timer.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        timer.tick().await;
+
+        notify_subscribers(&app.database, &client, config).await?;
+    }
+}
+
+async fn notify_subscribers(
+    database: &Database,
+    client: &reqwest::Client,
+    config: &RootNotificationsConfig,
+) -> anyhow::Result<()> {
+    let subscriptions = database.get_root_subscriptions().await?;
+    if subscriptions.is_empty() {
+        return Ok(());
+    }
+
+    let Some(root) = database
+        .get_latest_root_by_status(ProcessedStatus::Mined)
+        .await?
+    else {
+        return Ok(());
+    };
+
+    let Some((mined_at, db_now)) = database
+        .get_root_state_with_db_time(&root)
+        .await?
+        .and_then(|(root_state, db_now)| Some((root_state.mined_valid_as_of?, db_now)))
+    else {
+        return Ok(());
+    };
+
+    let max_root_age = chrono::Duration::from_std(config.max_root_age)?;
+
+    for subscription in &subscriptions {
+        let aging_threshold_percent = subscription
+            .aging_threshold_percent
+            .map_or(config.aging_threshold_percent, |percent| percent as u32);
+        let aging_after =
+            chrono::Duration::from_std(config.max_root_age * aging_threshold_percent / 100)?;
+
+        let mut kinds = vec![RootNotificationKind::RootMined];
+        if db_now - mined_at >= aging_after {
+            kinds.push(RootNotificationKind::RootAging);
+        }
+
+        for kind in kinds {
+            if database
+                .root_notification_sent(subscription.id, &root, kind)
+                .await?
+            {
+                continue;
+            }
+
+            let notification = RootNotification {
+                event: kind,
+                root,
+                mined_at,
+                expires_at: mined_at + max_root_age,
+            };
+
+            // Only deliveries which succeeded are recorded, so failed ones are
+            // retried on the next tick
+            if deliver(client, subscription, &notification).await {
+                database
+                    .insert_root_notification(subscription.id, &root, kind)
+                    .await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn deliver(
+    client: &reqwest::Client,
+    subscription: &RootSubscription,
+    notification: &RootNotification,
+) -> bool {
+    let result = client
+        .post(&subscription.url)
+        .json(notification)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status);
+
+    match result {
+        Ok(_) => {
+            info!(
+                url = %subscription.url,
+                ?notification,
+                "Root notification sent"
+            );
+            true
+        }
+        Err(error) => {
+            warn!(
+                url = %subscription.url,
+                ?error,
+                "Failed to deliver root notification"
+            );
+            false
+        }
+    }
+}
@@ -3,6 +3,8 @@ use std::time::Duration;
 
 use crate::app::App;
 use crate::database::methods::DbMethods as _;
+use crate::database::types::{BatchApproval, BatchEntry};
+use crate::database::{IsolationLevel, Tx};
 use crate::identity::processor::TransactionId;
 use tokio::sync::{mpsc, Notify};
 use tokio::time::MissedTickBehavior;
@@ -41,23 +43,115 @@ pub async fn process_batches(
             },
         }
 
-        let next_batch = app.database.get_next_batch_without_transaction().await?;
-        let Some(next_batch) = next_batch else {
+        // Proofs are only generated once the relayer can pay for the transaction
+        if app.is_batching_paused() {
+            tracing::debug!("Batch submission is paused");
             continue;
-        };
+        }
 
-        let tx_id = app
-            .identity_processor
-            .commit_identities(&next_batch)
+        // The batches stay claimed until the transaction is recorded, so that
+        // other sequencer instances don't submit them as well. The claim is
+        // taken in a transaction of its own, none is held while proving.
+        let claimer = app.instance_id();
+        let mut tx = app
+            .database
+            .begin_tx("claim_batches", IsolationLevel::ReadCommitted)
             .await?;
 
-        monitored_txs_sender.send(tx_id.clone()).await?;
+        let result = async {
+            let claimed = tx
+                .claim_next_batches(
+                    claimer,
+                    app.config().app.batch_claim_lease,
+                    app.config().app.max_batches_per_tx.max(1),
+                )
+                .await?;
+            let mut next_batches = claimed.clone();
+            if app.config().app.require_batch_approval {
+                next_batches = hold_unapproved_batches(&app, &mut tx, next_batches).await?;
+
+                let held: Vec<_> = claimed[next_batches.len()..]
+                    .iter()
+                    .map(|batch| batch.next_root)
+                    .collect();
+                tx.release_batch_claims(claimer, &held).await?;
+            }
+
+            anyhow::Ok(next_batches)
+        }
+        .await;
+        // Commits the batches put up for approval, if any
+        let next_batches = tx.finish(result).await?;
+
+        if next_batches.is_empty() {
+            continue;
+        }
+
+        let roots: Vec<_> = next_batches.iter().map(|batch| batch.next_root).collect();
+        let tx_id = match app.identity_processor.commit_batches(&next_batches).await {
+            Ok(tx_id) => tx_id,
+            Err(err) => {
+                app.database.release_batch_claims(claimer, &roots).await?;
+                return Err(err);
+            }
+        };
 
-        app.database
-            .insert_new_transaction(&tx_id, &next_batch.next_root)
+        let mut tx = app
+            .database
+            .begin_tx("record_batch_transaction", IsolationLevel::ReadCommitted)
             .await?;
+        let result = async {
+            for batch in &next_batches {
+                tx.insert_new_transaction(&tx_id, &batch.next_root).await?;
+            }
+            tx.release_batch_claims(claimer, &roots).await
+        }
+        .await;
+        let still_claimed = tx.finish(result).await?;
+
+        if still_claimed < roots.len() as u64 {
+            tracing::warn!(
+                %tx_id,
+                "The claim on the submitted batches expired before their transaction was \
+                 recorded, another instance may have submitted them as well"
+            );
+        }
+
+        monitored_txs_sender.send(tx_id).await?;
 
         // We want to check if there's a full batch available immediately
         wake_up_notify.notify_one();
     }
 }
+
+/// Returns the approved batches up to the first one which isn't, batches are
+/// submitted in order. The batches which weren't reviewed yet are put up for
+/// approval.
+async fn hold_unapproved_batches(
+    app: &App,
+    tx: &mut Tx,
+    mut batches: Vec<BatchEntry>,
+) -> anyhow::Result<Vec<BatchEntry>> {
+    let first_unapproved = batches
+        .iter()
+        .position(|batch| batch.approval != Some(BatchApproval::Approved))
+        .unwrap_or(batches.len());
+    let held = batches.split_off(first_unapproved);
+
+    let unreviewed: Vec<_> = held
+        .iter()
+        .filter(|batch| batch.approval.is_none())
+        .map(|batch| batch.next_root)
+        .collect();
+    if !unreviewed.is_empty() {
+        tracing::warn!(roots = ?unreviewed, "Batches are awaiting approval");
+        tx.mark_batches_awaiting_approval(&unreviewed).await?;
+    }
+
+    let awaiting_approval = tx.get_batches_awaiting_approval().await?;
+    app.metrics()
+        .batches_awaiting_approval
+        .set(awaiting_approval.len() as i64);
+
+    Ok(batches)
+}
@@ -11,14 +11,51 @@ pub mod index_packing;
 pub mod min_map;
 pub mod secret;
 pub mod serde_utils;
+pub mod trace_links;
 pub mod tree_updates;
 
 pub const TX_RETRY_LIMIT: u32 = 10;
 
-/// Retries a transaction a certain number of times
-/// Only errors originating from `Transaction::commit` are retried
-/// Errors originating from the transaction function `$expression` are not
-/// retried and are instead immediately rolled back.
+/// Errors a transaction can be retried on. Only serialization failures and
+/// deadlocks (SQLSTATE 40001 and 40P01) are, any other error would just fail
+/// the same way again.
+pub trait RetryableTxError {
+    fn is_retryable(&self) -> bool;
+}
+
+impl RetryableTxError for sqlx::Error {
+    fn is_retryable(&self) -> bool {
+        match self {
+            sqlx::Error::Database(err) => {
+                matches!(err.code().as_deref(), Some("40001" | "40P01"))
+            }
+            _ => false,
+        }
+    }
+}
+
+impl RetryableTxError for crate::database::Error {
+    fn is_retryable(&self) -> bool {
+        matches!(self, Self::InternalError(err) if err.is_retryable())
+    }
+}
+
+impl RetryableTxError for anyhow::Error {
+    fn is_retryable(&self) -> bool {
+        if let Some(err) = self.downcast_ref::<sqlx::Error>() {
+            return err.is_retryable();
+        }
+
+        self.downcast_ref::<crate::database::Error>()
+            .is_some_and(RetryableTxError::is_retryable)
+    }
+}
+
+/// Retries a transaction a certain number of times if it fails with a
+/// serialization failure or deadlock, see [`RetryableTxError`]. Other errors,
+/// whether from the transaction function `$expression` or from
+/// `Transaction::commit`, roll the transaction back and are returned right
+/// away.
 ///
 /// # Example
 /// ```ignore
@@ -35,13 +72,13 @@ macro_rules! retry_tx {
             loop {
                 let mut $tx = $pool.begin().await?;
                 res = async { $expression }.await;
-                let limit = 10;
+                let limit = $crate::utils::TX_RETRY_LIMIT;
                 if let Err(e) = res {
+                    $tx.rollback().await?;
                     counter += 1;
-                    if counter > limit {
+                    if counter > limit || !$crate::utils::RetryableTxError::is_retryable(&e) {
                         return Err(e.into());
                     } else {
-                        $tx.rollback().await?;
                         tracing::warn!(
                             error = ?e,
                             "db transaction returned error ({counter}/{limit})"
@@ -52,7 +89,7 @@ macro_rules! retry_tx {
                 match $tx.commit().await {
                     Err(e) => {
                         counter += 1;
-                        if counter > limit {
+                        if counter > limit || !$crate::utils::RetryableTxError::is_retryable(&e) {
                             return Err(e.into());
                         } else {
                             tracing::warn!(
@@ -0,0 +1,101 @@
+use opentelemetry::trace::{
+    SpanContext, SpanId, TraceContextExt as _, TraceFlags, TraceId, TraceState,
+};
+use tracing::{debug, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt as _;
+
+use crate::database::types::RequestTrace;
+
+/// Returns the trace and span id of the current span, or `None` if it isn't
+/// exported to OpenTelemetry.
+pub fn current_request_trace() -> Option<RequestTrace> {
+    let context = Span::current().context();
+    let span = context.span();
+    let span_context = span.span_context();
+
+    if !span_context.is_valid() {
+        return None;
+    }
+
+    Some(RequestTrace {
+        trace_id: span_context.trace_id().to_string(),
+        span_id: span_context.span_id().to_string(),
+    })
+}
+
+/// Links the current span to the spans of the requests it processes.
+///
+/// Every link is also logged as an event, which keeps the request trace ids
+/// searchable in backends that don't display span links.
+pub fn link_request_traces(traces: &[RequestTrace]) {
+    let span = Span::current();
+
+    for trace in traces {
+        debug!(
+            linked_trace_id = %trace.trace_id,
+            linked_span_id = %trace.span_id,
+            "Linked request trace"
+        );
+
+        let (Ok(trace_id), Ok(span_id)) = (
+            TraceId::from_hex(&trace.trace_id),
+            SpanId::from_hex(&trace.span_id),
+        ) else {
+            continue;
+        };
+
+        span.add_link(SpanContext::new(
+            trace_id,
+            span_id,
+            TraceFlags::SAMPLED,
+            true,
+            TraceState::default(),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing::info_span;
+    use tracing_test::traced_test;
+
+    use super::*;
+
+    #[test]
+    fn no_trace_without_exporter() {
+        let _span = info_span!("request").entered();
+
+        assert_eq!(current_request_trace(), None);
+    }
+
+    #[traced_test]
+    #[test]
+    fn links_are_logged_on_the_batch_span() {
+        let traces = [
+            RequestTrace {
+                trace_id: "4bf92f3577b34da6a3ce929d0e0e4736".to_string(),
+                span_id: "00f067aa0ba902b7".to_string(),
+            },
+            RequestTrace {
+                trace_id: "not hex".to_string(),
+                span_id: "00f067aa0ba902b7".to_string(),
+            },
+        ];
+
+        let _span = info_span!("insert_identities").entered();
+        link_request_traces(&traces);
+
+        logs_assert(|lines: &[&str]| {
+            lines
+                .iter()
+                .any(|line| {
+                    line.contains("insert_identities")
+                        && line.contains("linked_trace_id=4bf92f3577b34da6a3ce929d0e0e4736")
+                        && line.contains("linked_span_id=00f067aa0ba902b7")
+                })
+                .then_some(())
+                .ok_or_else(|| "missing linked request trace".to_string())
+        });
+        assert!(logs_contain("linked_trace_id=not hex"));
+    }
+}
@@ -0,0 +1,128 @@
+mod common;
+
+use common::prelude::*;
+use signup_sequencer::database::types::BatchType;
+use signup_sequencer::server::data::{TransactionSource, TransactionsResponse};
+
+const MINING_TIMEOUT: Duration = Duration::from_secs(30);
+
+async fn list_transactions(client: &Client, uri: &str, query: &str) -> reqwest::Response {
+    client
+        .get(format!("{uri}/v2/admin/transactions?{query}"))
+        .send()
+        .await
+        .expect("Failed to list transactions")
+}
+
+#[tokio::test]
+async fn admin_transactions() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let batch_size: usize = 3;
+
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, _, micro_oz) = spawn_deps(
+        initial_root,
+        &[batch_size],
+        &[],
+        *DEFAULT_TREE_DEPTH as u8,
+        &docker,
+    )
+    .await?;
+
+    let prover_mock = &insertion_prover_map[&batch_size];
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let temp_dir = tempfile::tempdir()?;
+
+    let config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_prover(prover_mock)
+        .build()?;
+
+    let (_, app_handle, local_addr, shutdown) = spawn_app(config.clone())
+        .await
+        .expect("Failed to spawn app.");
+
+    let test_identities = generate_test_identities(batch_size);
+    let identities_ref: Vec<Field> = test_identities
+        .iter()
+        .map(|i| Hash::from_str_radix(i, 16).unwrap())
+        .collect();
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    for leaf_index in 0..batch_size {
+        test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, leaf_index).await;
+    }
+
+    let batch_root: U256 = ref_tree.root().into();
+    let start = tokio::time::Instant::now();
+    loop {
+        let contract_root: U256 = mock_chain.identity_manager.latest_root().call().await?;
+        if contract_root == batch_root {
+            break;
+        }
+
+        assert!(start.elapsed() < MINING_TIMEOUT, "Batch was not mined");
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+
+    let response = list_transactions(&client, &uri, "").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response: TransactionsResponse = response.json().await?;
+
+    assert_eq!(response.source, TransactionSource::Relayer);
+    assert_eq!(response.transactions.len(), 1);
+
+    let transaction = &response.transactions[0];
+    assert!(transaction.hash.is_some());
+    assert!(transaction.status.is_some());
+    assert_eq!(transaction.batches.len(), 1);
+    let root: U256 = transaction.batches[0].root.into();
+    assert_eq!(root, batch_root);
+    assert_eq!(transaction.batches[0].batch_type, BatchType::Insertion);
+
+    // Filtering and paging is left to the relayer
+    let status = transaction.status.clone().unwrap();
+    let response: TransactionsResponse =
+        list_transactions(&client, &uri, &format!("status={status}"))
+            .await
+            .json()
+            .await?;
+    assert_eq!(response.transactions.len(), 1);
+
+    let response: TransactionsResponse = list_transactions(&client, &uri, "offset=1")
+        .await
+        .json()
+        .await?;
+    assert!(response.transactions.is_empty());
+
+    let response = list_transactions(&client, &uri, "status=stuck").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = list_transactions(&client, &uri, &format!("offset={}", usize::MAX)).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Shutdown the app properly for the final time
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,190 @@
+mod common;
+
+use common::prelude::*;
+use signup_sequencer::database::methods::DbMethods as _;
+use signup_sequencer::server::data::{AdminStatusResponse, BatchResponse};
+use test_utils::wait_for_async_condition;
+
+const STATUS_POLL_INTERVAL: Duration = Duration::from_millis(250);
+const STATUS_TIMEOUT: Duration = Duration::from_secs(60);
+
+async fn batches_awaiting_approval(client: &Client, uri: &str) -> Vec<Hash> {
+    let status: AdminStatusResponse = client
+        .get(format!("{uri}/v2/admin/status"))
+        .send()
+        .await
+        .expect("Failed to get status")
+        .json()
+        .await
+        .expect("Failed to parse status");
+
+    status.batches_awaiting_approval
+}
+
+async fn review_batch(
+    client: &Client,
+    uri: &str,
+    root: Hash,
+    decision: &str,
+) -> anyhow::Result<StatusCode> {
+    let response = client
+        .post(format!("{uri}/v2/admin/batches/{root:#x}/{decision}"))
+        .send()
+        .await?;
+
+    Ok(response.status())
+}
+
+#[tokio::test]
+async fn batch_approval() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let batch_size: usize = 3;
+
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, _, micro_oz) = spawn_deps(
+        initial_root,
+        &[batch_size],
+        &[],
+        *DEFAULT_TREE_DEPTH as u8,
+        &docker,
+    )
+    .await?;
+
+    let prover_mock = &insertion_prover_map[&batch_size];
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let temp_dir = tempfile::tempdir()?;
+
+    let config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_prover(prover_mock)
+        .require_batch_approval(true)
+        .build()?;
+
+    let (app, app_handle, local_addr, shutdown) = spawn_app(config.clone())
+        .await
+        .expect("Failed to spawn app.");
+
+    let test_identities = generate_test_identities(batch_size);
+    let identities_ref: Vec<Field> = test_identities
+        .iter()
+        .map(|i| Hash::from_str_radix(i, 16).unwrap())
+        .collect();
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    for leaf_index in 0..batch_size {
+        test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, leaf_index).await;
+    }
+    let batch_root = ref_tree.root();
+
+    // The full batch is held back instead of being submitted
+    wait_for_async_condition(
+        || async { batches_awaiting_approval(&client, &uri).await == vec![batch_root] },
+        STATUS_POLL_INTERVAL,
+        STATUS_TIMEOUT,
+    )
+    .await?;
+
+    let contract_root: U256 = mock_chain.identity_manager.latest_root().call().await?;
+    assert_eq!(contract_root, initial_root);
+
+    let rejected_batch = app
+        .database
+        .get_latest_batch()
+        .await?
+        .expect("The batch should exist");
+
+    // Rejecting unwinds the batch, its identities are inserted and batched
+    // again at the same leaves
+    assert_eq!(
+        review_batch(&client, &uri, batch_root, "reject").await?,
+        StatusCode::ACCEPTED
+    );
+    assert_eq!(
+        review_batch(&client, &uri, batch_root, "reject").await?,
+        StatusCode::NOT_FOUND
+    );
+
+    wait_for_async_condition(
+        || async {
+            let latest_batch = app.database.get_latest_batch().await.unwrap();
+            let rebatched = latest_batch.is_some_and(|batch| batch.id > rejected_batch.id);
+
+            rebatched && batches_awaiting_approval(&client, &uri).await == vec![batch_root]
+        },
+        STATUS_POLL_INTERVAL,
+        STATUS_TIMEOUT,
+    )
+    .await?;
+
+    let contract_root: U256 = mock_chain.identity_manager.latest_root().call().await?;
+    assert_eq!(contract_root, initial_root);
+
+    // Approving releases the batch for submission
+    assert_eq!(
+        review_batch(&client, &uri, batch_root, "approve").await?,
+        StatusCode::OK
+    );
+
+    // Both decisions are attributed, the unwound rejection in the change log
+    let batch: BatchResponse = client
+        .get(format!("{uri}/v2/admin/batches/{batch_root:#x}"))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(batch.approval_principal.as_deref(), Some("127.0.0.1"));
+
+    let decisions: Vec<_> = app
+        .database
+        .get_config_changes(None, 100)
+        .await?
+        .into_iter()
+        .filter(|change| change.setting == "batch.approval")
+        .map(|change| {
+            (
+                change.principal,
+                change.new_value.unwrap().0["approval"].clone(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        decisions,
+        [
+            ("127.0.0.1".to_owned(), serde_json::json!("rejected")),
+            ("127.0.0.1".to_owned(), serde_json::json!("approved"))
+        ]
+    );
+
+    for identity in &identities_ref {
+        test_inclusion_proof_mined(&mock_chain, &uri, &client, identity, false, false).await;
+    }
+
+    let contract_root: U256 = mock_chain.identity_manager.latest_root().call().await?;
+    assert_eq!(contract_root, batch_root.into());
+    assert!(batches_awaiting_approval(&client, &uri).await.is_empty());
+
+    // Shutdown the app properly for the final time
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,151 @@
+mod common;
+
+use common::prelude::*;
+use signup_sequencer::server::data::{
+    BatchNoteRequest, BatchResponse, ConfigChangesResponse, TransactionsResponse,
+};
+
+async fn annotate_batch(
+    client: &Client,
+    uri: &str,
+    root: Hash,
+    note: &str,
+) -> anyhow::Result<reqwest::Response> {
+    Ok(client
+        .patch(format!("{uri}/v2/admin/batches/{root:#x}"))
+        .json(&json!({ "note": note }))
+        .send()
+        .await?)
+}
+
+#[tokio::test]
+async fn batch_notes() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let batch_size: usize = 3;
+
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, _, micro_oz) = spawn_deps(
+        initial_root,
+        &[batch_size],
+        &[],
+        *DEFAULT_TREE_DEPTH as u8,
+        &docker,
+    )
+    .await?;
+
+    let prover_mock = &insertion_prover_map[&batch_size];
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let temp_dir = tempfile::tempdir()?;
+
+    let config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_prover(prover_mock)
+        .offchain_mode(true)
+        .build()?;
+
+    let (app, app_handle, local_addr, shutdown) =
+        spawn_app(config).await.expect("Failed to spawn app.");
+
+    let test_identities = generate_test_identities(batch_size);
+    let identities_ref: Vec<Field> = test_identities
+        .iter()
+        .map(|i| Hash::from_str_radix(i, 16).unwrap())
+        .collect();
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    for leaf_index in 0..batch_size {
+        test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, leaf_index).await;
+    }
+    flush_identities(&app).await?;
+    let root = ref_tree.root();
+
+    for note in ["Held back during incident", "Approved after review"] {
+        let response = annotate_batch(&client, &uri, root, note).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let batch: BatchResponse = client
+        .get(format!("{uri}/v2/admin/batches/{root:#x}"))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(batch.root, root);
+    assert_eq!(batch.note.as_deref(), Some("Approved after review"));
+    let notes: Vec<_> = batch.notes.iter().map(|note| note.note.as_str()).collect();
+    assert_eq!(
+        notes,
+        ["Held back during incident", "Approved after review"]
+    );
+    assert!(batch.notes.iter().all(|note| note.principal == "127.0.0.1"));
+    assert!(batch.notes[0].created_at <= batch.notes[1].created_at);
+
+    // The listing shows the latest note
+    let transactions: TransactionsResponse = client
+        .get(format!("{uri}/v2/admin/transactions"))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let listed = transactions
+        .transactions
+        .iter()
+        .flat_map(|tx| &tx.batches)
+        .find(|listed| listed.root == root)
+        .expect("Missing batch in listing");
+    assert_eq!(listed.note.as_deref(), Some("Approved after review"));
+
+    // Rejected notes aren't recorded
+    let too_long = "x".repeat(BatchNoteRequest::MAX_LENGTH + 1);
+    let response = annotate_batch(&client, &uri, root, &too_long).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = annotate_batch(&client, &uri, root, " ").await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = annotate_batch(&client, &uri, Hash::from(1), "Unknown").await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let changes: ConfigChangesResponse = client
+        .get(format!("{uri}/v2/admin/config-changes"))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let audited: Vec<_> = changes
+        .changes
+        .iter()
+        .filter(|change| change.setting == "batch.note")
+        .map(|change| change.new_value.as_ref().expect("Missing note")["note"].clone())
+        .collect();
+    assert_eq!(
+        audited,
+        [
+            json!("Held back during incident"),
+            json!("Approved after review")
+        ]
+    );
+
+    // Shutdown the app properly for the final time
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,168 @@
+mod common;
+
+use common::prelude::*;
+use signup_sequencer::server::data::{BulkImportResponse, MAX_REPORTED_INVALID_LINES};
+
+const LINES: u64 = 3000;
+
+/// Every 100th line is invalid and every 30th line repeats the previous
+/// line, the rest are new commitments
+fn import_file() -> (Vec<String>, u64, u64, Vec<u64>) {
+    let mut lines = vec![];
+    let mut duplicates = 0;
+    let mut invalid_lines = vec![];
+
+    for line_number in 1..=LINES {
+        let line = if line_number % 100 == 0 {
+            invalid_lines.push(line_number);
+            if line_number % 200 == 0 {
+                // Not in reduced form
+                format!("{:#x}", ruint::Uint::<256, 4>::MAX)
+            } else {
+                "not a commitment".to_string()
+            }
+        } else if line_number % 30 == 0 {
+            duplicates += 1;
+            format!("{:#x}", Field::from(line_number - 1))
+        } else {
+            format!("{:#x}", Field::from(line_number))
+        };
+
+        lines.push(line);
+    }
+
+    let accepted = LINES - duplicates - invalid_lines.len() as u64;
+
+    (lines, accepted, duplicates, invalid_lines)
+}
+
+async fn bulk_import(
+    uri: &str,
+    client: &Client,
+    import_id: &str,
+    lines: &[String],
+) -> anyhow::Result<BulkImportResponse> {
+    let response = client
+        .post(format!("{uri}/v2/admin/bulk-import?importId={import_id}"))
+        .body(lines.join("\n"))
+        .send()
+        .await?;
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    Ok(response.json().await?)
+}
+
+#[tokio::test]
+async fn bulk_import_is_resumable() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let batch_size = 3;
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, _deletion_prover_map, micro_oz) =
+        spawn_deps(
+            initial_root,
+            &[batch_size],
+            &[],
+            *DEFAULT_TREE_DEPTH as u8,
+            &docker,
+        )
+        .await?;
+
+    // The imported identities don't need to make it into the tree
+    let prover_mock = &insertion_prover_map[&batch_size];
+    prover_mock.set_availability(false).await;
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let temp_dir = tempfile::tempdir()?;
+
+    let config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_prover(prover_mock)
+        .build()?;
+
+    let (_, app_handle, local_addr, shutdown) =
+        spawn_app(config).await.expect("Failed to spawn app.");
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    let (lines, accepted, duplicates, invalid_lines) = import_file();
+
+    // Simulate a connection dropped halfway through the file
+    let interrupted_at = LINES as usize / 2;
+    let partial = bulk_import(&uri, &client, "migration", &lines[..interrupted_at]).await?;
+
+    assert_eq!(partial.lines_processed, interrupted_at as u64);
+    let partial_invalid: Vec<u64> = invalid_lines
+        .iter()
+        .copied()
+        .filter(|line| *line <= interrupted_at as u64)
+        .collect();
+    assert_eq!(partial.invalid_lines, partial_invalid);
+
+    // Resending the whole file only processes the remaining lines
+    let resumed = bulk_import(&uri, &client, "migration", &lines).await?;
+
+    assert_eq!(resumed.lines_processed, LINES);
+    assert_eq!(resumed.accepted, accepted);
+    assert_eq!(resumed.duplicates, duplicates);
+    assert_eq!(resumed.invalid, invalid_lines.len() as u64);
+    assert_eq!(
+        resumed.invalid_lines,
+        invalid_lines[partial_invalid.len()..].to_vec()
+    );
+
+    // A completed import is a no-op
+    let repeated = bulk_import(&uri, &client, "migration", &lines).await?;
+
+    assert_eq!(repeated.accepted, accepted);
+    assert_eq!(repeated.duplicates, duplicates);
+    assert!(repeated.invalid_lines.is_empty());
+
+    // A new import of the same file finds every commitment already queued
+    let reimported = bulk_import(&uri, &client, "reimport", &lines).await?;
+
+    assert_eq!(reimported.accepted, 0);
+    assert_eq!(reimported.duplicates, accepted + duplicates);
+    assert_eq!(reimported.invalid_lines, invalid_lines);
+
+    // Only the first invalid lines are reported, all of them are counted
+    let garbage = vec!["not a commitment".to_string(); 2 * MAX_REPORTED_INVALID_LINES];
+    let rejected = bulk_import(&uri, &client, "garbage", &garbage).await?;
+
+    assert_eq!(rejected.invalid, garbage.len() as u64);
+    assert_eq!(
+        rejected.invalid_lines,
+        (1..=MAX_REPORTED_INVALID_LINES as u64).collect::<Vec<_>>()
+    );
+
+    let response = client
+        .post(uri.clone() + "/inclusionProof")
+        .json(&json!({ "identityCommitment": Field::from(1) }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Shutdown the app properly for the final time
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use common::prelude::*;
+use signup_sequencer::config::CanaryConfig;
+use signup_sequencer::server::data::IdentityCountResponse;
+
+const CANARY_TIMEOUT: Duration = Duration::from_secs(60);
+
+async fn canary_ok(uri: &str, client: &Client) -> anyhow::Result<Option<i64>> {
+    let metrics = client
+        .get(format!("{uri}/metrics"))
+        .send()
+        .await?
+        .text()
+        .await?;
+
+    Ok(metrics
+        .lines()
+        .find_map(|line| line.strip_prefix("canary_ok "))
+        .map(|value| value.trim().parse())
+        .transpose()?)
+}
+
+#[tokio::test]
+async fn canary_is_verified_in_offchain_mode() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let batch_size = 3;
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, _deletion_prover_map, micro_oz) =
+        spawn_deps(
+            initial_root,
+            &[batch_size],
+            &[],
+            *DEFAULT_TREE_DEPTH as u8,
+            &docker,
+        )
+        .await?;
+
+    let prover_mock = &insertion_prover_map[&batch_size];
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let temp_dir = tempfile::tempdir()?;
+
+    let config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_prover(prover_mock)
+        .offchain_mode(true)
+        .canary(CanaryConfig {
+            seed: "test".to_string(),
+            timeout: CANARY_TIMEOUT,
+            allow_onchain: false,
+        })
+        .build()?;
+
+    let (_, app_handle, local_addr, shutdown, task_monitor) =
+        spawn_app_with_task_monitor(config.clone())
+            .await
+            .expect("Failed to spawn app.");
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    let start = tokio::time::Instant::now();
+    while canary_ok(&uri, &client).await? != Some(1) {
+        assert!(start.elapsed() < CANARY_TIMEOUT, "Canary was not verified");
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+
+    let response = client.get(uri.clone() + "/ready").send().await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Shutdown the app and start it again, the canary must be reused
+    task_monitor.stop().await;
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+
+    let (_, app_handle, local_addr, shutdown) = spawn_app(config.clone())
+        .await
+        .expect("Failed to spawn app.");
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+
+    // The gauge is still set by the previous run, give the canary some time to
+    // report again
+    tokio::time::sleep(Duration::from_secs(5)).await;
+    let start = tokio::time::Instant::now();
+    while canary_ok(&uri, &client).await? != Some(1) {
+        assert!(start.elapsed() < CANARY_TIMEOUT, "Canary was not verified");
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+
+    let count: IdentityCountResponse = client
+        .get(uri.clone() + "/v2/identities/count")
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(count.next_leaf, 1);
+
+    // Shutdown the app properly for the final time
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
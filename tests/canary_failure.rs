@@ -0,0 +1,84 @@
+mod common;
+
+use common::prelude::*;
+use signup_sequencer::config::CanaryConfig;
+
+const CANARY_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[tokio::test]
+async fn canary_fails_with_sabotaged_prover() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let batch_size = 3;
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, _deletion_prover_map, micro_oz) =
+        spawn_deps(
+            initial_root,
+            &[batch_size],
+            &[],
+            *DEFAULT_TREE_DEPTH as u8,
+            &docker,
+        )
+        .await?;
+
+    // Batches are never proven, so the canary can't be processed
+    let prover_mock = &insertion_prover_map[&batch_size];
+    prover_mock.set_availability(false).await;
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let temp_dir = tempfile::tempdir()?;
+
+    let config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_prover(prover_mock)
+        .canary(CanaryConfig {
+            seed: "test".to_string(),
+            timeout: CANARY_TIMEOUT,
+            allow_onchain: true,
+        })
+        .build()?;
+
+    let (_, app_handle, local_addr, shutdown) =
+        spawn_app(config).await.expect("Failed to spawn app.");
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    let response = client.get(uri.clone() + "/ready").send().await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    tokio::time::sleep(CANARY_TIMEOUT + Duration::from_secs(5)).await;
+
+    let metrics = client
+        .get(uri.clone() + "/metrics")
+        .send()
+        .await?
+        .text()
+        .await?;
+    assert!(metrics.lines().any(|line| line == "canary_ok 0"));
+
+    let response = client.get(uri.clone() + "/ready").send().await?;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    // Shutdown the app properly for the final time
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
@@ -11,6 +11,7 @@ abigen!(
         function verifyProof(uint256 root, uint256 signalHash, uint256 nullifierHash, uint256 externalNullifierHash, uint256[8] calldata proof) public view virtual
         function setRootHistoryExpiry(uint256 newExpiryTime) public virtual
         function queryRoot(uint256 root) public view virtual returns (RootInfo memory)
+        function latestRoot() public view virtual returns (uint256 root)
     ]"#,
     event_derives(serde::Deserialize, serde::Serialize)
 );
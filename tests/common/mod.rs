@@ -58,15 +58,16 @@ pub mod prelude {
         DEFAULT_TREE_DENSE_PREFIX_DEPTH, DEFAULT_TREE_DEPTH,
     };
     pub use super::{
-        abi as ContractAbi, generate_reference_proof, generate_test_identities,
-        init_tracing_subscriber, spawn_app, spawn_deps, spawn_mock_deletion_prover,
-        spawn_mock_insertion_prover, test_inclusion_proof, test_insert_identity, test_verify_proof,
-        test_verify_proof_on_chain,
+        abi as ContractAbi, flush_identities, generate_reference_proof, generate_test_identities,
+        init_tracing_subscriber, spawn_app, spawn_app_with_task_monitor, spawn_deps,
+        spawn_mock_deletion_prover, spawn_mock_insertion_prover, test_inclusion_proof,
+        test_insert_identity, test_verify_proof, test_verify_proof_on_chain, VERIFY_PROOF_TIMEOUT,
     };
     pub use crate::common::chain_mock::spawn_mock_chain;
     pub use crate::common::test_same_tree_states;
 }
 
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::str::FromStr;
@@ -79,6 +80,7 @@ use crate::common::chain_mock::SpecialisedClient;
 use crate::server::error::Error as ServerError;
 use futures::stream::FuturesUnordered;
 use futures::StreamExt;
+use prometheus::{Encoder, Registry, TextEncoder};
 use reqwest::{Body, Client, Method, Request, RequestBuilder, StatusCode};
 use semaphore::poseidon_tree::Proof;
 use signup_sequencer::identity_tree::ProcessedStatus::Mined;
@@ -86,15 +88,29 @@ use signup_sequencer::identity_tree::{
     InclusionProof, ProcessedStatus, Status, TreeState, TreeVersionReadOps,
 };
 use signup_sequencer::server::data::{
-    AddBatchSizeRequest, DeletionRequest, InclusionProofRequest, InclusionProofResponse,
-    InsertCommitmentRequest, RemoveBatchSizeRequest, VerifySemaphoreProofRequest,
+    AddBatchSizeRequest, AddBatchSizeResponse, DeletionRequest, InclusionProofRequest,
+    InclusionProofResponse, InsertCommitmentRequest, RemoveBatchSizeRequest,
+    VerifySemaphoreProofRequest,
 };
+use signup_sequencer::server::error::ErrorResponse;
 use signup_sequencer::task_monitor::TaskMonitor;
+use test_utils::{wait_for_async_condition, wait_for_condition};
 use testcontainers::clients::Cli;
 use tokio::net::TcpListener;
-use tracing::trace;
 
-const NUM_ATTEMPTS_FOR_INCLUSION_PROOF: usize = 20;
+const INCLUSION_PROOF_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const INCLUSION_PROOF_TIMEOUT: Duration = Duration::from_secs(100);
+const TREE_INIT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// How long a proof verification request may take before the test fails
+pub const VERIFY_PROOF_TIMEOUT: Duration = Duration::from_secs(30);
+
+thread_local! {
+    /// Metrics of the mock services, printed when a test fails. Every test runs
+    /// on its own thread, so only the mocks of the failing test are printed.
+    static MOCK_METRICS: RefCell<Vec<(String, Registry)>> = RefCell::default();
+}
+static DUMP_MOCK_METRICS_ON_PANIC: Once = Once::new();
 
 #[allow(clippy::too_many_arguments)]
 #[instrument(skip_all)]
@@ -107,7 +123,8 @@ pub async fn test_verify_proof(
     external_nullifier_hash: Field,
     proof: protocol::Proof,
     expected_failure: Option<&str>,
-) {
+    timeout: Duration,
+) -> anyhow::Result<()> {
     test_verify_proof_inner(
         uri,
         client,
@@ -118,6 +135,7 @@ pub async fn test_verify_proof(
         proof,
         None,
         expected_failure,
+        timeout,
     )
     .await
 }
@@ -134,7 +152,8 @@ pub async fn test_verify_proof_with_age(
     proof: protocol::Proof,
     max_root_age_seconds: i64,
     expected_failure: Option<&str>,
-) {
+    timeout: Duration,
+) -> anyhow::Result<()> {
     test_verify_proof_inner(
         uri,
         client,
@@ -145,6 +164,7 @@ pub async fn test_verify_proof_with_age(
         proof,
         Some(max_root_age_seconds),
         expected_failure,
+        timeout,
     )
     .await
 }
@@ -161,7 +181,8 @@ async fn test_verify_proof_inner(
     proof: protocol::Proof,
     max_root_age_seconds: Option<i64>,
     expected_failure: Option<&str>,
-) {
+    timeout: Duration,
+) -> anyhow::Result<()> {
     let body = construct_verify_proof_body(
         root,
         signal_hash,
@@ -177,22 +198,21 @@ async fn test_verify_proof_inner(
         None => format!("{uri}/verifySemaphoreProof"),
     };
 
-    let response = client
+    let request = client
         .request(Method::POST, uri)
         .header("Content-Type", "application/json")
         .body(body)
-        .send()
+        .send();
+    let response = tokio::time::timeout(timeout, request)
         .await
-        .expect("Failed to execute request");
+        .map_err(|_| anyhow::anyhow!("verify proof request timed out after {timeout:?}"))?
+        .context("Failed to execute verify proof request")?;
 
     let response_status = response.status();
-    let bytes = response
-        .bytes()
+    let result = response
+        .text()
         .await
-        .expect("Failed to get response bytes");
-
-    let result = String::from_utf8(bytes.into_iter().collect())
-        .expect("Could not parse response bytes to utf-8");
+        .context("Failed to read verify proof response")?;
 
     if let Some(expected_failure) = expected_failure {
         assert!(!response_status.is_success());
@@ -205,6 +225,8 @@ async fn test_verify_proof_inner(
     } else {
         assert!(response_status.is_success());
     }
+
+    Ok(())
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -251,88 +273,88 @@ pub async fn test_inclusion_proof(
     expect_failure: bool,
     offchain_mode_enabled: bool,
 ) {
-    for i in 0..NUM_ATTEMPTS_FOR_INCLUSION_PROOF {
-        let body = construct_inclusion_proof_body(leaf);
-        info!(?uri, "Contacting");
-        let response = client
-            .post(uri.to_owned() + "/inclusionProof")
-            .header("Content-Type", "application/json")
-            .body(body)
-            .send()
-            .await
-            .expect("Failed to execute request");
-
-        if expect_failure {
-            assert!(!response.status().is_success());
-            return;
-        } else {
-            assert!(response.status().is_success());
-        }
-
-        let response_status = response.status();
-        let bytes = response
-            .bytes()
-            .await
-            .expect("Failed to get response bytes");
-
-        let result = String::from_utf8(bytes.into_iter().collect())
-            .expect("Could not parse response bytes to utf-8");
-        let result = serde_json::from_str::<InclusionProofResponse>(&result)
-            .expect("Failed to parse response as json");
-
-        if let Some(root) = result.root {
-            if offchain_mode_enabled {
-                if result.status != Status::from(Mined) {
-                    info!("Got pending, waiting 5 seconds, iteration {}", i);
-                    tokio::time::sleep(Duration::from_secs(5)).await;
-                    continue;
-                }
-
-                // For offchain mode returning root in inclusion proof response means the proof
-                // is valid.
-                let proof_json = generate_reference_proof(
-                    ref_tree,
-                    leaf_index,
-                    result.status, // for off-chain mode status doesn't matter
-                );
-                assert_eq!(result, proof_json);
+    wait_for_async_condition(
+        || async move {
+            let body = construct_inclusion_proof_body(leaf);
+            info!(?uri, "Contacting");
+            let response = client
+                .post(uri.to_owned() + "/inclusionProof")
+                .header("Content-Type", "application/json")
+                .body(body)
+                .send()
+                .await
+                .expect("Failed to execute request");
 
-                return;
+            if expect_failure {
+                assert!(!response.status().is_success());
+                return true;
+            } else {
+                assert!(response.status().is_success());
             }
-            let root: U256 = root.into();
 
-            let (root, ..) = mock_chain
-                .identity_manager
-                .query_root(root)
-                .call()
+            let response_status = response.status();
+            let bytes = response
+                .bytes()
                 .await
-                .expect("Failed to call method queryRoot on mocked chain.");
+                .expect("Failed to get response bytes");
+
+            let result = String::from_utf8(bytes.into_iter().collect())
+                .expect("Could not parse response bytes to utf-8");
+            let result = serde_json::from_str::<InclusionProofResponse>(&result)
+                .expect("Failed to parse response as json");
+
+            if let Some(root) = result.root {
+                if offchain_mode_enabled {
+                    if result.status != Status::from(Mined) {
+                        info!("Got pending, waiting");
+                        return false;
+                    }
+
+                    // For offchain mode returning root in inclusion proof response means the proof
+                    // is valid.
+                    let proof_json = generate_reference_proof(
+                        ref_tree,
+                        leaf_index,
+                        result.status, // for off-chain mode status doesn't matter
+                    );
+                    assert_eq!(result, proof_json);
+
+                    return true;
+                }
+                let root: U256 = root.into();
 
-            if root != U256::zero() {
-                let proof_json = generate_reference_proof(ref_tree, leaf_index, result.status);
-                assert_eq!(result, proof_json);
+                let (root, ..) = mock_chain
+                    .identity_manager
+                    .query_root(root)
+                    .call()
+                    .await
+                    .expect("Failed to call method queryRoot on mocked chain.");
 
-                return;
-            }
-        }
+                if root != U256::zero() {
+                    let proof_json = generate_reference_proof(ref_tree, leaf_index, result.status);
+                    assert_eq!(result, proof_json);
 
-        assert_eq!(
-            result,
-            generate_reference_proof(
-                ref_tree,
-                leaf_index,
-                Status::Processed(ProcessedStatus::Pending)
-            )
-        );
-        assert_eq!(response_status, StatusCode::OK);
-        info!("Got pending, waiting 5 seconds, iteration {}", i);
-        tokio::time::sleep(Duration::from_secs(5)).await;
-    }
+                    return true;
+                }
+            }
 
-    panic!(
-        "Failed to get an inclusion proof after {} attempts!",
-        NUM_ATTEMPTS_FOR_INCLUSION_PROOF
-    );
+            assert_eq!(
+                result,
+                generate_reference_proof(
+                    ref_tree,
+                    leaf_index,
+                    Status::Processed(ProcessedStatus::Pending)
+                )
+            );
+            assert_eq!(response_status, StatusCode::OK);
+            info!("Got pending, waiting");
+            false
+        },
+        INCLUSION_PROOF_POLL_INTERVAL,
+        INCLUSION_PROOF_TIMEOUT,
+    )
+    .await
+    .expect("Failed to get an inclusion proof");
 }
 
 #[instrument(skip_all)]
@@ -344,68 +366,68 @@ pub async fn test_inclusion_proof_mined(
     expect_failure: bool,
     offchain_mode_enabled: bool,
 ) {
-    for i in 0..NUM_ATTEMPTS_FOR_INCLUSION_PROOF {
-        let body = construct_inclusion_proof_body(leaf);
-        info!(?uri, "Contacting");
-
-        let response = client
-            .post(uri.to_owned() + "/inclusionProof")
-            .header("Content-Type", "application/json")
-            .body(body)
-            .send()
-            .await
-            .expect("Failed to create inclusion proof");
-
-        if expect_failure {
-            assert!(!response.status().is_success());
-            return;
-        } else {
-            assert!(response.status().is_success());
-        }
-
-        let bytes = response
-            .bytes()
-            .await
-            .expect("Failed to get response bytes");
-        let result = String::from_utf8(bytes.into_iter().collect())
-            .expect("Could not parse response bytes to utf-8");
-        let result = serde_json::from_str::<InclusionProofResponse>(&result)
-            .expect("Failed to parse response as json");
-
-        if let Some(root) = result.root {
-            if offchain_mode_enabled {
-                if result.status != Status::from(Mined) {
-                    info!("Got pending, waiting 5 seconds, iteration {}", i);
-                    tokio::time::sleep(Duration::from_secs(5)).await;
-                    continue;
-                }
+    wait_for_async_condition(
+        || async move {
+            let body = construct_inclusion_proof_body(leaf);
+            info!(?uri, "Contacting");
+
+            let response = client
+                .post(uri.to_owned() + "/inclusionProof")
+                .header("Content-Type", "application/json")
+                .body(body)
+                .send()
+                .await
+                .expect("Failed to create inclusion proof");
 
-                // For offchain mode returning root in inclusion proof response means the proof
-                // is valid.
-                return;
+            if expect_failure {
+                assert!(!response.status().is_success());
+                return true;
+            } else {
+                assert!(response.status().is_success());
             }
-            let root: U256 = root.into();
 
-            let (root, ..) = mock_chain
-                .identity_manager
-                .query_root(root)
-                .call()
+            let bytes = response
+                .bytes()
                 .await
-                .expect("Failed to call method queryRoot on mocked chain.");
+                .expect("Failed to get response bytes");
+            let result = String::from_utf8(bytes.into_iter().collect())
+                .expect("Could not parse response bytes to utf-8");
+            let result = serde_json::from_str::<InclusionProofResponse>(&result)
+                .expect("Failed to parse response as json");
+
+            if let Some(root) = result.root {
+                if offchain_mode_enabled {
+                    if result.status != Status::from(Mined) {
+                        info!("Got pending, waiting");
+                        return false;
+                    }
+
+                    // For offchain mode returning root in inclusion proof response means the proof
+                    // is valid.
+                    return true;
+                }
+                let root: U256 = root.into();
 
-            if root != U256::zero() {
-                return;
-            }
-        }
+                let (root, ..) = mock_chain
+                    .identity_manager
+                    .query_root(root)
+                    .call()
+                    .await
+                    .expect("Failed to call method queryRoot on mocked chain.");
 
-        info!("Got pending, waiting 5 seconds, iteration {}", i);
-        tokio::time::sleep(Duration::from_secs(5)).await;
-    }
+                if root != U256::zero() {
+                    return true;
+                }
+            }
 
-    panic!(
-        "Failed to get an inclusion proof after {} attempts!",
-        NUM_ATTEMPTS_FOR_INCLUSION_PROOF
-    );
+            info!("Got pending, waiting");
+            false
+        },
+        INCLUSION_PROOF_POLL_INTERVAL,
+        INCLUSION_PROOF_TIMEOUT,
+    )
+    .await
+    .expect("Failed to get an inclusion proof");
 }
 
 #[instrument(skip_all)]
@@ -517,9 +539,10 @@ pub async fn test_add_batch_size(
         batch_size: batch_size as usize,
         timeout_seconds: 3,
         prover_type,
+        probe: false,
     })?);
 
-    client
+    let response = client
         .post(uri.into() + "/addBatchSize")
         .header("Content-Type", "application/json")
         .body(body)
@@ -527,6 +550,19 @@ pub async fn test_add_batch_size(
         .await
         .expect("Failed to create add batch size");
 
+    if response.status() != StatusCode::CREATED {
+        anyhow::bail!("Failed to add batch size: {}", response.status());
+    }
+
+    let bytes = response
+        .bytes()
+        .await
+        .expect("Failed to get response bytes");
+    let added: AddBatchSizeResponse = serde_json::from_slice(&bytes)?;
+
+    assert_eq!(added.batch_size, batch_size as usize);
+    assert_eq!(added.prover_type, prover_type);
+
     Ok(())
 }
 
@@ -551,18 +587,30 @@ pub async fn test_remove_batch_size(
         .await
         .expect("Failed to create remove batch size");
 
+    let status = response.status();
     let bytes = response
         .bytes()
         .await
         .expect("Failed to get response bytes");
 
-    let body_str = String::from_utf8(bytes.to_vec()).expect("Failed to decode response.");
+    if !expect_failure {
+        if status != StatusCode::OK {
+            anyhow::bail!("Failed to remove batch size: {status}");
+        }
 
-    if expect_failure && body_str != "The last batch size cannot be removed" {
-        anyhow::bail!("Expected failure, but got success");
-    } else {
-        Ok(())
+        return Ok(());
+    }
+
+    if status != StatusCode::CONFLICT {
+        anyhow::bail!("Expected failure, but got {status}");
     }
+
+    let error: ErrorResponse = serde_json::from_slice(&bytes)?;
+    if error.error_id != "last_prover" {
+        anyhow::bail!("Expected last_prover error, but got {}", error.error_id);
+    }
+
+    Ok(())
 }
 
 #[instrument(skip_all)]
@@ -654,11 +702,26 @@ fn construct_verify_proof_body(
 pub async fn spawn_app(
     config: Config,
 ) -> anyhow::Result<(Arc<App>, JoinHandle<()>, SocketAddr, Shutdown)> {
+    let (app, app_handle, local_addr, shutdown, _) = spawn_app_with_task_monitor(config).await?;
+
+    Ok((app, app_handle, local_addr, shutdown))
+}
+
+/// Like [`spawn_app`], but also returns the task monitor so that the tasks of
+/// the app can be stopped before it's started again.
+#[instrument(skip_all)]
+#[allow(clippy::type_complexity)]
+pub async fn spawn_app_with_task_monitor(
+    config: Config,
+) -> anyhow::Result<(Arc<App>, JoinHandle<()>, SocketAddr, Shutdown, TaskMonitor)> {
     let server_config = config.server.clone();
-    let app = App::new(config).await.expect("Failed to create App");
+    // Every app gets its own registry, apps are restarted within a test
+    let app = App::new_with_registry(config, Registry::new())
+        .await
+        .expect("Failed to create App");
     let shutdown = Shutdown::spawn(Duration::from_secs(30), Duration::from_secs(1));
 
-    TaskMonitor::init(app.clone(), shutdown.clone()).await;
+    let task_monitor = TaskMonitor::init(app.clone(), shutdown.clone()).await;
 
     let listener = TcpListener::bind(server_config.address)
         .await
@@ -667,42 +730,50 @@ pub async fn spawn_app(
 
     info!("Waiting for tree initialization");
     // For our tests to work we need the tree to be initialized.
-    while app.tree_state().is_err() {
-        trace!("Waiting for the tree to be initialized");
-        tokio::time::sleep(Duration::from_millis(250)).await;
-    }
+    wait_for_condition(
+        || app.tree_state().is_ok(),
+        Duration::from_millis(250),
+        TREE_INIT_TIMEOUT,
+    )
+    .await
+    .expect("Tree was not initialized");
+
+    let scheme = if server_config.tls.is_some() {
+        "https"
+    } else {
+        "http"
+    };
+    let uri = format!("{scheme}://{local_addr}");
 
     let app_clone = app.clone();
     let shutdown_clone = shutdown.clone();
     let app_handle = spawn({
         async move {
             info!("App thread starting");
-            server::bind_from_listener(
-                app_clone,
-                Duration::from_secs(30),
-                listener,
-                shutdown_clone,
-            )
-            .await
-            .expect("Failed to bind address");
+            server::bind_from_listener(app_clone, &server_config, listener, shutdown_clone)
+                .await
+                .expect("Failed to bind address");
             info!("App thread stopping");
         }
     });
 
+    // Tests serving TLS use self-signed certificates
+    let client = Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()?;
+
     info!("Checking app health");
-    check_health(&local_addr).await?;
+    check_health(&client, &uri).await?;
 
     info!("Checking metrics");
-    check_metrics(&local_addr).await?;
+    check_metrics(&client, &uri).await?;
 
     info!("App ready");
 
-    Ok((app, app_handle, local_addr, shutdown))
+    Ok((app, app_handle, local_addr, shutdown, task_monitor))
 }
 
-pub async fn check_metrics(socket_addr: &SocketAddr) -> anyhow::Result<()> {
-    let uri = format!("http://{}", socket_addr);
-    let client = Client::new();
+pub async fn check_metrics(client: &Client, uri: &str) -> anyhow::Result<()> {
     let response = client
         .get(uri.to_owned() + "/metrics")
         .send()
@@ -716,10 +787,7 @@ pub async fn check_metrics(socket_addr: &SocketAddr) -> anyhow::Result<()> {
     Ok(())
 }
 
-pub async fn check_health(socket_addr: &SocketAddr) -> anyhow::Result<()> {
-    let uri = format!("http://{}", socket_addr);
-    let client = Client::new();
-
+pub async fn check_health(client: &Client, uri: &str) -> anyhow::Result<()> {
     let response = client
         .get(uri.to_owned() + "/health")
         .send()
@@ -752,12 +820,22 @@ pub async fn spawn_deps<'a, 'b, 'c>(
     HashMap<usize, ProverService>,
     micro_oz::ServerHandle,
 )> {
-    let chain = spawn_mock_chain(
-        initial_root,
-        insertion_batch_sizes,
-        deletion_batch_sizes,
-        tree_depth,
-    );
+    // Starting anvil and the database container both block the current task
+    // until they're up, so the chain gets a task of its own to overlap them
+    let chain = {
+        let insertion_batch_sizes = insertion_batch_sizes.to_vec();
+        let deletion_batch_sizes = deletion_batch_sizes.to_vec();
+
+        spawn(async move {
+            spawn_mock_chain(
+                initial_root,
+                &insertion_batch_sizes,
+                &deletion_batch_sizes,
+                tree_depth,
+            )
+            .await
+        })
+    };
 
     let db_container = spawn_db(docker);
 
@@ -778,7 +856,7 @@ pub async fn spawn_deps<'a, 'b, 'c>(
         deletion_prover_futures.collect::<Vec<_>>()
     );
 
-    let chain = chain?;
+    let chain = chain??;
 
     let micro_oz = micro_oz::spawn(chain.anvil.endpoint(), chain.private_key.clone()).await?;
 
@@ -800,6 +878,21 @@ pub async fn spawn_deps<'a, 'b, 'c>(
         .map(|prover| (prover.batch_size(), prover))
         .collect::<HashMap<usize, ProverService>>();
 
+    dump_metrics_on_failure("micro-oz", micro_oz.metrics().registry().clone());
+    for prover in insertion_prover_map
+        .values()
+        .chain(deletion_prover_map.values())
+    {
+        dump_metrics_on_failure(
+            format!(
+                "{} prover mock (batch size {})",
+                prover.prover_type(),
+                prover.batch_size()
+            ),
+            prover.registry().clone(),
+        );
+    }
+
     Ok((
         chain,
         db_container?,
@@ -809,6 +902,49 @@ pub async fn spawn_deps<'a, 'b, 'c>(
     ))
 }
 
+/// Registers the metrics of a mock service to be printed if the test panics.
+fn dump_metrics_on_failure(name: impl Into<String>, registry: Registry) {
+    DUMP_MOCK_METRICS_ON_PANIC.call_once(|| {
+        let default_hook = std::panic::take_hook();
+
+        std::panic::set_hook(Box::new(move |info| {
+            default_hook(info);
+            eprintln!("{}", mock_metrics_dump());
+        }));
+    });
+
+    MOCK_METRICS.with(|registries| registries.borrow_mut().push((name.into(), registry)));
+}
+
+fn mock_metrics_dump() -> String {
+    // The panic may have happened while the registries were borrowed
+    let registries = MOCK_METRICS.try_with(|registries| {
+        registries
+            .try_borrow()
+            .map(|registries| registries.clone())
+            .ok()
+    });
+    let Ok(Some(registries)) = registries else {
+        return "Mock metrics unavailable".to_string();
+    };
+
+    let mut dump = String::new();
+    for (name, registry) in &registries {
+        let mut buffer = vec![];
+        if TextEncoder::new()
+            .encode(&registry.gather(), &mut buffer)
+            .is_err()
+        {
+            continue;
+        }
+
+        dump.push_str(&format!("===== {name} metrics =====\n"));
+        dump.push_str(&String::from_utf8_lossy(&buffer));
+    }
+
+    dump
+}
+
 async fn spawn_db(docker: &Cli) -> anyhow::Result<DockerContainer> {
     let db_container = postgres_docker_utils::setup(docker).await.unwrap();
 
@@ -835,6 +971,16 @@ pub async fn spawn_mock_deletion_prover(
     Ok(mock_prover_service)
 }
 
+/// Processes the queued identities right away instead of waiting for the batch
+/// timeouts, fails if they aren't processed within `FLUSH_TIMEOUT`.
+pub async fn flush_identities(app: &App) -> anyhow::Result<()> {
+    const FLUSH_TIMEOUT: Duration = Duration::from_secs(60);
+
+    tokio::time::timeout(FLUSH_TIMEOUT, app.flush())
+        .await
+        .context("Timed out flushing identities")?
+}
+
 /// Initializes the tracing subscriber.
 ///
 /// Set the `QUIET_MODE` environment variable to reduce the complexity of the
@@ -1,19 +1,25 @@
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 use std::mem::size_of;
 use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use anyhow::Context;
+use axum::body::Bytes;
 use axum::extract::State;
-use axum::routing::post;
+use axum::routing::{get, post};
 use axum::{Json, Router};
 use axum_server::Handle;
 use ethers::types::U256;
 use ethers::utils::keccak256;
-use hyper::StatusCode;
+use hyper::{HeaderMap, StatusCode};
+use prometheus::{
+    exponential_buckets, Encoder, Histogram, HistogramOpts, IntCounter, Registry, TextEncoder,
+};
 use semaphore::poseidon_tree::{Branch, Proof as TreeProof};
 use serde::{Deserialize, Serialize};
-use signup_sequencer::prover::ProverType;
+use signup_sequencer::prover::{signature, ProverType};
 use signup_sequencer::utils::index_packing::pack_indices;
 use tokio::sync::Mutex;
 
@@ -111,16 +117,80 @@ impl ProveResponse {
 pub struct ProverService {
     server: Handle,
     inner: Arc<Mutex<Prover>>,
+    metrics: ProverMetrics,
     address: SocketAddr,
     batch_size: usize,
     prover_type: ProverType,
 }
 
+/// Metrics of a single mock prover, kept in a registry of its own so that
+/// multiple provers can run next to the sequencer in one process.
+#[derive(Clone)]
+struct ProverMetrics {
+    registry: Registry,
+    requests: IntCounter,
+    latency: Histogram,
+    simulated_failures: IntCounter,
+}
+
+impl ProverMetrics {
+    fn new(batch_size: usize, prover_type: ProverType) -> anyhow::Result<Self> {
+        let labels = HashMap::from([
+            ("batch_size".to_string(), batch_size.to_string()),
+            ("prover_type".to_string(), prover_type.to_string()),
+        ]);
+        let registry = Registry::new_custom(None, Some(labels))?;
+
+        let requests = IntCounter::new("prover_mock_requests", "Prove requests received")?;
+        let latency = Histogram::with_opts(
+            HistogramOpts::new(
+                "prover_mock_latency_seconds",
+                "Time taken to answer a prove request",
+            )
+            .buckets(exponential_buckets(0.001, 2.0, 12)?),
+        )?;
+        let simulated_failures = IntCounter::new(
+            "prover_mock_simulated_failures",
+            "Prove requests rejected because the prover was made unavailable",
+        )?;
+
+        registry.register(Box::new(requests.clone()))?;
+        registry.register(Box::new(latency.clone()))?;
+        registry.register(Box::new(simulated_failures.clone()))?;
+
+        Ok(Self {
+            registry,
+            requests,
+            latency,
+            simulated_failures,
+        })
+    }
+
+    fn encode(&self) -> anyhow::Result<String> {
+        let mut buffer = vec![];
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+
+        Ok(String::from_utf8(buffer)?)
+    }
+}
+
+#[derive(Clone)]
+struct ServiceState {
+    prover: Arc<Mutex<Prover>>,
+    metrics: ProverMetrics,
+}
+
 struct Prover {
     is_available: bool,
     tree_depth: u8,
+    batch_sizes: Vec<usize>,
+    hmac_secret: Option<String>,
 }
 
+/// How far the timestamp of a signed request may be off from the clock of the
+/// mock prover.
+const MAX_SIGNATURE_AGE_S: u64 = 300;
+
 impl ProverService {
     /// Returns a new instance of the mock prover service, serving at the
     /// provided `url`.
@@ -134,16 +204,35 @@ impl ProverService {
         prover_type: ProverType,
     ) -> anyhow::Result<Self> {
         async fn prove(
-            state: State<Arc<Mutex<Prover>>>,
-            Json(input): Json<serde_json::Value>,
+            State(state): State<ServiceState>,
+            headers: HeaderMap,
+            body: Bytes,
         ) -> Result<Json<ProveResponse>, StatusCode> {
-            let state = state.lock().await;
+            state.metrics.requests.inc();
+            let _timer = state.metrics.latency.start_timer();
+
+            let mut prover = state.prover.lock().await;
+
+            if !prover.is_signed(&headers, &body) {
+                return Err(StatusCode::UNAUTHORIZED);
+            }
+
+            let input: serde_json::Value =
+                serde_json::from_slice(&body).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+            if !prover.is_available {
+                state.metrics.simulated_failures.inc();
+            }
 
             // Attempt to deserialize into InsertionProofInput
             if let Ok(deserialized_insertion_input) =
                 serde_json::from_value::<InsertionProofInput>(input.clone())
             {
-                return state
+                prover
+                    .batch_sizes
+                    .push(deserialized_insertion_input.identity_commitments.len());
+
+                return prover
                     .prove_insertion(deserialized_insertion_input)
                     .map(Json);
             }
@@ -152,20 +241,40 @@ impl ProverService {
             if let Ok(deserialized_deletion_input) =
                 serde_json::from_value::<DeletionProofInput>(input)
             {
-                return state.prove_deletion(deserialized_deletion_input).map(Json);
+                prover
+                    .batch_sizes
+                    .push(deserialized_deletion_input.identity_commitments.len());
+
+                return prover.prove_deletion(deserialized_deletion_input).map(Json);
             }
 
             // If both fail, return an error
             Err(StatusCode::BAD_REQUEST)
         }
 
+        async fn export_metrics(State(state): State<ServiceState>) -> Result<String, StatusCode> {
+            state
+                .metrics
+                .encode()
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+        }
+
         let inner = Arc::new(Mutex::new(Prover {
             is_available: true,
             tree_depth,
+            batch_sizes: vec![],
+            hmac_secret: None,
         }));
-        let state = inner.clone();
+        let metrics = ProverMetrics::new(batch_size, prover_type)?;
+        let state = ServiceState {
+            prover: inner.clone(),
+            metrics: metrics.clone(),
+        };
 
-        let app = Router::new().route("/prove", post(prove)).with_state(state);
+        let app = Router::new()
+            .route("/prove", post(prove))
+            .route("/metrics", get(export_metrics))
+            .with_state(state);
 
         // We use a random port here so that we can run multiple tests in many
         // threads/tasks
@@ -188,6 +297,7 @@ impl ProverService {
         let service = Self {
             server,
             inner,
+            metrics,
             address,
             batch_size,
             prover_type,
@@ -205,6 +315,13 @@ impl ProverService {
         inner.is_available = availability;
     }
 
+    /// Makes the prover reject prove requests that aren't signed with
+    /// `secret`.
+    pub async fn set_hmac_secret(&self, secret: impl Into<String>) {
+        let mut inner = self.inner.lock().await;
+        inner.hmac_secret = Some(secret.into());
+    }
+
     /// Shuts down the server and frees up the socket that it was using.
     pub fn stop(self) {
         self.server.shutdown();
@@ -214,6 +331,16 @@ impl ProverService {
         self.batch_size
     }
 
+    /// The sizes of the batches this prover was asked to prove, in order.
+    pub async fn received_batch_sizes(&self) -> Vec<usize> {
+        self.inner.lock().await.batch_sizes.clone()
+    }
+
+    /// The registry holding the metrics of this prover.
+    pub fn registry(&self) -> &Registry {
+        &self.metrics.registry
+    }
+
     pub fn prover_type(&self) -> ProverType {
         self.prover_type
     }
@@ -241,6 +368,33 @@ impl ProverService {
 }
 
 impl Prover {
+    /// Whether the request carries a recent signature of `body`, any request
+    /// passes if the prover has no secret.
+    fn is_signed(&self, headers: &HeaderMap, body: &[u8]) -> bool {
+        let Some(secret) = &self.hmac_secret else {
+            return true;
+        };
+
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+        let (Some(timestamp), Some(request_signature)) = (
+            header(signature::TIMESTAMP_HEADER),
+            header(signature::SIGNATURE_HEADER),
+        ) else {
+            return false;
+        };
+        let Ok(timestamp) = timestamp.parse::<u64>() else {
+            return false;
+        };
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time is after the epoch")
+            .as_secs();
+
+        now.abs_diff(timestamp) <= MAX_SIGNATURE_AGE_S
+            && signature::verify(secret, timestamp, body, request_signature)
+    }
+
     fn prove_insertion(&self, input: InsertionProofInput) -> Result<ProveResponse, StatusCode> {
         if !self.is_available {
             return Err(StatusCode::SERVICE_UNAVAILABLE);
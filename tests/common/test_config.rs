@@ -3,24 +3,43 @@ use std::time::Duration;
 
 use anyhow::Context;
 use ethers::types::Address;
+use once_cell::sync::Lazy;
 use signup_sequencer::config::{
-    default, AppConfig, Config, DatabaseConfig, NetworkConfig, OffchainModeConfig,
-    OzDefenderConfig, ProvidersConfig, RelayerConfig, ServerConfig, ServiceConfig, TreeConfig,
+    default, AppConfig, CanaryConfig, Config, DatabaseConfig, ExportsConfig,
+    ExternalRootOracleConfig, NetworkConfig, OffchainModeConfig, OzDefenderConfig,
+    PrometheusOutputConfig, ProvableSlaConfig, ProvidersConfig, RelayerConfig,
+    RootNotificationsConfig, RootPublicationConfig, ServerConfig, ServiceConfig, TlsConfig,
+    TreeConfig,
 };
-use signup_sequencer::prover::ProverConfig;
-use signup_sequencer::utils::secret::SecretUrl;
+use signup_sequencer::prover::{ProverConfig, ProverType};
+use signup_sequencer::utils::secret::{SecretString, SecretUrl};
 use url::Url;
 
 use crate::ProverService;
 
 pub const DEFAULT_BATCH_INSERTION_TIMEOUT_SECONDS: u64 = 10;
 pub const DEFAULT_BATCH_DELETION_TIMEOUT_SECONDS: u64 = 10;
-pub const DEFAULT_TREE_DEPTH: usize = 20;
-pub const DEFAULT_TREE_DENSE_PREFIX_DEPTH: usize = 10;
+/// Depth of the tree in tests. Run `cargo test-fast` or set
+/// `TEST_TREE_DEPTH=16` for a faster run with the same functional coverage.
+pub static DEFAULT_TREE_DEPTH: Lazy<usize> = Lazy::new(|| {
+    let default = if cfg!(feature = "fast-tests") { 16 } else { 20 };
+    env_or("TEST_TREE_DEPTH", default)
+});
+pub static DEFAULT_TREE_DENSE_PREFIX_DEPTH: Lazy<usize> =
+    Lazy::new(|| env_or("TEST_TREE_DENSE_PREFIX_DEPTH", *DEFAULT_TREE_DEPTH / 2));
 pub const DEFAULT_TIME_BETWEEN_SCANS_SECONDS: u64 = 1;
 pub const DEFAULT_SHUTDOWN_TIMEOUT_SECONDS: u64 = 5;
 pub const DEFAULT_SHUTDOWN_DELAY_SECONDS: u64 = 1;
 
+fn env_or(name: &str, default: usize) -> usize {
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .unwrap_or_else(|_| panic!("{name} must be a number, got {value:?}")),
+        Err(_) => default,
+    }
+}
+
 pub struct TestConfigBuilder {
     tree_depth: usize,
     dense_tree_prefix_depth: usize,
@@ -37,13 +56,25 @@ pub struct TestConfigBuilder {
     identity_manager_address: Option<Address>,
     primary_network_provider: Option<SecretUrl>,
     offchain_mode: bool,
+    root_notifications: RootNotificationsConfig,
+    canary: Option<CanaryConfig>,
+    metrics_namespace: Option<String>,
+    root_publication: Option<RootPublicationConfig>,
+    provable_sla: Option<ProvableSlaConfig>,
+    require_batch_approval: bool,
+    tls: Option<TlsConfig>,
+    verify_worker_threads: usize,
+    verify_queue_size: usize,
+    min_relayer_balance_gwei: Option<u64>,
+    external_root_oracle: Option<ExternalRootOracleConfig>,
+    exports: Option<ExportsConfig>,
 }
 
 impl TestConfigBuilder {
     pub fn new() -> Self {
         Self {
-            tree_depth: DEFAULT_TREE_DEPTH,
-            dense_tree_prefix_depth: DEFAULT_TREE_DENSE_PREFIX_DEPTH,
+            tree_depth: *DEFAULT_TREE_DEPTH,
+            dense_tree_prefix_depth: *DEFAULT_TREE_DENSE_PREFIX_DEPTH,
             prover_urls: vec![],
             batch_insertion_timeout: Duration::from_secs(DEFAULT_BATCH_INSERTION_TIMEOUT_SECONDS),
             batch_deletion_timeout: Duration::from_secs(DEFAULT_BATCH_DELETION_TIMEOUT_SECONDS),
@@ -57,6 +88,18 @@ impl TestConfigBuilder {
             identity_manager_address: None,
             primary_network_provider: None,
             offchain_mode: false,
+            root_notifications: RootNotificationsConfig::default(),
+            canary: None,
+            metrics_namespace: None,
+            root_publication: None,
+            provable_sla: None,
+            require_batch_approval: false,
+            tls: None,
+            verify_worker_threads: default::verify_worker_threads(),
+            verify_queue_size: default::verify_queue_size(),
+            min_relayer_balance_gwei: None,
+            external_root_oracle: None,
+            exports: None,
         }
     }
 
@@ -125,6 +168,25 @@ impl TestConfigBuilder {
             // TODO: Make this configurable?
             timeout_s: 30,
             batch_size: prover.batch_size(),
+            max_batch_size: None,
+            hmac_secret: None,
+            prover_type: prover.prover_type(),
+        };
+
+        self.prover_urls.push(prover_config);
+
+        self
+    }
+
+    /// Registers `prover` with a secret to sign its prove requests with, the
+    /// mock has to be given the same secret to check them.
+    pub fn add_signed_prover(mut self, prover: &ProverService, secret: &str) -> Self {
+        let prover_config = ProverConfig {
+            url: prover.url().to_string(),
+            timeout_s: 30,
+            batch_size: prover.batch_size(),
+            max_batch_size: None,
+            hmac_secret: Some(SecretString::new(secret)),
             prover_type: prover.prover_type(),
         };
 
@@ -133,12 +195,101 @@ impl TestConfigBuilder {
         self
     }
 
+    /// Registers `prover` as a ranged insertion prover taking any batch from
+    /// `batch_size` to `max_batch_size` identities.
+    pub fn add_ranged_prover(
+        mut self,
+        prover: &ProverService,
+        batch_size: usize,
+        max_batch_size: usize,
+    ) -> Self {
+        let prover_config = ProverConfig {
+            url: prover.url().to_string(),
+            timeout_s: 30,
+            batch_size,
+            max_batch_size: Some(max_batch_size),
+            hmac_secret: None,
+            prover_type: ProverType::Insertion,
+        };
+
+        self.prover_urls.push(prover_config);
+
+        self
+    }
+
     pub fn offchain_mode(mut self, enabled: bool) -> Self {
         self.offchain_mode = enabled;
 
         self
     }
 
+    pub fn root_notifications(mut self, root_notifications: RootNotificationsConfig) -> Self {
+        self.root_notifications = root_notifications;
+
+        self
+    }
+
+    pub fn canary(mut self, canary: CanaryConfig) -> Self {
+        self.canary = Some(canary);
+
+        self
+    }
+
+    pub fn metrics_namespace(mut self, metrics_namespace: &str) -> Self {
+        self.metrics_namespace = Some(metrics_namespace.to_string());
+
+        self
+    }
+
+    pub fn external_root_oracle(mut self, external_root_oracle: ExternalRootOracleConfig) -> Self {
+        self.external_root_oracle = Some(external_root_oracle);
+
+        self
+    }
+
+    pub fn exports(mut self, exports: ExportsConfig) -> Self {
+        self.exports = Some(exports);
+
+        self
+    }
+
+    pub fn root_publication(mut self, root_publication: RootPublicationConfig) -> Self {
+        self.root_publication = Some(root_publication);
+
+        self
+    }
+
+    pub fn provable_sla(mut self, provable_sla: ProvableSlaConfig) -> Self {
+        self.provable_sla = Some(provable_sla);
+
+        self
+    }
+
+    pub fn require_batch_approval(mut self, require_batch_approval: bool) -> Self {
+        self.require_batch_approval = require_batch_approval;
+
+        self
+    }
+
+    pub fn tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = Some(tls);
+
+        self
+    }
+
+    pub fn verify_workers(mut self, threads: usize, queue_size: usize) -> Self {
+        self.verify_worker_threads = threads;
+        self.verify_queue_size = queue_size;
+
+        self
+    }
+
+    pub fn min_relayer_balance_gwei(mut self, min_relayer_balance_gwei: u64) -> Self {
+        self.min_relayer_balance_gwei = Some(min_relayer_balance_gwei);
+
+        self
+    }
+
     pub fn build(self) -> anyhow::Result<Config> {
         let db_url = self.db_url.context("Missing database url")?;
 
@@ -150,10 +301,23 @@ impl TestConfigBuilder {
                 batch_insertion_timeout: self.batch_insertion_timeout,
                 batch_deletion_timeout: self.batch_deletion_timeout,
                 min_batch_deletion_size: self.min_batch_deletion_size,
+                max_deletions_per_hour: None,
+                max_total_pending_deletions: None,
+                allow_reinsert_after_deletion_days: None,
+                external_root_oracle: self.external_root_oracle,
                 scanning_window_size: default::scanning_window_size(),
+                max_scanning_window_size: default::max_scanning_window_size(),
                 scanning_chain_head_offset: default::scanning_chain_head_offset(),
                 time_between_scans: Duration::from_secs(DEFAULT_TIME_BETWEEN_SCANS_SECONDS),
+                max_rpc_retries: default::max_rpc_retries(),
                 monitored_txs_capacity: default::monitored_txs_capacity(),
+                max_batches_per_tx: default::max_batches_per_tx(),
+                max_prover_in_flight: default::max_prover_in_flight(),
+                batch_claim_lease: default::batch_claim_lease(),
+                min_relayer_balance_gwei: self.min_relayer_balance_gwei,
+                relayer_balance_check_interval: Duration::from_secs(1),
+                paused_batching_fails_readiness: false,
+                require_batch_approval: self.require_batch_approval,
                 shutdown_timeout: self.shutdown_timeout,
                 shutdown_delay: self.shutdown_delay,
             },
@@ -164,6 +328,8 @@ impl TestConfigBuilder {
                 cache_file: self.cache_file.context("Missing cache file")?,
                 force_cache_purge: default::force_cache_purge(),
                 initial_leaf_value: default::initial_leaf_value(),
+                recover_to_root: None,
+                allow_leaf_gaps: false,
             },
             network: if self.offchain_mode {
                 None
@@ -203,15 +369,33 @@ impl TestConfigBuilder {
                 database,
                 migrate: default::migrate(),
                 max_connections: default::max_connections(),
+                slow_transaction_threshold: default::slow_transaction_threshold(),
             },
             server: ServerConfig {
                 address: SocketAddr::from(([127, 0, 0, 1], 0)),
                 serve_timeout: default::serve_timeout(),
+                read_timeout: None,
+                write_timeout: None,
+                admin_timeout: None,
+                trusted_proxies: Default::default(),
+                verify_worker_threads: self.verify_worker_threads,
+                verify_queue_size: self.verify_queue_size,
+                v1_sunset_date: None,
+                prometheus_output: PrometheusOutputConfig::default(),
+                tls: self.tls,
+            },
+            service: ServiceConfig {
+                canary: self.canary,
+                metrics_namespace: self.metrics_namespace,
+                ..ServiceConfig::default()
             },
-            service: ServiceConfig::default(),
             offchain_mode: OffchainModeConfig {
                 enabled: self.offchain_mode,
+                root_publication: self.root_publication,
+                provable_sla: self.provable_sla,
             },
+            root_notifications: self.root_notifications,
+            exports: self.exports,
         };
 
         Ok(config)
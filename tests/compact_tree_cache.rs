@@ -0,0 +1,132 @@
+mod common;
+
+use common::prelude::*;
+use signup_sequencer::database::methods::DbMethods as _;
+use signup_sequencer::identity_tree::compaction::compact_tree_cache;
+use signup_sequencer::identity_tree::ProcessedStatus;
+
+use crate::common::test_delete_identity;
+
+#[tokio::test]
+async fn compact_tree_cache_shrinks_the_cache() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let insertion_batch_size: usize = 3;
+    let deletion_batch_size: usize = 2;
+
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, deletion_prover_map, micro_oz) =
+        spawn_deps(
+            initial_root,
+            &[insertion_batch_size],
+            &[deletion_batch_size],
+            *DEFAULT_TREE_DEPTH as u8,
+            &docker,
+        )
+        .await?;
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let temp_dir = tempfile::tempdir()?;
+
+    let config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_prover(&insertion_prover_map[&insertion_batch_size])
+        .add_prover(&deletion_prover_map[&deletion_batch_size])
+        .offchain_mode(true)
+        .build()?;
+
+    let (app, app_handle, local_addr, shutdown, task_monitor) =
+        spawn_app_with_task_monitor(config.clone())
+            .await
+            .expect("Failed to spawn app.");
+
+    let commitments: Vec<Field> = generate_test_identities(insertion_batch_size * 2)
+        .iter()
+        .map(|i| Hash::from_str_radix(i, 16).unwrap())
+        .collect();
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    for i in 0..commitments.len() {
+        test_insert_identity(&uri, &client, &mut ref_tree, &commitments, i).await;
+    }
+    flush_identities(&app).await?;
+
+    // Not continuous, so that the deletion batch isn't postponed
+    for i in [1, 3] {
+        test_delete_identity(&uri, &client, &mut ref_tree, &commitments, i, false).await;
+    }
+    flush_identities(&app).await?;
+
+    // The running app holds the cache
+    let database = app.database.clone();
+    let err = compact_tree_cache(&database, &config.tree)
+        .await
+        .expect_err("Compacted a cache in use");
+    assert!(err.to_string().contains("is in use"), "{err:?}");
+
+    task_monitor.stop().await;
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+    drop(app);
+
+    let report = compact_tree_cache(&database, &config.tree).await?;
+    let size_before = report.size_before.expect("Missing cache file");
+    assert!(
+        report.size_after < size_before,
+        "Cache grew from {size_before} to {} bytes",
+        report.size_after
+    );
+    assert_eq!(report.leaf_count, commitments.len() - 2);
+    assert_eq!(
+        Some(report.root),
+        database
+            .get_latest_root_by_status(ProcessedStatus::Mined)
+            .await?
+    );
+    assert_eq!(report.root, ref_tree.root());
+
+    // The compacted cache is picked up on restart
+    let (_, app_handle, local_addr, shutdown) =
+        spawn_app(config).await.expect("Failed to spawn app.");
+    let uri = "http://".to_owned() + &local_addr.to_string();
+
+    for (i, commitment) in commitments.iter().enumerate() {
+        test_inclusion_proof(
+            &mock_chain,
+            &uri,
+            &client,
+            i,
+            &ref_tree,
+            commitment,
+            i == 1 || i == 3,
+            true,
+        )
+        .await;
+    }
+
+    // Shutdown the app properly for the final time
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+    for (_, prover) in deletion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,136 @@
+mod common;
+
+use common::prelude::*;
+use reqwest::header::{CACHE_CONTROL, ETAG, IF_NONE_MATCH};
+use signup_sequencer::server::data::{InclusionProofResponse, TreeInfoResponse};
+
+async fn get(client: &Client, url: &str, etag: Option<&str>) -> reqwest::Response {
+    let mut request = client.get(url);
+    if let Some(etag) = etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+
+    request.send().await.expect("Failed to execute request")
+}
+
+fn etag(response: &reqwest::Response) -> String {
+    response
+        .headers()
+        .get(ETAG)
+        .expect("Missing ETag")
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn conditional_get() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let batch_size: usize = 3;
+
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, _, micro_oz) = spawn_deps(
+        initial_root,
+        &[batch_size],
+        &[],
+        *DEFAULT_TREE_DEPTH as u8,
+        &docker,
+    )
+    .await?;
+
+    let prover_mock = &insertion_prover_map[&batch_size];
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let temp_dir = tempfile::tempdir()?;
+
+    let config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_prover(prover_mock)
+        .offchain_mode(true)
+        .build()?;
+
+    let (app, app_handle, local_addr, shutdown) = spawn_app(config.clone())
+        .await
+        .expect("Failed to spawn app.");
+
+    let test_identities = generate_test_identities(2);
+    let identities_ref: Vec<Field> = test_identities
+        .iter()
+        .map(|i| Hash::from_str_radix(i, 16).unwrap())
+        .collect();
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, 0).await;
+    flush_identities(&app).await?;
+
+    let proof_url = format!(
+        "{uri}/v2/identities/{:#x}/inclusion-proof",
+        identities_ref[0]
+    );
+    let info_url = format!("{uri}/v2/tree/info");
+
+    let response = get(&client, &proof_url, None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let proof_etag = etag(&response);
+    // Proofs against mined roots may be reused for a while
+    assert_eq!(response.headers()[CACHE_CONTROL], "public, max-age=60");
+    let proof = response.json::<InclusionProofResponse>().await?;
+    assert_eq!(proof.root, Some(ref_tree.root()));
+
+    let response = get(&client, &info_url, None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let info_etag = etag(&response);
+    let info = response.json::<TreeInfoResponse>().await?;
+    assert_eq!(info.root, ref_tree.root());
+    assert_eq!(info.next_leaf, 1);
+
+    // Nothing changed, so nothing is sent
+    let response = get(&client, &proof_url, Some(&proof_etag)).await;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(etag(&response), proof_etag);
+    assert_eq!(response.headers()[CACHE_CONTROL], "public, max-age=60");
+    assert!(response.bytes().await?.is_empty());
+
+    let response = get(&client, &info_url, Some(&info_etag)).await;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    // The proof of the first identity changes with the root
+    test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, 1).await;
+    flush_identities(&app).await?;
+
+    let response = get(&client, &proof_url, Some(&proof_etag)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_ne!(etag(&response), proof_etag);
+    let proof = response.json::<InclusionProofResponse>().await?;
+    assert_eq!(proof.root, Some(ref_tree.root()));
+
+    let response = get(&client, &info_url, Some(&info_etag)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_ne!(etag(&response), info_etag);
+    let info = response.json::<TreeInfoResponse>().await?;
+    assert_eq!(info.next_leaf, 2);
+
+    // Shutdown the app properly for the final time
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
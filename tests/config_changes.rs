@@ -0,0 +1,195 @@
+mod common;
+
+use common::prelude::*;
+use signup_sequencer::server::data::{ConfigChangeInfo, ConfigChangesResponse};
+
+use crate::common::{test_add_batch_size, test_remove_batch_size};
+
+async fn config_changes(client: &Client, uri: &str, query: &str) -> Vec<ConfigChangeInfo> {
+    let response: ConfigChangesResponse = client
+        .get(format!("{uri}/v2/admin/config-changes{query}"))
+        .send()
+        .await
+        .expect("Failed to list config changes")
+        .json()
+        .await
+        .expect("Failed to parse config changes");
+
+    response.changes
+}
+
+#[tokio::test]
+async fn config_changes_are_recorded() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let batch_size: usize = 3;
+    let added_batch_size: usize = 2;
+
+    let ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, _, micro_oz) = spawn_deps(
+        initial_root,
+        &[batch_size, added_batch_size],
+        &[],
+        *DEFAULT_TREE_DEPTH as u8,
+        &docker,
+    )
+    .await?;
+
+    let prover_mock = &insertion_prover_map[&batch_size];
+    let added_prover_mock = &insertion_prover_map[&added_batch_size];
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let temp_dir = tempfile::tempdir()?;
+
+    let config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_prover(prover_mock)
+        .build()?;
+
+    let (_, app_handle, local_addr, shutdown) =
+        spawn_app(config).await.expect("Failed to spawn app.");
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    // Provers from the config aren't changes made at runtime
+    assert!(config_changes(&client, &uri, "").await.is_empty());
+
+    test_add_batch_size(
+        &uri,
+        added_prover_mock.url(),
+        added_batch_size as u64,
+        ProverType::Insertion,
+        &client,
+    )
+    .await?;
+
+    let response = client
+        .post(format!("{uri}/admin/liftDeletionLimit"))
+        .header("X-Request-Id", "lift-1")
+        .json(&json!({ "reason": "planned cleanup", "duration": "1h" }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    test_remove_batch_size(
+        &uri,
+        added_batch_size as u64,
+        &client,
+        ProverType::Insertion,
+        false,
+    )
+    .await?;
+
+    // Failed changes aren't recorded
+    test_remove_batch_size(
+        &uri,
+        batch_size as u64,
+        &client,
+        ProverType::Insertion,
+        true,
+    )
+    .await?;
+
+    let subscription_url = "http://127.0.0.1:1/roots";
+    for (aging_threshold_percent, request_id) in [(5, "subscribe-1"), (10, "subscribe-2")] {
+        let response = client
+            .post(format!("{uri}/v2/admin/subscriptions"))
+            .header("X-Request-Id", request_id)
+            .json(&json!({
+                "url": subscription_url,
+                "agingThresholdPercent": aging_threshold_percent,
+            }))
+            .send()
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let response = client
+        .delete(format!("{uri}/v2/admin/subscriptions"))
+        .json(&json!({ "url": subscription_url }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Removing it again fails and isn't recorded either
+    let response = client
+        .delete(format!("{uri}/v2/admin/subscriptions"))
+        .json(&json!({ "url": subscription_url }))
+        .send()
+        .await?;
+    assert!(!response.status().is_success());
+
+    let changes = config_changes(&client, &uri, "").await;
+    let settings: Vec<_> = changes
+        .iter()
+        .map(|change| change.setting.as_str())
+        .collect();
+    assert_eq!(
+        settings,
+        [
+            "provers.insertion.2",
+            "deletion_limit.lifted_until",
+            "provers.insertion.2",
+            "root_subscriptions",
+            "root_subscriptions",
+            "root_subscriptions"
+        ]
+    );
+    assert!(changes.windows(2).all(|pair| pair[0].id < pair[1].id));
+    assert!(changes.iter().all(|change| change.principal == "127.0.0.1"));
+
+    let [added, lifted, removed, subscribed, resubscribed, unsubscribed] = &changes[..] else {
+        unreachable!()
+    };
+
+    assert_eq!(added.old_value, None);
+    let added_prover = added.new_value.as_ref().expect("Missing added prover");
+    assert_eq!(added_prover["batch_size"], added_batch_size);
+    assert_eq!(added_prover["prover_type"], "insertion");
+
+    assert_eq!(lifted.request_id.as_deref(), Some("lift-1"));
+    assert_eq!(lifted.old_value, None);
+    let lifted_value = lifted.new_value.as_ref().expect("Missing lifted limit");
+    assert_eq!(lifted_value["reason"], "planned cleanup");
+
+    let removed_prover = removed.old_value.as_ref().expect("Missing removed prover");
+    assert_eq!(removed_prover["batch_size"], added_batch_size);
+    assert_eq!(removed.new_value, None);
+
+    let subscription = json!({ "url": subscription_url, "agingThresholdPercent": 5 });
+    let resubscription = json!({ "url": subscription_url, "agingThresholdPercent": 10 });
+    assert_eq!(subscribed.request_id.as_deref(), Some("subscribe-1"));
+    assert_eq!(subscribed.old_value, None);
+    assert_eq!(subscribed.new_value.as_ref(), Some(&subscription));
+    assert_eq!(resubscribed.request_id.as_deref(), Some("subscribe-2"));
+    assert_eq!(resubscribed.old_value.as_ref(), Some(&subscription));
+    assert_eq!(resubscribed.new_value.as_ref(), Some(&resubscription));
+    assert_eq!(unsubscribed.old_value.as_ref(), Some(&resubscription));
+    assert_eq!(unsubscribed.new_value, None);
+
+    // Pages continue after the last change seen
+    let page = config_changes(&client, &uri, &format!("?after_id={}&limit=1", added.id)).await;
+    assert_eq!(page, [lifted.clone()]);
+
+    // Shutdown the app properly for the final time
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,222 @@
+mod common;
+
+use common::prelude::*;
+use signup_sequencer::database::fault_injection::{self, Fault, Trigger, ANY_METHOD};
+use signup_sequencer::database::methods::DbMethods as _;
+use signup_sequencer::retry_tx;
+use signup_sequencer::server::error::ErrorResponse;
+
+use crate::common::test_delete_identity;
+
+async fn get(client: &Client, url: &str) -> reqwest::Response {
+    client
+        .get(url)
+        .send()
+        .await
+        .expect("Failed to execute request")
+}
+
+async fn error_id(response: reqwest::Response) -> String {
+    response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Expected a structured error")
+        .error_id
+}
+
+#[tokio::test]
+async fn db_fault_injection() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let batch_size: usize = 3;
+
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, deletion_prover_map, micro_oz) =
+        spawn_deps(
+            initial_root,
+            &[batch_size],
+            &[batch_size],
+            *DEFAULT_TREE_DEPTH as u8,
+            &docker,
+        )
+        .await?;
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let temp_dir = tempfile::tempdir()?;
+
+    let config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_prover(&insertion_prover_map[&batch_size])
+        .add_prover(&deletion_prover_map[&batch_size])
+        .offchain_mode(true)
+        .build()?;
+
+    let (app, app_handle, local_addr, shutdown) = spawn_app(config.clone())
+        .await
+        .expect("Failed to spawn app.");
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    let commitment = Hash::from(42);
+    let proof_url = format!("{uri}/v2/identities/{commitment:#x}/inclusion-proof");
+    let ready_url = format!("{uri}/ready");
+
+    fault_injection::reset();
+
+    info!("API errors are structured");
+    fault_injection::inject(
+        "get_unprocessed_commitment",
+        Fault::Unavailable,
+        Trigger::Always,
+    );
+    let response = get(&client, &proof_url).await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(error_id(response).await, "database_unavailable");
+
+    fault_injection::reset();
+    fault_injection::inject(
+        "get_unprocessed_commitment",
+        Fault::SerializationFailure,
+        Trigger::Nth(1),
+    );
+    let response = get(&client, &proof_url).await;
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(error_id(response).await, "database_error");
+
+    // Only the first call failed
+    let response = get(&client, &proof_url).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    info!("Transactions are retried");
+    fault_injection::reset();
+    fault_injection::inject(
+        "get_tree_epoch",
+        Fault::SerializationFailure,
+        Trigger::Nth(1),
+    );
+    let epoch: Result<u64, signup_sequencer::database::Error> =
+        retry_tx!(app.database, tx, { tx.get_tree_epoch().await }).await;
+    assert!(epoch.is_ok());
+    assert_eq!(fault_injection::calls("get_tree_epoch"), 2);
+
+    // Until the retry limit is reached
+    fault_injection::reset();
+    fault_injection::inject(
+        "get_tree_epoch",
+        Fault::SerializationFailure,
+        Trigger::Always,
+    );
+    let epoch: Result<u64, signup_sequencer::database::Error> =
+        retry_tx!(app.database, tx, { tx.get_tree_epoch().await }).await;
+    assert!(epoch.is_err());
+    assert_eq!(
+        fault_injection::calls("get_tree_epoch"),
+        signup_sequencer::utils::TX_RETRY_LIMIT as usize + 1
+    );
+
+    // Errors other than serialization failures are returned right away
+    fault_injection::reset();
+    fault_injection::inject("get_tree_epoch", Fault::Unavailable, Trigger::Always);
+    let epoch: Result<u64, signup_sequencer::database::Error> =
+        retry_tx!(app.database, tx, { tx.get_tree_epoch().await }).await;
+    assert!(epoch.is_err());
+    assert_eq!(fault_injection::calls("get_tree_epoch"), 1);
+
+    info!("Readiness follows the database");
+    fault_injection::reset();
+    assert_eq!(get(&client, &ready_url).await.status(), StatusCode::OK);
+
+    fault_injection::inject(ANY_METHOD, Fault::Unavailable, Trigger::Always);
+    assert_eq!(
+        get(&client, &ready_url).await.status(),
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+
+    // A database too slow to answer is as good as a database which is down
+    fault_injection::reset();
+    fault_injection::inject(
+        "ping",
+        Fault::Latency(Duration::from_secs(10)),
+        Trigger::Always,
+    );
+    assert_eq!(
+        get(&client, &ready_url).await.status(),
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+
+    fault_injection::reset();
+    assert_eq!(get(&client, &ready_url).await.status(), StatusCode::OK);
+
+    info!("Failing tasks back off");
+    fault_injection::inject("get_deletions_page", Fault::Unavailable, Trigger::Always);
+    tokio::time::sleep(Duration::from_secs(12)).await;
+
+    // The deletion task polls every 5 seconds and backs off for as long after
+    // failing
+    let calls = fault_injection::calls("get_deletions_page");
+    assert!(
+        (1..=4).contains(&calls),
+        "Deletion task made {calls} calls in 12 seconds"
+    );
+
+    fault_injection::reset();
+
+    info!("Failed deletions leave the tree alone");
+    let commitments: Vec<Field> = generate_test_identities(batch_size)
+        .iter()
+        .map(|i| Hash::from_str_radix(i, 16).unwrap())
+        .collect();
+    for i in 0..batch_size {
+        test_insert_identity(&uri, &client, &mut ref_tree, &commitments, i).await;
+    }
+    flush_identities(&app).await?;
+
+    // The deletion fails after its leaf was zeroed in the transaction, the
+    // task is restarted and deletes it again
+    fault_injection::inject("remove_deletions", Fault::Unavailable, Trigger::Nth(1));
+    test_delete_identity(&uri, &client, &mut ref_tree, &commitments, 0, false).await;
+    flush_identities(&app).await?;
+    assert!(fault_injection::calls("remove_deletions") >= 2);
+
+    assert_eq!(app.tree_state()?.latest_tree().get_root(), ref_tree.root());
+    assert!(app.database.get_deletions().await?.is_empty());
+    assert!(app
+        .database
+        .get_incomplete_deletion_intents()
+        .await?
+        .is_empty());
+
+    let zeroed_rows: (i64,) =
+        sqlx::query_as("SELECT COUNT(*) FROM identities WHERE commitment = $1")
+            .bind(Hash::ZERO)
+            .fetch_one(&**app.database)
+            .await?;
+    assert_eq!(zeroed_rows.0, 1);
+
+    fault_injection::reset();
+
+    // Shutdown the app properly for the final time
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+    for (_, prover) in deletion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
@@ -6,8 +6,6 @@ use common::prelude::*;
 
 use crate::common::test_delete_identity;
 
-const IDLE_TIME: u64 = 7;
-
 #[tokio::test]
 async fn delete_identities_onchain() -> anyhow::Result<()> {
     delete_identities(false).await
@@ -26,7 +24,7 @@ async fn delete_identities(offchain_mode_enabled: bool) -> anyhow::Result<()> {
     let insertion_batch_size: usize = 8;
     let deletion_batch_size: usize = 3;
 
-    let mut ref_tree = PoseidonTree::new(DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
     let initial_root: U256 = ref_tree.root().into();
 
     let docker = Cli::default();
@@ -35,7 +33,7 @@ async fn delete_identities(offchain_mode_enabled: bool) -> anyhow::Result<()> {
             initial_root,
             &[insertion_batch_size],
             &[deletion_batch_size],
-            DEFAULT_TREE_DEPTH as u8,
+            *DEFAULT_TREE_DEPTH as u8,
             &docker,
         )
         .await?;
@@ -64,9 +62,10 @@ async fn delete_identities(offchain_mode_enabled: bool) -> anyhow::Result<()> {
         .offchain_mode(offchain_mode_enabled)
         .build()?;
 
-    let (_, app_handle, local_addr, shutdown) = spawn_app(config.clone())
-        .await
-        .expect("Failed to spawn app.");
+    let (app, app_handle, local_addr, shutdown, task_monitor) =
+        spawn_app_with_task_monitor(config.clone())
+            .await
+            .expect("Failed to spawn app.");
 
     let test_identities = generate_test_identities(insertion_batch_size * 3);
     let identities_ref: Vec<Field> = test_identities
@@ -82,7 +81,7 @@ async fn delete_identities(offchain_mode_enabled: bool) -> anyhow::Result<()> {
         test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, i).await;
     }
 
-    tokio::time::sleep(Duration::from_secs(IDLE_TIME)).await;
+    flush_identities(&app).await?;
 
     // Check that we can also get these inclusion proofs back.
     for i in 0..insertion_batch_size {
@@ -105,7 +104,7 @@ async fn delete_identities(offchain_mode_enabled: bool) -> anyhow::Result<()> {
         test_delete_identity(&uri, &client, &mut ref_tree, &identities_ref, i, false).await;
     }
 
-    tokio::time::sleep(Duration::from_secs(IDLE_TIME * 8)).await;
+    flush_identities(&app).await?;
 
     // Ensure that identities have been deleted
     for i in 0..deletion_batch_size {
@@ -132,6 +131,7 @@ async fn delete_identities(offchain_mode_enabled: bool) -> anyhow::Result<()> {
     // Shutdown the app and reset the mock shutdown, allowing us to test the
     // behaviour with saved data.
     info!("Stopping the app for testing purposes");
+    task_monitor.stop().await;
     shutdown.shutdown();
     app_handle.await.unwrap();
 
@@ -6,8 +6,6 @@ use common::prelude::*;
 
 use crate::common::test_delete_identity;
 
-const IDLE_TIME: u64 = 7;
-
 #[tokio::test]
 async fn delete_padded_identity_onchain() -> anyhow::Result<()> {
     delete_padded_identity(false).await
@@ -26,7 +24,7 @@ async fn delete_padded_identity(offchain_mode_enabled: bool) -> anyhow::Result<(
     let insertion_batch_size: usize = 8;
     let deletion_batch_size: usize = 3;
 
-    let mut ref_tree = PoseidonTree::new(DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
     let initial_root: U256 = ref_tree.root().into();
 
     let docker = Cli::default();
@@ -35,7 +33,7 @@ async fn delete_padded_identity(offchain_mode_enabled: bool) -> anyhow::Result<(
             initial_root,
             &[insertion_batch_size],
             &[deletion_batch_size],
-            DEFAULT_TREE_DEPTH as u8,
+            *DEFAULT_TREE_DEPTH as u8,
             &docker,
         )
         .await?;
@@ -64,7 +62,7 @@ async fn delete_padded_identity(offchain_mode_enabled: bool) -> anyhow::Result<(
         .offchain_mode(offchain_mode_enabled)
         .build()?;
 
-    let (_, app_handle, local_addr, shutdown) =
+    let (app, app_handle, local_addr, shutdown) =
         spawn_app(config).await.expect("Failed to spawn app.");
 
     let test_identities = generate_test_identities(insertion_batch_size * 3);
@@ -81,7 +79,7 @@ async fn delete_padded_identity(offchain_mode_enabled: bool) -> anyhow::Result<(
         test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, i).await;
     }
 
-    tokio::time::sleep(Duration::from_secs(IDLE_TIME)).await;
+    flush_identities(&app).await?;
 
     // Check that we can also get these inclusion proofs back.
     for i in 0..insertion_batch_size {
@@ -103,10 +101,7 @@ async fn delete_padded_identity(offchain_mode_enabled: bool) -> anyhow::Result<(
     test_delete_identity(&uri, &client, &mut ref_tree, &identities_ref, 0, false).await;
     test_delete_identity(&uri, &client, &mut ref_tree, &identities_ref, 1, false).await;
 
-    tokio::time::sleep(Duration::from_secs(
-        DEFAULT_BATCH_DELETION_TIMEOUT_SECONDS * 3,
-    ))
-    .await;
+    flush_identities(&app).await?;
 
     // make sure that identity 3 wasn't deleted
     test_inclusion_proof(
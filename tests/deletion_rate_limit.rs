@@ -0,0 +1,114 @@
+mod common;
+
+use common::prelude::*;
+use signup_sequencer::database::methods::DbMethods as _;
+use signup_sequencer::server::data::DeletionRequest;
+use signup_sequencer::server::error::ErrorResponse;
+
+use crate::common::api_insert_identity;
+
+async fn delete(client: &Client, uri: &str, commitment: Hash) -> reqwest::Response {
+    client
+        .post(format!("{uri}/deleteIdentity"))
+        .json(&DeletionRequest {
+            identity_commitment: commitment,
+        })
+        .send()
+        .await
+        .expect("Failed to execute request")
+}
+
+#[tokio::test]
+async fn deletion_rate_limit() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let insertion_batch_size: usize = 3;
+    let deletion_batch_size: usize = 3;
+
+    let ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, deletion_prover_map, micro_oz) =
+        spawn_deps(
+            initial_root,
+            &[insertion_batch_size],
+            &[deletion_batch_size],
+            *DEFAULT_TREE_DEPTH as u8,
+            &docker,
+        )
+        .await?;
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let temp_dir = tempfile::tempdir()?;
+
+    // Deletions stay queued, so that they count against the limit
+    let mut config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_prover(&insertion_prover_map[&insertion_batch_size])
+        .add_prover(&deletion_prover_map[&deletion_batch_size])
+        .min_batch_deletion_size(deletion_batch_size)
+        .batch_deletion_timeout(Duration::from_secs(3600))
+        .offchain_mode(true)
+        .build()?;
+    config.app.max_total_pending_deletions = Some(1);
+
+    let (app, app_handle, local_addr, shutdown) =
+        spawn_app(config).await.expect("Failed to spawn app.");
+
+    let commitments: Vec<Field> = generate_test_identities(3)
+        .iter()
+        .map(|i| Hash::from_str_radix(i, 16).unwrap())
+        .collect();
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    for commitment in &commitments {
+        api_insert_identity(&uri, &client, commitment).await;
+    }
+    flush_identities(&app).await?;
+
+    let response = delete(&client, &uri, commitments[0]).await;
+    assert!(response.status().is_success());
+
+    // The second deletion is over the limit of pending deletions
+    let response = delete(&client, &uri, commitments[1]).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let error: ErrorResponse = response.json().await?;
+    assert_eq!(error.error_id, "deletion_rate_exceeded");
+    assert_eq!(app.database.count_deletions().await?, 1);
+
+    // Until the limits are lifted
+    let response = client
+        .post(format!("{uri}/admin/liftDeletionLimit"))
+        .json(&json!({ "reason": "planned cleanup", "duration": "1h" }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = delete(&client, &uri, commitments[1]).await;
+    assert!(response.status().is_success());
+    assert_eq!(app.database.count_deletions().await?, 2);
+
+    // Shutdown the app properly for the final time
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+    for (_, prover) in deletion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
@@ -1,11 +1,11 @@
 mod common;
 
 use common::prelude::*;
+use signup_sequencer::server::data::AddBatchSizeRequest;
+use signup_sequencer::server::error::ErrorResponse;
 
 use crate::common::{test_add_batch_size, test_remove_batch_size};
 
-const IDLE_TIME: u64 = 10;
-
 #[tokio::test]
 async fn dynamic_batch_sizes_onchain() -> anyhow::Result<()> {
     dynamic_batch_sizes(false).await
@@ -24,7 +24,7 @@ async fn dynamic_batch_sizes(offchain_mode_enabled: bool) -> anyhow::Result<()>
     let first_batch_size: usize = 3;
     let second_batch_size: usize = 2;
 
-    let mut ref_tree = PoseidonTree::new(DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
     let initial_root: U256 = ref_tree.root().into();
 
     let docker = Cli::default();
@@ -32,7 +32,7 @@ async fn dynamic_batch_sizes(offchain_mode_enabled: bool) -> anyhow::Result<()>
         initial_root,
         &[first_batch_size, second_batch_size],
         &[],
-        DEFAULT_TREE_DEPTH as u8,
+        *DEFAULT_TREE_DEPTH as u8,
         &docker,
     )
     .await?;
@@ -61,7 +61,7 @@ async fn dynamic_batch_sizes(offchain_mode_enabled: bool) -> anyhow::Result<()>
         .offchain_mode(offchain_mode_enabled)
         .build()?;
 
-    let (_, app_handle, local_addr, shutdown) =
+    let (app, app_handle, local_addr, shutdown) =
         spawn_app(config).await.expect("Failed to spawn app.");
 
     let test_identities = generate_test_identities(first_batch_size * 5);
@@ -79,8 +79,7 @@ async fn dynamic_batch_sizes(offchain_mode_enabled: bool) -> anyhow::Result<()>
     test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, 1).await;
     test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, 2).await;
 
-    // wait at leat 5 seconds before checking proof
-    tokio::time::sleep(Duration::from_secs(IDLE_TIME)).await;
+    flush_identities(&app).await?;
 
     // Check that we can get their inclusion proofs back.
     test_inclusion_proof(
@@ -120,6 +119,26 @@ async fn dynamic_batch_sizes(offchain_mode_enabled: bool) -> anyhow::Result<()>
     )
     .await;
 
+    // Nothing listens on the port once the listener is dropped
+    let unreachable_prover_url = {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        format!("http://{}", listener.local_addr()?)
+    };
+    let response = client
+        .post(format!("{uri}/addBatchSize?verify=true"))
+        .json(&AddBatchSizeRequest {
+            url: unreachable_prover_url,
+            batch_size: second_batch_size,
+            timeout_seconds: 3,
+            prover_type: second_prover.prover_type(),
+            probe: false,
+        })
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let error: ErrorResponse = response.json().await?;
+    assert_eq!(error.error_id, "prover_unreachable");
+
     test_add_batch_size(
         &uri,
         second_prover.url(),
@@ -171,11 +190,7 @@ async fn dynamic_batch_sizes(offchain_mode_enabled: bool) -> anyhow::Result<()>
     test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, 3).await;
     test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, 4).await;
 
-    // Force a timeout by resetting the tokio runtime's timer.
-    tokio::time::pause();
-    tokio::time::resume();
-
-    tokio::time::sleep(Duration::from_secs(IDLE_TIME)).await;
+    flush_identities(&app).await?;
 
     // Check that we can also get these inclusion proofs back.
     test_inclusion_proof(
@@ -215,10 +230,7 @@ async fn dynamic_batch_sizes(offchain_mode_enabled: bool) -> anyhow::Result<()>
 
     // We should be able to insert less than a full batch successfully.
     test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, 5).await;
-    tokio::time::pause();
-    tokio::time::resume();
-
-    tokio::time::sleep(Duration::from_secs(IDLE_TIME)).await;
+    flush_identities(&app).await?;
 
     test_inclusion_proof(
         &mock_chain,
@@ -245,10 +257,7 @@ async fn dynamic_batch_sizes(offchain_mode_enabled: bool) -> anyhow::Result<()>
 
     // So we should still be able to run a batch.
     test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, 6).await;
-    tokio::time::pause();
-    tokio::time::resume();
-
-    tokio::time::sleep(Duration::from_secs(IDLE_TIME)).await;
+    flush_identities(&app).await?;
 
     test_inclusion_proof(
         &mock_chain,
@@ -0,0 +1,132 @@
+mod common;
+
+use std::time::Instant;
+
+use common::prelude::*;
+use reqwest::header::CONTENT_TYPE;
+use signup_sequencer::config::ExportsConfig;
+use signup_sequencer::database::types::TreeUpdateEntry;
+use signup_sequencer::server::data::ExportInfo;
+
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(30);
+
+async fn get_export(client: &Client, uri: &str, id: i64) -> anyhow::Result<ExportInfo> {
+    let response = client
+        .get(format!("{uri}/v2/admin/exports/{id}"))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    Ok(response.json().await?)
+}
+
+#[tokio::test]
+async fn export_all_identities() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let batch_size: usize = 3;
+
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, _, micro_oz) = spawn_deps(
+        initial_root,
+        &[batch_size],
+        &[],
+        *DEFAULT_TREE_DEPTH as u8,
+        &docker,
+    )
+    .await?;
+
+    let prover_mock = &insertion_prover_map[&batch_size];
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let temp_dir = tempfile::tempdir()?;
+
+    let config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_prover(prover_mock)
+        .offchain_mode(true)
+        .exports(ExportsConfig {
+            directory: temp_dir.path().join("exports"),
+            chunk_size: 4,
+            claim_lease: Duration::from_secs(60),
+        })
+        .build()?;
+
+    let (app, app_handle, local_addr, shutdown) =
+        spawn_app(config).await.expect("Failed to spawn app.");
+
+    let test_identities = generate_test_identities(6);
+    let identities_ref: Vec<Field> = test_identities
+        .iter()
+        .map(|i| Hash::from_str_radix(i, 16).unwrap())
+        .collect();
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    for leaf_index in 0..identities_ref.len() {
+        test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, leaf_index).await;
+    }
+    flush_identities(&app).await?;
+
+    let response = client
+        .post(format!("{uri}/v2/admin/exports"))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let export: ExportInfo = response.json().await?;
+    assert!(export.completed_at.is_none());
+
+    let started = Instant::now();
+    let export = loop {
+        let export = get_export(&client, &uri, export.id).await?;
+        if export.completed_at.is_some() {
+            break export;
+        }
+
+        assert!(started.elapsed() < EXPORT_TIMEOUT, "Export didn't complete");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    };
+    assert_eq!(export.identities, 6);
+    assert_eq!(export.chunks.len(), 2);
+
+    let mut exported = vec![];
+    for chunk in &export.chunks {
+        let response = client.get(format!("{uri}{chunk}")).send().await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/x-ndjson");
+
+        for line in response.text().await?.lines() {
+            let update: TreeUpdateEntry = serde_json::from_str(line)?;
+            exported.push(update.element);
+        }
+    }
+    assert_eq!(exported, identities_ref);
+
+    let response = client
+        .get(format!("{uri}/v2/admin/exports/{}/chunks/2", export.id))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // Shutdown the app properly for the final time
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
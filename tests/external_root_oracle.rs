@@ -0,0 +1,211 @@
+mod common;
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode as HttpStatusCode;
+use axum::routing::get;
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use common::prelude::*;
+use signup_sequencer::config::ExternalRootOracleConfig;
+use signup_sequencer::RootOracleResponse;
+use tokio::net::TcpListener;
+
+/// Roots known to the mock oracle. A `None` timestamp makes the oracle fail.
+type KnownRoots = Arc<HashMap<Hash, Option<DateTime<Utc>>>>;
+
+async fn root(
+    State(known): State<KnownRoots>,
+    Path(root): Path<String>,
+) -> Result<Json<RootOracleResponse>, HttpStatusCode> {
+    let root = Hash::from_str_radix(root.trim_start_matches("0x"), 16)
+        .map_err(|_| HttpStatusCode::BAD_REQUEST)?;
+
+    match known.get(&root) {
+        Some(Some(timestamp)) => Ok(Json(RootOracleResponse {
+            known: true,
+            timestamp: Some(*timestamp),
+        })),
+        Some(None) => Err(HttpStatusCode::INTERNAL_SERVER_ERROR),
+        None => Ok(Json(RootOracleResponse {
+            known: false,
+            timestamp: None,
+        })),
+    }
+}
+
+async fn spawn_oracle(known: KnownRoots) -> anyhow::Result<SocketAddr> {
+    let router = Router::new()
+        .route("/root/:root", get(root))
+        .with_state(known);
+
+    let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))).await?;
+    let addr = listener.local_addr()?;
+
+    spawn(async move {
+        axum::serve(listener, router).await.unwrap();
+    });
+
+    Ok(addr)
+}
+
+/// A proof for a tree the sequencer has never seen.
+fn bridged_proof(secret: &[u8]) -> (Field, Field, protocol::Proof) {
+    let mut secret = secret.to_vec();
+    let identity = Identity::from_secret(&mut secret, None);
+
+    let mut tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    tree.set(0, identity.commitment());
+
+    let external_nullifier_hash = hash_to_field(b"external_hash");
+    let nullifier_hash = generate_nullifier_hash(&identity, external_nullifier_hash);
+    let proof = generate_proof(
+        &identity,
+        &tree.proof(0).unwrap(),
+        external_nullifier_hash,
+        hash_to_field(b"signal_hash"),
+    )
+    .unwrap();
+
+    (tree.root(), nullifier_hash, proof)
+}
+
+#[tokio::test]
+async fn external_root_oracle() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let batch_size = 3;
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, _, micro_oz) = spawn_deps(
+        initial_root,
+        &[batch_size],
+        &[],
+        *DEFAULT_TREE_DEPTH as u8,
+        &docker,
+    )
+    .await?;
+
+    let prover_mock = &insertion_prover_map[&batch_size];
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let (recent_root, recent_nullifier_hash, recent_proof) = bridged_proof(b"test_f0f0");
+    let (old_root, old_nullifier_hash, old_proof) = bridged_proof(b"test_f1f1");
+    let (failing_root, failing_nullifier_hash, failing_proof) = bridged_proof(b"test_f2f2");
+    let (unknown_root, unknown_nullifier_hash, unknown_proof) = bridged_proof(b"test_f3f3");
+
+    let known = KnownRoots::new(HashMap::from([
+        (recent_root, Some(Utc::now())),
+        (old_root, Some(Utc::now() - chrono::Duration::hours(2))),
+        (failing_root, None),
+    ]));
+    let oracle_addr = spawn_oracle(known).await?;
+
+    let temp_dir = tempfile::tempdir()?;
+
+    let config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_prover(prover_mock)
+        .offchain_mode(true)
+        .external_root_oracle(ExternalRootOracleConfig {
+            url: Url::parse(&format!("http://{oracle_addr}/"))?,
+            timeout: Duration::from_secs(2),
+            cache_ttl: Duration::from_secs(60),
+        })
+        .build()?;
+
+    let (_, app_handle, local_addr, shutdown) =
+        spawn_app(config).await.expect("Failed to spawn app.");
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    let signal_hash = hash_to_field(b"signal_hash");
+    let external_nullifier_hash = hash_to_field(b"external_hash");
+
+    let verify_v2 = |root: Field, nullifier_hash: Field, proof: protocol::Proof, query: &str| {
+        client
+            .post(format!("{uri}/v2/verifySemaphoreProof{query}"))
+            .json(&json!({
+                "root": root,
+                "signalHash": signal_hash,
+                "nullifierHash": nullifier_hash,
+                "externalNullifierHash": external_nullifier_hash,
+                "proof": proof,
+            }))
+            .send()
+    };
+
+    // A root confirmed by the oracle is accepted by v2
+    let response = verify_v2(recent_root, recent_nullifier_hash, recent_proof, "").await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // v1 only accepts roots of this instance
+    test_verify_proof(
+        &uri,
+        &client,
+        recent_root,
+        signal_hash,
+        recent_nullifier_hash,
+        external_nullifier_hash,
+        recent_proof,
+        Some("invalid root"),
+        VERIFY_PROOF_TIMEOUT,
+    )
+    .await?;
+
+    // The age of a bridged root is counted from the oracle's timestamp
+    let response = verify_v2(
+        old_root,
+        old_nullifier_hash,
+        old_proof,
+        "?maxRootAgeSeconds=3600",
+    )
+    .await?;
+    assert!(!response.status().is_success());
+    assert!(response.text().await?.contains("too old"));
+
+    let response = verify_v2(
+        old_root,
+        old_nullifier_hash,
+        old_proof,
+        "?maxRootAgeSeconds=10800",
+    )
+    .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Roots unknown to the oracle, or which it fails to answer for, are
+    // rejected
+    for (root, nullifier_hash, proof) in [
+        (unknown_root, unknown_nullifier_hash, unknown_proof),
+        (failing_root, failing_nullifier_hash, failing_proof),
+    ] {
+        let response = verify_v2(root, nullifier_hash, proof, "").await?;
+        assert!(!response.status().is_success());
+        assert!(response.text().await?.contains("invalid root"));
+    }
+
+    // Shutdown the app properly for the final time
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
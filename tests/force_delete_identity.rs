@@ -0,0 +1,107 @@
+mod common;
+
+use common::prelude::*;
+use signup_sequencer::database::methods::DbMethods as _;
+use signup_sequencer::server::data::DeletionRequest;
+
+use crate::common::api_insert_identity;
+
+#[tokio::test]
+async fn force_delete_identity() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let insertion_batch_size: usize = 3;
+    let deletion_batch_size: usize = 3;
+
+    let ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, deletion_prover_map, micro_oz) =
+        spawn_deps(
+            initial_root,
+            &[insertion_batch_size],
+            &[deletion_batch_size],
+            *DEFAULT_TREE_DEPTH as u8,
+            &docker,
+        )
+        .await?;
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let temp_dir = tempfile::tempdir()?;
+
+    let config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_prover(&insertion_prover_map[&insertion_batch_size])
+        .add_prover(&deletion_prover_map[&deletion_batch_size])
+        .offchain_mode(true)
+        .build()?;
+
+    let (app, app_handle, local_addr, shutdown, task_monitor) = spawn_app_with_task_monitor(config)
+        .await
+        .expect("Failed to spawn app.");
+
+    let commitments: Vec<Field> = generate_test_identities(2)
+        .iter()
+        .map(|i| Hash::from_str_radix(i, 16).unwrap())
+        .collect();
+    let (in_tree, queued) = (commitments[0], commitments[1]);
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    api_insert_identity(&uri, &client, &in_tree).await;
+    flush_identities(&app).await?;
+
+    // Without the tasks the second identity stays queued for insertion
+    task_monitor.stop().await;
+    api_insert_identity(&uri, &client, &queued).await;
+
+    // Forcing is refused outside of the admin routes
+    let response = client
+        .post(format!("{uri}/deleteIdentity?force=true"))
+        .json(&DeletionRequest {
+            identity_commitment: queued,
+        })
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(app.database.identity_exists(queued).await?);
+
+    let response = client
+        .delete(format!("{uri}/v2/admin/identities/{queued:#x}?force=true"))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    assert!(!app.database.identity_exists(queued).await?);
+
+    // Identities in the tree are deleted regularly, forced or not
+    let response = client
+        .delete(format!("{uri}/v2/admin/identities/{in_tree:#x}?force=true"))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(app.database.identity_exists(in_tree).await?);
+    assert_eq!(app.database.get_deletions().await?.len(), 1);
+
+    // Shutdown the app properly for the final time
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+    for (_, prover) in deletion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
@@ -20,7 +20,7 @@ async fn graceful_shutdown(offchain_mode_enabled: bool) -> anyhow::Result<()> {
     let insertion_batch_size: usize = 8;
     let deletion_batch_size: usize = 3;
 
-    let ref_tree = PoseidonTree::new(DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
     let initial_root: U256 = ref_tree.root().into();
 
     let docker = Cli::default();
@@ -29,7 +29,7 @@ async fn graceful_shutdown(offchain_mode_enabled: bool) -> anyhow::Result<()> {
             initial_root,
             &[insertion_batch_size],
             &[deletion_batch_size],
-            DEFAULT_TREE_DEPTH as u8,
+            *DEFAULT_TREE_DEPTH as u8,
             &docker,
         )
         .await?;
@@ -0,0 +1,133 @@
+mod common;
+
+use common::prelude::*;
+use reqwest::header::USER_AGENT;
+use signup_sequencer::server::data::{
+    DeletionRequest, IdentityOriginResponse, InsertCommitmentRequest,
+};
+
+async fn get_origin(
+    client: &Client,
+    uri: &str,
+    commitment: &Hash,
+) -> anyhow::Result<reqwest::Response> {
+    Ok(client
+        .get(format!("{uri}/v2/admin/identities/{commitment:#x}/origin"))
+        .send()
+        .await?)
+}
+
+#[tokio::test]
+async fn identity_origin() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let insertion_batch_size: usize = 3;
+    let deletion_batch_size: usize = 3;
+
+    let ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, deletion_prover_map, micro_oz) =
+        spawn_deps(
+            initial_root,
+            &[insertion_batch_size],
+            &[deletion_batch_size],
+            *DEFAULT_TREE_DEPTH as u8,
+            &docker,
+        )
+        .await?;
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let temp_dir = tempfile::tempdir()?;
+
+    let config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_prover(&insertion_prover_map[&insertion_batch_size])
+        .add_prover(&deletion_prover_map[&deletion_batch_size])
+        .offchain_mode(true)
+        .build()?;
+
+    let (app, app_handle, local_addr, shutdown) =
+        spawn_app(config).await.expect("Failed to spawn app.");
+
+    let commitments: Vec<Field> = generate_test_identities(insertion_batch_size)
+        .iter()
+        .map(|i| Hash::from_str_radix(i, 16).unwrap())
+        .collect();
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    for commitment in &commitments {
+        let response = client
+            .post(format!("{uri}/insertIdentity"))
+            .header(USER_AGENT, "inserter/1.0")
+            .json(&InsertCommitmentRequest {
+                identity_commitment: *commitment,
+            })
+            .send()
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    // The origin is kept once the identity is in the tree
+    flush_identities(&app).await?;
+
+    let origin: IdentityOriginResponse = get_origin(&client, &uri, &commitments[0])
+        .await?
+        .json()
+        .await?;
+    let insertion = origin.insertion.expect("Missing insertion origin");
+    assert_eq!(insertion.source_ip, Some([127, 0, 0, 1].into()));
+    assert_eq!(insertion.user_agent.as_deref(), Some("inserter/1.0"));
+    assert_eq!(origin.deletion, None);
+
+    let response = client
+        .post(format!("{uri}/deleteIdentity"))
+        .header(USER_AGENT, "deleter/1.0")
+        .json(&DeletionRequest {
+            identity_commitment: commitments[0],
+        })
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let origin: IdentityOriginResponse = get_origin(&client, &uri, &commitments[0])
+        .await?
+        .json()
+        .await?;
+    let deletion = origin.deletion.expect("Missing deletion origin");
+    assert_eq!(deletion.user_agent.as_deref(), Some("deleter/1.0"));
+    assert_eq!(
+        origin
+            .insertion
+            .and_then(|insertion| insertion.user_agent)
+            .as_deref(),
+        Some("inserter/1.0")
+    );
+
+    let response = get_origin(&client, &uri, &Hash::from(1)).await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // Shutdown the app properly for the final time
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+    for (_, prover) in deletion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
@@ -39,7 +39,7 @@ async fn immediate_deletion_of_continuous_set_of_identities(
     let insertion_batch_size: usize = 8;
     let deletion_batch_size: usize = 3;
 
-    let mut ref_tree = PoseidonTree::new(DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
     let initial_root: U256 = ref_tree.root().into();
 
     let docker = Cli::default();
@@ -48,7 +48,7 @@ async fn immediate_deletion_of_continuous_set_of_identities(
             initial_root,
             &[insertion_batch_size],
             &[deletion_batch_size],
-            DEFAULT_TREE_DEPTH as u8,
+            *DEFAULT_TREE_DEPTH as u8,
             &docker,
         )
         .await?;
@@ -77,7 +77,7 @@ async fn immediate_deletion_of_continuous_set_of_identities(
         .add_prover(mock_deletion_prover)
         .build()?;
 
-    let (_, app_handle, local_addr, shutdown) = spawn_app(config.clone())
+    let (app, app_handle, local_addr, shutdown) = spawn_app(config.clone())
         .await
         .expect("Failed to spawn app.");
 
@@ -95,7 +95,7 @@ async fn immediate_deletion_of_continuous_set_of_identities(
         test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, i).await;
     }
 
-    tokio::time::sleep(Duration::from_secs(IDLE_TIME)).await;
+    flush_identities(&app).await?;
 
     // Check that we can also get these inclusion proofs back.
     for i in 0..insertion_batch_size {
@@ -151,7 +151,7 @@ async fn immediate_deletion_of_continuous_set_of_identities(
     // gaps in deletions, or a new insertion must happen in between
     test_delete_identity(&uri, &client, &mut ref_tree, &identities_ref, 0, false).await;
 
-    tokio::time::sleep(Duration::from_secs(IDLE_TIME * 2)).await;
+    flush_identities(&app).await?;
 
     test_inclusion_proof_mined(
         &mock_chain,
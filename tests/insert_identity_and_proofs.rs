@@ -2,8 +2,6 @@ mod common;
 
 use common::prelude::*;
 
-const IDLE_TIME: u64 = 7;
-
 #[tokio::test]
 async fn insert_identity_and_proofs_onchain() -> anyhow::Result<()> {
     insert_identity_and_proofs(false).await
@@ -21,7 +19,7 @@ async fn insert_identity_and_proofs(offchain_mode_enabled: bool) -> anyhow::Resu
 
     let batch_size: usize = 3;
 
-    let mut ref_tree = PoseidonTree::new(DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
     let initial_root: U256 = ref_tree.root().into();
 
     let docker = Cli::default();
@@ -29,7 +27,7 @@ async fn insert_identity_and_proofs(offchain_mode_enabled: bool) -> anyhow::Resu
         initial_root,
         &[batch_size],
         &[],
-        DEFAULT_TREE_DEPTH as u8,
+        *DEFAULT_TREE_DEPTH as u8,
         &docker,
     )
     .await?;
@@ -57,9 +55,10 @@ async fn insert_identity_and_proofs(offchain_mode_enabled: bool) -> anyhow::Resu
         .offchain_mode(offchain_mode_enabled)
         .build()?;
 
-    let (_, app_handle, local_addr, shutdown) = spawn_app(config.clone())
-        .await
-        .expect("Failed to spawn app.");
+    let (app, app_handle, local_addr, shutdown, task_monitor) =
+        spawn_app_with_task_monitor(config.clone())
+            .await
+            .expect("Failed to spawn app.");
 
     let test_identities = generate_test_identities(batch_size * 3);
     let identities_ref: Vec<Field> = test_identities
@@ -101,7 +100,7 @@ async fn insert_identity_and_proofs(offchain_mode_enabled: bool) -> anyhow::Resu
     test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, 1).await;
     test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, 2).await;
 
-    tokio::time::sleep(Duration::from_secs(IDLE_TIME)).await;
+    flush_identities(&app).await?;
     // Check that we can get their inclusion proofs back.
     test_inclusion_proof(
         &mock_chain,
@@ -140,14 +139,11 @@ async fn insert_identity_and_proofs(offchain_mode_enabled: bool) -> anyhow::Resu
     )
     .await;
 
-    // Insert too few identities to trigger a batch, and then force the timeout to
-    // complete and submit a partial batch to the chain.
+    // Insert too few identities to trigger a batch, and then flush them to
+    // submit a partial batch to the chain.
     test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, 3).await;
     test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, 4).await;
-    tokio::time::pause();
-    tokio::time::resume();
-
-    tokio::time::sleep(Duration::from_secs(IDLE_TIME)).await;
+    flush_identities(&app).await?;
     // Check that we can also get these inclusion proofs back.
     test_inclusion_proof(
         &mock_chain,
@@ -177,13 +173,15 @@ async fn insert_identity_and_proofs(offchain_mode_enabled: bool) -> anyhow::Resu
     // Shutdown the app and reset the mock shutdown, allowing us to test the
     // behaviour with saved data.
     info!("Stopping the app for testing purposes");
+    task_monitor.stop().await;
     shutdown.shutdown();
     app_handle.await.unwrap();
 
     // Test loading the state from a file when the on-chain contract has the state.
-    let (_, app_handle, local_addr, shutdown) = spawn_app(config.clone())
-        .await
-        .expect("Failed to spawn app.");
+    let (_, app_handle, local_addr, shutdown, task_monitor) =
+        spawn_app_with_task_monitor(config.clone())
+            .await
+            .expect("Failed to spawn app.");
     let uri = "http://".to_owned() + &local_addr.to_string();
 
     // Check that we can still get inclusion proofs for identities we know to have
@@ -216,6 +214,7 @@ async fn insert_identity_and_proofs(offchain_mode_enabled: bool) -> anyhow::Resu
     // Shutdown the app and reset the mock shutdown, allowing us to test the
     // behaviour with the saved tree.
     info!("Stopping the app for testing purposes");
+    task_monitor.stop().await;
     shutdown.shutdown();
     app_handle.await.unwrap();
 
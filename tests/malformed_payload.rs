@@ -18,7 +18,7 @@ async fn malformed_payload(offchain_mode_enabled: bool) -> anyhow::Result<()> {
     init_tracing_subscriber();
     info!("Starting malformed payload test");
 
-    let ref_tree = PoseidonTree::new(DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
     let initial_root: U256 = ref_tree.root().into();
 
     let batch_size: usize = 3;
@@ -28,7 +28,7 @@ async fn malformed_payload(offchain_mode_enabled: bool) -> anyhow::Result<()> {
         initial_root,
         &[batch_size],
         &[],
-        DEFAULT_TREE_DEPTH as u8,
+        *DEFAULT_TREE_DEPTH as u8,
         &docker,
     )
     .await?;
@@ -0,0 +1,102 @@
+mod common;
+
+use common::prelude::*;
+
+async fn fetch_metrics(
+    client: &Client,
+    local_addr: std::net::SocketAddr,
+) -> anyhow::Result<String> {
+    Ok(client
+        .get(format!("http://{local_addr}/metrics"))
+        .send()
+        .await?
+        .text()
+        .await?)
+}
+
+fn metric_names(metrics: &str) -> Vec<&str> {
+    metrics
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split(['{', ' ']).next())
+        .collect()
+}
+
+#[tokio::test]
+async fn apps_in_one_process_report_their_own_metrics() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let batch_size: usize = 3;
+
+    let ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, _, micro_oz) = spawn_deps(
+        initial_root,
+        &[batch_size],
+        &[],
+        *DEFAULT_TREE_DEPTH as u8,
+        &docker,
+    )
+    .await?;
+    let other_db_container = postgres_docker_utils::setup(&docker).await?;
+
+    let prover_mock = &insertion_prover_map[&batch_size];
+
+    let temp_dir = tempfile::tempdir()?;
+
+    let mut apps = vec![];
+    for (namespace, db_container) in [
+        ("staging", &db_container),
+        ("production", &other_db_container),
+    ] {
+        let db_socket_addr = db_container.address();
+        let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+        let config = TestConfigBuilder::new()
+            .db_url(&db_url)
+            .oz_api_url(&micro_oz.endpoint())
+            .oz_address(micro_oz.address())
+            .identity_manager_address(mock_chain.identity_manager.address())
+            .primary_network_provider(mock_chain.anvil.endpoint())
+            .cache_file(temp_dir.path().join(namespace).to_str().unwrap())
+            .add_prover(prover_mock)
+            .offchain_mode(true)
+            .metrics_namespace(namespace)
+            .build()?;
+
+        let (_, app_handle, local_addr, shutdown) =
+            spawn_app(config).await.expect("Failed to spawn app.");
+
+        apps.push((namespace, app_handle, local_addr, shutdown));
+    }
+
+    let client = Client::new();
+
+    for (namespace, _, local_addr, _) in &apps {
+        let metrics = fetch_metrics(&client, *local_addr).await?;
+        let names = metric_names(&metrics);
+
+        assert!(names.contains(&format!("{namespace}_api_requests").as_str()));
+        assert!(
+            names
+                .iter()
+                .all(|name| name.starts_with(&format!("{namespace}_"))),
+            "Unexpected metrics in {namespace}: {names:?}"
+        );
+    }
+
+    // Shutdown the apps properly for the final time
+    for (_, app_handle, _, shutdown) in apps {
+        shutdown.shutdown();
+        app_handle.await.unwrap();
+    }
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
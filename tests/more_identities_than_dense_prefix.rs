@@ -31,8 +31,6 @@ async fn more_identities_than_dense_prefix(offchain_mode_enabled: bool) -> anyho
     // A total of 4 batches (4 * 4 = 16 identities)
     let num_identities_total = num_identities_in_dense_prefix + num_identities_above_dense_prefix;
 
-    let num_batches_total = num_identities_total / batch_size;
-
     let mut ref_tree = PoseidonTree::new(tree_depth + 1, ruint::Uint::ZERO);
     let initial_root: U256 = ref_tree.root().into();
 
@@ -65,9 +63,10 @@ async fn more_identities_than_dense_prefix(offchain_mode_enabled: bool) -> anyho
         .offchain_mode(offchain_mode_enabled)
         .build()?;
 
-    let (_, app_handle, local_addr, shutdown) = spawn_app(config.clone())
-        .await
-        .expect("Failed to spawn app.");
+    let (app, app_handle, local_addr, shutdown, task_monitor) =
+        spawn_app_with_task_monitor(config.clone())
+            .await
+            .expect("Failed to spawn app.");
 
     let test_identities = generate_test_identities(num_identities_total);
     let identities_ref: Vec<Field> = test_identities
@@ -85,8 +84,7 @@ async fn more_identities_than_dense_prefix(offchain_mode_enabled: bool) -> anyho
         test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, i).await;
     }
 
-    // Sleep long enough to process all the batches
-    tokio::time::sleep(Duration::from_secs(IDLE_TIME * num_batches_total as u64)).await;
+    flush_identities(&app).await?;
 
     // Check that we can get inclusion proof for the first identity
     test_inclusion_proof(
@@ -119,6 +117,7 @@ async fn more_identities_than_dense_prefix(offchain_mode_enabled: bool) -> anyho
     // Shutdown the app and reset the mock shutdown, allowing us to test the
     // behaviour with saved data.
     info!("Stopping the app for testing purposes");
+    task_monitor.stop().await;
     shutdown.shutdown();
     app_handle.await.unwrap();
 
@@ -18,11 +18,9 @@ async fn multi_prover(offchain_mode_enabled: bool) -> anyhow::Result<()> {
     init_tracing_subscriber();
     info!("Starting multi prover test");
 
-    let mut ref_tree = PoseidonTree::new(DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
     let initial_root: U256 = ref_tree.root().into();
 
-    let batch_timeout_seconds: u64 = 11;
-
     let batch_size_3: usize = 3;
     let batch_size_10: usize = 10;
 
@@ -31,7 +29,7 @@ async fn multi_prover(offchain_mode_enabled: bool) -> anyhow::Result<()> {
         initial_root,
         &[batch_size_3, batch_size_10],
         &[],
-        DEFAULT_TREE_DEPTH as u8,
+        *DEFAULT_TREE_DEPTH as u8,
         &docker,
     )
     .await?;
@@ -61,7 +59,7 @@ async fn multi_prover(offchain_mode_enabled: bool) -> anyhow::Result<()> {
         .build()?;
 
     tracing::info!("Spawning app");
-    let (_, app_handle, local_addr, shutdown) =
+    let (app, app_handle, local_addr, shutdown) =
         spawn_app(config).await.expect("Failed to spawn app.");
 
     let test_identities = generate_test_identities(batch_size_3 + batch_size_10);
@@ -85,8 +83,7 @@ async fn multi_prover(offchain_mode_enabled: bool) -> anyhow::Result<()> {
         test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, i).await;
     }
 
-    // Wait until a batch can be produced
-    tokio::time::sleep(Duration::from_secs(batch_timeout_seconds)).await;
+    flush_identities(&app).await?;
 
     // Identities should have been inserted and processed
     for (i, identity) in identities_ref.iter().enumerate().take(batch_size_3) {
@@ -114,7 +111,7 @@ async fn multi_prover(offchain_mode_enabled: bool) -> anyhow::Result<()> {
         test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, offset + i).await;
     }
 
-    tokio::time::sleep(Duration::from_secs(batch_timeout_seconds)).await;
+    flush_identities(&app).await?;
 
     // Identities should have been inserted and processed
     for i in 0..batch_size_10 {
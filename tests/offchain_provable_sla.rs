@@ -0,0 +1,135 @@
+mod common;
+
+use common::prelude::*;
+use signup_sequencer::config::ProvableSlaConfig;
+use signup_sequencer::server::data::AdminStatusResponse;
+use test_utils::wait_for_async_condition;
+
+const MAX_LATENCY: Duration = Duration::from_secs(5);
+const SLA_WINDOW: Duration = Duration::from_secs(20);
+const STATUS_POLL_INTERVAL: Duration = Duration::from_millis(250);
+const STATUS_TIMEOUT: Duration = Duration::from_secs(60);
+
+async fn sla_breached(client: &Client, uri: &str) -> bool {
+    let status: AdminStatusResponse = client
+        .get(format!("{uri}/v2/admin/status"))
+        .send()
+        .await
+        .expect("Failed to get status")
+        .json()
+        .await
+        .expect("Failed to parse status");
+
+    status.sla_breached
+}
+
+async fn await_sla_breached(client: &Client, uri: &str, breached: bool) -> anyhow::Result<()> {
+    wait_for_async_condition(
+        || async move { sla_breached(client, uri).await == breached },
+        STATUS_POLL_INTERVAL,
+        STATUS_TIMEOUT,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn offchain_provable_sla() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let batch_size: usize = 3;
+
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, _, micro_oz) = spawn_deps(
+        initial_root,
+        &[batch_size],
+        &[],
+        *DEFAULT_TREE_DEPTH as u8,
+        &docker,
+    )
+    .await?;
+
+    let prover_mock = &insertion_prover_map[&batch_size];
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let temp_dir = tempfile::tempdir()?;
+
+    let config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_prover(prover_mock)
+        .offchain_mode(true)
+        .provable_sla(ProvableSlaConfig {
+            max_latency: MAX_LATENCY,
+            window: SLA_WINDOW,
+        })
+        .build()?;
+
+    let (app, app_handle, local_addr, shutdown, task_monitor) =
+        spawn_app_with_task_monitor(config.clone())
+            .await
+            .expect("Failed to spawn app.");
+
+    let test_identities = generate_test_identities(1);
+    let identities_ref: Vec<Field> = test_identities
+        .iter()
+        .map(|i| Hash::from_str_radix(i, 16).unwrap())
+        .collect();
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    assert!(!sla_breached(&client, &uri).await);
+
+    // Without the tasks the identity stays queued, pretend it was received
+    // long before it's added to the tree
+    task_monitor.stop().await;
+    test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, 0).await;
+    sqlx::query("UPDATE unprocessed_identities SET created_at = created_at - INTERVAL '1 minute'")
+        .execute(&**app.database)
+        .await?;
+
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+    drop(app);
+
+    let (app, app_handle, local_addr, shutdown) = spawn_app(config.clone())
+        .await
+        .expect("Failed to spawn app.");
+    let uri = "http://".to_owned() + &local_addr.to_string();
+
+    // The identity is provable once it's added to the latest tree
+    await_sla_breached(&client, &uri, true).await?;
+    assert_eq!(app.tree_state()?.latest_tree().get_root(), ref_tree.root());
+
+    let metrics = client
+        .get(format!("{uri}/metrics"))
+        .send()
+        .await?
+        .text()
+        .await?;
+    assert!(metrics.contains("sla_breached 1"));
+    assert!(metrics.contains("insert_to_provable_seconds_count 1"));
+
+    // The breach clears once the slow identity leaves the window
+    await_sla_breached(&client, &uri, false).await?;
+
+    // Shutdown the app properly for the final time
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
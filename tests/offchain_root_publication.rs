@@ -0,0 +1,108 @@
+mod common;
+
+use common::prelude::*;
+use signup_sequencer::config::RootPublicationConfig;
+use signup_sequencer::identity_tree::publication::read_published_root;
+use signup_sequencer::identity_tree::ProcessedStatus;
+
+const PUBLICATION_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[tokio::test]
+async fn offchain_root_publication() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let batch_size: usize = 3;
+
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, _, micro_oz) = spawn_deps(
+        initial_root,
+        &[batch_size],
+        &[],
+        *DEFAULT_TREE_DEPTH as u8,
+        &docker,
+    )
+    .await?;
+
+    let prover_mock = &insertion_prover_map[&batch_size];
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let temp_dir = tempfile::tempdir()?;
+    let publication_path = temp_dir.path().join("root.json");
+
+    let signing_key = H256::from_low_u64_be(42);
+    let signer = LocalWallet::from_bytes(signing_key.as_bytes())?.address();
+
+    let config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_prover(prover_mock)
+        .offchain_mode(true)
+        .root_publication(RootPublicationConfig {
+            path: publication_path.clone(),
+            signing_key: signing_key.into(),
+        })
+        .build()?;
+
+    let (app, app_handle, local_addr, shutdown) = spawn_app(config.clone())
+        .await
+        .expect("Failed to spawn app.");
+
+    let test_identities = generate_test_identities(6);
+    let identities_ref: Vec<Field> = test_identities
+        .iter()
+        .map(|i| Hash::from_str_radix(i, 16).unwrap())
+        .collect();
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    // Each flushed batch moves the published root forward
+    for (batch_index, identities) in identities_ref.chunks(batch_size).enumerate() {
+        let first_leaf = batch_index * batch_size;
+
+        for leaf_index in first_leaf..first_leaf + identities.len() {
+            test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, leaf_index).await;
+        }
+
+        flush_identities(&app).await?;
+
+        let expected_root = ref_tree.root();
+        let start = tokio::time::Instant::now();
+        let document = loop {
+            if let Ok(document) = read_published_root(&publication_path, signer).await {
+                if document.root == expected_root {
+                    break document;
+                }
+            }
+
+            assert!(
+                start.elapsed() < PUBLICATION_TIMEOUT,
+                "Root {expected_root} was not published"
+            );
+            tokio::time::sleep(Duration::from_millis(250)).await;
+        };
+
+        assert_eq!(document.status, ProcessedStatus::Mined);
+        assert_eq!(document.tree_size, first_leaf + identities.len());
+    }
+
+    // Shutdown the app properly for the final time
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
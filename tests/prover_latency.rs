@@ -0,0 +1,188 @@
+mod common;
+
+use common::prelude::*;
+use signup_sequencer::database::types::BatchType;
+use sqlx::{Connection, PgConnection};
+
+use crate::common::test_delete_identity;
+
+const PROVING_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The batches which were proven, with the prover which did it and how long it
+/// took.
+async fn proven_batches(db_url: &str) -> anyhow::Result<Vec<(BatchType, String, i64)>> {
+    let mut conn = PgConnection::connect(db_url).await?;
+
+    Ok(sqlx::query_as(
+        r#"
+        SELECT batch_type, prover_url, proving_duration_ms
+        FROM batches
+        WHERE prover_url IS NOT NULL
+        ORDER BY id
+        "#,
+    )
+    .fetch_all(&mut conn)
+    .await?)
+}
+
+async fn proving_count(
+    client: &Client,
+    uri: &str,
+    prover: &ProverService,
+    batch_type: &str,
+) -> anyhow::Result<Option<u64>> {
+    let metrics = client
+        .get(format!("{uri}/metrics"))
+        .send()
+        .await?
+        .text()
+        .await?;
+
+    let url = Url::parse(&prover.url())?;
+    let labels = [
+        format!(
+            "prover=\"{}:{}\"",
+            url.host_str().unwrap(),
+            url.port().unwrap()
+        ),
+        format!("batch_type=\"{batch_type}\""),
+        format!("batch_size=\"{}\"", prover.batch_size()),
+    ];
+
+    Ok(metrics
+        .lines()
+        .filter(|line| line.starts_with("prover_proving_time_count{"))
+        .find(|line| labels.iter().all(|label| line.contains(label.as_str())))
+        .and_then(|line| line.rsplit(' ').next())
+        .map(str::parse)
+        .transpose()?)
+}
+
+#[tokio::test]
+async fn prover_latency() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let insertion_batch_size: usize = 3;
+    let deletion_batch_size: usize = 2;
+
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, deletion_prover_map, micro_oz) =
+        spawn_deps(
+            initial_root,
+            &[insertion_batch_size],
+            &[deletion_batch_size],
+            *DEFAULT_TREE_DEPTH as u8,
+            &docker,
+        )
+        .await?;
+
+    let mock_insertion_prover = &insertion_prover_map[&insertion_batch_size];
+    let mock_deletion_prover = &deletion_prover_map[&deletion_batch_size];
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let temp_dir = tempfile::tempdir()?;
+
+    let config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_prover(mock_insertion_prover)
+        .add_prover(mock_deletion_prover)
+        .build()?;
+
+    let (_, app_handle, local_addr, shutdown) =
+        spawn_app(config).await.expect("Failed to spawn app.");
+
+    let test_identities = generate_test_identities(insertion_batch_size);
+    let identities_ref: Vec<Field> = test_identities
+        .iter()
+        .map(|i| Hash::from_str_radix(i, 16).unwrap())
+        .collect();
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    for i in 0..insertion_batch_size {
+        test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, i).await;
+    }
+
+    let start = tokio::time::Instant::now();
+    while proven_batches(&db_url).await?.is_empty() {
+        assert!(
+            start.elapsed() < PROVING_TIMEOUT,
+            "Insertion batch was not proven"
+        );
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+
+    for i in 0..deletion_batch_size {
+        test_delete_identity(&uri, &client, &mut ref_tree, &identities_ref, i, false).await;
+    }
+
+    let start = tokio::time::Instant::now();
+    let batches = loop {
+        let batches = proven_batches(&db_url).await?;
+        if batches.len() == 2 {
+            break batches;
+        }
+
+        assert!(
+            start.elapsed() < PROVING_TIMEOUT,
+            "Deletion batch was not proven"
+        );
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    };
+
+    // Each batch is attributed to the prover of its type
+    let provers = [
+        (BatchType::Insertion, mock_insertion_prover),
+        (BatchType::Deletion, mock_deletion_prover),
+    ];
+    for ((batch_type, prover_url, duration_ms), (expected_type, prover)) in
+        batches.iter().zip(provers)
+    {
+        assert_eq!(*batch_type, expected_type);
+        let url = Url::parse(&prover.url())?;
+        assert_eq!(
+            *prover_url,
+            format!(
+                "{}:{}",
+                url.host_str().unwrap(),
+                url.port_or_known_default().unwrap()
+            ),
+            "Batch proven by the wrong prover"
+        );
+        assert!(*duration_ms >= 0);
+
+        let count = proving_count(&client, &uri, prover, &expected_type.to_string()).await?;
+        assert_eq!(count, Some(1), "No {expected_type} proving time series");
+    }
+
+    // The insertion prover never proved a deletion
+    assert_eq!(
+        proving_count(&client, &uri, mock_insertion_prover, "deletion").await?,
+        None
+    );
+
+    // Shutdown the app properly for the final time
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+    for (_, prover) in deletion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use common::prelude::*;
+
+/// Tests that a ranged prover proves a partial batch at its actual size instead
+/// of the batch being padded.
+#[tokio::test]
+async fn ranged_prover() -> anyhow::Result<()> {
+    init_tracing_subscriber();
+    info!("Starting ranged prover test");
+
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let num_identities: usize = 7;
+    let max_batch_size: usize = 10;
+
+    // The identity manager needs a verifier for every size the prover takes
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, _, micro_oz) = spawn_deps(
+        initial_root,
+        &[num_identities, max_batch_size],
+        &[],
+        *DEFAULT_TREE_DEPTH as u8,
+        &docker,
+    )
+    .await?;
+
+    let ranged_prover_mock = &insertion_prover_map[&max_batch_size];
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let temp_dir = tempfile::tempdir()?;
+
+    let config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_ranged_prover(ranged_prover_mock, 1, max_batch_size)
+        .build()?;
+
+    let (_, app_handle, local_addr, shutdown) =
+        spawn_app(config).await.expect("Failed to spawn app.");
+
+    let test_identities = generate_test_identities(num_identities);
+    let identities_ref: Vec<Field> = test_identities
+        .iter()
+        .map(|i| Hash::from_str_radix(i, 16).unwrap())
+        .collect();
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    for i in 0..num_identities {
+        test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, i).await;
+    }
+
+    // The batch is only created once the batch timeout passes
+    for identity in &identities_ref {
+        test_inclusion_proof_mined(&mock_chain, &uri, &client, identity, false, false).await;
+    }
+
+    assert_eq!(
+        ranged_prover_mock.received_batch_sizes().await,
+        vec![num_identities]
+    );
+
+    let contract_root: U256 = mock_chain.identity_manager.latest_root().call().await?;
+    assert_eq!(contract_root, ref_tree.root().into());
+
+    shutdown.shutdown();
+    app_handle.await?;
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
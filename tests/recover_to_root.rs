@@ -0,0 +1,294 @@
+mod common;
+
+use std::sync::Arc;
+
+use common::prelude::*;
+use prometheus::Registry;
+use signup_sequencer::database::methods::DbMethods as _;
+use signup_sequencer::identity_tree::ProcessedStatus;
+
+/// Initializes the tree of an app without starting any of its tasks, so that
+/// the state right after the recovery can be inspected.
+async fn init_tree_only(config: Config) -> anyhow::Result<Arc<App>> {
+    let app = App::new_with_registry(config, Registry::new()).await?;
+    app.clone().init_tree().await?;
+
+    Ok(app)
+}
+
+/// Asserts that the identities after `root` are pending again and that the
+/// batches after it were dropped.
+async fn assert_rolled_back(
+    app: &App,
+    root: &Hash,
+    rolled_back_roots: &[Hash],
+) -> anyhow::Result<()> {
+    for rolled_back_root in rolled_back_roots {
+        let state = app
+            .database
+            .get_root_state(rolled_back_root)
+            .await?
+            .context("Missing rolled back root")?;
+        assert_eq!(state.status, ProcessedStatus::Pending);
+    }
+
+    let latest_batch = app
+        .database
+        .get_latest_batch()
+        .await?
+        .context("Missing batch of the recovered root")?;
+    assert_eq!(latest_batch.next_root, *root);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn recover_to_root_offchain() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let batch_size: usize = 3;
+
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, _, micro_oz) = spawn_deps(
+        initial_root,
+        &[batch_size],
+        &[],
+        *DEFAULT_TREE_DEPTH as u8,
+        &docker,
+    )
+    .await?;
+
+    let prover_mock = &insertion_prover_map[&batch_size];
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let temp_dir = tempfile::tempdir()?;
+
+    let mut config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_prover(prover_mock)
+        .offchain_mode(true)
+        .build()?;
+
+    let (app, app_handle, local_addr, shutdown, task_monitor) =
+        spawn_app_with_task_monitor(config.clone())
+            .await
+            .expect("Failed to spawn app.");
+
+    let test_identities = generate_test_identities(9);
+    let identities_ref: Vec<Field> = test_identities
+        .iter()
+        .map(|i| Hash::from_str_radix(i, 16).unwrap())
+        .collect();
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    // Three batches, the last two of which are rolled back
+    let mut batch_roots = vec![];
+    for (batch_index, identities) in identities_ref.chunks(batch_size).enumerate() {
+        let first_leaf = batch_index * batch_size;
+
+        for leaf_index in first_leaf..first_leaf + identities.len() {
+            test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, leaf_index).await;
+        }
+
+        flush_identities(&app).await?;
+        batch_roots.push(ref_tree.root());
+    }
+
+    let tree_state = app.tree_state()?.clone();
+    assert_eq!(tree_state.mined_tree().get_root(), batch_roots[2]);
+
+    let latest_batch = app
+        .database
+        .get_latest_batch()
+        .await?
+        .expect("Batches should exist");
+    assert_eq!(latest_batch.next_root, batch_roots[2]);
+
+    info!("Stopping the app to recover to the first batch");
+    task_monitor.stop().await;
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+
+    config.tree.recover_to_root = Some(batch_roots[0]);
+
+    let app = init_tree_only(config.clone()).await?;
+    assert_rolled_back(&app, &batch_roots[0], &batch_roots[1..]).await?;
+    assert_eq!(
+        app.database.count_pending_identities().await?,
+        2 * batch_size as i32
+    );
+    drop(app);
+
+    // The recovery is applied only once, restarting with it still configured
+    // batches the rolled back identities again rather than rolling back anew
+    let (app, app_handle, _, shutdown) = spawn_app(config.clone())
+        .await
+        .expect("Failed to spawn app.");
+
+    // The rolled back identities are batched again, ending up at the same root
+    flush_identities(&app).await?;
+
+    let restored_tree_state = app.tree_state()?.clone();
+    assert_eq!(restored_tree_state.mined_tree().get_root(), batch_roots[2]);
+    test_same_tree_states(&tree_state, &restored_tree_state).await?;
+
+    // The tree agrees with the database
+    let mined_root = app
+        .database
+        .get_latest_root_by_status(ProcessedStatus::Mined)
+        .await?;
+    assert_eq!(mined_root, Some(batch_roots[2]));
+    assert_eq!(app.database.count_pending_identities().await?, 0);
+
+    // The batches were recreated rather than kept
+    let recreated_batch = app
+        .database
+        .get_latest_batch()
+        .await?
+        .expect("Batches should exist");
+    assert_eq!(recreated_batch.next_root, batch_roots[2]);
+    assert!(recreated_batch.id > latest_batch.id);
+
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+
+    // Later restarts leave the recreated batches alone
+    let app = init_tree_only(config).await?;
+    assert_eq!(
+        app.database.get_latest_batch().await?.map(|batch| batch.id),
+        Some(recreated_batch.id)
+    );
+    assert_eq!(app.database.count_pending_identities().await?, 0);
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn recover_to_root_onchain_refuses_mined_roots() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let batch_size: usize = 3;
+
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, _, micro_oz) = spawn_deps(
+        initial_root,
+        &[batch_size],
+        &[],
+        *DEFAULT_TREE_DEPTH as u8,
+        &docker,
+    )
+    .await?;
+
+    let prover_mock = &insertion_prover_map[&batch_size];
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let temp_dir = tempfile::tempdir()?;
+
+    let mut config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_prover(prover_mock)
+        .build()?;
+
+    let (app, app_handle, local_addr, shutdown) = spawn_app(config.clone())
+        .await
+        .expect("Failed to spawn app.");
+
+    let test_identities = generate_test_identities(3 * batch_size);
+    let identities_ref: Vec<Field> = test_identities
+        .iter()
+        .map(|i| Hash::from_str_radix(i, 16).unwrap())
+        .collect();
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    // Two batches, both mined on chain
+    let mut batch_roots = vec![];
+    for (batch_index, identities) in identities_ref[..2 * batch_size]
+        .chunks(batch_size)
+        .enumerate()
+    {
+        let first_leaf = batch_index * batch_size;
+
+        for leaf_index in first_leaf..first_leaf + identities.len() {
+            test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, leaf_index).await;
+        }
+
+        flush_identities(&app).await?;
+        batch_roots.push(ref_tree.root());
+    }
+
+    info!("Stopping the app to recover to the first batch");
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+
+    // The contract is already at the second root
+    config.tree.recover_to_root = Some(batch_roots[0]);
+    let error = init_tree_only(config.clone())
+        .await
+        .expect_err("Recovered past a mined root");
+    assert!(format!("{error:#}").contains("the contract is already at the newer root"));
+
+    // Nothing was rolled back, and restarts keep batching on top of the
+    // mined roots
+    let (app, app_handle, local_addr, shutdown) = spawn_app(config.clone())
+        .await
+        .expect("Failed to spawn app.");
+    assert_eq!(app.database.count_pending_identities().await?, 0);
+    assert!(
+        !app.database
+            .is_tree_recovery_applied(&batch_roots[0])
+            .await?
+    );
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    for leaf_index in 2 * batch_size..3 * batch_size {
+        test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, leaf_index).await;
+    }
+    flush_identities(&app).await?;
+
+    assert_eq!(
+        app.database
+            .get_latest_root_by_status(ProcessedStatus::Mined)
+            .await?,
+        Some(ref_tree.root())
+    );
+    assert_eq!(app.tree_state()?.mined_tree().get_root(), ref_tree.root());
+
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,114 @@
+mod common;
+
+use common::prelude::*;
+use signup_sequencer::database::methods::DbMethods as _;
+
+use crate::common::{api_insert_identity, construct_insert_identity_body, test_delete_identity};
+
+#[tokio::test]
+async fn reinsert_deleted_identity() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let insertion_batch_size: usize = 3;
+    let deletion_batch_size: usize = 3;
+
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, deletion_prover_map, micro_oz) =
+        spawn_deps(
+            initial_root,
+            &[insertion_batch_size],
+            &[deletion_batch_size],
+            *DEFAULT_TREE_DEPTH as u8,
+            &docker,
+        )
+        .await?;
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let temp_dir = tempfile::tempdir()?;
+
+    let mut config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_prover(&insertion_prover_map[&insertion_batch_size])
+        .add_prover(&deletion_prover_map[&deletion_batch_size])
+        .offchain_mode(true)
+        .build()?;
+    config.app.allow_reinsert_after_deletion_days = Some(0);
+
+    let (app, app_handle, local_addr, shutdown) =
+        spawn_app(config).await.expect("Failed to spawn app.");
+
+    let commitments: Vec<Field> = generate_test_identities(insertion_batch_size)
+        .iter()
+        .map(|i| Hash::from_str_radix(i, 16).unwrap())
+        .collect();
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    for i in 0..insertion_batch_size {
+        test_insert_identity(&uri, &client, &mut ref_tree, &commitments, i).await;
+    }
+    flush_identities(&app).await?;
+
+    test_delete_identity(&uri, &client, &mut ref_tree, &commitments, 0, false).await;
+    flush_identities(&app).await?;
+
+    // The re-inserted commitment goes to the next free leaf, its deleted leaf
+    // stays zeroed
+    api_insert_identity(&uri, &client, &commitments[0]).await;
+    ref_tree.set(insertion_batch_size, commitments[0]);
+    flush_identities(&app).await?;
+
+    let leaf = app
+        .database
+        .get_identity_leaf_index(&commitments[0])
+        .await?
+        .expect("Re-inserted identity is missing");
+    assert_eq!(leaf.leaf_index, insertion_batch_size);
+    assert!(app.database.get_unprocessed_commitments().await?.is_empty());
+
+    test_inclusion_proof(
+        &mock_chain,
+        &uri,
+        &client,
+        insertion_batch_size,
+        &ref_tree,
+        &commitments[0],
+        false,
+        true,
+    )
+    .await;
+
+    // It's live again, so a second insertion is refused
+    let response = client
+        .post(format!("{uri}/insertIdentity"))
+        .body(construct_insert_identity_body(&commitments[0]))
+        .header("Content-Type", "application/json")
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    // Shutdown the app properly for the final time
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+    for (_, prover) in deletion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
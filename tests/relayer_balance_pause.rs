@@ -0,0 +1,136 @@
+mod common;
+
+use common::prelude::*;
+use signup_sequencer::server::data::AdminStatusResponse;
+
+const MIN_RELAYER_BALANCE_GWEI: u64 = 1_000_000_000;
+const STATUS_TIMEOUT: Duration = Duration::from_secs(30);
+
+async fn set_balance(provider: &Provider<Http>, address: Address, balance: U256) {
+    provider
+        .request::<_, ()>("anvil_setBalance", (address, balance))
+        .await
+        .expect("Failed to set balance");
+}
+
+async fn await_batching_paused(client: &Client, uri: &str, paused: bool) {
+    let start = tokio::time::Instant::now();
+
+    loop {
+        let status: AdminStatusResponse = client
+            .get(format!("{uri}/v2/admin/status"))
+            .send()
+            .await
+            .expect("Failed to get status")
+            .json()
+            .await
+            .expect("Failed to parse status");
+
+        if status.batching_paused == paused {
+            return;
+        }
+
+        assert!(
+            start.elapsed() < STATUS_TIMEOUT,
+            "Batching paused did not become {paused}"
+        );
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+}
+
+#[tokio::test]
+async fn relayer_balance_pause() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let batch_size: usize = 3;
+
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, _, micro_oz) = spawn_deps(
+        initial_root,
+        &[batch_size],
+        &[],
+        *DEFAULT_TREE_DEPTH as u8,
+        &docker,
+    )
+    .await?;
+
+    let prover_mock = &insertion_prover_map[&batch_size];
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let temp_dir = tempfile::tempdir()?;
+
+    let config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_prover(prover_mock)
+        .min_relayer_balance_gwei(MIN_RELAYER_BALANCE_GWEI)
+        .build()?;
+
+    // Drain the relayer before the app starts
+    let provider = Provider::<Http>::try_from(mock_chain.anvil.endpoint())?;
+    let relayer_balance = provider.get_balance(micro_oz.address(), None).await?;
+    set_balance(&provider, micro_oz.address(), U256::zero()).await;
+
+    let (_, app_handle, local_addr, shutdown) = spawn_app(config.clone())
+        .await
+        .expect("Failed to spawn app.");
+
+    let test_identities = generate_test_identities(batch_size);
+    let identities_ref: Vec<Field> = test_identities
+        .iter()
+        .map(|i| Hash::from_str_radix(i, 16).unwrap())
+        .collect();
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    await_batching_paused(&client, &uri, true).await;
+
+    // Identities are still accepted, but not submitted
+    for leaf_index in 0..batch_size {
+        test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, leaf_index).await;
+    }
+
+    tokio::time::sleep(Duration::from_secs(10)).await;
+
+    let contract_root: U256 = mock_chain.identity_manager.latest_root().call().await?;
+    assert_eq!(contract_root, initial_root);
+
+    // Submission resumes once the relayer is topped up
+    set_balance(&provider, micro_oz.address(), relayer_balance).await;
+    await_batching_paused(&client, &uri, false).await;
+
+    let start = tokio::time::Instant::now();
+    loop {
+        let contract_root: U256 = mock_chain.identity_manager.latest_root().call().await?;
+        if contract_root == ref_tree.root().into() {
+            break;
+        }
+
+        assert!(
+            start.elapsed() < STATUS_TIMEOUT,
+            "Batch was not submitted after topping up"
+        );
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+
+    // Shutdown the app properly for the final time
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,164 @@
+mod common;
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::routing::post;
+use axum::{Json, Router};
+use common::prelude::*;
+use signup_sequencer::config::RootNotificationsConfig;
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Notification {
+    event: String,
+    root: Field,
+}
+
+type Received = Arc<Mutex<Vec<Notification>>>;
+
+async fn webhook(State(received): State<Received>, Json(notification): Json<Notification>) {
+    received.lock().await.push(notification);
+}
+
+async fn spawn_webhook() -> anyhow::Result<(SocketAddr, Received)> {
+    let received = Received::default();
+
+    let router = Router::new()
+        .route("/", post(webhook))
+        .with_state(received.clone());
+
+    let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))).await?;
+    let addr = listener.local_addr()?;
+
+    spawn(async move {
+        axum::serve(listener, router).await.unwrap();
+    });
+
+    Ok((addr, received))
+}
+
+#[tokio::test]
+async fn root_notifications() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let batch_size = 3;
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, _deletion_prover_map, micro_oz) =
+        spawn_deps(
+            initial_root,
+            &[batch_size],
+            &[],
+            *DEFAULT_TREE_DEPTH as u8,
+            &docker,
+        )
+        .await?;
+
+    let prover_mock = &insertion_prover_map[&batch_size];
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let temp_dir = tempfile::tempdir()?;
+
+    let config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_prover(prover_mock)
+        .root_notifications(RootNotificationsConfig {
+            max_root_age: Duration::from_secs(60),
+            aging_threshold_percent: 100,
+            interval: Duration::from_secs(1),
+            webhook_timeout: Duration::from_secs(1),
+        })
+        .build()?;
+
+    let (app, app_handle, local_addr, shutdown) =
+        spawn_app(config).await.expect("Failed to spawn app.");
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    let (webhook_addr, received) = spawn_webhook().await?;
+    let webhook_url = format!("http://{webhook_addr}/");
+
+    for percent in [0, 101] {
+        let response = client
+            .post(uri.clone() + "/v2/admin/subscriptions")
+            .json(&json!({ "url": webhook_url, "agingThresholdPercent": percent }))
+            .send()
+            .await?;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    // The subscriber is warned after 3 seconds rather than after the
+    // configured max root age
+    let response = client
+        .post(uri.clone() + "/v2/admin/subscriptions")
+        .json(&json!({ "url": webhook_url, "agingThresholdPercent": 5 }))
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+
+    let test_leaves: Vec<Field> = generate_test_identities(1)
+        .iter()
+        .map(|i| Hash::from_str_radix(i, 16).unwrap())
+        .collect();
+
+    let (_, root) = test_insert_identity(&uri, &client, &mut ref_tree, &test_leaves, 0).await;
+
+    flush_identities(&app).await?;
+
+    // Wait for the root to cross the aging threshold a couple of notification
+    // intervals ago
+    tokio::time::sleep(Duration::from_secs(6)).await;
+
+    {
+        let received = received.lock().await;
+        let count = |event: &str| {
+            received
+                .iter()
+                .filter(|notification| notification.root == root && notification.event == event)
+                .count()
+        };
+
+        assert_eq!(count("rootMined"), 1, "received: {received:?}");
+        assert_eq!(count("rootAging"), 1, "received: {received:?}");
+    }
+
+    let response = client
+        .delete(uri.clone() + "/v2/admin/subscriptions")
+        .json(&json!({ "url": webhook_url }))
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+
+    let response = client
+        .delete(uri.clone() + "/v2/admin/subscriptions")
+        .json(&json!({ "url": webhook_url }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // Shutdown the app properly for the final time
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
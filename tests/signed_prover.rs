@@ -0,0 +1,81 @@
+mod common;
+
+use common::prelude::*;
+
+const PROVER_SECRET: &str = "prover-secret";
+
+/// Tests that batches get proven by a prover that only accepts requests signed
+/// with its secret.
+#[tokio::test]
+async fn signed_prover() -> anyhow::Result<()> {
+    init_tracing_subscriber();
+    info!("Starting signed prover test");
+
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let batch_size: usize = 3;
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, _, micro_oz) = spawn_deps(
+        initial_root,
+        &[batch_size],
+        &[],
+        *DEFAULT_TREE_DEPTH as u8,
+        &docker,
+    )
+    .await?;
+
+    let prover_mock = &insertion_prover_map[&batch_size];
+    prover_mock.set_hmac_secret(PROVER_SECRET).await;
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let temp_dir = tempfile::tempdir()?;
+
+    let config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_signed_prover(prover_mock, PROVER_SECRET)
+        .build()?;
+
+    let (_, app_handle, local_addr, shutdown) =
+        spawn_app(config).await.expect("Failed to spawn app.");
+
+    let test_identities = generate_test_identities(batch_size);
+    let identities_ref: Vec<Field> = test_identities
+        .iter()
+        .map(|i| Hash::from_str_radix(i, 16).unwrap())
+        .collect();
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    for i in 0..batch_size {
+        test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, i).await;
+    }
+
+    // The mock rejects unsigned requests, so the batch only gets mined if the
+    // signature checks out
+    for identity in &identities_ref {
+        test_inclusion_proof_mined(&mock_chain, &uri, &client, identity, false, false).await;
+    }
+
+    assert_eq!(prover_mock.received_batch_sizes().await, vec![batch_size]);
+
+    let contract_root: U256 = mock_chain.identity_manager.latest_root().call().await?;
+    assert_eq!(contract_root, ref_tree.root().into());
+
+    shutdown.shutdown();
+    app_handle.await?;
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,112 @@
+mod common;
+
+use common::prelude::*;
+use signup_sequencer::server::data::{IdentityCountResponse, SimulateInsertResponse};
+
+async fn simulate_insert(uri: &str, client: &Client, commitment: &Field) -> reqwest::Response {
+    client
+        .post(format!(
+            "{uri}/v2/admin/identities/{commitment:#x}/simulate"
+        ))
+        .send()
+        .await
+        .expect("Failed to simulate insert")
+}
+
+#[tokio::test]
+async fn simulate_insert_identity() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let batch_size = 3;
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, _deletion_prover_map, micro_oz) =
+        spawn_deps(
+            initial_root,
+            &[batch_size],
+            &[],
+            *DEFAULT_TREE_DEPTH as u8,
+            &docker,
+        )
+        .await?;
+
+    let prover_mock = &insertion_prover_map[&batch_size];
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let temp_dir = tempfile::tempdir()?;
+
+    let config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_prover(prover_mock)
+        .build()?;
+
+    let (_, app_handle, local_addr, shutdown) =
+        spawn_app(config).await.expect("Failed to spawn app.");
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    let test_leaves: Vec<Field> = generate_test_identities(1)
+        .iter()
+        .map(|i| Hash::from_str_radix(i, 16).unwrap())
+        .collect();
+    let commitment = test_leaves[0];
+
+    let response = simulate_insert(&uri, &client, &commitment).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let simulated: SimulateInsertResponse = response.json().await?;
+
+    ref_tree.set(0, commitment);
+
+    assert_eq!(simulated.leaf_index, 0);
+    assert_eq!(simulated.root, ref_tree.root());
+    assert_eq!(simulated.proof, ref_tree.proof(0).unwrap());
+    assert_eq!(simulated.proof.root(commitment), simulated.root);
+
+    // The simulation must not have queued the commitment
+    let response = client
+        .post(uri.clone() + "/inclusionProof")
+        .json(&json!({ "identityCommitment": commitment }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let count: IdentityCountResponse = client
+        .get(uri.clone() + "/v2/identities/count")
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(count.leaf_count, 0);
+    assert_eq!(count.next_leaf, 0);
+
+    // Commitments the real insert would reject are rejected as well
+    let response = simulate_insert(&uri, &client, &Field::ZERO).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    test_insert_identity(&uri, &client, &mut ref_tree, &test_leaves, 0).await;
+
+    let response = simulate_insert(&uri, &client, &commitment).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    // Shutdown the app properly for the final time
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use std::time::Instant;
+
+use common::prelude::*;
+
+/// Generous upper bound on the setup shared by all integration tests, the
+/// dependencies are started concurrently and take ~2s each
+const SPAWN_DEPS_TIME_LIMIT: Duration = Duration::from_secs(10);
+
+#[tokio::test]
+async fn spawn_deps_starts_dependencies_concurrently() -> anyhow::Result<()> {
+    init_tracing_subscriber();
+
+    let ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let docker = Cli::default();
+
+    let start = Instant::now();
+    let (_mock_chain, _db_container, insertion_prover_map, deletion_prover_map, _micro_oz) =
+        spawn_deps(initial_root, &[3], &[3], *DEFAULT_TREE_DEPTH as u8, &docker).await?;
+    let elapsed = start.elapsed();
+
+    info!(?elapsed, "Dependencies spawned");
+    assert!(
+        elapsed < SPAWN_DEPS_TIME_LIMIT,
+        "spawn_deps took {elapsed:?}"
+    );
+
+    for (_, prover) in insertion_prover_map
+        .into_iter()
+        .chain(deletion_prover_map.into_iter())
+    {
+        prover.stop();
+    }
+
+    Ok(())
+}
@@ -19,7 +19,7 @@ async fn tree_restore_empty(offchain_mode_enabled: bool) -> anyhow::Result<()> {
 
     let batch_size: usize = 3;
 
-    let ref_tree = PoseidonTree::new(DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
     let initial_root: U256 = ref_tree.root().into();
 
     let docker = Cli::default();
@@ -27,7 +27,7 @@ async fn tree_restore_empty(offchain_mode_enabled: bool) -> anyhow::Result<()> {
         initial_root,
         &[batch_size],
         &[],
-        DEFAULT_TREE_DEPTH as u8,
+        *DEFAULT_TREE_DEPTH as u8,
         &docker,
     )
     .await?;
@@ -55,7 +55,7 @@ async fn tree_restore_empty(offchain_mode_enabled: bool) -> anyhow::Result<()> {
         .offchain_mode(offchain_mode_enabled)
         .build()?;
 
-    let (app, app_handle, _, shutdown) = spawn_app(config.clone())
+    let (app, app_handle, _, shutdown, task_monitor) = spawn_app_with_task_monitor(config.clone())
         .await
         .expect("Failed to spawn app.");
 
@@ -66,6 +66,7 @@ async fn tree_restore_empty(offchain_mode_enabled: bool) -> anyhow::Result<()> {
     // Shutdown the app and reset the mock shutdown, allowing us to test the
     // behaviour with saved data.
     info!("Stopping the app for testing purposes");
+    task_monitor.stop().await;
     shutdown.shutdown();
     app_handle.await.unwrap();
 
@@ -2,8 +2,6 @@ mod common;
 
 use common::prelude::*;
 
-const IDLE_TIME: u64 = 7;
-
 #[tokio::test]
 async fn tree_restore_multiple_commitments_onchain() -> anyhow::Result<()> {
     tree_restore_multiple_commitments(false).await
@@ -21,7 +19,7 @@ async fn tree_restore_multiple_commitments(offchain_mode_enabled: bool) -> anyho
 
     let batch_size: usize = 3;
 
-    let mut ref_tree = PoseidonTree::new(DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
     let initial_root: U256 = ref_tree.root().into();
 
     let docker = Cli::default();
@@ -29,7 +27,7 @@ async fn tree_restore_multiple_commitments(offchain_mode_enabled: bool) -> anyho
         initial_root,
         &[batch_size],
         &[],
-        DEFAULT_TREE_DEPTH as u8,
+        *DEFAULT_TREE_DEPTH as u8,
         &docker,
     )
     .await?;
@@ -57,9 +55,10 @@ async fn tree_restore_multiple_commitments(offchain_mode_enabled: bool) -> anyho
         .offchain_mode(offchain_mode_enabled)
         .build()?;
 
-    let (app, app_handle, local_addr, shutdown) = spawn_app(config.clone())
-        .await
-        .expect("Failed to spawn app.");
+    let (app, app_handle, local_addr, shutdown, task_monitor) =
+        spawn_app_with_task_monitor(config.clone())
+            .await
+            .expect("Failed to spawn app.");
 
     let test_identities = generate_test_identities(3);
     let identities_ref: Vec<Field> = test_identities
@@ -74,7 +73,7 @@ async fn tree_restore_multiple_commitments(offchain_mode_enabled: bool) -> anyho
     test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, 1).await;
     test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, 2).await;
 
-    tokio::time::sleep(Duration::from_secs(IDLE_TIME)).await;
+    flush_identities(&app).await?;
 
     let tree_state = app.tree_state()?.clone();
 
@@ -83,6 +82,7 @@ async fn tree_restore_multiple_commitments(offchain_mode_enabled: bool) -> anyho
     // Shutdown the app and reset the mock shutdown, allowing us to test the
     // behaviour with saved data.
     info!("Stopping the app for testing purposes");
+    task_monitor.stop().await;
     shutdown.shutdown();
     app_handle.await.unwrap();
 
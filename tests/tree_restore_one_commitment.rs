@@ -2,8 +2,6 @@ mod common;
 
 use common::prelude::*;
 
-const IDLE_TIME: u64 = 7;
-
 #[tokio::test]
 async fn tree_restore_one_commitment_onchain() -> anyhow::Result<()> {
     tree_restore_one_commitment(false).await
@@ -21,7 +19,7 @@ async fn tree_restore_one_commitment(offchain_mode_enabled: bool) -> anyhow::Res
 
     let batch_size: usize = 1;
 
-    let mut ref_tree = PoseidonTree::new(DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
     let initial_root: U256 = ref_tree.root().into();
 
     let docker = Cli::default();
@@ -29,7 +27,7 @@ async fn tree_restore_one_commitment(offchain_mode_enabled: bool) -> anyhow::Res
         initial_root,
         &[batch_size],
         &[],
-        DEFAULT_TREE_DEPTH as u8,
+        *DEFAULT_TREE_DEPTH as u8,
         &docker,
     )
     .await?;
@@ -57,9 +55,10 @@ async fn tree_restore_one_commitment(offchain_mode_enabled: bool) -> anyhow::Res
         .offchain_mode(offchain_mode_enabled)
         .build()?;
 
-    let (app, app_handle, local_addr, shutdown) = spawn_app(config.clone())
-        .await
-        .expect("Failed to spawn app.");
+    let (app, app_handle, local_addr, shutdown, task_monitor) =
+        spawn_app_with_task_monitor(config.clone())
+            .await
+            .expect("Failed to spawn app.");
 
     let test_identities = generate_test_identities(1);
     let identities_ref: Vec<Field> = test_identities
@@ -72,7 +71,7 @@ async fn tree_restore_one_commitment(offchain_mode_enabled: bool) -> anyhow::Res
 
     test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, 0).await;
 
-    tokio::time::sleep(Duration::from_secs(IDLE_TIME)).await;
+    flush_identities(&app).await?;
 
     let tree_state = app.tree_state()?.clone();
 
@@ -81,6 +80,7 @@ async fn tree_restore_one_commitment(offchain_mode_enabled: bool) -> anyhow::Res
     // Shutdown the app and reset the mock shutdown, allowing us to test the
     // behaviour with saved data.
     info!("Stopping the app for testing purposes");
+    task_monitor.stop().await;
     shutdown.shutdown();
     app_handle.await.unwrap();
 
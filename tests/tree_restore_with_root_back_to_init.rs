@@ -2,8 +2,6 @@ mod common;
 
 use common::prelude::*;
 
-const IDLE_TIME: u64 = 7;
-
 #[tokio::test]
 async fn tree_restore_with_root_back_to_init_onchain() -> anyhow::Result<()> {
     tree_restore_with_root_back_to_init(false).await
@@ -16,7 +14,7 @@ async fn tree_restore_with_root_back_to_init(offchain_mode_enabled: bool) -> any
 
     let batch_size: usize = 3;
 
-    let mut ref_tree = PoseidonTree::new(DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
     let initial_root: U256 = ref_tree.root().into();
 
     let docker = Cli::default();
@@ -24,7 +22,7 @@ async fn tree_restore_with_root_back_to_init(offchain_mode_enabled: bool) -> any
         initial_root,
         &[batch_size],
         &[],
-        DEFAULT_TREE_DEPTH as u8,
+        *DEFAULT_TREE_DEPTH as u8,
         &docker,
     )
     .await?;
@@ -52,9 +50,10 @@ async fn tree_restore_with_root_back_to_init(offchain_mode_enabled: bool) -> any
         .offchain_mode(offchain_mode_enabled)
         .build()?;
 
-    let (app, app_handle, local_addr, shutdown) = spawn_app(config.clone())
-        .await
-        .expect("Failed to spawn app.");
+    let (app, app_handle, local_addr, shutdown, task_monitor) =
+        spawn_app_with_task_monitor(config.clone())
+            .await
+            .expect("Failed to spawn app.");
 
     let test_identities = generate_test_identities(3);
     let identities_ref: Vec<Field> = test_identities
@@ -69,7 +68,7 @@ async fn tree_restore_with_root_back_to_init(offchain_mode_enabled: bool) -> any
     test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, 1).await;
     test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, 2).await;
 
-    tokio::time::sleep(Duration::from_secs(IDLE_TIME)).await;
+    flush_identities(&app).await?;
 
     // Check that we can also get these inclusion proofs back.
     test_inclusion_proof(
@@ -118,6 +117,7 @@ async fn tree_restore_with_root_back_to_init(offchain_mode_enabled: bool) -> any
     // Shutdown the app and reset the mock shutdown, allowing us to test the
     // behaviour with saved data.
     info!("Stopping the app for testing purposes");
+    task_monitor.stop().await;
     shutdown.shutdown();
     app_handle.await.unwrap();
 
@@ -127,7 +127,7 @@ async fn tree_restore_with_root_back_to_init(offchain_mode_enabled: bool) -> any
     tokio::time::sleep(Duration::from_secs(2)).await;
 
     let mock_chain =
-        spawn_mock_chain(initial_root, &[batch_size], &[], DEFAULT_TREE_DEPTH as u8).await?;
+        spawn_mock_chain(initial_root, &[batch_size], &[], *DEFAULT_TREE_DEPTH as u8).await?;
     let micro_oz =
         micro_oz::spawn(mock_chain.anvil.endpoint(), mock_chain.private_key.clone()).await?;
 
@@ -165,7 +165,7 @@ async fn tree_restore_with_root_back_to_init(offchain_mode_enabled: bool) -> any
         initial_root.into()
     );
 
-    tokio::time::sleep(Duration::from_secs(IDLE_TIME)).await;
+    flush_identities(&app).await?;
 
     // Check that we can also get these inclusion proofs back.
     test_inclusion_proof(
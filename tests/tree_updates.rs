@@ -0,0 +1,153 @@
+mod common;
+
+use common::prelude::*;
+use signup_sequencer::identity_tree::ProcessedStatus;
+use signup_sequencer::server::data::TreeUpdatesResponse;
+
+async fn get_tree_updates(
+    client: &Client,
+    uri: &str,
+    after_sequence_id: usize,
+) -> anyhow::Result<TreeUpdatesResponse> {
+    let response = client
+        .get(format!(
+            "{uri}/v2/tree/updates?after_sequence_id={after_sequence_id}&limit=4&status=mined"
+        ))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    Ok(response.json().await?)
+}
+
+#[tokio::test]
+async fn tree_updates() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let batch_size: usize = 3;
+
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, _, micro_oz) = spawn_deps(
+        initial_root,
+        &[batch_size],
+        &[],
+        *DEFAULT_TREE_DEPTH as u8,
+        &docker,
+    )
+    .await?;
+
+    let prover_mock = &insertion_prover_map[&batch_size];
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let temp_dir = tempfile::tempdir()?;
+
+    let mut config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_prover(prover_mock)
+        .offchain_mode(true)
+        .build()?;
+
+    let (app, app_handle, local_addr, shutdown, task_monitor) =
+        spawn_app_with_task_monitor(config.clone())
+            .await
+            .expect("Failed to spawn app.");
+
+    let test_identities = generate_test_identities(6);
+    let identities_ref: Vec<Field> = test_identities
+        .iter()
+        .map(|i| Hash::from_str_radix(i, 16).unwrap())
+        .collect();
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    let mut batch_roots = vec![];
+    for (batch_index, identities) in identities_ref.chunks(batch_size).enumerate() {
+        let first_leaf = batch_index * batch_size;
+
+        for leaf_index in first_leaf..first_leaf + identities.len() {
+            test_insert_identity(&uri, &client, &mut ref_tree, &identities_ref, leaf_index).await;
+        }
+
+        flush_identities(&app).await?;
+        batch_roots.push(ref_tree.root());
+    }
+
+    // Page through both batches
+    let first_page = get_tree_updates(&client, &uri, 0).await?;
+    assert_eq!(first_page.epoch, 0);
+    assert_eq!(first_page.updates.len(), 4);
+
+    let second_page = get_tree_updates(&client, &uri, first_page.next_after_sequence_id).await?;
+    assert_eq!(second_page.updates.len(), 2);
+
+    let last_page = get_tree_updates(&client, &uri, second_page.next_after_sequence_id).await?;
+    assert!(last_page.updates.is_empty());
+    assert_eq!(
+        last_page.next_after_sequence_id,
+        second_page.next_after_sequence_id
+    );
+
+    let updates: Vec<_> = first_page
+        .updates
+        .into_iter()
+        .chain(second_page.updates)
+        .collect();
+    for (leaf_index, update) in updates.iter().enumerate() {
+        assert_eq!(update.leaf_index, leaf_index);
+        assert_eq!(update.element, identities_ref[leaf_index]);
+        assert_eq!(update.status, ProcessedStatus::Mined);
+    }
+    assert_eq!(updates[2].post_root, batch_roots[0]);
+    assert_eq!(updates[5].post_root, batch_roots[1]);
+
+    // Rewind to the first batch
+    task_monitor.stop().await;
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+
+    config.tree.recover_to_root = Some(batch_roots[0]);
+
+    let (app, app_handle, local_addr, shutdown) = spawn_app(config.clone())
+        .await
+        .expect("Failed to spawn app.");
+    let uri = "http://".to_owned() + &local_addr.to_string();
+
+    flush_identities(&app).await?;
+
+    // The rewound updates are delivered again in the new epoch
+    let first_page = get_tree_updates(&client, &uri, 0).await?;
+    assert_eq!(first_page.epoch, 1);
+
+    let second_page = get_tree_updates(&client, &uri, first_page.next_after_sequence_id).await?;
+    assert_eq!(second_page.epoch, 1);
+
+    let updates: Vec<_> = first_page
+        .updates
+        .into_iter()
+        .chain(second_page.updates)
+        .collect();
+    assert_eq!(updates.len(), 6);
+    assert_eq!(updates[5].post_root, batch_roots[1]);
+
+    // Shutdown the app properly for the final time
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
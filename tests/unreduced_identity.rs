@@ -14,7 +14,7 @@ async fn test_unreduced_identity_offchain() -> anyhow::Result<()> {
 async fn test_unreduced_identity(offchain_mode_enabled: bool) -> anyhow::Result<()> {
     info!("Starting unavailable prover test");
 
-    let ref_tree = PoseidonTree::new(DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
     let initial_root: U256 = ref_tree.root().into();
     let batch_size: usize = 3;
 
@@ -23,7 +23,7 @@ async fn test_unreduced_identity(offchain_mode_enabled: bool) -> anyhow::Result<
         initial_root,
         &[batch_size],
         &[],
-        DEFAULT_TREE_DEPTH as u8,
+        *DEFAULT_TREE_DEPTH as u8,
         &docker,
     )
     .await?;
@@ -0,0 +1,121 @@
+mod common;
+
+use chrono::NaiveDate;
+use common::prelude::*;
+
+use crate::common::construct_insert_identity_body;
+
+#[tokio::test]
+async fn v1_deprecation() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let insertion_batch_size: usize = 3;
+    let deletion_batch_size: usize = 3;
+
+    let ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, deletion_prover_map, micro_oz) =
+        spawn_deps(
+            initial_root,
+            &[insertion_batch_size],
+            &[deletion_batch_size],
+            *DEFAULT_TREE_DEPTH as u8,
+            &docker,
+        )
+        .await?;
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let temp_dir = tempfile::tempdir()?;
+
+    let mut config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_prover(&insertion_prover_map[&insertion_batch_size])
+        .add_prover(&deletion_prover_map[&deletion_batch_size])
+        .offchain_mode(true)
+        .build()?;
+    config.server.v1_sunset_date = NaiveDate::from_ymd_opt(2025, 6, 30);
+
+    let (_, app_handle, local_addr, shutdown) =
+        spawn_app(config).await.expect("Failed to spawn app.");
+
+    let commitments: Vec<Field> = generate_test_identities(1)
+        .iter()
+        .map(|i| Hash::from_str_radix(i, 16).unwrap())
+        .collect();
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    // Both the read and the write routes of the v1 API are deprecated
+    let response = client.get(format!("{uri}/listBatchSizes")).send().await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["deprecation"], "true");
+    assert_eq!(
+        response.headers()["sunset"],
+        "Mon, 30 Jun 2025 00:00:00 GMT"
+    );
+
+    let response = client
+        .post(format!("{uri}/insertIdentity"))
+        .body(construct_insert_identity_body(&commitments[0]))
+        .header("Content-Type", "application/json")
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["deprecation"], "true");
+
+    for route in [
+        "/v2/identities/count",
+        "/v2/tree/info",
+        "/v2/admin/status",
+        "/health",
+    ] {
+        let response = client.get(format!("{uri}{route}")).send().await?;
+        assert!(
+            !response.headers().contains_key("deprecation"),
+            "{route} is deprecated"
+        );
+        assert!(
+            !response.headers().contains_key("sunset"),
+            "{route} has a sunset"
+        );
+    }
+
+    let metrics = client
+        .get(format!("{uri}/metrics"))
+        .send()
+        .await?
+        .text()
+        .await?;
+    assert!(
+        metrics
+            .lines()
+            .any(|line| line.contains("v1_requests_total")
+                && line.contains(r#"endpoint="/listBatchSizes""#)
+                && line.ends_with(" 1")),
+        "{metrics}"
+    );
+
+    // Shutdown the app properly for the final time
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+    for (_, prover) in deletion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
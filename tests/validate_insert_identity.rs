@@ -0,0 +1,142 @@
+mod common;
+
+use common::prelude::*;
+use signup_sequencer::server::data::{
+    IdentityCountResponse, InsertRejection, ValidateInsertResponse,
+};
+use signup_sequencer::MODULUS;
+
+async fn validate_insert(client: &Client, uri: &str, commitment: &Field) -> ValidateInsertResponse {
+    let response = client
+        .get(format!("{uri}/v2/identities/{commitment:#x}/validate"))
+        .send()
+        .await
+        .expect("Failed to validate insert");
+
+    // Rejections are part of the response, not errors
+    assert_eq!(response.status(), StatusCode::OK);
+
+    response.json().await.expect("Failed to parse response")
+}
+
+#[tokio::test]
+async fn validate_insert_identity() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let batch_size = 3;
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, _deletion_prover_map, micro_oz) =
+        spawn_deps(
+            initial_root,
+            &[batch_size],
+            &[],
+            *DEFAULT_TREE_DEPTH as u8,
+            &docker,
+        )
+        .await?;
+
+    let prover_mock = &insertion_prover_map[&batch_size];
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let temp_dir = tempfile::tempdir()?;
+
+    let config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_prover(prover_mock)
+        .build()?;
+
+    let (_, app_handle, local_addr, shutdown) =
+        spawn_app(config).await.expect("Failed to spawn app.");
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    let test_leaves: Vec<Field> = generate_test_identities(1)
+        .iter()
+        .map(|i| Hash::from_str_radix(i, 16).unwrap())
+        .collect();
+    let commitment = test_leaves[0];
+
+    let validated = validate_insert(&client, &uri, &commitment).await;
+    assert!(validated.insertable);
+    assert_eq!(validated.reason, None);
+
+    // The validation must not have queued the commitment
+    let response = client
+        .post(uri.clone() + "/inclusionProof")
+        .json(&json!({ "identityCommitment": commitment }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let count: IdentityCountResponse = client
+        .get(uri.clone() + "/v2/identities/count")
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(count.leaf_count, 0);
+    assert_eq!(count.next_leaf, 0);
+
+    for (commitment, reason) in [
+        (Field::ZERO, InsertRejection::InvalidCommitment),
+        (MODULUS, InsertRejection::UnreducedCommitment),
+    ] {
+        let validated = validate_insert(&client, &uri, &commitment).await;
+        assert!(!validated.insertable);
+        assert_eq!(validated.reason, Some(reason));
+    }
+
+    // Anything below the modulus is a field element
+    let validated = validate_insert(&client, &uri, &(MODULUS - Field::from(1))).await;
+    assert!(validated.insertable);
+
+    test_insert_identity(&uri, &client, &mut ref_tree, &test_leaves, 0).await;
+
+    let validated = validate_insert(&client, &uri, &commitment).await;
+    assert!(!validated.insertable);
+    assert_eq!(validated.reason, Some(InsertRejection::DuplicateCommitment));
+
+    let response = client
+        .post(uri.clone() + "/insertIdentity")
+        .json(&json!({ "identityCommitment": commitment }))
+        .send()
+        .await?;
+    assert!(response.status().is_client_error());
+
+    // Validations aren't insertions, only the two inserts are timed
+    let metrics = client
+        .get(uri.clone() + "/metrics")
+        .send()
+        .await?
+        .text()
+        .await?;
+    for status in ["success", "duplicate"] {
+        let line = format!(
+            "identity_insertion_processing_duration_seconds_count{{status=\"{status}\"}} 1"
+        );
+        assert!(metrics.lines().any(|l| l == line), "Missing {line}");
+    }
+
+    // Shutdown the app properly for the final time
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
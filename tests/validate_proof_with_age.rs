@@ -21,7 +21,7 @@ async fn validate_proof_with_age(offchain_mode_enabled: bool) -> anyhow::Result<
     init_tracing_subscriber();
     info!("Starting integration test");
 
-    let mut ref_tree = PoseidonTree::new(DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
     let initial_root: U256 = ref_tree.root().into();
 
     let batch_timeout_seconds: u64 = 1;
@@ -35,7 +35,7 @@ async fn validate_proof_with_age(offchain_mode_enabled: bool) -> anyhow::Result<
             initial_root,
             &[batch_size],
             &[],
-            DEFAULT_TREE_DEPTH as u8,
+            *DEFAULT_TREE_DEPTH as u8,
             &docker,
         )
         .await?;
@@ -117,8 +117,24 @@ async fn validate_proof_with_age(offchain_mode_enabled: bool) -> anyhow::Result<
         proof,
         2,
         None,
+        VERIFY_PROOF_TIMEOUT,
     )
-    .await;
+    .await?;
+
+    // A negative max root age would make the age check vacuous
+    let response = client
+        .post(format!("{uri}/verifySemaphoreProof?maxRootAgeSeconds=-1"))
+        .json(&json!({
+            "root": root,
+            "signalHash": signal_hash,
+            "nullifierHash": nullifier_hash,
+            "externalNullifierHash": external_nullifier_hash,
+            "proof": proof,
+        }))
+        .send()
+        .await?;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
 
     // Insert the 2nd identity to produce new root
     test_insert_identity(&uri, &client, &mut ref_tree, &test_leaves, 1).await;
@@ -137,8 +153,9 @@ async fn validate_proof_with_age(offchain_mode_enabled: bool) -> anyhow::Result<
         proof,
         2,
         Some("Root provided in semaphore proof is too old."),
+        VERIFY_PROOF_TIMEOUT,
     )
-    .await;
+    .await?;
 
     let max_age_of_proof = (Instant::now() - time_of_identity_insertion).as_secs();
     assert!(
@@ -157,8 +174,9 @@ async fn validate_proof_with_age(offchain_mode_enabled: bool) -> anyhow::Result<
         proof,
         max_age_of_proof as i64,
         None,
+        VERIFY_PROOF_TIMEOUT,
     )
-    .await;
+    .await?;
 
     // Shutdown the app properly for the final time
     shutdown.shutdown();
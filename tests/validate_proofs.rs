@@ -1,6 +1,7 @@
 mod common;
 
 use common::prelude::*;
+use signup_sequencer::MODULUS;
 
 #[tokio::test]
 async fn validate_proofs_onchain() -> anyhow::Result<()> {
@@ -17,11 +18,9 @@ async fn validate_proofs(offchain_mode_enabled: bool) -> anyhow::Result<()> {
     init_tracing_subscriber();
     info!("Starting integration test");
 
-    let mut ref_tree = PoseidonTree::new(DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
     let initial_root: U256 = ref_tree.root().into();
 
-    let batch_timeout_seconds: u64 = 1;
-
     let batch_size = 3;
 
     let docker = Cli::default();
@@ -29,7 +28,7 @@ async fn validate_proofs(offchain_mode_enabled: bool) -> anyhow::Result<()> {
         initial_root,
         &[batch_size],
         &[],
-        DEFAULT_TREE_DEPTH as u8,
+        *DEFAULT_TREE_DEPTH as u8,
         &docker,
     )
     .await?;
@@ -58,7 +57,7 @@ async fn validate_proofs(offchain_mode_enabled: bool) -> anyhow::Result<()> {
         .offchain_mode(offchain_mode_enabled)
         .build()?;
 
-    let (_, app_handle, local_addr, shutdown) =
+    let (app, app_handle, local_addr, shutdown) =
         spawn_app(config).await.expect("Failed to spawn app.");
 
     let uri = "http://".to_owned() + &local_addr.to_string();
@@ -102,7 +101,7 @@ async fn validate_proofs(offchain_mode_enabled: bool) -> anyhow::Result<()> {
         .unwrap()
     });
 
-    tokio::time::sleep(Duration::from_secs(15 + batch_timeout_seconds)).await;
+    flush_identities(&app).await?;
 
     let proof = proof_task.await.unwrap();
 
@@ -115,8 +114,9 @@ async fn validate_proofs(offchain_mode_enabled: bool) -> anyhow::Result<()> {
         external_nullifier_hash,
         proof,
         None,
+        VERIFY_PROOF_TIMEOUT,
     )
-    .await;
+    .await?;
 
     test_inclusion_proof(
         &mock_chain,
@@ -143,6 +143,49 @@ async fn validate_proofs(offchain_mode_enabled: bool) -> anyhow::Result<()> {
         .expect("Proof should verify correctly on chain.");
     }
 
+    // REPLAYED PROOF
+
+    // The v2 endpoint accepts each nullifier hash only once
+    let verify_v2 = || {
+        client
+            .post(format!("{uri}/v2/verifySemaphoreProof"))
+            .json(&json!({
+                "root": root,
+                "signalHash": signal_hash,
+                "nullifierHash": nullifier_hash,
+                "externalNullifierHash": external_nullifier_hash,
+                "proof": proof,
+            }))
+            .send()
+    };
+
+    let nullifier_status = || async {
+        client
+            .get(format!(
+                "{uri}/v2/semaphore-proof/nullifiers/{nullifier_hash:#x}"
+            ))
+            .send()
+            .await?
+            .json::<serde_json::Value>()
+            .await
+    };
+
+    assert_eq!(nullifier_status().await?, json!({ "spent": false }));
+
+    assert_eq!(verify_v2().await?.status(), StatusCode::OK);
+    assert_eq!(verify_v2().await?.status(), StatusCode::CONFLICT);
+
+    let status = nullifier_status().await?;
+    assert_eq!(status["spent"], json!(true));
+    assert_eq!(status["nullifierHash"], json!(nullifier_hash));
+    assert!(status["spentAt"].is_string());
+
+    let response = client
+        .get(format!("{uri}/v2/semaphore-proof/nullifiers/not-a-hash"))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
     // INVALID PROOF
 
     let invalid_nullifier_hash = generate_nullifier_hash(&IDENTITIES[1], external_nullifier_hash);
@@ -156,8 +199,9 @@ async fn validate_proofs(offchain_mode_enabled: bool) -> anyhow::Result<()> {
         external_nullifier_hash,
         proof,
         Some("invalid semaphore proof"),
+        VERIFY_PROOF_TIMEOUT,
     )
-    .await;
+    .await?;
 
     if !offchain_mode_enabled {
         test_verify_proof_on_chain(
@@ -172,6 +216,59 @@ async fn validate_proofs(offchain_mode_enabled: bool) -> anyhow::Result<()> {
         .expect_err("Proof verified on chain when it shouldn't have.");
     }
 
+    // UNREDUCED HASHES
+
+    // Hashes outside of the scalar field are rejected before the proof is
+    // checked, while the largest field element is only rejected by the proof
+    for (unreduced_signal_hash, unreduced_external_nullifier_hash, expected_failure) in [
+        (
+            MODULUS,
+            external_nullifier_hash,
+            "provided signal hash is not in reduced form",
+        ),
+        (
+            signal_hash,
+            MODULUS,
+            "provided external nullifier hash is not in reduced form",
+        ),
+        (
+            MODULUS - Field::from(1),
+            external_nullifier_hash,
+            "invalid semaphore proof",
+        ),
+        (
+            signal_hash,
+            MODULUS - Field::from(1),
+            "invalid semaphore proof",
+        ),
+    ] {
+        test_verify_proof(
+            &uri,
+            &client,
+            root,
+            unreduced_signal_hash,
+            nullifier_hash,
+            unreduced_external_nullifier_hash,
+            proof,
+            Some(expected_failure),
+            VERIFY_PROOF_TIMEOUT,
+        )
+        .await?;
+    }
+
+    let response = client
+        .post(format!("{uri}/v2/verifySemaphoreProof"))
+        .json(&json!({
+            "root": root,
+            "signalHash": MODULUS,
+            "nullifierHash": nullifier_hash,
+            "externalNullifierHash": external_nullifier_hash,
+            "proof": proof,
+        }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
     // IDENTITY NOT IN TREE
 
     ref_tree.set(1, TEST_LEAVES[1]);
@@ -196,8 +293,9 @@ async fn validate_proofs(offchain_mode_enabled: bool) -> anyhow::Result<()> {
         external_nullifier_hash,
         new_proof,
         Some("invalid semaphore proof"),
+        VERIFY_PROOF_TIMEOUT,
     )
-    .await;
+    .await?;
 
     if !offchain_mode_enabled {
         test_verify_proof_on_chain(
@@ -225,8 +323,9 @@ async fn validate_proofs(offchain_mode_enabled: bool) -> anyhow::Result<()> {
         external_nullifier_hash,
         new_proof,
         Some("invalid root"),
+        VERIFY_PROOF_TIMEOUT,
     )
-    .await;
+    .await?;
 
     if !offchain_mode_enabled {
         test_verify_proof_on_chain(
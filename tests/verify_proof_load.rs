@@ -0,0 +1,173 @@
+mod common;
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use common::prelude::*;
+use signup_sequencer::server::data::{InclusionProofRequest, VerifySemaphoreProofRequest};
+use tokio::time::Instant;
+
+const CONCURRENT_VERIFICATIONS: usize = 64;
+const LATENCY_SAMPLES: usize = 50;
+
+/// Median latency of inclusion proof requests for `leaf`
+async fn inclusion_proof_latency(uri: &str, client: &Client, leaf: &Hash) -> Duration {
+    let mut latencies = Vec::with_capacity(LATENCY_SAMPLES);
+
+    for _ in 0..LATENCY_SAMPLES {
+        let start = Instant::now();
+        let response = client
+            .post(format!("{uri}/inclusionProof"))
+            .json(&InclusionProofRequest {
+                identity_commitment: *leaf,
+            })
+            .send()
+            .await
+            .expect("Failed to request inclusion proof");
+        assert_eq!(response.status(), StatusCode::OK);
+
+        latencies.push(start.elapsed());
+    }
+
+    latencies.sort();
+    latencies[LATENCY_SAMPLES / 2]
+}
+
+// Saturates the verification workers, run with `--ignored`
+#[tokio::test]
+#[ignore]
+async fn verify_proof_load() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let batch_size = 3;
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, _, micro_oz) = spawn_deps(
+        initial_root,
+        &[batch_size],
+        &[],
+        *DEFAULT_TREE_DEPTH as u8,
+        &docker,
+    )
+    .await?;
+
+    let prover_mock = &insertion_prover_map[&batch_size];
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let temp_dir = tempfile::tempdir()?;
+
+    let config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_prover(prover_mock)
+        .offchain_mode(true)
+        .verify_workers(1, 8)
+        .build()?;
+
+    let (_, app_handle, local_addr, shutdown) =
+        spawn_app(config).await.expect("Failed to spawn app.");
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    let mut secret = *b"test_f0f0";
+    let identity = Identity::from_secret(&mut secret, None);
+    let test_leaves = [identity.commitment()];
+
+    let (merkle_proof, root) =
+        test_insert_identity(&uri, &client, &mut ref_tree, &test_leaves, 0).await;
+    test_inclusion_proof_mined(&mock_chain, &uri, &client, &test_leaves[0], false, true).await;
+
+    let signal_hash = hash_to_field(b"signal_hash");
+    let external_nullifier_hash = hash_to_field(b"external_hash");
+    let request = VerifySemaphoreProofRequest {
+        root,
+        signal_hash,
+        nullifier_hash: generate_nullifier_hash(&identity, external_nullifier_hash),
+        external_nullifier_hash,
+        proof: generate_proof(
+            &identity,
+            &merkle_proof,
+            external_nullifier_hash,
+            signal_hash,
+        )
+        .unwrap(),
+    };
+
+    let baseline = inclusion_proof_latency(&uri, &client, &test_leaves[0]).await;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let verified = Arc::new(AtomicUsize::new(0));
+    let overloaded = Arc::new(AtomicUsize::new(0));
+    let verifiers: Vec<_> = (0..CONCURRENT_VERIFICATIONS)
+        .map(|_| {
+            let (uri, client, request) = (uri.clone(), client.clone(), request.clone());
+            let (stop, verified, overloaded) = (stop.clone(), verified.clone(), overloaded.clone());
+
+            spawn(async move {
+                while !stop.load(Ordering::Relaxed) {
+                    let response = client
+                        .post(format!("{uri}/verifySemaphoreProof"))
+                        .json(&request)
+                        .send()
+                        .await
+                        .expect("Failed to request proof verification");
+
+                    match response.status() {
+                        StatusCode::OK => verified.fetch_add(1, Ordering::Relaxed),
+                        StatusCode::SERVICE_UNAVAILABLE => {
+                            overloaded.fetch_add(1, Ordering::Relaxed)
+                        }
+                        status => panic!("Unexpected verification status {status}"),
+                    };
+                }
+            })
+        })
+        .collect();
+
+    // Let the queue fill up
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    let under_load = inclusion_proof_latency(&uri, &client, &test_leaves[0]).await;
+
+    stop.store(true, Ordering::Relaxed);
+    for verifier in verifiers {
+        verifier.await?;
+    }
+
+    let verified = verified.load(Ordering::Relaxed);
+    let overloaded = overloaded.load(Ordering::Relaxed);
+    info!(
+        ?baseline,
+        ?under_load,
+        verified,
+        overloaded,
+        "Inclusion proof latency with saturated verification"
+    );
+
+    assert!(verified > 0, "No proof was verified");
+    assert!(overloaded > 0, "Verification was never overloaded");
+    assert!(
+        under_load <= baseline * 2 + Duration::from_millis(20),
+        "Inclusion proofs slowed down from {baseline:?} to {under_load:?}"
+    );
+
+    // Shutdown the app properly for the final time
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}
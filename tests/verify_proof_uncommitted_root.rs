@@ -0,0 +1,154 @@
+mod common;
+
+use common::prelude::*;
+use signup_sequencer::database::methods::DbMethods as _;
+use sqlx::{Connection, PgConnection};
+
+use crate::common::test_verify_proof_with_age;
+
+const INSERTION_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[tokio::test]
+async fn verify_proof_uncommitted_root() -> anyhow::Result<()> {
+    // Initialize logging for the test.
+    init_tracing_subscriber();
+    info!("Starting integration test");
+
+    let mut ref_tree = PoseidonTree::new(*DEFAULT_TREE_DEPTH + 1, ruint::Uint::ZERO);
+    let initial_root: U256 = ref_tree.root().into();
+
+    let batch_size = 3;
+
+    let docker = Cli::default();
+    let (mock_chain, db_container, insertion_prover_map, _, micro_oz) = spawn_deps(
+        initial_root,
+        &[batch_size],
+        &[],
+        *DEFAULT_TREE_DEPTH as u8,
+        &docker,
+    )
+    .await?;
+
+    let prover_mock = &insertion_prover_map[&batch_size];
+
+    let db_socket_addr = db_container.address();
+    let db_url = format!("postgres://postgres:postgres@{db_socket_addr}/database");
+
+    let temp_dir = tempfile::tempdir()?;
+
+    let config = TestConfigBuilder::new()
+        .db_url(&db_url)
+        .oz_api_url(&micro_oz.endpoint())
+        .oz_address(micro_oz.address())
+        .identity_manager_address(mock_chain.identity_manager.address())
+        .primary_network_provider(mock_chain.anvil.endpoint())
+        .cache_file(temp_dir.path().join("testfile").to_str().unwrap())
+        .add_prover(prover_mock)
+        .offchain_mode(true)
+        .build()?;
+
+    let (app, app_handle, local_addr, shutdown) =
+        spawn_app(config).await.expect("Failed to spawn app.");
+
+    let uri = "http://".to_owned() + &local_addr.to_string();
+    let client = Client::new();
+
+    let mut secret = *b"test_f0f0";
+    let identity = Identity::from_secret(&mut secret, None);
+    let test_leaves = [identity.commitment()];
+
+    // Stalls the insertion task between updating the latest tree and
+    // committing the new roots to the database. Reads go through.
+    let mut lock_conn = PgConnection::connect(&db_url).await?;
+    let mut lock_tx = lock_conn.begin().await?;
+    sqlx::query("LOCK TABLE identities IN SHARE ROW EXCLUSIVE MODE")
+        .execute(&mut *lock_tx)
+        .await?;
+
+    let (merkle_proof, root) =
+        test_insert_identity(&uri, &client, &mut ref_tree, &test_leaves, 0).await;
+
+    let start = tokio::time::Instant::now();
+    while app.tree_state()?.latest_tree().get_root() != root {
+        assert!(
+            start.elapsed() < INSERTION_TIMEOUT,
+            "Identity was not inserted into the latest tree"
+        );
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    let signal_hash = hash_to_field(b"signal_hash");
+    let external_nullifier_hash = hash_to_field(b"external_hash");
+    let nullifier_hash = generate_nullifier_hash(&identity, external_nullifier_hash);
+    let proof = generate_proof(
+        &identity,
+        &merkle_proof,
+        external_nullifier_hash,
+        signal_hash,
+    )
+    .unwrap();
+
+    // The root is only in the latest tree
+    test_verify_proof_with_age(
+        &uri,
+        &client,
+        root,
+        signal_hash,
+        nullifier_hash,
+        external_nullifier_hash,
+        proof,
+        60,
+        None,
+        VERIFY_PROOF_TIMEOUT,
+    )
+    .await?;
+
+    // A root which is neither in the database nor the latest tree is still
+    // rejected
+    test_verify_proof(
+        &uri,
+        &client,
+        Hash::from(42),
+        signal_hash,
+        nullifier_hash,
+        external_nullifier_hash,
+        proof,
+        Some("invalid root"),
+        VERIFY_PROOF_TIMEOUT,
+    )
+    .await?;
+
+    lock_tx.rollback().await?;
+
+    // Once committed, the root is served from the database
+    let start = tokio::time::Instant::now();
+    while app.database.get_root_state(&root).await?.is_none() {
+        assert!(
+            start.elapsed() < INSERTION_TIMEOUT,
+            "Root was not committed"
+        );
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    test_verify_proof(
+        &uri,
+        &client,
+        root,
+        signal_hash,
+        nullifier_hash,
+        external_nullifier_hash,
+        proof,
+        None,
+        VERIFY_PROOF_TIMEOUT,
+    )
+    .await?;
+
+    // Shutdown the app properly for the final time
+    shutdown.shutdown();
+    app_handle.await.unwrap();
+    for (_, prover) in insertion_prover_map.into_iter() {
+        prover.stop();
+    }
+
+    Ok(())
+}